@@ -0,0 +1,13 @@
+-- 文档历史版本表（保存时写入快照，用于恢复和对比）
+CREATE TABLE IF NOT EXISTS file_revisions (
+    id TEXT PRIMARY KEY NOT NULL,
+    file_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT, -- 快照时的 HTML 内容
+    content_plain TEXT, -- 快照时的纯文本（用于对比）
+    reason TEXT NOT NULL, -- 'autosave', 'manual', 'restore'
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_revisions_file ON file_revisions(file_id, created_at DESC);
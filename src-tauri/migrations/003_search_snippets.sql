@@ -0,0 +1,45 @@
+-- 重建全文搜索表：改为自带文本的 FTS5 表
+-- （snippet()/highlight() 需要从索引中读取原文，外部内容表的列与 files 表不对应）
+DROP TRIGGER IF EXISTS files_ai;
+DROP TRIGGER IF EXISTS files_au;
+DROP TRIGGER IF EXISTS files_ad;
+DROP TABLE IF EXISTS files_fts;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
+    file_id UNINDEXED,
+    title,
+    content,
+    tags
+);
+
+-- 触发器：插入文件时同步到 FTS（使用 content_plain）
+CREATE TRIGGER IF NOT EXISTS files_ai AFTER INSERT ON files BEGIN
+    INSERT INTO files_fts(file_id, title, content, tags)
+    VALUES (new.id, new.title, COALESCE(new.content_plain, ''), '');
+END;
+
+-- 触发器：更新文件时同步到 FTS
+CREATE TRIGGER IF NOT EXISTS files_au AFTER UPDATE ON files BEGIN
+    UPDATE files_fts
+    SET title = new.title, content = COALESCE(new.content_plain, '')
+    WHERE file_id = new.id;
+END;
+
+-- 触发器：删除文件时从 FTS 删除
+CREATE TRIGGER IF NOT EXISTS files_ad AFTER DELETE ON files BEGIN
+    DELETE FROM files_fts WHERE file_id = old.id;
+END;
+
+-- 回填已有文件及其标签
+INSERT INTO files_fts(file_id, title, content, tags)
+SELECT
+    f.id,
+    f.title,
+    COALESCE(f.content_plain, ''),
+    COALESCE((
+        SELECT group_concat(t.name, ' ')
+        FROM file_tags ft
+        INNER JOIN tags t ON ft.tag_id = t.id
+        WHERE ft.file_id = f.id
+    ), '')
+FROM files f;
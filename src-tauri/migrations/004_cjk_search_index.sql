@@ -0,0 +1,15 @@
+-- 中日韩分词：索引内容改由 Rust 侧分词后写入，插入/更新触发器不再适用
+-- （删除触发器保留，用于级联删除时清理索引）
+DROP TRIGGER IF EXISTS files_ai;
+DROP TRIGGER IF EXISTS files_au;
+
+DELETE FROM files_fts;
+
+-- 应用元数据（键值对）
+CREATE TABLE IF NOT EXISTS app_meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+
+-- 标记索引需要重建，启动时由 SearchService 按新分词规则重新写入
+INSERT OR REPLACE INTO app_meta (key, value) VALUES ('search_index_version', '0');
@@ -0,0 +1,4 @@
+-- 文件词数（由 Rust 侧按中日韩字符计数，已有文件在启动时回填）
+ALTER TABLE files ADD COLUMN word_count INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_files_created ON files(created_at DESC);
@@ -0,0 +1,25 @@
+-- 文档分块及其向量（用于语义搜索和相似文档推荐）
+CREATE TABLE IF NOT EXISTS file_chunks (
+    id TEXT PRIMARY KEY NOT NULL,
+    file_id TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    start_offset INTEGER NOT NULL, -- 在 content_plain 中的起始位置（字符）
+    end_offset INTEGER NOT NULL, -- 结束位置（不含）
+    embedding BLOB NOT NULL, -- f32 小端序数组，已归一化
+    model TEXT NOT NULL, -- 生成向量的模型标识
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_chunks_file ON file_chunks(file_id, chunk_index);
+CREATE INDEX IF NOT EXISTS idx_file_chunks_model ON file_chunks(model);
+
+-- 每个文件的向量化状态，文件更新时间或模型变化时需要重新生成
+CREATE TABLE IF NOT EXISTS file_embeddings (
+    file_id TEXT PRIMARY KEY NOT NULL,
+    model TEXT NOT NULL,
+    source_updated_at TEXT NOT NULL, -- 生成向量时文件的 updated_at
+    indexed_at TEXT NOT NULL,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
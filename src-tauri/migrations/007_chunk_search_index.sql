@@ -0,0 +1,13 @@
+-- 分块全文索引：用于检索上下文时按 BM25 为分块排名（内容由 Rust 侧分词后写入）
+CREATE VIRTUAL TABLE IF NOT EXISTS file_chunks_fts USING fts5(
+    chunk_id UNINDEXED,
+    content
+);
+
+-- 触发器：删除分块（包括随文件级联删除）时从 FTS 删除
+CREATE TRIGGER IF NOT EXISTS file_chunks_ad AFTER DELETE ON file_chunks BEGIN
+    DELETE FROM file_chunks_fts WHERE chunk_id = old.id;
+END;
+
+-- 标记索引需要重建，启动时由 SearchService 写入已有分块
+INSERT OR REPLACE INTO app_meta (key, value) VALUES ('search_index_version', '0');
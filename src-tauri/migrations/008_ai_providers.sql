@@ -0,0 +1,24 @@
+-- AI 提供商（OpenAI 兼容接口）
+CREATE TABLE IF NOT EXISTS ai_providers (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    base_url TEXT NOT NULL,
+    model TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 0,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    api_key_encrypted TEXT, -- AES-256-GCM 加密，密钥保存在应用数据目录的 provider.key 中
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- 最多一个默认提供商
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ai_providers_default ON ai_providers(is_default) WHERE is_default = 1;
+
+INSERT OR IGNORE INTO ai_providers (id, name, base_url, model, enabled, is_default, created_at, updated_at)
+VALUES
+    ('openai-default', 'OpenAI', 'https://api.openai.com/v1', 'gpt-4o-mini', 0, 0, datetime('now'), datetime('now')),
+    ('anthropic-default', 'Anthropic', 'https://api.anthropic.com/v1', 'claude-3-haiku-20240307', 0, 0, datetime('now'), datetime('now'));
+
+-- 向量化后端改为引用提供商，清除以明文保存 API Key 的旧配置
+DELETE FROM app_meta
+WHERE key = 'embedding_config' AND json_extract(value, '$.backend') = 'openai';
@@ -0,0 +1,5 @@
+-- 文档统计信息（由 Rust 侧从正文 HTML 计算，已有文件在启动时回填）
+ALTER TABLE files ADD COLUMN char_count INTEGER;
+ALTER TABLE files ADD COLUMN paragraph_count INTEGER;
+ALTER TABLE files ADD COLUMN reading_minutes INTEGER;
+ALTER TABLE files ADD COLUMN outline TEXT; -- 标题大纲 JSON：[{ "level": 1, "text": "..." }]
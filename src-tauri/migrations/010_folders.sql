@@ -0,0 +1,20 @@
+-- 文件夹（工作空间内按 parent_id 嵌套，根目录的 parent_id 为 NULL）
+CREATE TABLE IF NOT EXISTS folders (
+    id TEXT PRIMARY KEY NOT NULL,
+    workspace_id TEXT NOT NULL,
+    parent_id TEXT,
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE CASCADE
+);
+
+-- 同一目录下文件夹不能重名
+CREATE UNIQUE INDEX IF NOT EXISTS idx_folders_name ON folders(workspace_id, COALESCE(parent_id, ''), name);
+CREATE INDEX IF NOT EXISTS idx_folders_parent ON folders(parent_id);
+
+-- 文件所在的文件夹，NULL 表示工作空间根目录
+ALTER TABLE files ADD COLUMN folder_id TEXT REFERENCES folders(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_files_folder ON files(folder_id);
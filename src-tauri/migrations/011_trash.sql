@@ -0,0 +1,7 @@
+-- 回收站：删除时间，NULL 表示未删除
+-- 删除工作空间时其中的文件写入相同的时间，恢复时据此区分单独删除的文件
+ALTER TABLE workspaces ADD COLUMN deleted_at TEXT;
+ALTER TABLE files ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_workspaces_deleted ON workspaces(deleted_at);
+CREATE INDEX IF NOT EXISTS idx_files_deleted ON files(deleted_at);
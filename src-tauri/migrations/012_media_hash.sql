@@ -0,0 +1,5 @@
+-- 媒体内容的 SHA-256，同一工作空间中内容相同的媒体只保存一份
+-- 已有媒体在启动时回填并合并重复项
+ALTER TABLE media_assets ADD COLUMN content_hash TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_media_hash ON media_assets(workspace_id, content_hash);
@@ -0,0 +1,9 @@
+-- 008 中预置提供商的时间用 datetime('now') 写入（"YYYY-MM-DD HH:MM:SS"），
+-- 与其他记录的 RFC 3339 格式不一致，排序和解析都会出错；已应用的迁移不能修改，在这里改写
+UPDATE ai_providers
+SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at)
+WHERE created_at NOT LIKE '%T%';
+
+UPDATE ai_providers
+SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at)
+WHERE updated_at NOT LIKE '%T%';
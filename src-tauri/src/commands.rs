@@ -1,16 +1,20 @@
+use crate::error::{AppError, Result};
 use crate::models::*;
+use crate::services::media::probe::ImageInfo;
 use crate::AppState;
-use tauri::{Manager, State, WebviewUrl, WebviewWindowBuilder};
+use std::path::{Path, PathBuf};
+use tauri::ipc::{Channel, InvokeBody, Request};
+use tauri::{Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder};
 
 // ============ 窗口管理命令 ============
 
 #[tauri::command]
-pub async fn open_editor_window(app: tauri::AppHandle, file_id: String) -> Result<String, String> {
+pub async fn open_editor_window(app: tauri::AppHandle, file_id: String) -> Result<String> {
     let window_label = format!("editor-{}", file_id);
 
     // 检查窗口是否已存在
     if let Some(window) = app.get_webview_window(&window_label) {
-        window.set_focus().map_err(|e| e.to_string())?;
+        window.set_focus()?;
         return Ok(window_label);
     }
 
@@ -23,19 +27,18 @@ pub async fn open_editor_window(app: tauri::AppHandle, file_id: String) -> Resul
         .min_inner_size(600.0, 400.0)
         .resizable(true)
         .center()
-        .build()
-        .map_err(|e| e.to_string())?;
+        .build()?;
 
     Ok(window_label)
 }
 
 #[tauri::command]
-pub async fn open_settings_window(app: tauri::AppHandle) -> Result<String, String> {
+pub async fn open_settings_window(app: tauri::AppHandle) -> Result<String> {
     let window_label = "settings";
 
     // 检查窗口是否已存在
     if let Some(window) = app.get_webview_window(window_label) {
-        window.set_focus().map_err(|e| e.to_string())?;
+        window.set_focus()?;
         return Ok(window_label.to_string());
     }
 
@@ -46,95 +49,159 @@ pub async fn open_settings_window(app: tauri::AppHandle) -> Result<String, Strin
         .min_inner_size(500.0, 400.0)
         .resizable(true)
         .center()
-        .build()
-        .map_err(|e| e.to_string())?;
+        .build()?;
 
     Ok(window_label.to_string())
 }
 
+// ============ 设置命令 ============
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, AppState>) -> Result<Settings> {
+    Ok(state.settings_service.get().await)
+}
+
+/// 保存设置并广播给所有窗口（包括调用方），之后打开的窗口通过 get_settings 读取
+#[tauri::command]
+pub async fn update_settings(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings: Settings,
+) -> Result<Settings> {
+    let settings = state.settings_service.update(settings).await?;
+    app.emit("settings-changed", &settings)?;
+    Ok(settings)
+}
+
 // ============ 工作空间命令 ============
 
 #[tauri::command]
 pub async fn create_workspace(
     state: State<'_, AppState>,
     data: CreateWorkspace,
-) -> Result<Workspace, String> {
+) -> Result<Workspace> {
+    state.workspace_service.create(data).await
+}
+
+#[tauri::command]
+pub async fn get_workspace(state: State<'_, AppState>, id: String) -> Result<Option<Workspace>> {
+    state.workspace_service.get(&id).await
+}
+
+#[tauri::command]
+pub async fn list_workspaces(state: State<'_, AppState>) -> Result<Vec<Workspace>> {
+    state.workspace_service.list().await
+}
+
+#[tauri::command]
+pub async fn update_workspace(
+    state: State<'_, AppState>,
+    id: String,
+    data: UpdateWorkspace,
+) -> Result<Workspace> {
+    state.workspace_service.update(&id, data).await
+}
+
+/// 把工作空间及其文件移入回收站
+#[tauri::command]
+pub async fn delete_workspace(state: State<'_, AppState>, id: String) -> Result<()> {
+    state.workspace_service.delete(&id).await
+}
+
+// ============ 导入导出命令 ============
+
+/// 导出工作空间为 zip 归档，进度通过 `on_progress` 推送给调用的窗口
+#[tauri::command]
+pub async fn export_workspace(
+    state: State<'_, AppState>,
+    workspace_id: String,
+    dest_path: String,
+    on_progress: Channel<ArchiveProgress>,
+) -> Result<ExportSummary> {
     state
-        .workspace_service
-        .create(data)
+        .archive_service
+        .export_workspace(&workspace_id, Path::new(&dest_path), |progress| {
+            let _ = on_progress.send(progress);
+        })
         .await
-        .map_err(|e| e.to_string())
 }
 
+/// 从 zip 归档导入为新的工作空间
 #[tauri::command]
-pub async fn get_workspace(
+pub async fn import_workspace(
     state: State<'_, AppState>,
-    id: String,
-) -> Result<Option<Workspace>, String> {
+    archive_path: String,
+    on_progress: Channel<ArchiveProgress>,
+) -> Result<Workspace> {
     state
-        .workspace_service
-        .get(&id)
+        .archive_service
+        .import_workspace(Path::new(&archive_path), |progress| {
+            let _ = on_progress.send(progress);
+        })
         .await
-        .map_err(|e| e.to_string())
 }
 
+/// 把 Markdown 文件批量导入为文档
 #[tauri::command]
-pub async fn list_workspaces(state: State<'_, AppState>) -> Result<Vec<Workspace>, String> {
+pub async fn import_markdown_files(
+    state: State<'_, AppState>,
+    workspace_id: String,
+    paths: Vec<String>,
+) -> Result<Vec<File>> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
     state
-        .workspace_service
-        .list()
+        .archive_service
+        .import_markdown_files(&workspace_id, &paths)
         .await
-        .map_err(|e| e.to_string())
 }
 
+/// 把文档导出为 Markdown 文件
 #[tauri::command]
-pub async fn update_workspace(
+pub async fn export_file_as_markdown(
     state: State<'_, AppState>,
-    id: String,
-    data: UpdateWorkspace,
-) -> Result<Workspace, String> {
+    file_id: String,
+    dest_path: String,
+) -> Result<()> {
     state
-        .workspace_service
-        .update(&id, data)
+        .archive_service
+        .export_file_as_markdown(&file_id, Path::new(&dest_path))
         .await
-        .map_err(|e| e.to_string())
 }
 
+/// 导入 Obsidian / Logseq 风格的 Markdown 笔记库
 #[tauri::command]
-pub async fn delete_workspace(state: State<'_, AppState>, id: String) -> Result<(), String> {
+pub async fn import_vault(
+    state: State<'_, AppState>,
+    workspace_id: String,
+    root_path: String,
+    on_progress: Channel<ArchiveProgress>,
+) -> Result<VaultImportReport> {
     state
-        .workspace_service
-        .delete(&id)
+        .vault_service
+        .import_vault(&workspace_id, Path::new(&root_path), |progress| {
+            let _ = on_progress.send(progress);
+        })
         .await
-        .map_err(|e| e.to_string())
 }
 
 // ============ 文件命令 ============
 
 #[tauri::command]
-pub async fn create_file(state: State<'_, AppState>, data: CreateFile) -> Result<File, String> {
-    state
-        .file_service
-        .create(data)
-        .await
-        .map_err(|e| e.to_string())
+pub async fn create_file(state: State<'_, AppState>, data: CreateFile) -> Result<File> {
+    state.file_service.create(data).await
 }
 
 #[tauri::command]
-pub async fn get_file(state: State<'_, AppState>, id: String) -> Result<Option<File>, String> {
-    state.file_service.get(&id).await.map_err(|e| e.to_string())
+pub async fn get_file(state: State<'_, AppState>, id: String) -> Result<Option<File>> {
+    state.file_service.get(&id).await
 }
 
 #[tauri::command]
 pub async fn list_files_by_workspace(
     state: State<'_, AppState>,
     workspace_id: String,
-) -> Result<Vec<File>, String> {
-    state
-        .file_service
-        .list_by_workspace(&workspace_id)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Vec<File>> {
+    state.file_service.list_by_workspace(&workspace_id).await
 }
 
 #[tauri::command]
@@ -142,64 +209,189 @@ pub async fn list_files_by_type(
     state: State<'_, AppState>,
     workspace_id: String,
     file_type: String,
-) -> Result<Vec<File>, String> {
+) -> Result<Vec<File>> {
     state
         .file_service
         .list_by_type(&workspace_id, &file_type)
         .await
-        .map_err(|e| e.to_string())
 }
 
+/// 分页列出文件摘要
 #[tauri::command]
-pub async fn update_file(
+pub async fn list_files(state: State<'_, AppState>, query: ListFilesQuery) -> Result<FilePage> {
+    state.file_service.list(query).await
+}
+
+#[tauri::command]
+pub async fn update_file(state: State<'_, AppState>, id: String, data: UpdateFile) -> Result<File> {
+    state.file_service.update(&id, data).await
+}
+
+/// 把文件移入回收站
+#[tauri::command]
+pub async fn delete_file(state: State<'_, AppState>, id: String) -> Result<()> {
+    state.file_service.delete(&id).await
+}
+
+// ============ 历史版本命令 ============
+
+#[tauri::command]
+pub async fn list_file_revisions(
+    state: State<'_, AppState>,
+    file_id: String,
+) -> Result<Vec<FileRevisionSummary>> {
+    state.revision_service.list(&file_id).await
+}
+
+#[tauri::command]
+pub async fn get_file_revision(
     state: State<'_, AppState>,
     id: String,
-    data: UpdateFile,
-) -> Result<File, String> {
+) -> Result<Option<FileRevision>> {
+    state.revision_service.get(&id).await
+}
+
+/// 手动保存版本（例如 AI 改写前）
+#[tauri::command]
+pub async fn create_file_revision(
+    state: State<'_, AppState>,
+    file_id: String,
+) -> Result<Option<FileRevision>> {
+    state.file_service.create_revision(&file_id).await
+}
+
+#[tauri::command]
+pub async fn restore_file_revision(state: State<'_, AppState>, id: String) -> Result<File> {
+    state.file_service.restore_revision(&id).await
+}
+
+/// 对比两个版本；不传 to_id 时与当前内容对比
+#[tauri::command]
+pub async fn diff_file_revisions(
+    state: State<'_, AppState>,
+    from_id: String,
+    to_id: Option<String>,
+    granularity: Option<DiffGranularity>,
+) -> Result<RevisionDiff> {
     state
-        .file_service
-        .update(&id, data)
+        .revision_service
+        .diff(&from_id, to_id.as_deref(), granularity.unwrap_or_default())
         .await
-        .map_err(|e| e.to_string())
+}
+
+// ============ 文件夹命令 ============
+
+#[tauri::command]
+pub async fn create_folder(state: State<'_, AppState>, data: CreateFolder) -> Result<Folder> {
+    state.folder_service.create(data).await
 }
 
 #[tauri::command]
-pub async fn delete_file(state: State<'_, AppState>, id: String) -> Result<(), String> {
+pub async fn rename_folder(state: State<'_, AppState>, id: String, name: String) -> Result<Folder> {
+    state.folder_service.rename(&id, &name).await
+}
+
+/// 移动文件夹，不传 `parent_id` 时移到根目录
+#[tauri::command]
+pub async fn move_folder(
+    state: State<'_, AppState>,
+    id: String,
+    parent_id: Option<String>,
+) -> Result<Folder> {
     state
-        .file_service
-        .delete(&id)
+        .folder_service
+        .move_to(&id, parent_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
 }
 
-// ============ 标签命令 ============
+/// 删除文件夹，默认把其中的内容移到上一级
+#[tauri::command]
+pub async fn delete_folder(
+    state: State<'_, AppState>,
+    id: String,
+    mode: Option<FolderDeleteMode>,
+) -> Result<()> {
+    state
+        .folder_service
+        .delete(&id, mode.unwrap_or_default())
+        .await
+}
 
+/// 移动文件，不传 `folder_id` 时移到根目录
 #[tauri::command]
-pub async fn create_tag(state: State<'_, AppState>, data: CreateTag) -> Result<Tag, String> {
+pub async fn move_files_to_folder(
+    state: State<'_, AppState>,
+    file_ids: Vec<String>,
+    folder_id: Option<String>,
+) -> Result<()> {
     state
-        .tag_service
-        .create(data)
+        .folder_service
+        .move_files(&file_ids, folder_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_tag(state: State<'_, AppState>, id: String) -> Result<Option<Tag>, String> {
-    state.tag_service.get(&id).await.map_err(|e| e.to_string())
+pub async fn get_folder_tree(
+    state: State<'_, AppState>,
+    workspace_id: String,
+) -> Result<FolderTree> {
+    state.folder_service.tree(&workspace_id).await
+}
+
+// ============ 回收站命令 ============
+
+#[tauri::command]
+pub async fn list_trash(state: State<'_, AppState>) -> Result<TrashContents> {
+    state.trash_service.list().await
 }
 
 #[tauri::command]
-pub async fn list_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
-    state.tag_service.list().await.map_err(|e| e.to_string())
+pub async fn restore_file(state: State<'_, AppState>, id: String) -> Result<File> {
+    state.trash_service.restore_file(&id).await
 }
 
+/// 恢复工作空间及随它一起删除的文件
 #[tauri::command]
-pub async fn delete_tag(state: State<'_, AppState>, id: String) -> Result<(), String> {
-    state
-        .tag_service
-        .delete(&id)
-        .await
-        .map_err(|e| e.to_string())
+pub async fn restore_workspace(state: State<'_, AppState>, id: String) -> Result<Workspace> {
+    state.trash_service.restore_workspace(&id).await
+}
+
+/// 彻底删除回收站中的所有项目
+#[tauri::command]
+pub async fn empty_trash(state: State<'_, AppState>) -> Result<TrashPurgeResult> {
+    state.trash_service.empty().await
+}
+
+#[tauri::command]
+pub async fn get_trash_config(state: State<'_, AppState>) -> Result<TrashConfig> {
+    state.trash_service.get_config().await
+}
+
+#[tauri::command]
+pub async fn set_trash_config(state: State<'_, AppState>, config: TrashConfig) -> Result<()> {
+    state.trash_service.set_config(config).await
+}
+
+// ============ 标签命令 ============
+
+#[tauri::command]
+pub async fn create_tag(state: State<'_, AppState>, data: CreateTag) -> Result<Tag> {
+    state.tag_service.create(data).await
+}
+
+#[tauri::command]
+pub async fn get_tag(state: State<'_, AppState>, id: String) -> Result<Option<Tag>> {
+    state.tag_service.get(&id).await
+}
+
+#[tauri::command]
+pub async fn list_tags(state: State<'_, AppState>) -> Result<Vec<Tag>> {
+    state.tag_service.list().await
+}
+
+#[tauri::command]
+pub async fn delete_tag(state: State<'_, AppState>, id: String) -> Result<()> {
+    state.tag_service.delete(&id).await
 }
 
 #[tauri::command]
@@ -207,12 +399,8 @@ pub async fn add_file_tag(
     state: State<'_, AppState>,
     file_id: String,
     tag_id: String,
-) -> Result<(), String> {
-    state
-        .file_service
-        .add_tag(&file_id, &tag_id)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<()> {
+    state.file_service.add_tag(&file_id, &tag_id).await
 }
 
 #[tauri::command]
@@ -220,24 +408,13 @@ pub async fn remove_file_tag(
     state: State<'_, AppState>,
     file_id: String,
     tag_id: String,
-) -> Result<(), String> {
-    state
-        .file_service
-        .remove_tag(&file_id, &tag_id)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<()> {
+    state.file_service.remove_tag(&file_id, &tag_id).await
 }
 
 #[tauri::command]
-pub async fn get_file_tags(
-    state: State<'_, AppState>,
-    file_id: String,
-) -> Result<Vec<Tag>, String> {
-    state
-        .file_service
-        .get_tags(&file_id)
-        .await
-        .map_err(|e| e.to_string())
+pub async fn get_file_tags(state: State<'_, AppState>, file_id: String) -> Result<Vec<Tag>> {
+    state.file_service.get_tags(&file_id).await
 }
 
 // ============ 搜索命令 ============
@@ -246,12 +423,8 @@ pub async fn get_file_tags(
 pub async fn search_files(
     state: State<'_, AppState>,
     query: SearchQuery,
-) -> Result<Vec<SearchResult>, String> {
-    state
-        .search_service
-        .search(query)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Vec<SearchResult>> {
+    state.search_service.search(query).await
 }
 
 #[tauri::command]
@@ -260,12 +433,11 @@ pub async fn search_by_tags(
     workspace_id: Option<String>,
     tag_names: Vec<String>,
     match_all: bool,
-) -> Result<Vec<SearchResult>, String> {
+) -> Result<Vec<SearchResult>> {
     state
         .search_service
         .search_by_tags(workspace_id, tag_names, match_all)
         .await
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -273,171 +445,270 @@ pub async fn find_similar_files(
     state: State<'_, AppState>,
     file_id: String,
     limit: i64,
-) -> Result<Vec<SearchResult>, String> {
-    state
-        .search_service
+) -> Result<Vec<SearchResult>> {
+    // 优先按内容相似度推荐，源文档没有内容或没有相近文档时退回到共同标签
+    let results = state
+        .embedding_service
         .find_similar(&file_id, limit)
+        .await?;
+    if !results.is_empty() {
+        return Ok(results);
+    }
+
+    state.search_service.find_similar(&file_id, limit).await
+}
+
+/// 语义搜索，返回最相近的文档片段
+#[tauri::command]
+pub async fn semantic_search(
+    state: State<'_, AppState>,
+    query: SemanticQuery,
+) -> Result<Vec<ChunkMatch>> {
+    state.embedding_service.search(query).await
+}
+
+/// 检索与问题相关的文档片段（全文与向量检索融合排名），用于为 AI 回答提供上下文
+#[tauri::command]
+pub async fn retrieve_context(
+    state: State<'_, AppState>,
+    query: RetrievalQuery,
+) -> Result<Vec<ContextChunk>> {
+    state.embedding_service.retrieve_context(query).await
+}
+
+/// 为尚未向量化或已修改的文档生成向量，返回处理的文档数
+#[tauri::command]
+pub async fn index_embeddings(
+    state: State<'_, AppState>,
+    workspace_id: Option<String>,
+) -> Result<i64> {
+    state
+        .embedding_service
+        .index_pending(workspace_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
 }
 
-// ============ 媒体命令 ============
+#[tauri::command]
+pub async fn get_embedding_config(state: State<'_, AppState>) -> Result<EmbeddingConfig> {
+    Ok(state.embedding_service.get_config().await)
+}
 
-/// 上传媒体文件（接收 base64 数据）
 #[tauri::command]
-pub async fn upload_media(
+pub async fn set_embedding_config(
     state: State<'_, AppState>,
-    workspace_id: String,
-    file_id: String,
-    file_name: String,
-    mime_type: String,
-    data: String, // base64 encoded
-) -> Result<MediaAsset, String> {
-    use base64::{engine::general_purpose::STANDARD, Engine};
-    use std::fs;
-
-    // 解码 base64
-    let bytes = STANDARD.decode(&data).map_err(|e| e.to_string())?;
-    let file_size = bytes.len() as i64;
-
-    // 生成 UUID 和文件路径
-    let id = uuid::Uuid::new_v4().to_string();
-    let extension = file_name.split('.').last().unwrap_or("bin");
-    let stored_name = format!("{}.{}", id, extension);
-    let file_path = state.media_dir.join(&stored_name);
-
-    // 写入文件
-    fs::write(&file_path, &bytes).map_err(|e| e.to_string())?;
-
-    // 获取图片尺寸（如果是图片）
-    let (width, height) = if mime_type.starts_with("image/") {
-        get_image_dimensions(&bytes).unwrap_or((None, None))
-    } else {
-        (None, None)
-    };
+    config: EmbeddingConfig,
+) -> Result<()> {
+    state.embedding_service.set_config(config).await
+}
 
-    // 保存到数据库
-    let asset = state
-        .media_service
-        .create(crate::models::CreateMediaAsset {
-            workspace_id: workspace_id.clone(),
-            file_name,
-            file_path: file_path.to_string_lossy().to_string(),
-            file_size,
-            mime_type,
-            width,
-            height,
+// ============ AI 命令 ============
+
+#[tauri::command]
+pub async fn list_providers(state: State<'_, AppState>) -> Result<Vec<AiProvider>> {
+    state.provider_service.list().await
+}
+
+#[tauri::command]
+pub async fn get_provider(state: State<'_, AppState>, id: String) -> Result<Option<AiProvider>> {
+    state.provider_service.get(&id).await
+}
+
+#[tauri::command]
+pub async fn create_provider(
+    state: State<'_, AppState>,
+    data: CreateAiProvider,
+) -> Result<AiProvider> {
+    state.provider_service.create(data).await
+}
+
+#[tauri::command]
+pub async fn update_provider(
+    state: State<'_, AppState>,
+    id: String,
+    data: UpdateAiProvider,
+) -> Result<AiProvider> {
+    state.provider_service.update(&id, data).await
+}
+
+#[tauri::command]
+pub async fn delete_provider(state: State<'_, AppState>, id: String) -> Result<()> {
+    state.provider_service.delete(&id).await
+}
+
+#[tauri::command]
+pub async fn set_default_provider(state: State<'_, AppState>, id: Option<String>) -> Result<()> {
+    state.provider_service.set_default(id.as_deref()).await
+}
+
+/// 测试提供商连接，返回可用的模型列表
+#[tauri::command]
+pub async fn test_provider_connection(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<ProviderConnection> {
+    state.provider_service.test_connection(&id).await
+}
+
+/// 流式对话补全：内容片段通过 `on_event` 推送给调用的窗口，完成后返回完整回复
+#[tauri::command]
+pub async fn chat_completion(
+    state: State<'_, AppState>,
+    request_id: String,
+    request: ChatRequest,
+    on_event: Channel<ChatEvent>,
+) -> Result<String> {
+    let endpoint = state
+        .provider_service
+        .resolve(request.provider_id.as_deref())
+        .await?;
+
+    state
+        .ai_service
+        .chat(&request_id, endpoint, request, |event| {
+            let _ = on_event.send(event);
         })
         .await
-        .map_err(|e| e.to_string())?;
+}
+
+/// 取消进行中的对话补全，返回请求是否存在
+#[tauri::command]
+pub async fn cancel_chat_completion(
+    state: State<'_, AppState>,
+    request_id: String,
+) -> Result<bool> {
+    Ok(state.ai_service.cancel(&request_id))
+}
+
+// ============ 媒体命令 ============
 
-    // 关联到文档
+/// 开始分块上传媒体，进度通过 `on_progress` 推送给调用的窗口，返回上传 id
+#[tauri::command]
+pub async fn begin_media_upload(
+    state: State<'_, AppState>,
+    data: BeginMediaUpload,
+    on_progress: Channel<MediaUploadProgress>,
+) -> Result<String> {
     state
         .media_service
-        .link_to_file(&file_id, &asset.id)
+        .begin_upload(data, move |progress| {
+            let _ = on_progress.send(progress);
+        })
         .await
-        .map_err(|e| e.to_string())?;
+}
 
-    Ok(asset)
+/// 追加上传分块，请求体为原始字节，`x-upload-id` 和 `x-upload-offset` 请求头指定上传和偏移
+#[tauri::command]
+pub async fn append_media_chunk(state: State<'_, AppState>, request: Request<'_>) -> Result<i64> {
+    let InvokeBody::Raw(chunk) = request.body() else {
+        return Err(AppError::Validation(
+            "Chunk must be sent as raw bytes".to_string(),
+        ));
+    };
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| AppError::Validation(format!("Missing header: {}", name)))
+    };
+    let upload_id = header("x-upload-id")?;
+    let offset = header("x-upload-offset")?
+        .parse()
+        .map_err(|_| AppError::Validation("Invalid x-upload-offset header".to_string()))?;
+
+    state
+        .media_service
+        .append_upload(upload_id, offset, chunk.clone())
+        .await
 }
 
-/// 获取媒体信息
+/// 完成分块上传：校验大小和 SHA-256（可选）后创建媒体并关联到文档
 #[tauri::command]
-pub async fn get_media(
+pub async fn finish_media_upload(
     state: State<'_, AppState>,
-    id: String,
-) -> Result<Option<MediaAsset>, String> {
+    upload_id: String,
+    sha256: Option<String>,
+) -> Result<MediaAsset> {
     state
         .media_service
-        .get(&id)
+        .finish_upload(&upload_id, sha256.as_deref())
         .await
-        .map_err(|e| e.to_string())
+}
+
+/// 取消分块上传，返回上传是否存在
+#[tauri::command]
+pub async fn abort_media_upload(state: State<'_, AppState>, upload_id: String) -> Result<bool> {
+    Ok(state.media_service.abort_upload(&upload_id).await)
+}
+
+/// 获取媒体信息
+#[tauri::command]
+pub async fn get_media(state: State<'_, AppState>, id: String) -> Result<Option<MediaAsset>> {
+    state.media_service.get(&id).await
 }
 
 /// 获取媒体文件的实际路径（用于渲染）
 #[tauri::command]
-pub async fn get_media_path(state: State<'_, AppState>, id: String) -> Result<String, String> {
+pub async fn get_media_path(state: State<'_, AppState>, id: String) -> Result<String> {
     let asset = state
         .media_service
         .get(&id)
-        .await
-        .map_err(|e| e.to_string())?
-        .ok_or("Media not found")?;
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Media not found: {}", id)))?;
 
     Ok(asset.file_path)
 }
 
-/// 获取文档关联的所有媒体
+/// 读取图片媒体的格式、尺寸、EXIF 方向、颜色类型和动画帧数
 #[tauri::command]
-pub async fn list_file_media(
+pub async fn get_media_image_info(
     state: State<'_, AppState>,
-    file_id: String,
-) -> Result<Vec<MediaAsset>, String> {
-    state
-        .media_service
-        .get_file_media(&file_id)
-        .await
-        .map_err(|e| e.to_string())
+    id: String,
+) -> Result<Option<ImageInfo>> {
+    state.media_service.image_info(&id).await
 }
 
-/// 删除媒体
+/// 获取媒体缩略图的路径，缩略图不存在时生成；SVG 和小图返回原图路径
 #[tauri::command]
-pub async fn delete_media(state: State<'_, AppState>, id: String) -> Result<(), String> {
-    // 获取媒体信息
-    if let Some(asset) = state
-        .media_service
-        .get(&id)
-        .await
-        .map_err(|e| e.to_string())?
-    {
-        // 删除文件
-        let _ = std::fs::remove_file(&asset.file_path);
-    }
-
-    // 从数据库删除
+pub async fn get_media_thumbnail(
+    state: State<'_, AppState>,
+    id: String,
+    size: Option<ThumbnailSize>,
+) -> Result<String> {
     state
         .media_service
-        .delete(&id)
+        .get_thumbnail(&id, size.unwrap_or_default())
         .await
-        .map_err(|e| e.to_string())
 }
 
-/// 获取图片尺寸
-fn get_image_dimensions(data: &[u8]) -> Option<(Option<i32>, Option<i32>)> {
-    // 简单解析 PNG/JPEG 头部获取尺寸
-    if data.len() < 24 {
-        return None;
-    }
+/// 为已有的图片媒体补生成缩略图
+#[tauri::command]
+pub async fn generate_media_thumbnails(
+    state: State<'_, AppState>,
+) -> Result<ThumbnailBackfillReport> {
+    state.media_service.backfill_thumbnails().await
+}
 
-    // PNG
-    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
-        let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]) as i32;
-        let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]) as i32;
-        return Some((Some(width), Some(height)));
-    }
+/// 获取文档关联的所有媒体
+#[tauri::command]
+pub async fn list_file_media(
+    state: State<'_, AppState>,
+    file_id: String,
+) -> Result<Vec<MediaAsset>> {
+    state.media_service.get_file_media(&file_id).await
+}
 
-    // JPEG - 需要查找 SOF0 标记
-    if data.starts_with(&[0xFF, 0xD8]) {
-        let mut i = 2;
-        while i < data.len() - 9 {
-            if data[i] == 0xFF {
-                let marker = data[i + 1];
-                // SOF0, SOF1, SOF2 标记
-                if marker >= 0xC0 && marker <= 0xC2 {
-                    let height = u16::from_be_bytes([data[i + 5], data[i + 6]]) as i32;
-                    let width = u16::from_be_bytes([data[i + 7], data[i + 8]]) as i32;
-                    return Some((Some(width), Some(height)));
-                }
-                if marker == 0xD9 || marker == 0xDA {
-                    break;
-                }
-                let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
-                i += 2 + len;
-            } else {
-                i += 1;
-            }
-        }
-    }
+/// 删除媒体，磁盘文件没有被其他媒体使用时一并删除
+#[tauri::command]
+pub async fn delete_media(state: State<'_, AppState>, id: String) -> Result<()> {
+    state.media_service.delete(&id).await
+}
 
-    None
+/// 清理没有被任何文档引用的媒体，`dry_run` 时只返回报告
+#[tauri::command]
+pub async fn collect_media_garbage(
+    state: State<'_, AppState>,
+    dry_run: bool,
+) -> Result<MediaGcReport> {
+    state.media_service.collect_garbage(dry_run).await
 }
@@ -27,3 +27,18 @@ impl Database {
         &self.pool
     }
 }
+
+/// 测试用的内存数据库，已运行全部迁移
+#[cfg(test)]
+pub async fn memory_pool() -> Pool<Sqlite> {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    // 每个连接都是独立的内存数据库，只使用一个连接
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
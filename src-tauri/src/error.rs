@@ -0,0 +1,103 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use thiserror::Error;
+
+/// 应用统一错误类型
+///
+/// 返回给前端时序列化为 `{ "code": "NOT_FOUND", "message": "..." }`，
+/// `code` 是稳定的机器可读标识，`message` 仅用于展示。
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    Validation(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Database(sqlx::Error),
+
+    #[error("{0}")]
+    Ai(String),
+
+    #[error("{0}")]
+    Cancelled(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+pub type Result<T, E = AppError> = std::result::Result<T, E>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::Validation(_) => "VALIDATION",
+            AppError::Io(_) => "IO",
+            AppError::Database(_) => "DATABASE",
+            AppError::Ai(_) => "AI",
+            AppError::Cancelled(_) => "CANCELLED",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        use sqlx::error::ErrorKind;
+
+        match &e {
+            sqlx::Error::RowNotFound => AppError::NotFound("Record not found".to_string()),
+            sqlx::Error::Database(db) => match db.kind() {
+                ErrorKind::UniqueViolation | ErrorKind::ForeignKeyViolation => {
+                    AppError::Conflict(db.message().to_string())
+                }
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                    AppError::Validation(db.message().to_string())
+                }
+                _ => AppError::Database(e),
+            },
+            _ => AppError::Database(e),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => AppError::Io(e),
+            _ => AppError::Validation(format!("Invalid archive: {}", e)),
+        }
+    }
+}
+
+impl From<image::ImageError> for AppError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => AppError::Io(e),
+            _ => AppError::Validation(format!("Unsupported image: {}", e)),
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
@@ -1,7 +1,12 @@
 mod commands;
 mod db;
+mod error;
+mod logger;
+mod markdown;
 mod models;
+mod sanitize;
 mod services;
+mod text;
 
 use commands::*;
 use db::Database;
@@ -12,14 +17,25 @@ use tauri::Manager;
 pub struct AppState {
     pub workspace_service: WorkspaceService,
     pub file_service: FileService,
+    pub folder_service: FolderService,
+    pub revision_service: RevisionService,
     pub tag_service: TagService,
+    pub trash_service: TrashService,
     pub search_service: SearchService,
+    pub embedding_service: EmbeddingService,
+    pub ai_service: AiService,
+    pub provider_service: ProviderService,
+    pub settings_service: SettingsService,
+    pub archive_service: ArchiveService,
+    pub vault_service: VaultService,
     pub media_service: MediaService,
     pub media_dir: PathBuf,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logger::init();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
@@ -32,6 +48,9 @@ pub fn run() {
             // 创建数据库路径
             let db_path: PathBuf = app_dir.join("ai_editor.db");
 
+            // AI 提供商 API Key 的加密密钥文件
+            let provider_key_path = app_dir.join("provider.key");
+
             // 创建媒体存储目录
             let media_dir = app_dir.join("media");
             std::fs::create_dir_all(&media_dir).expect("Failed to create media directory");
@@ -48,12 +67,58 @@ pub fn run() {
                 let app_state = AppState {
                     workspace_service: WorkspaceService::new(pool.clone()),
                     file_service: FileService::new(pool.clone()),
+                    folder_service: FolderService::new(pool.clone()),
+                    revision_service: RevisionService::new(pool.clone()),
                     tag_service: TagService::new(pool.clone()),
+                    trash_service: TrashService::new(pool.clone(), media_dir.clone()),
                     search_service: SearchService::new(pool.clone()),
-                    media_service: MediaService::new(pool.clone()),
+                    embedding_service: EmbeddingService::new(
+                        pool.clone(),
+                        provider_key_path.clone(),
+                    ),
+                    ai_service: AiService::new(),
+                    provider_service: ProviderService::new(pool.clone(), provider_key_path),
+                    settings_service: SettingsService::new(pool.clone()),
+                    archive_service: ArchiveService::new(pool.clone(), media_dir.clone()),
+                    vault_service: VaultService::new(pool.clone(), media_dir.clone()),
+                    media_service: MediaService::new(pool.clone(), media_dir.clone()),
                     media_dir,
                 };
 
+                // 数据维护任务失败时只记录日志，不影响启动，下次启动会重试：
+                // 为旧数据补算统计信息、为旧媒体计算哈希并合并重复的媒体、
+                // 彻底删除回收站中超过保留天数的项目
+                if let Err(e) = app_state.file_service.backfill_stats().await {
+                    log::error!("Failed to backfill file stats: {}", e);
+                }
+                if let Err(e) = app_state.media_service.backfill_hashes().await {
+                    log::error!("Failed to backfill media hashes: {}", e);
+                }
+                if let Err(e) = app_state.trash_service.purge_expired().await {
+                    log::error!("Failed to purge expired trash: {}", e);
+                }
+
+                // 按当前分词规则检查并重建全文索引
+                app_state
+                    .search_service
+                    .ensure_index()
+                    .await
+                    .expect("Failed to build search index");
+
+                // 读取向量化后端配置
+                app_state
+                    .embedding_service
+                    .load_config()
+                    .await
+                    .expect("Failed to load embedding config");
+
+                // 读取应用设置
+                app_state
+                    .settings_service
+                    .load()
+                    .await
+                    .expect("Failed to load settings");
+
                 // 管理状态
                 app.manage(app_state);
             });
@@ -64,19 +129,49 @@ pub fn run() {
             // 窗口管理
             open_editor_window,
             open_settings_window,
+            // 设置
+            get_settings,
+            update_settings,
             // 工作空间
             create_workspace,
             get_workspace,
             list_workspaces,
             update_workspace,
             delete_workspace,
+            // 导入导出
+            export_workspace,
+            import_workspace,
+            import_markdown_files,
+            export_file_as_markdown,
+            import_vault,
             // 文件
             create_file,
             get_file,
             list_files_by_workspace,
             list_files_by_type,
+            list_files,
             update_file,
             delete_file,
+            // 历史版本
+            list_file_revisions,
+            get_file_revision,
+            create_file_revision,
+            restore_file_revision,
+            diff_file_revisions,
+            // 文件夹
+            create_folder,
+            rename_folder,
+            move_folder,
+            delete_folder,
+            move_files_to_folder,
+            get_folder_tree,
+            // 回收站
+            list_trash,
+            restore_file,
+            restore_workspace,
+            empty_trash,
+            get_trash_config,
+            set_trash_config,
             // 标签
             create_tag,
             get_tag,
@@ -89,12 +184,34 @@ pub fn run() {
             search_files,
             search_by_tags,
             find_similar_files,
+            semantic_search,
+            retrieve_context,
+            index_embeddings,
+            get_embedding_config,
+            set_embedding_config,
+            // AI
+            list_providers,
+            get_provider,
+            create_provider,
+            update_provider,
+            delete_provider,
+            set_default_provider,
+            test_provider_connection,
+            chat_completion,
+            cancel_chat_completion,
             // 媒体
-            upload_media,
+            begin_media_upload,
+            append_media_chunk,
+            finish_media_upload,
+            abort_media_upload,
             get_media,
             get_media_path,
+            get_media_image_info,
+            get_media_thumbnail,
+            generate_media_thumbnails,
             list_file_media,
             delete_media,
+            collect_media_garbage,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,44 @@
+//! 后端日志：通过 `log` 宏记录，输出到标准错误
+
+use chrono::Utc;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// 本程序的日志输出 info 及以上级别，依赖库（如 sqlx 的语句日志）只输出 warn 及以上级别
+fn max_level(target: &str) -> Level {
+    if target.starts_with(env!("CARGO_CRATE_NAME")) {
+        Level::Info
+    } else {
+        Level::Warn
+    }
+}
+
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= max_level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{} {:<5} {}: {}",
+                Utc::now().to_rfc3339(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// 安装日志记录器，重复调用时忽略
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
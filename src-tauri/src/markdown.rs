@@ -0,0 +1,1073 @@
+//! HTML 与 Markdown 转换
+
+use ego_tree::NodeRef;
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use scraper::{ElementRef, Html, Node};
+
+/// 把编辑器保存的 HTML 转换为 CommonMark（含 GFM 删除线、任务列表和表格）
+///
+/// 只处理编辑器会产生的元素，无法表示的元素（如下划线）保留其文本。
+pub fn html_to_markdown(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let blocks = render_blocks(*fragment.root_element());
+
+    let mut markdown = blocks.join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// 把 Markdown（CommonMark + GFM 表格、删除线、任务列表）转换为编辑器的 HTML
+///
+/// 输出与 TipTap 保存的结构一致：列表项内容包在 `<p>` 中，图片为块级的 `<img data-media-src>`，
+/// 任务列表使用 `data-type="taskList"`。front matter 被忽略，内嵌的 HTML 按文本保留。
+pub fn markdown_to_html(markdown: &str) -> String {
+    render_html(markdown, parser_options(), None)
+}
+
+/// 导入笔记库时改写图片和链接地址
+pub trait LinkResolver {
+    /// 图片地址，返回 None 时保留原地址
+    fn image(&self, src: &str) -> Option<String>;
+
+    /// 普通链接地址，返回 None 时保留原地址
+    fn link(&self, href: &str) -> Option<String>;
+
+    /// `[[wiki link]]` 的地址，返回 None 时只保留链接文字
+    fn wiki_link(&self, target: &str) -> Option<String>;
+}
+
+/// 同 [`markdown_to_html`]，另外支持 `[[wiki link]]` 和 `![[嵌入]]`，地址由 `resolver` 改写
+///
+/// 无法作为图片解析的 `![[嵌入]]`（如嵌入其他笔记）按 wiki link 处理。
+pub fn markdown_to_html_with(markdown: &str, resolver: &dyn LinkResolver) -> String {
+    render_html(
+        markdown,
+        parser_options() | Options::ENABLE_WIKILINKS,
+        Some(resolver),
+    )
+}
+
+/// 笔记中引用的图片地址（包括 `![[嵌入]]`），按出现顺序去重
+pub fn markdown_image_sources(markdown: &str) -> Vec<String> {
+    let mut sources: Vec<String> = Vec::new();
+
+    for event in Parser::new_ext(markdown, parser_options() | Options::ENABLE_WIKILINKS) {
+        if let Event::Start(Tag::Image { dest_url, .. }) = event {
+            if !sources.iter().any(|s| *s == *dest_url) {
+                sources.push(dest_url.to_string());
+            }
+        }
+    }
+
+    sources
+}
+
+fn render_html(markdown: &str, options: Options, resolver: Option<&dyn LinkResolver>) -> String {
+    let events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+
+    let mut writer = HtmlWriter {
+        resolver,
+        ..HtmlWriter::default()
+    };
+    for (index, event) in events.iter().enumerate() {
+        writer.event(event, &events[index + 1..]);
+    }
+
+    writer.out.replace("<p></p>", "")
+}
+
+/// 笔记开头的元数据：YAML front matter 或 Logseq 的 `key:: value` 页面属性
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// 拆出笔记开头的元数据，返回元数据和正文
+///
+/// 只解析 `title` 和 `tags`（`tag`）两个字段，支持 `tags: [a, b]`、`tags: a b` 和多行列表。
+pub fn split_front_matter(markdown: &str) -> (FrontMatter, &str) {
+    let text = markdown.strip_prefix('\u{feff}').unwrap_or(markdown);
+
+    if let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    {
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            let trimmed = line.trim_end();
+            if trimmed == "---" || trimmed == "..." {
+                let yaml = &rest[..offset];
+                return (parse_yaml_front_matter(yaml), &rest[offset + line.len()..]);
+            }
+            offset += line.len();
+        }
+        return (FrontMatter::default(), text);
+    }
+
+    parse_logseq_properties(text)
+}
+
+fn parse_yaml_front_matter(yaml: &str) -> FrontMatter {
+    let mut front_matter = FrontMatter::default();
+    let mut list_key: Option<String> = None;
+
+    for line in yaml.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        // 多行列表的项：`  - value`
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if line.starts_with([' ', '\t', '-']) && list_key.as_deref() == Some("tags") {
+                push_tag(&mut front_matter.tags, &unquote(item.trim()));
+            }
+            continue;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+
+        list_key = None;
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        match key.as_str() {
+            "title" if !value.is_empty() => front_matter.title = Some(unquote(value)),
+            "tags" | "tag" if value.is_empty() => list_key = Some("tags".to_string()),
+            "tags" | "tag" => match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(items) => {
+                    for item in items.split(',') {
+                        push_tag(&mut front_matter.tags, &unquote(item.trim()));
+                    }
+                }
+                // `tags: a, b` 或 `tags: a b`
+                None => {
+                    for item in value.split([',', ' ']) {
+                        push_tag(&mut front_matter.tags, &unquote(item.trim()));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    front_matter
+}
+
+/// Logseq 页面属性位于文件开头，每行一个 `key:: value`
+fn parse_logseq_properties(text: &str) -> (FrontMatter, &str) {
+    let mut front_matter = FrontMatter::default();
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let Some((key, value)) = line.trim_end().split_once(":: ") else {
+            break;
+        };
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            break;
+        }
+
+        match key.to_lowercase().as_str() {
+            "title" => front_matter.title = Some(value.trim().to_string()),
+            "tags" => {
+                for item in value.split(',') {
+                    push_tag(&mut front_matter.tags, item);
+                }
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    (front_matter, &text[offset..])
+}
+
+/// 标签值：去掉 `#` 和 `[[ ]]`
+fn tag_name(value: &str) -> Option<String> {
+    let value = value.trim().trim_start_matches('#');
+    let value = value
+        .strip_prefix("[[")
+        .and_then(|v| v.strip_suffix("]]"))
+        .unwrap_or(value)
+        .trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn push_tag(tags: &mut Vec<String>, value: &str) {
+    if let Some(tag) = tag_name(value) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+}
+
+/// 去掉 YAML 字符串的引号，双引号字符串按 JSON 规则处理转义
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return serde_json::from_str(value)
+            .unwrap_or_else(|_| value[1..value.len() - 1].to_string());
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+    value.to_string()
+}
+
+/// 第一个标题的文本，用作导入文档的标题
+pub fn markdown_title(markdown: &str) -> Option<String> {
+    let mut title: Option<String> = None;
+
+    for event in Parser::new_ext(markdown, parser_options()) {
+        match (&mut title, event) {
+            (None, Event::Start(Tag::Heading { .. })) => title = Some(String::new()),
+            (Some(title), Event::Text(text) | Event::Code(text)) => title.push_str(&text),
+            (Some(title), Event::SoftBreak) => title.push(' '),
+            (Some(title), Event::End(TagEnd::Heading(_))) => {
+                let text = title.trim();
+                return (!text.is_empty()).then(|| text.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parser_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+}
+
+#[derive(Default)]
+struct HtmlWriter<'a> {
+    resolver: Option<&'a dyn LinkResolver>,
+    out: String,
+    /// 打开的列表是否为任务列表
+    lists: Vec<bool>,
+    /// 当前位置可以直接写入行内内容（段落、标题、单元格中）
+    inline: bool,
+    /// 当前段落是为紧凑列表项补上的
+    implicit: bool,
+    /// 当前在 `<p>` 中（图片需要先结束段落）
+    paragraph: bool,
+    table_head: bool,
+    code: Option<String>,
+    /// 收集替代文字中的图片
+    image: Option<(Embed, String)>,
+    /// 打开的链接是否写出了 `<a>`（无法解析的 wiki link 只保留文字）
+    links: Vec<bool>,
+    metadata: bool,
+}
+
+enum Embed {
+    Image(String),
+    Link(Option<String>),
+}
+
+impl HtmlWriter<'_> {
+    /// `rest` 为当前事件之后的事件，用于判断列表项是否为任务项
+    fn event(&mut self, event: &Event, rest: &[Event]) {
+        if self.metadata {
+            self.metadata = !matches!(event, Event::End(TagEnd::MetadataBlock(_)));
+            return;
+        }
+
+        if let Some(code) = &mut self.code {
+            match event {
+                Event::Text(text) => code.push_str(text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let code = self.code.take().unwrap_or_default();
+                    let code = code.strip_suffix('\n').unwrap_or(&code);
+                    self.out.push_str(&escape_html_text(code));
+                    self.out.push_str("</code></pre>");
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some((_, alt)) = &mut self.image {
+            match event {
+                Event::Text(text) | Event::Code(text) => alt.push_str(text),
+                Event::End(TagEnd::Image) => match self.image.take() {
+                    Some((Embed::Image(src), alt)) => self.write_image(&src, &alt),
+                    Some((Embed::Link(Some(href)), text)) => {
+                        self.open_tag(&format!("<a href=\"{}\">", escape_html(&href)));
+                        self.text(&text);
+                        self.out.push_str("</a>");
+                    }
+                    Some((Embed::Link(None), text)) => self.text(&text),
+                    None => {}
+                },
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag, rest),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(text),
+            Event::Code(code) => {
+                self.open_inline();
+                self.out.push_str("<code>");
+                self.out.push_str(&escape_html_text(code));
+                self.out.push_str("</code>");
+            }
+            Event::InlineMath(math) => self.text(&format!("${}$", math)),
+            Event::DisplayMath(math) => self.text(&format!("$${}$$", math)),
+            Event::Html(html) => self.text(html.trim_end_matches('\n')),
+            Event::InlineHtml(html) => {
+                if matches!(html.trim(), "<br>" | "<br/>" | "<br />") {
+                    self.out.push_str("<br>");
+                } else {
+                    self.text(html);
+                }
+            }
+            Event::FootnoteReference(name) => self.text(&format!("[^{}]", name)),
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => {
+                self.open_inline();
+                self.out.push_str("<br>");
+            }
+            Event::Rule => {
+                self.close_implicit();
+                self.out.push_str("<hr>");
+            }
+            Event::TaskListMarker(_) => {}
+        }
+    }
+
+    fn start(&mut self, tag: &Tag, rest: &[Event]) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => {
+                self.close_implicit();
+                self.out.push_str("<p>");
+                self.inline = true;
+                self.paragraph = true;
+            }
+            Tag::Heading { level, .. } => {
+                self.close_implicit();
+                self.out.push_str(&format!("<{}>", level));
+                self.inline = true;
+            }
+            Tag::BlockQuote(_) => {
+                self.close_implicit();
+                self.out.push_str("<blockquote>");
+            }
+            Tag::CodeBlock(kind) => {
+                self.close_implicit();
+                match kind {
+                    CodeBlockKind::Fenced(info) => match info.split_whitespace().next() {
+                        Some(language) => self.out.push_str(&format!(
+                            "<pre><code class=\"language-{}\">",
+                            escape_html(language)
+                        )),
+                        None => self.out.push_str("<pre><code>"),
+                    },
+                    CodeBlockKind::Indented => self.out.push_str("<pre><code>"),
+                }
+                self.code = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.close_implicit();
+                // 编辑器的列表不能混合普通项和任务项，按第一项决定
+                let task = task_marker(rest).is_some();
+                self.lists.push(task);
+                match start {
+                    _ if task => self.out.push_str("<ul data-type=\"taskList\">"),
+                    Some(1) => self.out.push_str("<ol>"),
+                    Some(start) => self.out.push_str(&format!("<ol start=\"{}\">", start)),
+                    None => self.out.push_str("<ul>"),
+                }
+            }
+            Tag::Item => {
+                self.close_implicit();
+                if self.lists.last() == Some(&true) {
+                    let checked = task_marker(rest).unwrap_or(false);
+                    self.out.push_str(&format!(
+                        "<li data-type=\"taskItem\" data-checked=\"{}\"><label><input type=\"checkbox\"{}><span></span></label><div>",
+                        checked,
+                        if checked { " checked=\"checked\"" } else { "" }
+                    ));
+                } else {
+                    self.out.push_str("<li>");
+                }
+            }
+            Tag::Table(_) => {
+                self.close_implicit();
+                self.out.push_str("<table><tbody>");
+            }
+            Tag::TableHead => {
+                self.table_head = true;
+                self.out.push_str("<tr>");
+            }
+            Tag::TableRow => self.out.push_str("<tr>"),
+            Tag::TableCell => {
+                self.out.push_str(if self.table_head {
+                    "<th><p>"
+                } else {
+                    "<td><p>"
+                });
+                self.inline = true;
+            }
+            Tag::Emphasis => self.open_tag("<em>"),
+            Tag::Strong => self.open_tag("<strong>"),
+            Tag::Strikethrough => self.open_tag("<s>"),
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                let href = match (link_type, self.resolver) {
+                    (LinkType::WikiLink { .. }, resolver) => {
+                        resolver.and_then(|r| r.wiki_link(dest_url))
+                    }
+                    (_, Some(resolver)) => {
+                        Some(resolver.link(dest_url).unwrap_or(dest_url.to_string()))
+                    }
+                    (_, None) => Some(dest_url.to_string()),
+                };
+
+                self.links.push(href.is_some());
+                if let Some(href) = href {
+                    self.open_tag(&format!("<a href=\"{}\">", escape_html(&href)));
+                }
+            }
+            Tag::Image {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                let src = self.resolver.and_then(|r| r.image(dest_url));
+                let embed = match (src, link_type) {
+                    (Some(src), _) => Embed::Image(src),
+                    (None, LinkType::WikiLink { .. }) => {
+                        Embed::Link(self.resolver.and_then(|r| r.wiki_link(dest_url)))
+                    }
+                    (None, _) => Embed::Image(dest_url.to_string()),
+                };
+                self.image = Some((embed, String::new()));
+            }
+            Tag::MetadataBlock(_) => self.metadata = true,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: &TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => {
+                self.out.push_str("</p>");
+                self.inline = false;
+                self.paragraph = false;
+            }
+            TagEnd::Heading(level) => {
+                self.out.push_str(&format!("</{}>", level));
+                self.inline = false;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.close_implicit();
+                self.out.push_str("</blockquote>");
+            }
+            TagEnd::List(ordered) => {
+                self.close_implicit();
+                let task = self.lists.pop().unwrap_or(false);
+                self.out
+                    .push_str(if *ordered && !task { "</ol>" } else { "</ul>" });
+            }
+            TagEnd::Item => {
+                self.close_implicit();
+                if self.lists.last() == Some(&true) {
+                    self.out.push_str("</div></li>");
+                } else {
+                    self.out.push_str("</li>");
+                }
+            }
+            TagEnd::Table => self.out.push_str("</tbody></table>"),
+            TagEnd::TableHead => {
+                self.table_head = false;
+                self.out.push_str("</tr>");
+            }
+            TagEnd::TableRow => self.out.push_str("</tr>"),
+            TagEnd::TableCell => {
+                self.out.push_str(if self.table_head {
+                    "</p></th>"
+                } else {
+                    "</p></td>"
+                });
+                self.inline = false;
+            }
+            TagEnd::Emphasis => self.out.push_str("</em>"),
+            TagEnd::Strong => self.out.push_str("</strong>"),
+            TagEnd::Strikethrough => self.out.push_str("</s>"),
+            TagEnd::Link if self.links.pop().unwrap_or(true) => self.out.push_str("</a>"),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        self.open_inline();
+        self.out.push_str(&escape_html_text(text));
+    }
+
+    fn open_tag(&mut self, tag: &str) {
+        self.open_inline();
+        self.out.push_str(tag);
+    }
+
+    /// 紧凑列表项的内容没有段落，编辑器要求列表项以段落开始
+    fn open_inline(&mut self) {
+        if !self.inline {
+            self.out.push_str("<p>");
+            self.inline = true;
+            self.implicit = true;
+            self.paragraph = true;
+        }
+    }
+
+    fn close_implicit(&mut self) {
+        if self.implicit {
+            self.out.push_str("</p>");
+            self.inline = false;
+            self.implicit = false;
+            self.paragraph = false;
+        }
+    }
+
+    /// 编辑器的图片是块级节点，位于段落中时把段落拆开
+    fn write_image(&mut self, src: &str, alt: &str) {
+        let src = escape_html(src);
+        let image = format!(
+            "<img mediasrc=\"{}\" alt=\"{}\" data-media-src=\"{}\">",
+            src,
+            escape_html(alt),
+            src
+        );
+
+        if !self.paragraph {
+            self.out.push_str(&image);
+        } else if self.implicit {
+            self.close_implicit();
+            self.out.push_str(&image);
+        } else {
+            self.out.push_str("</p>");
+            self.out.push_str(&image);
+            self.out.push_str("<p>");
+        }
+    }
+}
+
+/// 列表项开头的任务标记（`[ ]` 或 `[x]`）
+fn task_marker(rest: &[Event]) -> Option<bool> {
+    rest.iter()
+        .find(|e| !matches!(e, Event::Start(Tag::Item | Tag::Paragraph)))
+        .and_then(|e| match e {
+            Event::TaskListMarker(checked) => Some(*checked),
+            _ => None,
+        })
+}
+
+/// 转义属性值
+pub(crate) fn escape_html(text: &str) -> String {
+    escape_html_text(text).replace('"', "&quot;")
+}
+
+/// 转义文本节点（与编辑器一致，引号不转义）
+fn escape_html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 渲染块级内容，每个元素是一个段落、标题、列表等
+fn render_blocks(parent: NodeRef<Node>) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut inline = String::new();
+
+    for child in parent.children() {
+        let Some(element) = ElementRef::wrap(child) else {
+            if let Node::Text(text) = child.value() {
+                inline.push_str(&escape_text(text));
+            }
+            continue;
+        };
+
+        let name = element.value().name();
+
+        // 编辑器的图片是块级节点，直接位于段落之间
+        if name == "img" {
+            flush_paragraph(&mut inline, &mut blocks);
+            blocks.push(render_inline(child));
+            continue;
+        }
+
+        if !is_block(name) {
+            inline.push_str(&render_inline(child));
+            continue;
+        }
+
+        flush_paragraph(&mut inline, &mut blocks);
+
+        match name {
+            "p" => {
+                let mut text = render_inline(child);
+                flush_paragraph(&mut text, &mut blocks);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = render_inline(child).replace('\n', " ");
+                let text = text.trim();
+                if !text.is_empty() {
+                    blocks.push(format!("{} {}", "#".repeat(level), text));
+                }
+            }
+            "blockquote" => {
+                let inner = render_blocks(child).join("\n\n");
+                if !inner.is_empty() {
+                    blocks.push(prefix_lines(&inner, "> ", ">"));
+                }
+            }
+            "pre" => blocks.push(render_code_block(element)),
+            "ul" | "ol" => {
+                let list = render_list(element, name == "ol");
+                if !list.is_empty() {
+                    blocks.push(list);
+                }
+            }
+            "table" => {
+                let table = render_table(element);
+                if !table.is_empty() {
+                    blocks.push(table);
+                }
+            }
+            "hr" => blocks.push("---".to_string()),
+            // 任务列表项中的复选框由列表标记表示
+            "label" if contains_input(element) => {}
+            _ => blocks.extend(render_blocks(child)),
+        }
+    }
+
+    flush_paragraph(&mut inline, &mut blocks);
+    blocks
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "header"
+            | "footer"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "blockquote"
+            | "pre"
+            | "ul"
+            | "ol"
+            | "li"
+            | "table"
+            | "hr"
+            | "label"
+            | "body"
+            | "html"
+    )
+}
+
+/// 把累积的行内内容作为一个段落输出
+fn flush_paragraph(inline: &mut String, blocks: &mut Vec<String>) {
+    let text = inline.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    let text = text.trim();
+    if !text.is_empty() {
+        blocks.push(escape_line_start(text));
+    }
+    inline.clear();
+}
+
+/// 渲染行内内容
+fn render_inline(node: NodeRef<Node>) -> String {
+    let Some(element) = ElementRef::wrap(node) else {
+        return match node.value() {
+            Node::Text(text) => escape_text(text),
+            _ => String::new(),
+        };
+    };
+
+    let children = || node.children().map(render_inline).collect::<String>();
+
+    match element.value().name() {
+        "strong" | "b" => wrap(&children(), "**"),
+        "em" | "i" => wrap(&children(), "*"),
+        "s" | "del" | "strike" => wrap(&children(), "~~"),
+        "code" => inline_code(&element.text().collect::<String>()),
+        "br" => "\\\n".to_string(),
+        "a" => {
+            let text = children();
+            match element.value().attr("href") {
+                Some(href) if !href.is_empty() => {
+                    format!("[{}]({})", text.trim(), escape_url(href))
+                }
+                _ => text,
+            }
+        }
+        "img" => {
+            let attr = |name| element.value().attr(name).filter(|v| !v.is_empty());
+            let src = attr("src")
+                .or_else(|| attr("data-media-src"))
+                .or_else(|| attr("mediasrc"))
+                .unwrap_or_default();
+            let alt = attr("alt").unwrap_or_default();
+            format!("![{}]({})", escape_text(alt), escape_url(src))
+        }
+        "input" => String::new(),
+        _ => children(),
+    }
+}
+
+/// 用强调标记包裹内容，首尾空白移到标记外（`** a**` 不是合法的强调）
+fn wrap(content: &str, marker: &str) -> String {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return content.to_string();
+    }
+
+    let leading = &content[..content.len() - content.trim_start().len()];
+    let trailing = &content[content.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+/// 行内代码：反引号数量多于内容中最长的连续反引号
+fn inline_code(code: &str) -> String {
+    if code.is_empty() {
+        return String::new();
+    }
+
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let padding = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", fence, padding, code, padding, fence)
+}
+
+fn render_code_block(pre: ElementRef) -> String {
+    let code = pre.text().collect::<String>();
+    let code = code.strip_suffix('\n').unwrap_or(&code);
+
+    // TipTap 的代码块为 <pre><code class="language-xxx">
+    let language = pre
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "code")
+        .and_then(|e| {
+            e.value()
+                .classes()
+                .find_map(|c| c.strip_prefix("language-"))
+                .map(str::to_string)
+        })
+        .unwrap_or_default();
+
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn render_list(list: ElementRef, ordered: bool) -> String {
+    let start = list
+        .value()
+        .attr("start")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    let mut items = Vec::new();
+    for (index, item) in list
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "li")
+        .enumerate()
+    {
+        let mut marker = if ordered {
+            format!("{}. ", start + index)
+        } else {
+            "- ".to_string()
+        };
+
+        // 任务列表项：<li data-type="taskItem" data-checked="true">
+        let task = match item.value().attr("data-checked") {
+            Some("true") => "[x] ",
+            Some(_) => "[ ] ",
+            None => "",
+        };
+
+        let blocks = render_blocks(*item);
+        let mut content = String::new();
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                // 嵌套列表紧跟在上一行后，其他块之间空一行
+                let nested = block.starts_with("- ") || starts_with_ordered_marker(block);
+                content.push_str(if nested { "\n" } else { "\n\n" });
+            }
+            content.push_str(block);
+        }
+
+        let indent = " ".repeat(marker.len());
+        marker.push_str(task);
+        let body = prefix_lines(&content, &indent, "");
+        items.push(format!("{}{}", marker, body.trim_start()));
+    }
+
+    items.join("\n")
+}
+
+fn render_table(table: ElementRef) -> String {
+    let rows: Vec<Vec<String>> = table
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "tr")
+        .map(|row| {
+            row.children()
+                .filter_map(ElementRef::wrap)
+                .filter(|c| matches!(c.value().name(), "td" | "th"))
+                .map(|cell| {
+                    let text = render_blocks(*cell).join(" ");
+                    text.replace('\n', " ").replace('|', "\\|")
+                })
+                .collect()
+        })
+        .filter(|row: &Vec<String>| !row.is_empty())
+        .collect();
+
+    let Some(columns) = rows.iter().map(Vec::len).max() else {
+        return String::new();
+    };
+
+    let line = |cells: &[String]| {
+        let mut cells = cells.to_vec();
+        cells.resize(columns, String::new());
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut lines = vec![line(&rows[0]), line(&vec!["---".to_string(); columns])];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+/// 给每一行加前缀，空行使用 `empty_prefix`
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                empty_prefix.to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 转义文本中的 Markdown 语法字符，并按 HTML 规则把连续空白合并为一个空格
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut last_space = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !last_space {
+                escaped.push(' ');
+            }
+            last_space = true;
+            continue;
+        }
+        last_space = false;
+
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '~') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// 段落开头的 `#`、`>`、`-`、`+`、`1.` 等会被当作块级语法，需要转义
+fn escape_line_start(text: &str) -> String {
+    if text.starts_with(['#', '>', '-', '+', '=']) {
+        return format!("\\{}", text);
+    }
+
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && text[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &text[..digits], &text[digits..]);
+    }
+
+    text.to_string()
+}
+
+fn starts_with_ordered_marker(text: &str) -> bool {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && text[digits..].starts_with(". ")
+}
+
+/// 链接地址中的空格和括号会截断链接，需要编码
+fn escape_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+fn longest_run(text: &str, target: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == target {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// 是否包含 `<input>`（任务列表项的复选框）
+fn contains_input(element: ElementRef) -> bool {
+    element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .any(|e| e.value().name() == "input")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 编辑器 HTML 导出为 Markdown 再导入，应得到相同的 HTML
+    fn assert_round_trip(html: &str) {
+        let markdown = html_to_markdown(html);
+        assert_eq!(markdown_to_html(&markdown), html, "markdown:\n{}", markdown);
+        // 再导出一次，Markdown 保持不变
+        assert_eq!(html_to_markdown(&markdown_to_html(&markdown)), markdown);
+    }
+
+    #[test]
+    fn headings_round_trip() {
+        assert_round_trip("<h1>Title</h1><h2>Section</h2><h3>中文标题</h3><h6>Deep</h6>");
+        assert_round_trip("<h2>With <em>emphasis</em> and <code>code</code></h2>");
+    }
+
+    #[test]
+    fn inline_marks_round_trip() {
+        assert_round_trip(
+            "<p>Plain <strong>bold</strong> <em>italic</em> <s>struck</s> <code>a `tick`</code></p>",
+        );
+        assert_round_trip("<p># not a heading, 1. not a list, *not emphasis*</p>");
+        assert_round_trip("<p>line one<br>line two</p>");
+    }
+
+    #[test]
+    fn nested_lists_round_trip() {
+        assert_round_trip(
+            "<ul><li><p>one</p><ul><li><p>nested</p><ol><li><p>deep</p></li></ol></li></ul></li><li><p>two</p></li></ul>",
+        );
+        assert_round_trip("<ol start=\"3\"><li><p>third</p></li><li><p>fourth</p></li></ol>");
+    }
+
+    #[test]
+    fn task_lists_round_trip() {
+        assert_round_trip(concat!(
+            "<ul data-type=\"taskList\">",
+            "<li data-type=\"taskItem\" data-checked=\"true\"><label><input type=\"checkbox\" checked=\"checked\"><span></span></label><div><p>done</p></div></li>",
+            "<li data-type=\"taskItem\" data-checked=\"false\"><label><input type=\"checkbox\"><span></span></label><div><p>todo</p></div></li>",
+            "</ul>"
+        ));
+    }
+
+    #[test]
+    fn fenced_code_round_trip() {
+        assert_round_trip(
+            "<pre><code class=\"language-rust\">fn main() {\n    println!(\"&lt;hi&gt;\");\n}</code></pre>",
+        );
+        assert_round_trip("<pre><code>no language\n```\nfence inside</code></pre>");
+    }
+
+    #[test]
+    fn tables_round_trip() {
+        assert_round_trip(concat!(
+            "<table><tbody>",
+            "<tr><th><p>Name</p></th><th><p>Value</p></th></tr>",
+            "<tr><td><p>a | b</p></td><td><p><strong>1</strong></p></td></tr>",
+            "</tbody></table>"
+        ));
+    }
+
+    #[test]
+    fn links_and_images_round_trip() {
+        assert_round_trip("<p>See <a href=\"https://example.com/a%20b\">the docs</a>.</p>");
+        assert_round_trip(
+            "<img mediasrc=\"https://example.com/cat.png\" alt=\"a cat\" data-media-src=\"https://example.com/cat.png\">",
+        );
+    }
+
+    #[test]
+    fn media_refs_round_trip() {
+        let id = "0b6f7c1e-8a4d-4a53-9d0e-2f1c3b4a5d6e";
+        let html = format!(
+            "<p>before</p><img mediasrc=\"media://{id}\" alt=\"diagram\" data-media-src=\"media://{id}\"><p>after</p>"
+        );
+        assert_round_trip(&html);
+        assert!(html_to_markdown(&html).contains(&format!("![diagram](media://{})", id)));
+
+        // 只有 data-media-src 的旧内容导出为同样的 Markdown
+        let editor = format!("<img data-media-src=\"media://{id}\" alt=\"diagram\">");
+        assert_eq!(
+            html_to_markdown(&editor),
+            format!("![diagram](media://{})\n", id)
+        );
+    }
+
+    #[test]
+    fn front_matter_is_parsed_and_not_rendered() {
+        // 与导出文档时写入的 front matter 格式一致（字符串为 JSON 形式）
+        let markdown = concat!(
+            "---\n",
+            "title: \"Notes: \\\"quoted\\\" 笔记\"\n",
+            "tags: [\"rust\", \"中文\"]\n",
+            "created: \"2024-01-01T00:00:00+00:00\"\n",
+            "updated: \"2024-01-02T00:00:00+00:00\"\n",
+            "---\n\n",
+            "# Heading\n\nBody\n"
+        );
+
+        let (front_matter, body) = split_front_matter(markdown);
+        assert_eq!(
+            front_matter.title.as_deref(),
+            Some("Notes: \"quoted\" 笔记")
+        );
+        assert_eq!(front_matter.tags, ["rust", "中文"]);
+        assert_eq!(body, "\n# Heading\n\nBody\n");
+
+        let html = markdown_to_html(markdown);
+        assert_eq!(html, "<h1>Heading</h1><p>Body</p>");
+        assert_eq!(html_to_markdown(&html), "# Heading\n\nBody\n");
+        assert_eq!(markdown_title(markdown).as_deref(), Some("Heading"));
+    }
+}
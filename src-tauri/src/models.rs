@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -8,6 +9,7 @@ pub struct Workspace {
     pub description: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub deleted_at: Option<String>, // 移入回收站的时间
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +37,20 @@ pub struct File {
     pub mime_type: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub word_count: Option<i64>, // 词数（中日韩字符按字计）
+    pub char_count: Option<i64>, // 字符数（不含空白）
+    pub paragraph_count: Option<i64>,
+    pub reading_minutes: Option<i64>, // 预计阅读时间（分钟）
+    pub outline: Option<Json<Vec<OutlineHeading>>>, // 标题大纲
+    pub folder_id: Option<String>,    // 所在文件夹，None 表示根目录
+    pub deleted_at: Option<String>,   // 移入回收站的时间
+}
+
+// 文档大纲中的标题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineHeading {
+    pub level: i64, // 1-6
+    pub text: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,22 +59,185 @@ pub struct CreateFile {
     pub file_type: String,
     pub title: String,
     pub content: Option<String>,
-    pub content_plain: Option<String>,
     pub file_path: Option<String>,
     pub file_size: Option<i64>,
     pub mime_type: Option<String>,
+    pub folder_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateFile {
     pub title: Option<String>,
     pub content: Option<String>,
-    pub content_plain: Option<String>,
     pub file_path: Option<String>,
     pub file_size: Option<i64>,
     pub mime_type: Option<String>,
 }
 
+// 文件列表项（不含正文）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSummary {
+    pub id: String,
+    pub workspace_id: String,
+    pub file_type: String,
+    pub title: String,
+    pub size: i64, // 媒体为文件大小，文档为内容字节数
+    pub word_count: i64,
+    pub char_count: i64,
+    pub paragraph_count: i64,
+    pub reading_minutes: i64,
+    pub folder_id: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSortField {
+    Title,
+    Created,
+    #[default]
+    Updated,
+    Size,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFilesQuery {
+    pub workspace_id: String,
+    pub file_type: Option<String>,
+    pub sort_by: Option<FileSortField>,
+    pub direction: Option<SortDirection>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePage {
+    pub items: Vec<FileSummary>,
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+    pub has_more: bool,
+}
+
+// ============ 文件夹 ============
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Folder {
+    pub id: String,
+    pub workspace_id: String,
+    pub parent_id: Option<String>, // None 表示位于根目录
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFolder {
+    pub workspace_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+}
+
+// 删除文件夹时如何处理其中的内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderDeleteMode {
+    Cascade, // 删除子文件夹，其中的文件移入回收站
+    #[default]
+    Reparent, // 子文件夹和文件移到上一级
+}
+
+// 文件夹树中的文件（不含正文）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FolderTreeFile {
+    pub id: String,
+    pub folder_id: Option<String>,
+    pub file_type: String,
+    pub title: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: Folder,
+    pub path: String, // 从根目录开始的路径，如 `projects/web`
+    pub children: Vec<FolderNode>,
+    pub files: Vec<FolderTreeFile>,
+}
+
+// 工作空间的完整目录树
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderTree {
+    pub folders: Vec<FolderNode>,
+    pub files: Vec<FolderTreeFile>, // 根目录下的文件
+}
+
+// 文档历史版本
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FileRevision {
+    pub id: String,
+    pub file_id: String,
+    pub title: String,
+    pub content: Option<String>,
+    pub content_plain: Option<String>,
+    pub reason: String, // autosave, manual, restore
+    pub created_at: String,
+}
+
+// 历史版本列表项（不含正文）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FileRevisionSummary {
+    pub id: String,
+    pub file_id: String,
+    pub title: String,
+    pub reason: String,
+    pub char_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    #[default]
+    Paragraph,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionDiff {
+    pub from_id: String,
+    pub to_id: Option<String>, // None 表示与当前文档对比
+    pub granularity: DiffGranularity,
+    pub segments: Vec<DiffSegment>,
+    pub insertions: usize, // 新增的段落/词数
+    pub deletions: usize,  // 删除的段落/词数
+}
+
 // 媒体资源
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct MediaAsset {
@@ -71,6 +250,7 @@ pub struct MediaAsset {
     pub width: Option<i32>,
     pub height: Option<i32>,
     pub created_at: String,
+    pub content_hash: Option<String>, // 内容的 SHA-256（十六进制），磁盘文件丢失的旧媒体为空
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +262,71 @@ pub struct CreateMediaAsset {
     pub mime_type: String,
     pub width: Option<i32>,
     pub height: Option<i32>,
+    pub content_hash: Option<String>,
+}
+
+// 媒体清理结果，dry_run 时只报告不删除
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaGcReport {
+    pub dry_run: bool,
+    pub orphan_assets: Vec<MediaAsset>, // 没有任何文档（含回收站和历史版本）引用的媒体
+    pub orphan_files: Vec<String>,      // 媒体目录中没有对应记录的文件
+    pub missing_assets: Vec<String>,    // 记录存在但磁盘文件已丢失的媒体 id，只报告
+    pub links_added: i64,               // 文档中引用了但缺少的关联
+    pub links_removed: i64,             // 文档中已不再引用的关联
+    pub freed_bytes: i64,               // dry_run 时为可释放的空间
+}
+
+// 开始分块上传媒体，size 为文件的总字节数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeginMediaUpload {
+    pub workspace_id: String,
+    pub file_id: String, // 上传完成后关联的文档
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: i64,
+}
+
+// 分块上传过程中推送给调用窗口的进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaUploadProgress {
+    pub upload_id: String,
+    pub received: i64,
+    pub total: i64,
+}
+
+// 缩略图尺寸，按长边缩放
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    #[default]
+    Small, // 256px，用于网格和列表
+    Large, // 1024px，用于预览
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 2] = [ThumbnailSize::Small, ThumbnailSize::Large];
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 256,
+            ThumbnailSize::Large => 1024,
+        }
+    }
+}
+
+// 为已有媒体补生成缩略图的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThumbnailBackfillReport {
+    pub generated: i64,            // 新生成的缩略图数
+    pub skipped: i64,              // 缩略图已存在或原图足够小的媒体数
+    pub failed: Vec<MediaFailure>, // 无法解码或文件已丢失的媒体
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaFailure {
+    pub media_id: String,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -111,18 +356,391 @@ pub struct SearchQuery {
     pub workspace_id: Option<String>,
     pub file_type: Option<String>,
     pub tags: Option<Vec<String>>,
+    pub folder: Option<String>, // 文件夹路径，包含子文件夹
     pub limit: Option<i64>,
+    pub snippet_tokens: Option<i64>,    // 摘录长度（词数，1-64）
+    pub weights: Option<SearchWeights>, // BM25 列权重
+    pub recency_boost: Option<f64>,     // 更新时间加成，0 或不传表示不加成
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+// 排名时各列的 BM25 权重，标题和标签命中默认高于正文
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SearchWeights {
+    pub title: f64,
+    pub content: f64,
+    pub tags: f64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            title: 10.0,
+            content: 1.0,
+            tags: 5.0,
+        }
+    }
+}
+
+// 匹配位置，按字符（Unicode 标量）计算的左闭右开区间
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub id: String,
     pub workspace_id: String,
     pub file_type: String,
     pub title: String,
-    pub content: Option<String>,
+    pub title_ranges: Vec<MatchRange>,   // 标题中的匹配位置
+    pub snippet: String,                 // 纯文本摘录
+    pub snippet_ranges: Vec<MatchRange>, // 摘录中的匹配位置
     pub file_path: Option<String>,
     pub created_at: String,
     pub updated_at: String,
-    pub rank: f64, // FTS5 排名分数
+    pub rank: f64, // 相关度分数，越大越相关（无全文条件时为 0）
+}
+
+// 向量化后端配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum EmbeddingConfig {
+    // 内置的特征哈希向量，离线可用
+    #[default]
+    Hashing,
+    // 通过已配置的提供商调用 OpenAI 兼容的 /embeddings 接口
+    Provider {
+        provider_id: String,
+        model: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticQuery {
+    pub query: String,
+    pub workspace_id: Option<String>,
+    pub file_type: Option<String>,
+    pub limit: Option<i64>,
+}
+
+// 语义搜索命中的文档片段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkMatch {
+    pub file_id: String,
+    pub workspace_id: String,
+    pub title: String,
+    pub chunk_index: i64,
+    pub content: String,
+    pub start_offset: i64, // 在 content_plain 中的字符位置
+    pub end_offset: i64,
+    pub score: f64, // 余弦相似度
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalQuery {
+    pub query: String,
+    pub workspace_id: Option<String>,
+    pub file_type: Option<String>,
+    pub tags: Option<Vec<String>>, // 文件需包含所有标签
+    pub limit: Option<i64>,
+}
+
+// 检索到的上下文片段，按全文与向量排名融合后的分数排序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextChunk {
+    pub file_id: String,
+    pub workspace_id: String,
+    pub title: String,
+    pub chunk_index: i64,
+    pub content: String,
+    pub start_offset: i64, // 在 content_plain 中的字符位置
+    pub end_offset: i64,
+    pub score: f64,                // 倒数排名融合分数
+    pub keyword_rank: Option<i64>, // 全文检索中的名次（从 1 开始）
+    pub vector_rank: Option<i64>,  // 向量检索中的名次（从 1 开始）
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String, // 'system', 'user', 'assistant'
+    pub content: String,
+}
+
+// 对话补全请求（OpenAI 兼容接口）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub provider_id: Option<String>, // 不传时使用默认提供商
+    pub model: Option<String>,       // 不传时使用提供商配置的模型
+    pub messages: Vec<ChatMessage>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+}
+
+// 流式补全过程中推送给调用窗口的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    // 新生成的内容片段
+    Delta { content: String },
+    // 请求失败，将在 delay_ms 毫秒后进行第 attempt 次重试
+    Retry { attempt: u32, delay_ms: u64 },
+}
+
+// AI 提供商（不包含 API Key，只标记是否已设置）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AiProvider {
+    pub id: String,
+    pub name: String,
+    pub base_url: String,
+    pub model: String,
+    pub enabled: bool,
+    pub is_default: bool,
+    pub has_api_key: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAiProvider {
+    pub name: String,
+    pub base_url: String,
+    pub model: String,
+    pub enabled: Option<bool>,
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateAiProvider {
+    pub name: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub enabled: Option<bool>,
+    pub api_key: Option<String>, // 空字符串表示清除
+}
+
+// 测试连接结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConnection {
+    pub models: Vec<String>, // /models 返回的模型列表
+    pub latency_ms: u64,
+}
+
+// ============ 回收站 ============
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrashedWorkspace {
+    pub id: String,
+    pub name: String,
+    pub file_count: i64, // 随工作空间一起删除的文件数
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrashedFile {
+    pub id: String,
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub file_type: String,
+    pub title: String,
+    pub deleted_at: String,
+}
+
+// 回收站内容：已删除的工作空间，以及所在工作空间未删除的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashContents {
+    pub workspaces: Vec<TrashedWorkspace>,
+    pub files: Vec<TrashedFile>,
+}
+
+// 彻底删除的数量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashPurgeResult {
+    pub workspaces: i64,
+    pub files: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashConfig {
+    pub retention_days: u32, // 超过天数的项目在启动时彻底删除，0 表示只在清空回收站时删除
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+// ============ 应用设置 ============
+
+// 所有窗口共享的应用设置，字段名与前端一致使用 camelCase，缺失的字段取默认值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32, // 结构版本，由后端写入
+    pub appearance: AppearanceSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppearanceSettings {
+    pub theme: ThemeMode,
+    pub font_size: FontSize,
+    pub editor_width: EditorWidth,
+    pub line_height: f64, // 1.0 - 3.0
+    pub font_family: String,
+    pub show_line_numbers: bool,
+    pub highlight_current_line: bool,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            theme: ThemeMode::default(),
+            font_size: FontSize::default(),
+            editor_width: EditorWidth::default(),
+            line_height: 1.6,
+            font_family: "system-ui".to_string(),
+            show_line_numbers: false,
+            highlight_current_line: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorWidth {
+    Narrow,
+    #[default]
+    Medium,
+    Wide,
+    Full,
+}
+
+// ============ 导入导出 ============
+
+// 工作空间归档中的 manifest.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub workspace: Workspace,
+    #[serde(default)]
+    pub folders: Vec<Folder>, // 早期归档没有文件夹
+    pub files: Vec<ManifestFile>,
+    pub tags: Vec<Tag>,
+    pub file_tags: Vec<FileTagLink>,
+    pub media_assets: Vec<ManifestMedia>,
+    pub file_media: Vec<FileMediaLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub id: String,
+    pub file_type: String,
+    pub title: String,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    pub file_size: Option<i64>,
+    pub mime_type: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub html_path: Option<String>,     // 文档的 HTML（归档内路径）
+    pub markdown_path: Option<String>, // 文档的 Markdown
+    pub data_path: Option<String>,     // 非文档文件的内容，源文件缺失时为空
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestMedia {
+    pub id: String,
+    pub file_name: String,
+    pub file_size: i64,
+    pub mime_type: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub created_at: String,
+    pub path: Option<String>, // 归档内路径，源文件缺失时为空
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FileTagLink {
+    pub file_id: String,
+    pub tag_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FileMediaLink {
+    pub file_id: String,
+    pub media_id: String,
+    pub created_at: String,
+}
+
+// 导入导出过程中推送给调用窗口的进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveProgress {
+    pub stage: ArchiveStage,
+    pub completed: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveStage {
+    Documents,
+    Media,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub documents: i64,
+    pub media: i64,
+    pub missing_media: i64, // 数据库中有记录但文件已丢失的媒体
+    pub size: i64,          // 归档大小（字节）
+}
+
+// ============ 笔记库导入 ============
+
+/// 笔记库导入结果，路径为相对笔记库根目录的路径
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultImportReport {
+    pub imported: Vec<VaultImportedFile>,
+    pub skipped: Vec<VaultImportIssue>,
+    pub failed: Vec<VaultImportIssue>,
+    pub media: i64, // 复制到媒体目录的图片数
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultImportedFile {
+    pub path: String,
+    pub file_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultImportIssue {
+    pub path: String,
+    pub reason: String,
 }
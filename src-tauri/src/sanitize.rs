@@ -0,0 +1,199 @@
+use crate::services::media::MEDIA_SCHEME;
+use ammonia::Builder;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+/// 与编辑器 schema 对应的白名单：StarterKit、图片、任务列表和表格（Markdown 导入会生成）
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::empty();
+    builder
+        .tags(HashSet::from([
+            "p",
+            "br",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "strong",
+            "b",
+            "em",
+            "i",
+            "s",
+            "del",
+            "strike",
+            "u",
+            "code",
+            "pre",
+            "blockquote",
+            "ul",
+            "ol",
+            "li",
+            "hr",
+            "a",
+            "img",
+            "table",
+            "thead",
+            "tbody",
+            "tr",
+            "th",
+            "td",
+            "label",
+            "input",
+            "span",
+            "div",
+        ]))
+        .generic_attributes(HashSet::new())
+        .tag_attributes(HashMap::from([
+            ("a", HashSet::from(["href", "target"])),
+            (
+                "img",
+                HashSet::from([
+                    "src",
+                    "mediasrc",
+                    "data-media-src",
+                    "alt",
+                    "width",
+                    "height",
+                ]),
+            ),
+            ("code", HashSet::from(["class"])),
+            ("ol", HashSet::from(["start", "type"])),
+            ("ul", HashSet::from(["data-type"])),
+            ("li", HashSet::from(["data-type", "data-checked"])),
+            ("input", HashSet::from(["type", "checked"])),
+            ("th", HashSet::from(["colspan", "rowspan", "colwidth"])),
+            ("td", HashSet::from(["colspan", "rowspan", "colwidth"])),
+        ]))
+        .url_schemes(HashSet::from(["http", "https", "mailto", "media", "doc"]))
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .attribute_filter(filter_attribute);
+    builder
+});
+
+/// 清理文档 HTML：去掉脚本、事件属性、不安全的链接以及编辑器不支持的标签和属性
+pub fn sanitize_html(html: &str) -> String {
+    SANITIZER.clean(html).to_string()
+}
+
+fn filter_attribute<'u>(element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+    match (element, attribute) {
+        // 媒体引用不是 ammonia 认识的 URL 属性，只接受 media:// 地址
+        ("img", "mediasrc" | "data-media-src") => {
+            value.starts_with(MEDIA_SCHEME).then_some(value.into())
+        }
+        ("a", "target") => (value == "_blank").then_some(value.into()),
+        ("input", "type") => (value == "checkbox").then_some(value.into()),
+        // 代码块只保留 language-* 类名
+        ("code", "class") => {
+            let classes: Vec<&str> = value
+                .split_whitespace()
+                .filter(|class| {
+                    class.strip_prefix("language-").is_some_and(|language| {
+                        !language.is_empty()
+                            && language
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || "-_+#.".contains(c))
+                    })
+                })
+                .collect();
+            (!classes.is_empty()).then(|| classes.join(" ").into())
+        }
+        _ => Some(value.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 编辑器保存的内容清理后保持不变
+    fn assert_kept(html: &str) {
+        assert_eq!(sanitize_html(html), html);
+    }
+
+    #[test]
+    fn scripts_and_event_handlers_are_removed() {
+        assert_eq!(
+            sanitize_html("<p onclick=\"steal()\">hi<script>alert(1)</script></p>"),
+            "<p>hi</p>"
+        );
+        assert_eq!(
+            sanitize_html("<img src=\"x\" onerror=\"alert(1)\"><style>p{}</style><iframe src=\"https://evil\"></iframe>"),
+            "<img src=\"x\">"
+        );
+        assert_eq!(
+            sanitize_html("<p style=\"color:red\" class=\"x\" data-foo=\"1\">text</p>"),
+            "<p>text</p>"
+        );
+    }
+
+    #[test]
+    fn unsafe_urls_are_removed() {
+        assert_eq!(
+            sanitize_html("<a href=\"javascript:alert(1)\">x</a>"),
+            "<a rel=\"noopener noreferrer nofollow\">x</a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"data:text/html,<script>alert(1)</script>\">x</a>"),
+            "<a rel=\"noopener noreferrer nofollow\">x</a>"
+        );
+        assert_eq!(
+            sanitize_html("<img src=\"data:image/png;base64,AAAA\" alt=\"a\">"),
+            "<img alt=\"a\">"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"https://example.com\" target=\"_top\">x</a>"),
+            "<a href=\"https://example.com\" rel=\"noopener noreferrer nofollow\">x</a>"
+        );
+    }
+
+    #[test]
+    fn media_attributes_only_accept_media_urls() {
+        assert_eq!(
+            sanitize_html(
+                "<img mediasrc=\"javascript:alert(1)\" data-media-src=\"https://evil.example/x.png\" alt=\"a\">"
+            ),
+            "<img alt=\"a\">"
+        );
+        assert_kept(
+            "<img mediasrc=\"media://abc-123\" alt=\"pic\" data-media-src=\"media://abc-123\">",
+        );
+    }
+
+    #[test]
+    fn editor_output_is_kept() {
+        // 任务列表
+        assert_kept(
+            "<ul data-type=\"taskList\"><li data-checked=\"true\" data-type=\"taskItem\"><label><input type=\"checkbox\" checked=\"checked\"><span></span></label><div><p>done</p></div></li>\
+             <li data-checked=\"false\" data-type=\"taskItem\"><label><input type=\"checkbox\"><span></span></label><div><p>todo</p></div></li></ul>",
+        );
+        // 代码块和有序列表
+        assert_kept("<pre><code class=\"language-rust\">fn main() {}</code></pre>");
+        assert_kept("<ol start=\"3\"><li><p>third</p></li></ol>");
+        // 表格
+        assert_kept(
+            "<table><tbody><tr><th colspan=\"1\" rowspan=\"1\"><p>a</p></th><th colspan=\"2\" rowspan=\"1\" colwidth=\"120,80\"><p>b</p></th></tr>\
+             <tr><td colspan=\"1\" rowspan=\"1\"><p>1</p></td><td colspan=\"2\" rowspan=\"1\"><p>2</p></td></tr></tbody></table>",
+        );
+        // 文档之间的链接
+        assert_kept(
+            "<p><a target=\"_blank\" href=\"doc://3f2a\" rel=\"noopener noreferrer nofollow\">other note</a></p>",
+        );
+        assert_kept("<h2>Title</h2><blockquote><p><strong>b</strong> <em>i</em> <s>s</s> <code>c</code></p></blockquote><hr>");
+    }
+
+    #[test]
+    fn code_classes_keep_only_languages() {
+        assert_eq!(
+            sanitize_html("<code class=\"hljs language-c++ evil\">x</code>"),
+            "<code class=\"language-c++\">x</code>"
+        );
+        assert_eq!(
+            sanitize_html("<code class=\"language-\\\" onmouseover\">x</code>"),
+            "<code>x</code>"
+        );
+    }
+}
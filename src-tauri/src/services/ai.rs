@@ -0,0 +1,660 @@
+use super::provider::ProviderEndpoint;
+use crate::error::{AppError, Result};
+use crate::models::{ChatEvent, ChatMessage, ChatRequest};
+use reqwest::{header, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// 建立连接的超时时间
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// 发出请求到收到响应头的超时时间
+const RESPONSE_TIMEOUT_SECS: u64 = 60;
+
+/// 流式响应中两次收到数据之间的最长间隔
+const IDLE_TIMEOUT_SECS: u64 = 60;
+
+/// 最多重试次数（不含第一次请求）
+const MAX_RETRIES: u32 = 3;
+
+/// 第一次重试前的等待时间，之后每次翻倍
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// 单次重试的最长等待时间（包括服务端 Retry-After 指定的时间）
+const RETRY_MAX_DELAY_MS: u64 = 30_000;
+
+/// OpenAI 兼容接口的对话补全
+///
+/// 请求在 Rust 侧发出，API Key 不经过前端网络请求，也不受 CORS 限制。
+/// 每个请求由调用方指定 id，可通过 [`Self::cancel`] 取消；
+/// 连接失败、超时、429 和 5xx 在收到任何内容之前会按指数退避重试。
+pub struct AiService {
+    client: reqwest::Client,
+    requests: Mutex<HashMap<String, PendingRequest>>,
+    next_token: AtomicU64,
+    // 超时和退避时间，测试中可以缩短
+    idle_timeout: Duration,
+    retry_base_delay: Duration,
+}
+
+impl Default for AiService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AiService {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            requests: Mutex::new(HashMap::new()),
+            next_token: AtomicU64::new(0),
+            idle_timeout: Duration::from_secs(IDLE_TIMEOUT_SECS),
+            retry_base_delay: Duration::from_millis(RETRY_BASE_DELAY_MS),
+        }
+    }
+
+    /// 流式对话补全，每收到一段内容调用一次 `on_event`，完成后返回完整回复
+    pub async fn chat(
+        &self,
+        request_id: &str,
+        endpoint: ProviderEndpoint,
+        request: ChatRequest,
+        mut on_event: impl FnMut(ChatEvent),
+    ) -> Result<String> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut requests = self.requests.lock().unwrap();
+            if requests.contains_key(request_id) {
+                return Err(AppError::Conflict(format!(
+                    "Request already in progress: {}",
+                    request_id
+                )));
+            }
+            requests.insert(
+                request_id.to_string(),
+                PendingRequest {
+                    token,
+                    cancel: cancel_tx,
+                },
+            );
+        }
+        // 正常结束、出错或调用方丢弃 future 时都会移除
+        let _guard = RequestGuard {
+            requests: &self.requests,
+            request_id,
+            token,
+        };
+
+        // 取消时直接丢弃请求的 future，连接随之关闭
+        tokio::select! {
+            result = self.run(&endpoint, &request, &mut on_event) => result,
+            Ok(()) = cancel_rx => Err(AppError::Cancelled(format!(
+                "Request cancelled: {}",
+                request_id
+            ))),
+        }
+    }
+
+    /// 取消进行中的请求，返回请求是否存在
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.requests.lock().unwrap().remove(request_id) {
+            Some(pending) => {
+                let _ = pending.cancel.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn run(
+        &self,
+        endpoint: &ProviderEndpoint,
+        request: &ChatRequest,
+        on_event: &mut impl FnMut(ChatEvent),
+    ) -> Result<String> {
+        let url = format!(
+            "{}/chat/completions",
+            endpoint.base_url.trim_end_matches('/')
+        );
+        let body = CompletionBody {
+            model: request.model.as_deref().unwrap_or(&endpoint.model),
+            messages: &request.messages,
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            stream: true,
+        };
+
+        let mut attempt = 0;
+        loop {
+            match self.send(&url, endpoint.api_key.as_deref(), &body).await {
+                Ok(response) => return read_response(response, self.idle_timeout, on_event).await,
+                Err(failure) if failure.retryable && attempt < MAX_RETRIES => {
+                    let delay = failure
+                        .retry_after
+                        .unwrap_or_else(|| self.retry_base_delay * 2u32.pow(attempt))
+                        .min(Duration::from_millis(RETRY_MAX_DELAY_MS));
+                    attempt += 1;
+
+                    on_event(ChatEvent::Retry {
+                        attempt,
+                        delay_ms: delay.as_millis() as u64,
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+
+    /// 发出请求并检查状态码
+    async fn send(
+        &self,
+        url: &str,
+        api_key: Option<&str>,
+        body: &CompletionBody<'_>,
+    ) -> std::result::Result<Response, SendFailure> {
+        let mut request = self.client.post(url).json(body);
+        if let Some(api_key) = api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response =
+            match tokio::time::timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECS), request.send())
+                .await
+            {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    return Err(SendFailure {
+                        retryable: e.is_connect() || e.is_timeout() || e.is_request(),
+                        retry_after: None,
+                        error: AppError::Ai(format!("AI request failed: {}", e)),
+                    })
+                }
+                Err(_) => {
+                    return Err(SendFailure {
+                        retryable: true,
+                        retry_after: None,
+                        error: AppError::Ai("AI request timed out".to_string()),
+                    })
+                }
+            };
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+
+        Err(SendFailure {
+            retryable: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            retry_after,
+            error: AppError::Ai(format!(
+                "AI request failed ({}): {}",
+                status,
+                error_message(&body)
+            )),
+        })
+    }
+}
+
+/// 进行中的请求，`token` 区分先后使用同一 id 的请求
+struct PendingRequest {
+    token: u64,
+    cancel: oneshot::Sender<()>,
+}
+
+/// 请求结束时移除登记；请求已被取消且 id 被新请求占用时不影响新请求
+struct RequestGuard<'a> {
+    requests: &'a Mutex<HashMap<String, PendingRequest>>,
+    request_id: &'a str,
+    token: u64,
+}
+
+impl Drop for RequestGuard<'_> {
+    fn drop(&mut self) {
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+        if requests
+            .get(self.request_id)
+            .is_some_and(|pending| pending.token == self.token)
+        {
+            requests.remove(self.request_id);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CompletionBody<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i64>,
+    stream: bool,
+}
+
+struct SendFailure {
+    error: AppError,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+/// 读取补全结果：按 SSE 逐段解析；服务端不支持流式而直接返回 JSON 时一次性读取
+async fn read_response(
+    mut response: Response,
+    idle_timeout: Duration,
+    on_event: &mut impl FnMut(ChatEvent),
+) -> Result<String> {
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    if !is_stream {
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::Ai(format!("Invalid AI response: {}", e)))?;
+        let content = body["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        if !content.is_empty() {
+            on_event(ChatEvent::Delta {
+                content: content.clone(),
+            });
+        }
+        return Ok(content);
+    }
+
+    let mut content = String::new();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    loop {
+        let chunk = tokio::time::timeout(idle_timeout, response.chunk())
+            .await
+            .map_err(|_| AppError::Ai("AI response timed out".to_string()))?
+            .map_err(|e| AppError::Ai(format!("AI response interrupted: {}", e)))?;
+        let Some(chunk) = chunk else {
+            return Ok(content);
+        };
+        buffer.extend_from_slice(&chunk);
+
+        // 按行解析，未收完的行留在缓冲区（UTF-8 多字节字符不含换行符，按字节切分是安全的）
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+
+            let data = data.trim();
+            if data == "[DONE]" {
+                return Ok(content);
+            }
+
+            let delta = parse_delta(data)?;
+            if !delta.is_empty() {
+                content.push_str(&delta);
+                on_event(ChatEvent::Delta { content: delta });
+            }
+        }
+    }
+}
+
+/// 解析一条流式数据中的增量内容
+fn parse_delta(data: &str) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(data)
+        .map_err(|e| AppError::Ai(format!("Invalid AI stream data: {}", e)))?;
+
+    if value.get("error").is_some() {
+        return Err(AppError::Ai(format!(
+            "AI stream error: {}",
+            error_message(data)
+        )));
+    }
+
+    Ok(value["choices"][0]["delta"]["content"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// 从错误响应体中提取错误信息（`{"error": {"message": ...}}`），否则原样返回
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            let error = v.get("error")?;
+            error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .or_else(|| error.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock_server::{MockServer, Reply};
+
+    fn service() -> AiService {
+        AiService {
+            idle_timeout: Duration::from_millis(300),
+            retry_base_delay: Duration::from_millis(1),
+            ..AiService::new()
+        }
+    }
+
+    fn endpoint(server: &MockServer) -> ProviderEndpoint {
+        ProviderEndpoint {
+            base_url: server.base_url.clone(),
+            api_key: Some("sk-test".to_string()),
+            model: "test-model".to_string(),
+        }
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            provider_id: None,
+            model: None,
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: "hi".to_string(),
+            }],
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    fn delta(content: &str) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::json!({ "choices": [{ "delta": { "content": content } }] })
+        )
+    }
+
+    fn deltas(events: &[ChatEvent]) -> Vec<String> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ChatEvent::Delta { content } => Some(content.clone()),
+                ChatEvent::Retry { .. } => None,
+            })
+            .collect()
+    }
+
+    fn retries(events: &[ChatEvent]) -> Vec<(u32, u64)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ChatEvent::Retry { attempt, delay_ms } => Some((*attempt, *delay_ms)),
+                ChatEvent::Delta { .. } => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn streams_deltas_split_across_reads() {
+        // 第二条数据在行中间、多字节字符中间被拆开
+        let second = delta("你好");
+        let (head, tail) = second.as_bytes().split_at(second.find('好').unwrap() + 1);
+        let first = delta("Hello, ");
+        let server = MockServer::sequence(vec![Reply::sse(&[
+            b": keep-alive\n\n",
+            first.as_bytes(),
+            head,
+            tail,
+            b"data: {\"choices\":[{\"delta\":{}}]}\n\ndata: [DONE]\n\n",
+        ])])
+        .await;
+
+        let mut events = Vec::new();
+        let content = service()
+            .chat("stream", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(content, "Hello, 你好");
+        assert_eq!(deltas(&events), ["Hello, ", "你好"]);
+
+        let body: serde_json::Value = serde_json::from_str(&server.requests()[0]).unwrap();
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"][0]["content"], "hi");
+    }
+
+    #[tokio::test]
+    async fn reads_non_streaming_json_response() {
+        let server = MockServer::sequence(vec![Reply::json(
+            200,
+            r#"{"choices":[{"message":{"content":"whole answer"}}]}"#,
+        )])
+        .await;
+
+        let mut events = Vec::new();
+        let content = service()
+            .chat("json", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(content, "whole answer");
+        assert_eq!(deltas(&events), ["whole answer"]);
+    }
+
+    #[tokio::test]
+    async fn stream_error_fails_the_request() {
+        let server = MockServer::sequence(vec![Reply::sse(&[
+            delta("partial").as_bytes(),
+            b"data: {\"error\":{\"message\":\"overloaded\"}}\n\n",
+        ])])
+        .await;
+
+        let error = service()
+            .chat("error", endpoint(&server), request(), |_| {})
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("overloaded")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_and_server_errors_with_backoff() {
+        let server = MockServer::sequence(vec![
+            Reply::json(429, r#"{"error":{"message":"slow down"}}"#).header("Retry-After", "0"),
+            Reply::json(503, "unavailable"),
+            Reply::json(500, "boom"),
+            Reply::sse(&[delta("ok").as_bytes(), b"data: [DONE]\n\n"]),
+        ])
+        .await;
+
+        let mut events = Vec::new();
+        let content = service()
+            .chat("retry", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(content, "ok");
+        // Retry-After 优先，否则从基础间隔开始翻倍
+        assert_eq!(retries(&events), [(1, 0), (2, 2), (3, 4)]);
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let server = MockServer::sequence(vec![Reply::json(
+            502,
+            r#"{"error":{"message":"bad gateway"}}"#,
+        )])
+        .await;
+
+        let mut events = Vec::new();
+        let error = service()
+            .chat("exhausted", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("bad gateway")),
+            "{:?}",
+            error
+        );
+        assert_eq!(retries(&events).len(), MAX_RETRIES as usize);
+        assert_eq!(server.requests().len(), MAX_RETRIES as usize + 1);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::sequence(vec![Reply::json(
+            400,
+            r#"{"error":{"message":"unknown model"}}"#,
+        )])
+        .await;
+
+        let mut events = Vec::new();
+        let error = service()
+            .chat("invalid", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("unknown model")),
+            "{:?}",
+            error
+        );
+        assert!(events.is_empty());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn stalled_stream_times_out() {
+        let server = MockServer::sequence(vec![
+            Reply::sse(&[delta("first").as_bytes()]).hold(Duration::from_secs(10))
+        ])
+        .await;
+
+        let mut events = Vec::new();
+        let error = service()
+            .chat("stalled", endpoint(&server), request(), |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("timed out")),
+            "{:?}",
+            error
+        );
+        // 已收到内容后不再重试
+        assert_eq!(deltas(&events), ["first"]);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_request_in_progress() {
+        let server = MockServer::sequence(vec![
+            Reply::sse(&[delta("first").as_bytes()]).hold(Duration::from_secs(10))
+        ])
+        .await;
+        let service = AiService {
+            idle_timeout: Duration::from_secs(10),
+            ..service()
+        };
+
+        // 收到第一段内容后取消
+        let (received_tx, received_rx) = oneshot::channel();
+        let mut received_tx = Some(received_tx);
+        let chat = service.chat("cancel", endpoint(&server), request(), |_| {
+            if let Some(tx) = received_tx.take() {
+                let _ = tx.send(());
+            }
+        });
+        let cancel = async {
+            received_rx.await.unwrap();
+            service.cancel("cancel")
+        };
+
+        let (result, cancelled) = tokio::join!(chat, cancel);
+        assert!(cancelled);
+        assert!(
+            matches!(result, Err(AppError::Cancelled(_))),
+            "{:?}",
+            result
+        );
+
+        // 请求结束后不能再取消，同一 id 可以再次使用
+        assert!(!service.cancel("cancel"));
+        assert!(service.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropping_chat_future_releases_request_id() {
+        let server = MockServer::sequence(vec![
+            Reply::sse(&[delta("first").as_bytes()]).hold(Duration::from_secs(10)),
+            Reply::sse(&[delta("again").as_bytes(), b"data: [DONE]\n\n"]),
+        ])
+        .await;
+        let service = AiService {
+            idle_timeout: Duration::from_secs(10),
+            ..service()
+        };
+
+        // 收到第一段内容后丢弃 future，相当于前端关闭了窗口
+        let (received_tx, received_rx) = oneshot::channel();
+        let mut received_tx = Some(received_tx);
+        let chat = service.chat("dropped", endpoint(&server), request(), |_| {
+            if let Some(tx) = received_tx.take() {
+                let _ = tx.send(());
+            }
+        });
+        tokio::select! {
+            result = chat => panic!("request finished early: {:?}", result),
+            _ = received_rx => {}
+        }
+
+        assert!(service.requests.lock().unwrap().is_empty());
+        assert!(!service.cancel("dropped"));
+
+        let content = service
+            .chat("dropped", endpoint(&server), request(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(content, "again");
+        assert!(service.requests.lock().unwrap().is_empty());
+    }
+}
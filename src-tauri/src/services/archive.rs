@@ -0,0 +1,1551 @@
+use super::blocking;
+use super::file::update_document_stats;
+use super::media::{write_media_file, StoredFile, MEDIA_SCHEME};
+use super::search::index_file;
+use super::tag::find_or_create;
+use crate::error::{AppError, Result};
+use crate::markdown::{escape_html, html_to_markdown, markdown_title, markdown_to_html};
+use crate::models::{
+    ArchiveProgress, ArchiveStage, ExportSummary, File, FileMediaLink, FileTagLink, Folder,
+    ManifestFile, ManifestMedia, MediaAsset, Tag, Workspace, WorkspaceManifest,
+};
+use crate::sanitize::sanitize_html;
+use chrono::Utc;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 归档格式标识
+pub const ARCHIVE_FORMAT: &str = "ai-editor-workspace";
+
+/// 归档结构版本，导入时拒绝比它新的归档
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+
+/// 工作空间的 zip 归档
+///
+/// 归档结构：
+/// - `manifest.json`：工作空间、文件夹、文件、标签、媒体及其关联
+/// - `documents/<id>.html`、`documents/<id>.md`：文档内容，`media://` 引用改写为相对路径
+/// - `media/<id>.<ext>`：媒体文件
+/// - `files/<id>.<ext>`：非文档文件的内容
+///
+/// 归档和磁盘文件的读写在阻塞线程池中执行，数据库操作留在异步任务中。
+pub struct ArchiveService {
+    pool: Pool<Sqlite>,
+    media_dir: PathBuf,
+}
+
+impl ArchiveService {
+    pub fn new(pool: Pool<Sqlite>, media_dir: PathBuf) -> Self {
+        Self { pool, media_dir }
+    }
+
+    /// 导出工作空间到 `dest`，先写入临时文件，完成后再替换目标文件
+    pub async fn export_workspace(
+        &self,
+        workspace_id: &str,
+        dest: &Path,
+        mut on_progress: impl FnMut(ArchiveProgress),
+    ) -> Result<ExportSummary> {
+        let partial = dest.with_extension("part");
+        let result = match self
+            .write_archive(workspace_id, &partial, &mut on_progress)
+            .await
+        {
+            Ok(summary) => {
+                let (partial, dest) = (partial.clone(), dest.to_path_buf());
+                blocking(move || Ok(fs::rename(partial, dest)?))
+                    .await
+                    .map(|()| summary)
+            }
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            let _ = blocking(move || Ok(fs::remove_file(partial)?)).await;
+        }
+
+        result
+    }
+
+    /// 从归档导入为新的工作空间
+    ///
+    /// 所有 id 重新生成，标签按名称与已有标签合并，媒体复制到媒体目录并改写文档中的引用，
+    /// 归档中缺失的媒体的引用改为不指向任何媒体的 `media://`，编辑器显示为加载失败的图片。
+    /// 任一步失败时回滚数据库并删除已复制的文件。
+    pub async fn import_workspace(
+        &self,
+        source: &Path,
+        mut on_progress: impl FnMut(ArchiveProgress),
+    ) -> Result<Workspace> {
+        let source = source.to_path_buf();
+        let (archive, manifest) = blocking(move || {
+            let mut archive = ZipArchive::new(fs::File::open(source)?)?;
+            let manifest = read_manifest(&mut archive)?;
+            Ok((archive, manifest))
+        })
+        .await?;
+
+        let mut written = Vec::new();
+        let result = self
+            .import_manifest(archive, &manifest, &mut written, &mut on_progress)
+            .await;
+
+        if result.is_err() {
+            let _ = blocking(move || {
+                for path in &written {
+                    let _ = fs::remove_file(path);
+                }
+                Ok(())
+            })
+            .await;
+        }
+
+        result
+    }
+
+    async fn import_manifest(
+        &self,
+        mut archive: ZipArchive<fs::File>,
+        manifest: &WorkspaceManifest,
+        written: &mut Vec<PathBuf>,
+        on_progress: &mut impl FnMut(ArchiveProgress),
+    ) -> Result<Workspace> {
+        let now = Utc::now().to_rfc3339();
+        let total = (manifest.media_assets.len() + manifest.files.len()) as i64;
+        let mut completed = 0;
+        let mut report = |stage, completed| {
+            on_progress(ArchiveProgress {
+                stage,
+                completed,
+                total,
+            })
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        let workspace = sqlx::query_as::<_, Workspace>(
+            r#"
+            INSERT INTO workspaces (id, name, description, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&manifest.workspace.name)
+        .bind(&manifest.workspace.description)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // 媒体：复制到媒体目录，归档中缺失的媒体不导入
+        let mut media_ids = HashMap::new();
+        let mut media_refs = Vec::new();
+        let mut hash_ids = HashMap::new();
+        for media in &manifest.media_assets {
+            completed += 1;
+            report(ArchiveStage::Media, completed);
+            let Some(path) = &media.path else {
+                continue;
+            };
+
+            // 归档中内容相同的媒体只导入一次
+            let stored;
+            (archive, stored) = self.extract(archive, path, written).await?;
+            let id = match hash_ids.get(&stored.hash) {
+                Some(id) => String::clone(id),
+                None => {
+                    let id = Uuid::new_v4().to_string();
+                    sqlx::query(
+                        r#"
+                        INSERT INTO media_assets (
+                            id, workspace_id, file_name, file_path, file_size,
+                            mime_type, width, height, created_at, content_hash
+                        )
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(&id)
+                    .bind(&workspace.id)
+                    .bind(&media.file_name)
+                    .bind(stored.path.to_string_lossy().to_string())
+                    .bind(stored.size)
+                    .bind(&media.mime_type)
+                    .bind(media.width)
+                    .bind(media.height)
+                    .bind(&media.created_at)
+                    .bind(&stored.hash)
+                    .execute(&mut *tx)
+                    .await?;
+                    hash_ids.insert(stored.hash, id.clone());
+                    id
+                }
+            };
+
+            media_refs.push((format!("../{}", path), format!("{}{}", MEDIA_SCHEME, id)));
+            media_ids.insert(media.id.as_str(), id);
+        }
+
+        let folder_ids = insert_folders(&mut tx, &workspace.id, &manifest.folders).await?;
+
+        // 标签：同名标签使用已有的
+        let mut tag_ids = HashMap::new();
+        for tag in &manifest.tags {
+            let id = find_or_create(&mut tx, &tag.name, tag.color.as_deref()).await?;
+            tag_ids.insert(tag.id.as_str(), id);
+        }
+
+        let mut file_ids = HashMap::new();
+        for file in &manifest.files {
+            let id = Uuid::new_v4().to_string();
+
+            let content = match &file.html_path {
+                Some(path) => {
+                    let html;
+                    let path = path.clone();
+                    (archive, html) =
+                        with_archive(archive, move |archive| read_entry(archive, &path)).await?;
+                    Some(sanitize_html(&restore_media_refs(
+                        html_body(&html),
+                        &media_refs,
+                        &media_ids,
+                    )))
+                }
+                None => None,
+            };
+
+            let file_path = match &file.data_path {
+                Some(path) => {
+                    let stored;
+                    (archive, stored) = self.extract(archive, path, written).await?;
+                    Some(stored.path.to_string_lossy().to_string())
+                }
+                None => None,
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO files (
+                    id, workspace_id, file_type, title, content,
+                    file_path, file_size, mime_type, created_at, updated_at, folder_id
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&id)
+            .bind(&workspace.id)
+            .bind(&file.file_type)
+            .bind(&file.title)
+            .bind(&content)
+            .bind(&file_path)
+            .bind(file.file_size)
+            .bind(&file.mime_type)
+            .bind(&file.created_at)
+            .bind(&file.updated_at)
+            .bind(
+                file.folder_id
+                    .as_deref()
+                    .and_then(|folder_id| folder_ids.get(folder_id)),
+            )
+            .execute(&mut *tx)
+            .await?;
+            update_document_stats(&mut tx, &id).await?;
+
+            file_ids.insert(file.id.as_str(), id);
+
+            completed += 1;
+            report(ArchiveStage::Documents, completed);
+        }
+
+        for link in &manifest.file_tags {
+            if let (Some(file_id), Some(tag_id)) = (
+                file_ids.get(link.file_id.as_str()),
+                tag_ids.get(link.tag_id.as_str()),
+            ) {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO file_tags (file_id, tag_id, created_at) VALUES (?, ?, ?)",
+                )
+                .bind(file_id)
+                .bind(tag_id)
+                .bind(&link.created_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for link in &manifest.file_media {
+            if let (Some(file_id), Some(media_id)) = (
+                file_ids.get(link.file_id.as_str()),
+                media_ids.get(link.media_id.as_str()),
+            ) {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO file_media (file_id, media_id, created_at) VALUES (?, ?, ?)",
+                )
+                .bind(file_id)
+                .bind(media_id)
+                .bind(&link.created_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        // 标签关联写入后再建立全文索引
+        for id in file_ids.values() {
+            index_file(&mut tx, id).await?;
+        }
+
+        tx.commit().await?;
+
+        report(ArchiveStage::Done, total);
+
+        Ok(workspace)
+    }
+
+    /// 把 Markdown 文件导入为工作空间中的文档，标题取第一个标题，没有标题时使用文件名
+    ///
+    /// 先读取全部文件再在一个事务中写入，任一文件失败时都不导入。
+    pub async fn import_markdown_files(
+        &self,
+        workspace_id: &str,
+        paths: &[PathBuf],
+    ) -> Result<Vec<File>> {
+        let paths = paths.to_vec();
+        let documents = blocking(move || {
+            let mut documents = Vec::with_capacity(paths.len());
+            for path in paths {
+                let markdown = fs::read_to_string(&path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+
+                let title = markdown_title(&markdown).unwrap_or_else(|| {
+                    path.file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+                documents.push((title, sanitize_html(&markdown_to_html(&markdown))));
+            }
+            Ok(documents)
+        })
+        .await?;
+
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM workspaces WHERE id = ?")
+            .bind(workspace_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if exists.is_none() {
+            return Err(AppError::NotFound(format!(
+                "Workspace not found: {}",
+                workspace_id
+            )));
+        }
+
+        let mut files = Vec::with_capacity(documents.len());
+        for (title, content) in documents {
+            let id = Uuid::new_v4().to_string();
+
+            sqlx::query(
+                r#"
+                INSERT INTO files (id, workspace_id, file_type, title, content, created_at, updated_at)
+                VALUES (?, ?, 'document', ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&id)
+            .bind(workspace_id)
+            .bind(&title)
+            .bind(&content)
+            .bind(&now)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+            update_document_stats(&mut tx, &id).await?;
+            index_file(&mut tx, &id).await?;
+
+            let file = sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = ?")
+                .bind(&id)
+                .fetch_one(&mut *tx)
+                .await?;
+            files.push(file);
+        }
+
+        tx.commit().await?;
+
+        Ok(files)
+    }
+
+    /// 把文档导出为 Markdown 文件，引用的媒体复制到同目录的 `<文件名>.assets/` 下
+    pub async fn export_file_as_markdown(&self, file_id: &str, dest: &Path) -> Result<()> {
+        let file = sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("File not found: {}", file_id)))?;
+
+        if file.file_type != "document" {
+            return Err(AppError::Validation(
+                "Only documents can be exported as Markdown".to_string(),
+            ));
+        }
+
+        let tags: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT t.name FROM tags t
+            INNER JOIN file_tags ft ON ft.tag_id = t.id
+            WHERE ft.file_id = ?
+            ORDER BY t.name
+            "#,
+        )
+        .bind(file_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let tags: Vec<&str> = tags.iter().map(|(name,)| name.as_str()).collect();
+
+        let assets =
+            sqlx::query_as::<_, MediaAsset>("SELECT * FROM media_assets WHERE workspace_id = ?")
+                .bind(&file.workspace_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        // 只复制文档中引用且源文件存在的媒体
+        let content = file.content.as_deref().unwrap_or_default();
+        let referenced: Vec<String> = assets
+            .iter()
+            .filter(|asset| content.contains(&format!("{}{}", MEDIA_SCHEME, asset.id)))
+            .map(|asset| asset.file_path.clone())
+            .collect();
+        let existing = existing_files(referenced).await?;
+
+        let stem = dest
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let assets_dir = format!("{}.assets", stem);
+        let media_paths: HashMap<&str, (String, String)> = assets
+            .iter()
+            .filter(|asset| existing.contains(&asset.file_path))
+            .map(|asset| {
+                let path = archive_path(&assets_dir, &asset.id, &asset.file_path);
+                (asset.id.as_str(), (path, asset.file_path.clone()))
+            })
+            .collect();
+
+        let html = rewrite_media_refs(content, |id| {
+            media_paths.get(id).map(|(path, _)| path.clone())
+        });
+
+        let mut markdown = front_matter(&file.title, &tags, &file.created_at, &file.updated_at);
+        let copies: Vec<(String, String)> = media_paths.into_values().collect();
+        let dest = dest.to_path_buf();
+        blocking(move || {
+            markdown.push_str(&html_to_markdown(&html));
+            fs::write(&dest, markdown)?;
+
+            let dir = dest.parent().unwrap_or(Path::new(""));
+            if !copies.is_empty() {
+                fs::create_dir_all(dir.join(&assets_dir))?;
+            }
+            for (path, source) in copies {
+                fs::copy(source, dir.join(path))?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// 把归档中的文件解压到媒体目录，文件名为内容的哈希
+    async fn extract(
+        &self,
+        archive: ZipArchive<fs::File>,
+        path: &str,
+        written: &mut Vec<PathBuf>,
+    ) -> Result<(ZipArchive<fs::File>, StoredFile)> {
+        let media_dir = self.media_dir.clone();
+        let path = path.to_string();
+        let (archive, stored) = with_archive(archive, move |archive| {
+            let mut entry = archive
+                .by_name(&path)
+                .map_err(|_| AppError::Validation(format!("Missing archive entry: {}", path)))?;
+            write_media_file(&media_dir, &path, &mut entry)
+        })
+        .await?;
+
+        // 已存在的文件属于其他媒体，导入失败时不能删除
+        if stored.created {
+            written.push(stored.path.clone());
+        }
+
+        Ok((archive, stored))
+    }
+
+    async fn write_archive(
+        &self,
+        workspace_id: &str,
+        path: &Path,
+        on_progress: &mut impl FnMut(ArchiveProgress),
+    ) -> Result<ExportSummary> {
+        let workspace = sqlx::query_as::<_, Workspace>("SELECT * FROM workspaces WHERE id = ?")
+            .bind(workspace_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Workspace not found: {}", workspace_id)))?;
+
+        // 正文在写入时逐个读取，避免一次载入整个工作空间
+        let rows = sqlx::query(
+            r#"
+            SELECT id, file_type, title, folder_id, file_path, file_size, mime_type, created_at, updated_at
+            FROM files
+            WHERE workspace_id = ? AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let folders = sqlx::query_as::<_, Folder>(
+            "SELECT * FROM folders WHERE workspace_id = ? ORDER BY created_at",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tags = sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT DISTINCT t.* FROM tags t
+            INNER JOIN file_tags ft ON ft.tag_id = t.id
+            INNER JOIN files f ON f.id = ft.file_id
+            WHERE f.workspace_id = ? AND f.deleted_at IS NULL
+            ORDER BY t.name
+            "#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let file_tags = sqlx::query_as::<_, FileTagLink>(
+            r#"
+            SELECT ft.* FROM file_tags ft
+            INNER JOIN files f ON f.id = ft.file_id
+            WHERE f.workspace_id = ? AND f.deleted_at IS NULL
+            "#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let assets = sqlx::query_as::<_, MediaAsset>(
+            "SELECT * FROM media_assets WHERE workspace_id = ? ORDER BY created_at",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let file_media = sqlx::query_as::<_, FileMediaLink>(
+            r#"
+            SELECT fm.* FROM file_media fm
+            INNER JOIN files f ON f.id = fm.file_id
+            WHERE f.workspace_id = ? AND f.deleted_at IS NULL
+            "#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let sources = assets
+            .iter()
+            .map(|asset| asset.file_path.clone())
+            .chain(
+                rows.iter()
+                    .filter(|row| row.get::<String, _>("file_type") != "document")
+                    .filter_map(|row| row.get::<Option<String>, _>("file_path")),
+            )
+            .collect();
+        let existing = existing_files(sources).await?;
+
+        // 媒体在归档中的路径，源文件已丢失的不写入
+        let media_paths: HashMap<String, String> = assets
+            .iter()
+            .filter(|asset| existing.contains(&asset.file_path))
+            .map(|asset| {
+                let path = archive_path("media", &asset.id, &asset.file_path);
+                (asset.id.clone(), path)
+            })
+            .collect();
+
+        let tag_names: HashMap<&str, &str> = tags
+            .iter()
+            .map(|t| (t.id.as_str(), t.name.as_str()))
+            .collect();
+
+        let mut files = Vec::with_capacity(rows.len());
+        let mut data_files = Vec::new();
+        for row in &rows {
+            let mut file = ManifestFile {
+                id: row.get("id"),
+                file_type: row.get("file_type"),
+                title: row.get("title"),
+                folder_id: row.get("folder_id"),
+                file_size: row.get("file_size"),
+                mime_type: row.get("mime_type"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                html_path: None,
+                markdown_path: None,
+                data_path: None,
+            };
+
+            // 非文档文件的内容在磁盘上，与媒体一起复制
+            if file.file_type != "document" {
+                if let Some(source) = row.get::<Option<String>, _>("file_path") {
+                    if existing.contains(&source) {
+                        let path = archive_path("files", &file.id, &source);
+                        file.data_path = Some(path.clone());
+                        data_files.push((source, path));
+                    }
+                }
+            }
+
+            files.push(file);
+        }
+
+        let documents = files.iter().filter(|f| f.file_type == "document").count() as i64;
+        let total = documents + (media_paths.len() + data_files.len()) as i64;
+        let mut completed = 0;
+        let mut report = |stage, completed| {
+            on_progress(ArchiveProgress {
+                stage,
+                completed,
+                total,
+            })
+        };
+
+        let path = path.to_path_buf();
+        let mut zip = blocking(move || Ok(ZipWriter::new(fs::File::create(path)?))).await?;
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for file in files.iter_mut().filter(|f| f.file_type == "document") {
+            let (content,): (Option<String>,) =
+                sqlx::query_as("SELECT content FROM files WHERE id = ?")
+                    .bind(&file.id)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+            // 文档位于 documents/，媒体引用改为相对路径
+            let html = with_img_src(content.as_deref().unwrap_or_default());
+            let html = rewrite_media_refs(&html, |id| {
+                media_paths.get(id).map(|path| format!("../{}", path))
+            });
+
+            let file_tag_names: Vec<&str> = file_tags
+                .iter()
+                .filter(|ft| ft.file_id == file.id)
+                .filter_map(|ft| tag_names.get(ft.tag_id.as_str()).copied())
+                .collect();
+
+            let html_path = format!("documents/{}.html", file.id);
+            let markdown_path = format!("documents/{}.md", file.id);
+            let page = html_document(&file.title, &html);
+            let yaml = front_matter(
+                &file.title,
+                &file_tag_names,
+                &file.created_at,
+                &file.updated_at,
+            );
+            zip = with_zip(zip, {
+                let (html_path, markdown_path) = (html_path.clone(), markdown_path.clone());
+                move |zip| {
+                    zip.start_file(html_path, deflated)?;
+                    zip.write_all(page.as_bytes())?;
+                    zip.start_file(markdown_path, deflated)?;
+                    zip.write_all(yaml.as_bytes())?;
+                    zip.write_all(html_to_markdown(&html).as_bytes())?;
+                    Ok(())
+                }
+            })
+            .await?;
+
+            file.html_path = Some(html_path);
+            file.markdown_path = Some(markdown_path);
+
+            completed += 1;
+            report(ArchiveStage::Documents, completed);
+        }
+
+        let mut media = Vec::with_capacity(assets.len());
+        for asset in &assets {
+            let path = media_paths.get(&asset.id).cloned();
+            if let Some(path) = &path {
+                let (source, path) = (asset.file_path.clone(), path.clone());
+                zip = with_zip(zip, move |zip| copy_into(zip, Path::new(&source), &path)).await?;
+                completed += 1;
+                report(ArchiveStage::Media, completed);
+            }
+
+            media.push(ManifestMedia {
+                id: asset.id.clone(),
+                file_name: asset.file_name.clone(),
+                file_size: asset.file_size,
+                mime_type: asset.mime_type.clone(),
+                width: asset.width,
+                height: asset.height,
+                created_at: asset.created_at.clone(),
+                path,
+            });
+        }
+
+        for (source, path) in data_files {
+            zip = with_zip(zip, move |zip| copy_into(zip, Path::new(&source), &path)).await?;
+            completed += 1;
+            report(ArchiveStage::Media, completed);
+        }
+
+        let manifest = WorkspaceManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            workspace,
+            folders,
+            files,
+            tags,
+            file_tags,
+            media_assets: media,
+            file_media,
+        };
+        let json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| AppError::Internal(format!("Failed to write manifest: {}", e)))?;
+        let size = blocking(move || {
+            zip.start_file(MANIFEST_PATH, deflated)?;
+            zip.write_all(&json)?;
+
+            let archive = zip.finish()?;
+            let size = archive.metadata()?.len() as i64;
+            archive.sync_all()?;
+            Ok(size)
+        })
+        .await?;
+
+        report(ArchiveStage::Done, total);
+
+        Ok(ExportSummary {
+            documents,
+            media: media_paths.len() as i64,
+            missing_media: (assets.len() - media_paths.len()) as i64,
+            size,
+        })
+    }
+}
+
+/// 读取并校验 manifest.json
+fn read_manifest(archive: &mut ZipArchive<fs::File>) -> Result<WorkspaceManifest> {
+    let json = read_entry(archive, MANIFEST_PATH)?;
+
+    // 先检查格式和版本，字段不兼容时给出明确的错误
+    let header: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| AppError::Validation(format!("Invalid manifest: {}", e)))?;
+    if header.get("format").and_then(|v| v.as_str()) != Some(ARCHIVE_FORMAT) {
+        return Err(AppError::Validation("Not a workspace archive".to_string()));
+    }
+    let version = header.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version == 0 || version > ARCHIVE_VERSION as u64 {
+        return Err(AppError::Validation(format!(
+            "Unsupported archive version: {} (supported: {})",
+            version, ARCHIVE_VERSION
+        )));
+    }
+
+    serde_json::from_value(header)
+        .map_err(|e| AppError::Validation(format!("Invalid manifest: {}", e)))
+}
+
+fn read_entry(archive: &mut ZipArchive<fs::File>, path: &str) -> Result<String> {
+    let mut entry = archive
+        .by_name(path)
+        .map_err(|_| AppError::Validation(format!("Missing archive entry: {}", path)))?;
+
+    let mut text = String::new();
+    io::Read::read_to_string(&mut entry, &mut text)
+        .map_err(|e| AppError::Validation(format!("Invalid archive entry {}: {}", path, e)))?;
+    Ok(text)
+}
+
+/// 取出导出页面 `<body>` 中的内容
+fn html_body(html: &str) -> &str {
+    let start = html.find("<body>").map(|i| i + "<body>".len());
+    let end = html.rfind("</body>");
+
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => html[start..end].trim_matches('\n'),
+        _ => html,
+    }
+}
+
+/// 按新 id 写入文件夹，返回旧 id 到新 id 的映射
+///
+/// 父文件夹先于子文件夹写入；父文件夹不在归档中的文件夹放到根目录。
+async fn insert_folders(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    workspace_id: &str,
+    folders: &[Folder],
+) -> Result<HashMap<String, String>> {
+    let known: HashSet<&str> = folders.iter().map(|f| f.id.as_str()).collect();
+    let mut folder_ids: HashMap<String, String> = HashMap::new();
+    let mut pending: Vec<&Folder> = folders.iter().collect();
+
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<&Folder>, Vec<&Folder>) =
+            pending
+                .into_iter()
+                .partition(|folder| match &folder.parent_id {
+                    Some(parent_id) if known.contains(parent_id.as_str()) => {
+                        folder_ids.contains_key(parent_id)
+                    }
+                    _ => true,
+                });
+
+        // 剩下的父子关系成环，依次写入，父文件夹尚未写入的放到根目录
+        let (ready, rest) = if ready.is_empty() {
+            (rest, Vec::new())
+        } else {
+            (ready, rest)
+        };
+
+        for folder in ready {
+            let id = Uuid::new_v4().to_string();
+            let parent_id = folder
+                .parent_id
+                .as_deref()
+                .and_then(|parent_id| folder_ids.get(parent_id));
+
+            sqlx::query(
+                r#"
+                INSERT INTO folders (id, workspace_id, parent_id, name, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&id)
+            .bind(workspace_id)
+            .bind(parent_id)
+            .bind(&folder.name)
+            .bind(&folder.created_at)
+            .bind(&folder.updated_at)
+            .execute(&mut **tx)
+            .await?;
+
+            folder_ids.insert(folder.id.clone(), id);
+        }
+
+        pending = rest;
+    }
+
+    Ok(folder_ids)
+}
+
+/// 把导出时改写的相对路径换回新的 `media://` 引用，并去掉导出时补上的 `src`
+///
+/// 归档中没有的媒体去掉 id，只留下 `media://`，避免旧 id 碰巧指向其他媒体。
+fn restore_media_refs(
+    html: &str,
+    media_refs: &[(String, String)],
+    media_ids: &HashMap<&str, String>,
+) -> String {
+    let mut content = rewrite_media_refs(&without_img_src(html), |id| match media_ids.get(id) {
+        Some(new_id) => Some(format!("{}{}", MEDIA_SCHEME, new_id)),
+        None => Some(MEDIA_SCHEME.to_string()),
+    });
+    for (relative, reference) in media_refs {
+        content = content.replace(relative.as_str(), reference);
+    }
+
+    content
+}
+
+/// 归档内路径 `<dir>/<id>.<ext>`，扩展名取自源文件
+fn archive_path(dir: &str, id: &str, source: &str) -> String {
+    match Path::new(source).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}/{}.{}", dir, id, ext.to_lowercase()),
+        None => format!("{}/{}", dir, id),
+    }
+}
+
+/// 在阻塞线程池中写入归档，完成后交还 `ZipWriter`
+async fn with_zip(
+    mut zip: ZipWriter<fs::File>,
+    write: impl FnOnce(&mut ZipWriter<fs::File>) -> Result<()> + Send + 'static,
+) -> Result<ZipWriter<fs::File>> {
+    blocking(move || {
+        write(&mut zip)?;
+        Ok(zip)
+    })
+    .await
+}
+
+/// 在阻塞线程池中读取归档，完成后交还 `ZipArchive`
+async fn with_archive<T: Send + 'static>(
+    mut archive: ZipArchive<fs::File>,
+    read: impl FnOnce(&mut ZipArchive<fs::File>) -> Result<T> + Send + 'static,
+) -> Result<(ZipArchive<fs::File>, T)> {
+    blocking(move || {
+        let value = read(&mut archive)?;
+        Ok((archive, value))
+    })
+    .await
+}
+
+/// 在阻塞线程池中检查哪些源文件存在
+async fn existing_files(paths: Vec<String>) -> Result<HashSet<String>> {
+    blocking(move || {
+        Ok(paths
+            .into_iter()
+            .filter(|path| Path::new(path).is_file())
+            .collect())
+    })
+    .await
+}
+
+/// 把磁盘文件写入归档；媒体大多已是压缩格式，直接存储
+fn copy_into(zip: &mut ZipWriter<fs::File>, source: &Path, path: &str) -> Result<()> {
+    let mut reader = fs::File::open(source)?;
+    let large = reader.metadata()?.len() >= u32::MAX as u64;
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(large);
+
+    zip.start_file(path, options)?;
+    io::copy(&mut reader, zip)?;
+    Ok(())
+}
+
+/// 替换内容中的 `media://<id>` 引用，`replace` 返回 None 时保留原样
+pub(crate) fn rewrite_media_refs(
+    content: &str,
+    replace: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find(MEDIA_SCHEME) {
+        let after = &rest[start + MEDIA_SCHEME.len()..];
+        let id_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(after.len());
+        let id = &after[..id_len];
+
+        result.push_str(&rest[..start]);
+        match replace(id) {
+            Some(replacement) => result.push_str(&replacement),
+            None => result.push_str(&rest[start..start + MEDIA_SCHEME.len() + id_len]),
+        }
+        rest = &after[id_len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// 编辑器保存的图片只有 `data-media-src`，补上 `src` 使导出的 HTML 可以直接浏览
+fn with_img_src(html: &str) -> String {
+    const ATTR: &str = "data-media-src=\"";
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<img") {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + len];
+
+        result.push_str(&rest[..start]);
+        result.push_str("<img");
+
+        let has_src = tag.contains(" src=") || tag.contains("\tsrc=") || tag.contains("\nsrc=");
+        if let (false, Some(pos)) = (has_src, tag.find(ATTR)) {
+            let value = &tag[pos + ATTR.len()..];
+            if let Some(end) = value.find('"') {
+                result.push_str(" src=\"");
+                result.push_str(&value[..end]);
+                result.push('"');
+            }
+        }
+
+        result.push_str(&tag[4..]);
+        rest = &rest[start + len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// [`with_img_src`] 的逆操作：`src` 与 `data-media-src` 相同时去掉 `src`
+fn without_img_src(html: &str) -> String {
+    const PREFIX: &str = "<img src=\"";
+    const ATTR: &str = "data-media-src=\"";
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(PREFIX) {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + len];
+        let src = tag[PREFIX.len()..].split('"').next().unwrap_or_default();
+        let media_src = tag
+            .find(ATTR)
+            .and_then(|pos| tag[pos + ATTR.len()..].split('"').next());
+
+        result.push_str(&rest[..start]);
+        if media_src == Some(src) {
+            result.push_str("<img");
+            result.push_str(&tag[PREFIX.len() + src.len() + 1..]);
+        } else {
+            result.push_str(tag);
+        }
+        rest = &rest[start + len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// 完整的 HTML 页面，导入时读取 `<body>` 的内容
+fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+/// Markdown 文件开头的 YAML front matter（字符串使用 JSON 形式，也是合法的 YAML）
+fn front_matter(title: &str, tags: &[&str], created_at: &str, updated_at: &str) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+
+    let mut yaml = String::from("---\n");
+    yaml.push_str(&format!("title: {}\n", quote(title)));
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|t| quote(t)).collect();
+        yaml.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    }
+    yaml.push_str(&format!("created: {}\n", quote(created_at)));
+    yaml.push_str(&format!("updated: {}\n", quote(updated_at)));
+    yaml.push_str("---\n\n");
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateFolder, CreateMediaAsset, CreateTag, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::folder::FolderService;
+    use crate::services::media::MediaService;
+    use crate::services::tag::TagService;
+    use crate::services::workspace::WorkspaceService;
+
+    fn image(media_id: &str) -> String {
+        format!(
+            "<img data-media-src=\"{}{}\" alt=\"pic\">",
+            MEDIA_SCHEME, media_id
+        )
+    }
+
+    #[tokio::test]
+    async fn round_trip_restores_folders_and_strips_missing_media() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("archive-{}", Uuid::new_v4()));
+        let media_dir = temp.join("media");
+        fs::create_dir_all(&media_dir).unwrap();
+
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Source".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+
+        let folders = FolderService::new(pool.clone());
+        let parent = folders
+            .create(CreateFolder {
+                workspace_id: workspace.id.clone(),
+                parent_id: None,
+                name: "Projects".to_string(),
+            })
+            .await
+            .unwrap();
+        let child = folders
+            .create(CreateFolder {
+                workspace_id: workspace.id.clone(),
+                parent_id: Some(parent.id.clone()),
+                name: "Rust".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        let present = media
+            .store(
+                &workspace.id,
+                "present.png".to_string(),
+                "image/png".to_string(),
+                b"present image",
+            )
+            .await
+            .unwrap();
+        let missing = media
+            .create(CreateMediaAsset {
+                workspace_id: workspace.id.clone(),
+                file_name: "missing.png".to_string(),
+                file_path: temp.join("missing.png").to_string_lossy().to_string(),
+                file_size: 1,
+                mime_type: "image/png".to_string(),
+                width: None,
+                height: None,
+                content_hash: None,
+            })
+            .await
+            .unwrap();
+
+        let files = FileService::new(pool.clone());
+        let create = |title: &str, content: String, folder_id: Option<String>| CreateFile {
+            workspace_id: workspace.id.clone(),
+            file_type: "document".to_string(),
+            title: title.to_string(),
+            content: Some(content),
+            file_path: None,
+            file_size: None,
+            mime_type: None,
+            folder_id,
+        };
+        files
+            .create(create(
+                "Nested",
+                format!("<p>text</p>{}{}", image(&present.id), image(&missing.id)),
+                Some(child.id.clone()),
+            ))
+            .await
+            .unwrap();
+        files
+            .create(create("Root", "<p>root</p>".to_string(), None))
+            .await
+            .unwrap();
+
+        let archive = ArchiveService::new(pool.clone(), media_dir.clone());
+        let path = temp.join("workspace.zip");
+        let summary = archive
+            .export_workspace(&workspace.id, &path, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(summary.missing_media, 1);
+
+        let imported = archive.import_workspace(&path, |_| {}).await.unwrap();
+
+        let new_folders = sqlx::query_as::<_, Folder>(
+            "SELECT * FROM folders WHERE workspace_id = ? ORDER BY name",
+        )
+        .bind(&imported.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(new_folders.len(), 2);
+        let (new_parent, new_child) = (&new_folders[0], &new_folders[1]);
+        assert_eq!(new_parent.name, "Projects");
+        assert_eq!(new_parent.parent_id, None);
+        assert_eq!(new_child.name, "Rust");
+        assert_eq!(new_child.parent_id.as_ref(), Some(&new_parent.id));
+        assert_ne!(new_child.id, child.id);
+
+        let new_files =
+            sqlx::query_as::<_, File>("SELECT * FROM files WHERE workspace_id = ? ORDER BY title")
+                .bind(&imported.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(new_files.len(), 2);
+        let (nested, root) = (&new_files[0], &new_files[1]);
+        assert_eq!(nested.folder_id.as_ref(), Some(&new_child.id));
+        assert_eq!(root.folder_id, None);
+
+        let new_media = MediaService::new(pool.clone(), media_dir.clone())
+            .list_by_workspace(&imported.id)
+            .await
+            .unwrap();
+        assert_eq!(new_media.len(), 1);
+
+        // 存在的媒体指向新记录，缺失的媒体不再指向旧 id
+        let content = nested.content.as_deref().unwrap();
+        assert!(content.contains(&format!("{}{}\"", MEDIA_SCHEME, new_media[0].id)));
+        assert!(!content.contains(&present.id));
+        assert!(!content.contains(&missing.id));
+        assert!(content.contains(&format!("data-media-src=\"{}\"", MEDIA_SCHEME)));
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+
+    #[tokio::test]
+    async fn archives_without_folders_import_into_root() {
+        let mut manifest: serde_json::Value = serde_json::json!({
+            "format": ARCHIVE_FORMAT,
+            "version": ARCHIVE_VERSION,
+            "exported_at": "2024-01-01T00:00:00Z",
+            "workspace": {
+                "id": "w", "name": "Old", "description": null,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+            },
+            "files": [{
+                "id": "f", "file_type": "document", "title": "Doc",
+                "file_size": null, "mime_type": null,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z",
+                "html_path": "documents/f.html", "markdown_path": null, "data_path": null
+            }],
+            "tags": [],
+            "file_tags": [],
+            "media_assets": [],
+            "file_media": []
+        });
+        let parsed: WorkspaceManifest = serde_json::from_value(manifest.clone()).unwrap();
+        assert!(parsed.folders.is_empty());
+        assert_eq!(parsed.files[0].folder_id, None);
+
+        // 父文件夹不在归档中或父子关系成环时放到根目录，不会死循环
+        manifest["folders"] = serde_json::json!([
+            {"id": "a", "workspace_id": "w", "parent_id": "b", "name": "A",
+             "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"},
+            {"id": "b", "workspace_id": "w", "parent_id": "a", "name": "B",
+             "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"},
+            {"id": "c", "workspace_id": "w", "parent_id": "gone", "name": "C",
+             "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"}
+        ]);
+        manifest["files"][0]["folder_id"] = serde_json::json!("c");
+
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("archive-{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp).unwrap();
+        let path = temp.join("old.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+        zip.start_file(MANIFEST_PATH, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.start_file("documents/f.html", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(html_document("Doc", "<p>old</p>").as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let archive = ArchiveService::new(pool.clone(), temp.join("media"));
+        let imported = archive.import_workspace(&path, |_| {}).await.unwrap();
+
+        let folders = sqlx::query_as::<_, Folder>(
+            "SELECT * FROM folders WHERE workspace_id = ? ORDER BY name",
+        )
+        .bind(&imported.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let names: Vec<&str> = folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "C"]);
+        assert_eq!(folders[2].parent_id, None);
+
+        let file = sqlx::query_as::<_, File>("SELECT * FROM files WHERE workspace_id = ?")
+            .bind(&imported.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(file.folder_id.as_ref(), Some(&folders[2].id));
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+
+    #[tokio::test]
+    async fn export_then_import_keeps_documents_tags_and_media() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("archive-{}", Uuid::new_v4()));
+        let media_dir = temp.join("media");
+        fs::create_dir_all(&media_dir).unwrap();
+
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Source".to_string(),
+                description: Some("notes".to_string()),
+            })
+            .await
+            .unwrap();
+
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        let photo = media
+            .store(
+                &workspace.id,
+                "photo.png".to_string(),
+                "image/png".to_string(),
+                b"photo bytes",
+            )
+            .await
+            .unwrap();
+        let scan = media
+            .store(
+                &workspace.id,
+                "scan.jpg".to_string(),
+                "image/jpeg".to_string(),
+                b"scan bytes",
+            )
+            .await
+            .unwrap();
+
+        let tags = TagService::new(pool.clone());
+        let rust = tags
+            .create(CreateTag {
+                name: "rust".to_string(),
+                color: Some("#ff0000".to_string()),
+            })
+            .await
+            .unwrap();
+        let notes = tags
+            .create(CreateTag {
+                name: "notes".to_string(),
+                color: None,
+            })
+            .await
+            .unwrap();
+
+        let files = FileService::new(pool.clone());
+        let mut originals = Vec::new();
+        for (title, body, asset, file_tags) in [
+            (
+                "First",
+                "<h1>Plan</h1><p>Hello <strong>world</strong></p>",
+                &photo,
+                vec![&rust, &notes],
+            ),
+            (
+                "Second",
+                "<ul><li><p>one</p></li><li><p>two</p></li></ul>",
+                &scan,
+                vec![&notes],
+            ),
+        ] {
+            let file = files
+                .create(CreateFile {
+                    workspace_id: workspace.id.clone(),
+                    file_type: "document".to_string(),
+                    title: title.to_string(),
+                    content: Some(format!("{}{}", body, image(&asset.id))),
+                    file_path: None,
+                    file_size: None,
+                    mime_type: None,
+                    folder_id: None,
+                })
+                .await
+                .unwrap();
+            media.link_to_file(&file.id, &asset.id).await.unwrap();
+            for tag in file_tags {
+                files.add_tag(&file.id, &tag.id).await.unwrap();
+            }
+            originals.push(file);
+        }
+
+        let archive = ArchiveService::new(pool.clone(), media_dir.clone());
+        let path = temp.join("workspace.zip");
+        let summary = archive
+            .export_workspace(&workspace.id, &path, |_| {})
+            .await
+            .unwrap();
+        assert_eq!((summary.documents, summary.media), (2, 2));
+        assert!(!path.with_extension("part").exists());
+
+        let imported = archive.import_workspace(&path, |_| {}).await.unwrap();
+        assert_eq!(imported.name, "Source");
+        assert_eq!(imported.description.as_deref(), Some("notes"));
+
+        // 媒体：元数据和内容一致，内容相同的文件共用
+        let new_media = media.list_by_workspace(&imported.id).await.unwrap();
+        assert_eq!(new_media.len(), 2);
+        let mut media_ids = HashMap::new();
+        for old in [&photo, &scan] {
+            let new = new_media
+                .iter()
+                .find(|m| m.file_name == old.file_name)
+                .unwrap();
+            assert_ne!(new.id, old.id);
+            assert_eq!(
+                (&new.mime_type, new.file_size, &new.content_hash),
+                (&old.mime_type, old.file_size, &old.content_hash)
+            );
+            assert_eq!(
+                fs::read(&new.file_path).unwrap(),
+                fs::read(&old.file_path).unwrap()
+            );
+            media_ids.insert(old.id.clone(), new.id.clone());
+        }
+
+        // 文档：正文中的媒体引用换成新 id 后与原文相同，标签按名称复用
+        let new_files =
+            sqlx::query_as::<_, File>("SELECT * FROM files WHERE workspace_id = ? ORDER BY title")
+                .bind(&imported.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(new_files.len(), 2);
+        for (old, new) in originals.iter().zip(&new_files) {
+            assert_eq!(new.title, old.title);
+            let expected = rewrite_media_refs(old.content.as_deref().unwrap(), |id| {
+                media_ids
+                    .get(id)
+                    .map(|id| format!("{}{}", MEDIA_SCHEME, id))
+            });
+            assert_eq!(new.content.as_deref(), Some(expected.as_str()));
+            assert_eq!(new.word_count, old.word_count);
+
+            let tag_ids = |tags: Vec<Tag>| tags.into_iter().map(|t| t.id).collect::<Vec<_>>();
+            assert_eq!(
+                tag_ids(files.get_tags(&new.id).await.unwrap()),
+                tag_ids(files.get_tags(&old.id).await.unwrap())
+            );
+
+            let linked: Vec<String> = media
+                .get_file_media(&new.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+            let expected: Vec<String> = media
+                .get_file_media(&old.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|m| media_ids[&m.id].clone())
+                .collect();
+            assert_eq!(linked, expected);
+        }
+        assert_eq!(TagService::new(pool.clone()).list().await.unwrap().len(), 2);
+
+        // 单个文档导出为 Markdown，引用的媒体复制到同名的 assets 目录
+        let dest = temp.join("first.md");
+        archive
+            .export_file_as_markdown(&originals[0].id, &dest)
+            .await
+            .unwrap();
+        let markdown = fs::read_to_string(&dest).unwrap();
+        assert!(markdown.starts_with("---\ntitle: \"First\"\ntags: [\"notes\", \"rust\"]\n"));
+        let asset = format!("first.assets/{}.png", photo.id);
+        assert!(markdown.contains(&asset), "{}", markdown);
+        assert_eq!(fs::read(temp.join(asset)).unwrap(), b"photo bytes");
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_import_removes_extracted_media() {
+        let manifest = serde_json::json!({
+            "format": ARCHIVE_FORMAT,
+            "version": ARCHIVE_VERSION,
+            "exported_at": "2024-01-01T00:00:00Z",
+            "workspace": {
+                "id": "w", "name": "Broken", "description": null,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+            },
+            "files": [{
+                "id": "f", "file_type": "document", "title": "Doc",
+                "file_size": null, "mime_type": null,
+                "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z",
+                "html_path": "documents/missing.html", "markdown_path": null, "data_path": null
+            }],
+            "tags": [],
+            "file_tags": [],
+            "media_assets": [{
+                "id": "m", "file_name": "a.png", "file_size": 5, "mime_type": "image/png",
+                "width": null, "height": null, "created_at": "2024-01-01T00:00:00Z",
+                "path": "media/m.png"
+            }],
+            "file_media": []
+        });
+
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("archive-{}", Uuid::new_v4()));
+        let media_dir = temp.join("media");
+        fs::create_dir_all(&media_dir).unwrap();
+        let path = temp.join("broken.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+        zip.start_file(MANIFEST_PATH, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.start_file("media/m.png", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"image").unwrap();
+        zip.finish().unwrap();
+
+        let archive = ArchiveService::new(pool.clone(), media_dir.clone());
+        let error = archive.import_workspace(&path, |_| {}).await.unwrap_err();
+        assert!(
+            matches!(&error, AppError::Validation(message) if message.contains("documents/missing.html")),
+            "{:?}",
+            error
+        );
+
+        // 事务回滚，已解压的媒体文件被删除
+        let (workspaces,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM workspaces")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(workspaces, 0);
+        assert_eq!(fs::read_dir(&media_dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+
+    #[tokio::test]
+    async fn markdown_files_import_all_or_nothing() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("archive-{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp).unwrap();
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Notes".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+
+        let titled = temp.join("titled.md");
+        fs::write(&titled, "# Heading\n\nSome **bold** text\n").unwrap();
+        let untitled = temp.join("untitled.md");
+        fs::write(&untitled, "just text\n").unwrap();
+
+        let archive = ArchiveService::new(pool.clone(), temp.join("media"));
+        let error = archive
+            .import_markdown_files(&workspace.id, &[titled.clone(), temp.join("gone.md")])
+            .await
+            .unwrap_err();
+        assert!(matches!(&error, AppError::Io(_)), "{:?}", error);
+        assert!(FileService::new(pool.clone())
+            .list_by_workspace(&workspace.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let files = archive
+            .import_markdown_files(&workspace.id, &[titled, untitled])
+            .await
+            .unwrap();
+        let titles: Vec<&str> = files.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, ["Heading", "untitled"]);
+        assert!(files[0]
+            .content
+            .as_deref()
+            .unwrap()
+            .contains("<strong>bold</strong>"));
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+}
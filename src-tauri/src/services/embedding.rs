@@ -0,0 +1,1381 @@
+use super::provider::ProviderService;
+use super::search::{excerpt_sql, map_search_row, placeholders};
+use crate::error::{AppError, Result};
+use crate::models::{
+    ChunkMatch, ContextChunk, EmbeddingConfig, RetrievalQuery, SearchResult, SemanticQuery,
+};
+use crate::text::{is_cjk, segment};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// 每个分块的最大长度（字符数）
+const CHUNK_CHARS: usize = 800;
+
+/// 超长段落按窗口切分时相邻分块的重叠长度（字符数）
+const CHUNK_OVERLAP: usize = 100;
+
+/// 内置哈希向量的维度
+const HASHING_DIM: usize = 256;
+
+/// 单次请求 /embeddings 的最大输入条数
+const REMOTE_BATCH_SIZE: usize = 64;
+
+const REMOTE_TIMEOUT_SECS: u64 = 60;
+
+/// 使用远程后端时，每次查询前最多补齐的文件数，其余的由后续查询或 [`EmbeddingService::index_pending`] 补齐
+const QUERY_CATCH_UP_FILES: i64 = 8;
+
+/// 语义搜索默认返回的分块数
+const DEFAULT_LIMIT: i64 = 20;
+
+/// 检索上下文默认返回的分块数
+const DEFAULT_CONTEXT_LIMIT: i64 = 8;
+
+/// 排名融合时每一路召回的候选分块数
+const FUSION_CANDIDATES: usize = 50;
+
+/// 倒数排名融合的平滑常数
+const RRF_K: f64 = 60.0;
+
+/// 全文召回表达式中的最大词数
+const MAX_KEYWORD_TERMS: usize = 64;
+
+/// 文档分块与向量化，提供语义搜索和相似文档推荐
+///
+/// 向量以归一化的 f32 数组存放在 `file_chunks` 中，查询时在内存中暴力计算余弦相似度。
+/// 文件更新后不会立即重新向量化，而是在下次查询前补齐（见 [`Self::catch_up`]）。
+pub struct EmbeddingService {
+    pool: Pool<Sqlite>,
+    providers: ProviderService,
+    client: reqwest::Client,
+    config: RwLock<EmbeddingConfig>,
+}
+
+impl EmbeddingService {
+    pub fn new(pool: Pool<Sqlite>, provider_key_path: PathBuf) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REMOTE_TIMEOUT_SECS))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            providers: ProviderService::new(pool.clone(), provider_key_path),
+            pool,
+            client,
+            config: RwLock::new(EmbeddingConfig::default()),
+        }
+    }
+
+    /// 从数据库读取保存的后端配置
+    pub async fn load_config(&self) -> Result<()> {
+        let saved: Option<(String,)> =
+            sqlx::query_as("SELECT value FROM app_meta WHERE key = 'embedding_config'")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        if let Some((json,)) = saved {
+            if let Ok(config) = serde_json::from_str(&json) {
+                *self.config.write().await = config;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_config(&self) -> EmbeddingConfig {
+        self.config.read().await.clone()
+    }
+
+    /// 切换向量化后端；已有向量的模型标识不一致，会在下次查询时重新生成
+    pub async fn set_config(&self, config: EmbeddingConfig) -> Result<()> {
+        if let EmbeddingConfig::Provider { provider_id, model } = &config {
+            if self.providers.get(provider_id).await?.is_none() {
+                return Err(AppError::NotFound(format!(
+                    "Provider not found: {}",
+                    provider_id
+                )));
+            }
+            if model.trim().is_empty() {
+                return Err(AppError::Validation(
+                    "Embedding model must not be empty".to_string(),
+                ));
+            }
+        }
+
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Internal(format!("Failed to save embedding config: {}", e)))?;
+        sqlx::query("INSERT OR REPLACE INTO app_meta (key, value) VALUES ('embedding_config', ?)")
+            .bind(json)
+            .execute(&self.pool)
+            .await?;
+
+        *self.config.write().await = config;
+
+        Ok(())
+    }
+
+    /// 为单个文件重新分块并生成向量，已是最新时跳过；返回是否实际生成
+    pub async fn index_file(&self, file_id: &str) -> Result<bool> {
+        let config = self.get_config().await;
+        let model = model_id(&config);
+
+        let row = sqlx::query(
+            r#"
+            SELECT f.content_plain, f.updated_at,
+                   e.model as indexed_model, e.source_updated_at as indexed_at
+            FROM files f
+            LEFT JOIN file_embeddings e ON e.file_id = f.id
+            WHERE f.id = ?
+            "#,
+        )
+        .bind(file_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("File not found: {}", file_id)))?;
+
+        let content_plain: Option<String> = row.get("content_plain");
+        let updated_at: String = row.get("updated_at");
+        let indexed_model: Option<String> = row.get("indexed_model");
+        let indexed_at: Option<String> = row.get("indexed_at");
+        if indexed_model.as_deref() == Some(model.as_str())
+            && indexed_at.as_deref() == Some(updated_at.as_str())
+        {
+            return Ok(false);
+        }
+
+        let chars: Vec<char> = content_plain.unwrap_or_default().chars().collect();
+        let spans = chunk_spans(&chars);
+        let texts: Vec<String> = spans
+            .iter()
+            .map(|&(start, end)| chars[start..end].iter().collect())
+            .collect();
+
+        // 网络请求在事务之外完成
+        let vectors = self.embed(&config, &texts).await?;
+
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        // 触发器会同时清理分块的全文索引
+        sqlx::query("DELETE FROM file_chunks WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for (index, ((start, end), (text, vector))) in spans
+            .iter()
+            .zip(texts.iter().zip(vectors.iter()))
+            .enumerate()
+        {
+            let chunk_id = Uuid::new_v4().to_string();
+
+            sqlx::query(
+                r#"
+                INSERT INTO file_chunks (
+                    id, file_id, chunk_index, content, start_offset, end_offset,
+                    embedding, model, created_at
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&chunk_id)
+            .bind(file_id)
+            .bind(index as i64)
+            .bind(text)
+            .bind(*start as i64)
+            .bind(*end as i64)
+            .bind(encode_vector(vector))
+            .bind(&model)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("INSERT INTO file_chunks_fts (chunk_id, content) VALUES (?, ?)")
+                .bind(&chunk_id)
+                .bind(segment(text))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO file_embeddings (file_id, model, source_updated_at, indexed_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(file_id)
+        .bind(&model)
+        .bind(&updated_at)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// 为新建、已修改或模型已变化的文件生成向量，返回处理的文件数
+    pub async fn index_pending(&self, workspace_id: Option<&str>) -> Result<i64> {
+        self.index_outdated(workspace_id, None).await
+    }
+
+    /// 查询前补齐向量
+    ///
+    /// 内置哈希向量在本地计算，全部补齐；远程后端每次只补齐最近修改的少量文件，
+    /// 避免第一次查询就通过网络向量化整个工作空间。补齐失败时记录日志，使用已有的向量查询。
+    async fn catch_up(&self, workspace_id: Option<&str>) {
+        let limit = match self.get_config().await {
+            EmbeddingConfig::Hashing => None,
+            EmbeddingConfig::Provider { .. } => Some(QUERY_CATCH_UP_FILES),
+        };
+
+        if let Err(e) = self.index_outdated(workspace_id, limit).await {
+            log::warn!("Failed to update embeddings before query: {}", e);
+        }
+    }
+
+    /// 为需要更新的文件生成向量，最近修改的优先，`limit` 为最多处理的文件数
+    async fn index_outdated(&self, workspace_id: Option<&str>, limit: Option<i64>) -> Result<i64> {
+        let model = model_id(&self.get_config().await);
+
+        let mut sql = String::from(
+            r#"
+            SELECT f.id FROM files f
+            LEFT JOIN file_embeddings e ON e.file_id = f.id
+            WHERE (e.file_id IS NULL OR e.model != ? OR e.source_updated_at != f.updated_at)
+              AND f.deleted_at IS NULL
+            "#,
+        );
+        if workspace_id.is_some() {
+            sql.push_str(" AND f.workspace_id = ?");
+        }
+        sql.push_str(" ORDER BY f.updated_at DESC LIMIT ?");
+
+        let mut q = sqlx::query_as::<_, (String,)>(&sql).bind(&model);
+        if let Some(workspace_id) = workspace_id {
+            q = q.bind(workspace_id);
+        }
+        let ids = q.bind(limit.unwrap_or(-1)).fetch_all(&self.pool).await?;
+
+        let mut indexed = 0;
+        for (id,) in ids {
+            if self.index_file(&id).await? {
+                indexed += 1;
+            }
+        }
+
+        Ok(indexed)
+    }
+
+    /// 语义搜索：返回与查询最相近的文档分块
+    pub async fn search(&self, query: SemanticQuery) -> Result<Vec<ChunkMatch>> {
+        let text = query.query.trim();
+        if text.is_empty() {
+            return Err(AppError::Validation(
+                "Search query must not be empty".to_string(),
+            ));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 200) as usize;
+
+        self.catch_up(query.workspace_id.as_deref()).await;
+
+        let config = self.get_config().await;
+        let model = model_id(&config);
+        let query_vector = self
+            .embed(&config, &[text.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let (filter, params) = scope_filter(query.workspace_id, query.file_type, Vec::new());
+        let mut matches: Vec<ChunkMatch> = self
+            .load_chunks(&model, &filter, params)
+            .await?
+            .into_iter()
+            .filter_map(|(_, chunk, vector)| {
+                let score = dot(&query_vector, &vector)?;
+                (score > 0.0).then_some(ChunkMatch { score, ..chunk })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+
+        Ok(matches)
+    }
+
+    /// 检索上下文：全文（BM25）与向量两路召回分块，按倒数排名融合（RRF）后返回
+    ///
+    /// 全文召回时问题中的任一词命中即可，适合直接使用自然语言提问。
+    pub async fn retrieve_context(&self, query: RetrievalQuery) -> Result<Vec<ContextChunk>> {
+        let text = query.query.trim();
+        if text.is_empty() {
+            return Err(AppError::Validation(
+                "Search query must not be empty".to_string(),
+            ));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_CONTEXT_LIMIT).clamp(1, 50) as usize;
+
+        self.catch_up(query.workspace_id.as_deref()).await;
+
+        let config = self.get_config().await;
+        let model = model_id(&config);
+        let query_vector = self
+            .embed(&config, &[text.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let (filter, params) = scope_filter(
+            query.workspace_id,
+            query.file_type,
+            query.tags.unwrap_or_default(),
+        );
+
+        // 向量召回
+        let mut chunks: HashMap<String, ChunkMatch> = HashMap::new();
+        let mut vector_hits: Vec<(String, f64)> = Vec::new();
+        for (id, chunk, vector) in self.load_chunks(&model, &filter, params.clone()).await? {
+            if let Some(score) = dot(&query_vector, &vector).filter(|s| *s > 0.0) {
+                vector_hits.push((id.clone(), score));
+            }
+            chunks.insert(id, chunk);
+        }
+        vector_hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        vector_hits.truncate(FUSION_CANDIDATES);
+
+        // 全文召回
+        let keyword_hits: Vec<(String,)> = match keyword_match_expr(text) {
+            Some(expr) => {
+                let sql = format!(
+                    r#"
+                    SELECT c.id
+                    FROM file_chunks_fts
+                    INNER JOIN file_chunks c ON c.id = file_chunks_fts.chunk_id
+                    INNER JOIN files f ON c.file_id = f.id
+                    WHERE file_chunks_fts MATCH ? AND c.model = ? AND f.deleted_at IS NULL {}
+                    ORDER BY bm25(file_chunks_fts)
+                    LIMIT ?
+                    "#,
+                    filter
+                );
+                let mut q = sqlx::query_as(&sql).bind(expr).bind(&model);
+                for param in params {
+                    q = q.bind(param);
+                }
+                q.bind(FUSION_CANDIDATES as i64)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+
+        // 倒数排名融合：每一路贡献 1 / (k + 名次)
+        let mut fused: HashMap<String, (f64, Option<i64>, Option<i64>)> = HashMap::new();
+        for (rank, (id,)) in keyword_hits.into_iter().enumerate() {
+            let entry = fused.entry(id).or_insert((0.0, None, None));
+            entry.0 += 1.0 / (RRF_K + rank as f64 + 1.0);
+            entry.1 = Some(rank as i64 + 1);
+        }
+        for (rank, (id, _)) in vector_hits.into_iter().enumerate() {
+            let entry = fused.entry(id).or_insert((0.0, None, None));
+            entry.0 += 1.0 / (RRF_K + rank as f64 + 1.0);
+            entry.2 = Some(rank as i64 + 1);
+        }
+
+        let mut results: Vec<ContextChunk> = fused
+            .into_iter()
+            .filter_map(|(id, (score, keyword_rank, vector_rank))| {
+                let chunk = chunks.remove(&id)?;
+                Some(ContextChunk {
+                    file_id: chunk.file_id,
+                    workspace_id: chunk.workspace_id,
+                    title: chunk.title,
+                    chunk_index: chunk.chunk_index,
+                    content: chunk.content,
+                    start_offset: chunk.start_offset,
+                    end_offset: chunk.end_offset,
+                    score,
+                    keyword_rank,
+                    vector_rank,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.file_id.cmp(&b.file_id))
+                .then_with(|| a.chunk_index.cmp(&b.chunk_index))
+        });
+        results.truncate(limit);
+
+        Ok(results)
+    }
+
+    /// 相似文档推荐（基于内容向量，限定在同一工作空间）
+    ///
+    /// 每个文档取其分块向量的平均值作为文档向量；源文档没有内容时返回空列表。
+    pub async fn find_similar(&self, file_id: &str, limit: i64) -> Result<Vec<SearchResult>> {
+        let (workspace_id,): (String,) =
+            sqlx::query_as("SELECT workspace_id FROM files WHERE id = ?")
+                .bind(file_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("File not found: {}", file_id)))?;
+
+        // 源文档不受补齐数量的限制
+        if let Err(e) = self.index_file(file_id).await {
+            log::warn!("Failed to update embeddings of {}: {}", file_id, e);
+        }
+        self.catch_up(Some(&workspace_id)).await;
+
+        let model = model_id(&self.get_config().await);
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+            r#"
+            SELECT c.file_id, c.embedding
+            FROM file_chunks c
+            INNER JOIN files f ON c.file_id = f.id
+            WHERE f.workspace_id = ? AND c.model = ? AND f.deleted_at IS NULL
+            "#,
+        )
+        .bind(&workspace_id)
+        .bind(&model)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sums: HashMap<String, Vec<f32>> = HashMap::new();
+        for (id, blob) in rows {
+            let vector = decode_vector(blob);
+            let sum = sums.entry(id).or_insert_with(|| vec![0.0; vector.len()]);
+            if sum.len() == vector.len() {
+                sum.iter_mut().zip(&vector).for_each(|(s, v)| *s += v);
+            }
+        }
+        let mut vectors: HashMap<String, Vec<f32>> = sums
+            .into_iter()
+            .map(|(id, sum)| (id, normalize(sum)))
+            .collect();
+
+        let Some(source) = vectors.remove(file_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut scored: Vec<(String, f64)> = vectors
+            .into_iter()
+            .filter_map(|(id, vector)| {
+                let score = dot(&source, &vector)?;
+                (score > 0.0).then_some((id, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit.max(0) as usize);
+
+        if scored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            r#"
+            SELECT
+                f.id,
+                f.workspace_id,
+                f.file_type,
+                f.title as title_marked,
+                {} as snippet_marked,
+                f.file_path,
+                f.created_at,
+                f.updated_at,
+                0.0 as rank
+            FROM files f
+            WHERE f.id IN ({})
+            "#,
+            excerpt_sql(),
+            placeholders(scored.len())
+        );
+        let mut q = sqlx::query(&sql);
+        for (id, _) in &scored {
+            q = q.bind(id);
+        }
+        let rows = q.fetch_all(&self.pool).await?;
+
+        let mut by_id: HashMap<String, SearchResult> = rows
+            .iter()
+            .map(map_search_row)
+            .map(|r| (r.id.clone(), r))
+            .collect();
+
+        let results = scored
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut result = by_id.remove(&id)?;
+                result.rank = score;
+                Some(result)
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// 读取指定模型、满足过滤条件的分块及其向量（`score` 为 0）
+    async fn load_chunks(
+        &self,
+        model: &str,
+        filter: &str,
+        params: Vec<String>,
+    ) -> Result<Vec<(String, ChunkMatch, Vec<f32>)>> {
+        let sql = format!(
+            r#"
+            SELECT c.id, c.file_id, f.workspace_id, f.title, c.chunk_index, c.content,
+                   c.start_offset, c.end_offset, c.embedding
+            FROM file_chunks c
+            INNER JOIN files f ON c.file_id = f.id
+            WHERE c.model = ? AND f.deleted_at IS NULL {}
+            "#,
+            filter
+        );
+
+        let mut q = sqlx::query(&sql).bind(model);
+        for param in params {
+            q = q.bind(param);
+        }
+        let rows = q.fetch_all(&self.pool).await?;
+
+        let chunks = rows
+            .iter()
+            .map(|row| {
+                let chunk = ChunkMatch {
+                    file_id: row.get("file_id"),
+                    workspace_id: row.get("workspace_id"),
+                    title: row.get("title"),
+                    chunk_index: row.get("chunk_index"),
+                    content: row.get("content"),
+                    start_offset: row.get("start_offset"),
+                    end_offset: row.get("end_offset"),
+                    score: 0.0,
+                };
+                (row.get("id"), chunk, decode_vector(row.get("embedding")))
+            })
+            .collect();
+
+        Ok(chunks)
+    }
+
+    /// 生成归一化的向量，顺序与输入一致
+    ///
+    /// 远程接口返回的向量维度必须一致（包括不同批次之间），否则视为无效响应。
+    async fn embed(&self, config: &EmbeddingConfig, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        match config {
+            EmbeddingConfig::Hashing => Ok(inputs.iter().map(|s| hashing_embed(s)).collect()),
+            EmbeddingConfig::Provider { provider_id, model } => {
+                let endpoint = self.providers.resolve(Some(provider_id)).await?;
+                let url = format!("{}/embeddings", endpoint.base_url.trim_end_matches('/'));
+                let mut vectors = Vec::with_capacity(inputs.len());
+
+                for batch in inputs.chunks(REMOTE_BATCH_SIZE) {
+                    let mut request = self.client.post(&url).json(&serde_json::json!({
+                        "model": model,
+                        "input": batch,
+                    }));
+                    if let Some(api_key) = &endpoint.api_key {
+                        request = request.bearer_auth(api_key);
+                    }
+
+                    let response = request
+                        .send()
+                        .await
+                        .map_err(|e| AppError::Ai(format!("Embedding request failed: {}", e)))?;
+                    let status = response.status();
+                    if !status.is_success() {
+                        let body = response.text().await.unwrap_or_default();
+                        return Err(AppError::Ai(format!(
+                            "Embedding request failed ({}): {}",
+                            status, body
+                        )));
+                    }
+
+                    let mut body: EmbeddingResponse = response
+                        .json()
+                        .await
+                        .map_err(|e| AppError::Ai(format!("Invalid embedding response: {}", e)))?;
+                    if body.data.len() != batch.len() {
+                        return Err(AppError::Ai(format!(
+                            "Invalid embedding response: expected {} vectors, got {}",
+                            batch.len(),
+                            body.data.len()
+                        )));
+                    }
+
+                    body.data.sort_by_key(|d| d.index);
+                    // 维度以第一个向量为准
+                    let dim = vectors.first().unwrap_or(&body.data[0].embedding).len();
+                    if dim == 0 {
+                        return Err(AppError::Ai(
+                            "Invalid embedding response: empty vector".to_string(),
+                        ));
+                    }
+                    if let Some(d) = body.data.iter().find(|d| d.embedding.len() != dim) {
+                        return Err(AppError::Ai(format!(
+                            "Invalid embedding response: expected dimension {}, got {}",
+                            dim,
+                            d.embedding.len()
+                        )));
+                    }
+
+                    vectors.extend(body.data.into_iter().map(|d| normalize(d.embedding)));
+                }
+
+                Ok(vectors)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// 分块查询的过滤条件（以 AND 开头，`f` 为 files 表别名）及其参数
+fn scope_filter(
+    workspace_id: Option<String>,
+    file_type: Option<String>,
+    tags: Vec<String>,
+) -> (String, Vec<String>) {
+    let mut filter = String::new();
+    let mut params = Vec::new();
+
+    if let Some(workspace_id) = workspace_id {
+        filter.push_str(" AND f.workspace_id = ?");
+        params.push(workspace_id);
+    }
+
+    if let Some(file_type) = file_type {
+        filter.push_str(" AND f.file_type = ?");
+        params.push(file_type);
+    }
+
+    if !tags.is_empty() {
+        filter.push_str(&format!(
+            r#" AND f.id IN (
+                SELECT ft.file_id FROM file_tags ft
+                INNER JOIN tags t ON ft.tag_id = t.id
+                WHERE t.name IN ({})
+                GROUP BY ft.file_id
+                HAVING COUNT(DISTINCT t.id) = {}
+            )"#,
+            placeholders(tags.len()),
+            tags.len()
+        ));
+        params.extend(tags);
+    }
+
+    (filter, params)
+}
+
+/// 把自然语言问题转为全文检索表达式：任一词命中即可，由 BM25 决定排名
+///
+/// 中日韩文字按相邻二字组成短语（只有一个字时单独使用），其他文字按词匹配，
+/// 较长的词使用前缀匹配以覆盖复数等词形变化。
+fn keyword_match_expr(text: &str) -> Option<String> {
+    fn flush(word: &mut String, run: &mut Vec<char>, terms: &mut Vec<String>) {
+        if word.chars().count() >= 3 {
+            terms.push(format!("\"{}\"*", word));
+        } else if !word.is_empty() {
+            terms.push(format!("\"{}\"", word));
+        }
+        word.clear();
+
+        match run.len() {
+            0 => {}
+            1 => terms.push(format!("\"{}\"", run[0])),
+            _ => terms.extend(
+                run.windows(2)
+                    .map(|pair| format!("\"{} {}\"", pair[0], pair[1])),
+            ),
+        }
+        run.clear();
+    }
+
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut run = Vec::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                flush(&mut word, &mut Vec::new(), &mut terms);
+            }
+            run.push(c);
+        } else if c.is_alphanumeric() {
+            if !run.is_empty() {
+                flush(&mut String::new(), &mut run, &mut terms);
+            }
+            word.extend(c.to_lowercase());
+        } else {
+            flush(&mut word, &mut run, &mut terms);
+        }
+    }
+    flush(&mut word, &mut run, &mut terms);
+
+    terms.sort();
+    terms.dedup();
+    terms.truncate(MAX_KEYWORD_TERMS);
+
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// 向量的模型标识，模型不同的向量不可比较
+fn model_id(config: &EmbeddingConfig) -> String {
+    match config {
+        EmbeddingConfig::Hashing => format!("hashing-{}", HASHING_DIM),
+        EmbeddingConfig::Provider { provider_id, model } => format!("{}@{}", model, provider_id),
+    }
+}
+
+/// 切分纯文本，返回各分块的字符区间
+///
+/// 按段落累积到不超过 [`CHUNK_CHARS`]；单个超长段落按固定窗口切分，相邻窗口重叠 [`CHUNK_OVERLAP`]。
+fn chunk_spans(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+
+    for (start, end) in paragraph_spans(chars) {
+        if let Some((chunk_start, chunk_end)) = current {
+            if end - chunk_start <= CHUNK_CHARS {
+                current = Some((chunk_start, end));
+                continue;
+            }
+            spans.push((chunk_start, chunk_end));
+            current = None;
+        }
+
+        if end - start <= CHUNK_CHARS {
+            current = Some((start, end));
+            continue;
+        }
+
+        let mut window_start = start;
+        loop {
+            let window_end = (window_start + CHUNK_CHARS).min(end);
+            spans.push((window_start, window_end));
+            if window_end == end {
+                break;
+            }
+            window_start = window_end - CHUNK_OVERLAP;
+        }
+    }
+
+    if let Some(span) = current {
+        spans.push(span);
+    }
+
+    spans
+}
+
+/// 按行切分并去掉首尾空白，跳过空行
+fn paragraph_spans(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut line_start = 0;
+
+    for i in 0..=chars.len() {
+        if i < chars.len() && chars[i] != '\n' {
+            continue;
+        }
+
+        let mut start = line_start;
+        let mut end = i;
+        while start < end && chars[start].is_whitespace() {
+            start += 1;
+        }
+        while end > start && chars[end - 1].is_whitespace() {
+            end -= 1;
+        }
+        if start < end {
+            spans.push((start, end));
+        }
+
+        line_start = i + 1;
+    }
+
+    spans
+}
+
+/// 内置的特征哈希向量
+///
+/// 词（小写的连续字母数字）和中日韩字符的单字、相邻二字组合经 FNV-1a 哈希到固定维度，
+/// 哈希值的一位决定正负号以抵消碰撞带来的偏差。不理解语义，但能反映词汇重合程度。
+fn hashing_embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; HASHING_DIM];
+    let mut add = |feature: &str| {
+        let hash = fnv1a(feature.as_bytes());
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[(hash % HASHING_DIM as u64) as usize] += sign;
+    };
+
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            let mut buf = [0u8; 4];
+            add(c.encode_utf8(&mut buf));
+            if let Some(prev) = prev_cjk {
+                add(&format!("{}{}", prev, c));
+            }
+            prev_cjk = Some(c);
+        } else {
+            prev_cjk = None;
+        }
+
+        if c.is_alphanumeric() && !is_cjk(c) {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            add(&word);
+            word.clear();
+        }
+    }
+    if !word.is_empty() {
+        add(&word);
+    }
+
+    normalize(vector)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// 归一化向量的点积即余弦相似度；维度不一致时返回 None
+fn dot(a: &[f32], b: &[f32]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    Some(a.iter().zip(b).map(|(x, y)| (x * y) as f64).sum())
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(blob: Vec<u8>) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateAiProvider, CreateFile, CreateTag, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::mock_server::{MockServer, Reply};
+    use crate::services::tag::TagService;
+    use crate::services::workspace::WorkspaceService;
+
+    /// 使用模拟服务器作为向量化后端
+    async fn service(server: &MockServer) -> (EmbeddingService, EmbeddingConfig) {
+        let key_path = std::env::temp_dir().join(format!("embedding-{}.key", Uuid::new_v4()));
+        let service = EmbeddingService::new(memory_pool().await, key_path);
+        let provider = service
+            .providers
+            .create(CreateAiProvider {
+                name: "Mock".to_string(),
+                base_url: server.base_url.clone(),
+                model: "chat-model".to_string(),
+                enabled: Some(true),
+                api_key: Some("sk-test".to_string()),
+            })
+            .await
+            .unwrap();
+
+        let config = EmbeddingConfig::Provider {
+            provider_id: provider.id,
+            model: "embed-model".to_string(),
+        };
+        service.set_config(config.clone()).await.unwrap();
+        (service, config)
+    }
+
+    /// 按输入生成 `[n, 1]` 形式的向量（`n` 为输入的数字），并打乱返回顺序
+    fn embeddings(body: &str, dim: usize) -> Reply {
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        let data: Vec<serde_json::Value> = body["input"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, input)| {
+                let n: f32 = input.as_str().unwrap().parse().unwrap();
+                let mut embedding = vec![1.0; dim];
+                embedding[0] = n;
+                serde_json::json!({ "index": index, "embedding": embedding })
+            })
+            .collect();
+        Reply::json(200, &serde_json::json!({ "data": data }).to_string())
+    }
+
+    fn inputs(count: usize) -> Vec<String> {
+        (0..count).map(|n| n.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn remote_embeddings_are_batched_in_order() {
+        let server = MockServer::start(|_, body| embeddings(body, 2)).await;
+        let (service, config) = service(&server).await;
+
+        let count = REMOTE_BATCH_SIZE + 6;
+        let vectors = service.embed(&config, &inputs(count)).await.unwrap();
+
+        let requests: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|body| serde_json::from_str(body).unwrap())
+            .collect();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["model"], "embed-model");
+        assert_eq!(
+            requests[0]["input"].as_array().unwrap().len(),
+            REMOTE_BATCH_SIZE
+        );
+        assert_eq!(requests[1]["input"].as_array().unwrap().len(), 6);
+        assert_eq!(requests[1]["input"][0], REMOTE_BATCH_SIZE.to_string());
+
+        // 按 index 排序并归一化，顺序与输入一致
+        assert_eq!(vectors.len(), count);
+        for (n, vector) in vectors.iter().enumerate() {
+            assert_eq!(vector.len(), 2);
+            assert!((vector[0] / vector[1] - n as f32).abs() < 1e-3);
+            let norm: f32 = vector.iter().map(|v| v * v).sum();
+            assert!((norm - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[tokio::test]
+    async fn mismatched_dimensions_in_a_batch_are_rejected() {
+        let server = MockServer::sequence(vec![Reply::json(
+            200,
+            r#"{"data":[{"index":0,"embedding":[1,2,3]},{"index":1,"embedding":[1,2]}]}"#,
+        )])
+        .await;
+        let (service, config) = service(&server).await;
+
+        let error = service.embed(&config, &inputs(2)).await.unwrap_err();
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("expected dimension 3, got 2")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn mismatched_dimensions_across_batches_are_rejected() {
+        let server =
+            MockServer::start(|index, body| embeddings(body, if index == 0 { 2 } else { 3 })).await;
+        let (service, config) = service(&server).await;
+
+        let error = service
+            .embed(&config, &inputs(REMOTE_BATCH_SIZE + 1))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, AppError::Ai(message) if message.contains("expected dimension 2, got 3")),
+            "{:?}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_or_missing_vectors_are_rejected() {
+        let server = MockServer::sequence(vec![
+            Reply::json(200, r#"{"data":[{"index":0,"embedding":[]}]}"#),
+            Reply::json(200, r#"{"data":[{"index":0,"embedding":[1,0]}]}"#),
+            Reply::json(429, r#"{"error":{"message":"rate limited"}}"#),
+        ])
+        .await;
+        let (service, config) = service(&server).await;
+
+        let empty = service.embed(&config, &inputs(1)).await.unwrap_err();
+        assert!(
+            matches!(&empty, AppError::Ai(message) if message.contains("empty vector")),
+            "{:?}",
+            empty
+        );
+
+        let missing = service.embed(&config, &inputs(2)).await.unwrap_err();
+        assert!(
+            matches!(&missing, AppError::Ai(message) if message.contains("expected 2 vectors, got 1")),
+            "{:?}",
+            missing
+        );
+
+        let failed = service.embed(&config, &inputs(1)).await.unwrap_err();
+        assert!(
+            matches!(&failed, AppError::Ai(message) if message.contains("429") && message.contains("rate limited")),
+            "{:?}",
+            failed
+        );
+    }
+
+    /// 按输入长度生成向量，输入包含 `broken` 时返回服务端错误
+    fn length_embeddings(body: &str) -> Reply {
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        let inputs = body["input"].as_array().unwrap();
+        if inputs
+            .iter()
+            .any(|input| input.as_str().unwrap().contains("broken"))
+        {
+            return Reply::json(500, r#"{"error":{"message":"model crashed"}}"#);
+        }
+
+        let data: Vec<serde_json::Value> = inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let len = input.as_str().unwrap().len() as f32;
+                serde_json::json!({ "index": index, "embedding": [1.0, len] })
+            })
+            .collect();
+        Reply::json(200, &serde_json::json!({ "data": data }).to_string())
+    }
+
+    /// 在新工作空间中按顺序创建文档，返回工作空间 id
+    async fn documents(pool: &Pool<Sqlite>, texts: &[&str]) -> String {
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Embeddings".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+
+        let files = FileService::new(pool.clone());
+        for (i, text) in texts.iter().enumerate() {
+            files
+                .create(CreateFile {
+                    workspace_id: workspace.id.clone(),
+                    file_type: "document".to_string(),
+                    title: format!("Doc {}", i),
+                    content: Some(format!("<p>{}</p>", text)),
+                    file_path: None,
+                    file_size: None,
+                    mime_type: None,
+                    folder_id: None,
+                })
+                .await
+                .unwrap();
+            // 保证更新时间各不相同
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        workspace.id
+    }
+
+    async fn indexed_files(pool: &Pool<Sqlite>) -> i64 {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM file_embeddings")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    fn semantic_query(query: &str) -> SemanticQuery {
+        SemanticQuery {
+            query: query.to_string(),
+            workspace_id: None,
+            file_type: None,
+            limit: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_catch_up_is_bounded_per_query() {
+        let server = MockServer::start(|_, body| length_embeddings(body)).await;
+        let (service, _) = service(&server).await;
+        let texts: Vec<String> = (0..QUERY_CATCH_UP_FILES + 2)
+            .map(|i| format!("note number {}", i))
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        documents(&service.pool, &texts).await;
+
+        let results = service.search(semantic_query("note")).await.unwrap();
+        assert_eq!(results.len() as i64, QUERY_CATCH_UP_FILES);
+        assert_eq!(indexed_files(&service.pool).await, QUERY_CATCH_UP_FILES);
+
+        // 剩下的由下一次查询或手动补齐
+        assert_eq!(service.index_pending(None).await.unwrap(), 2);
+        assert_eq!(indexed_files(&service.pool).await, QUERY_CATCH_UP_FILES + 2);
+    }
+
+    #[tokio::test]
+    async fn search_uses_existing_vectors_when_catch_up_fails() {
+        let server = MockServer::start(|_, body| length_embeddings(body)).await;
+        let (service, _) = service(&server).await;
+        // 更新时间倒序处理：先成功向量化 good，再在 broken 上失败
+        documents(&service.pool, &["broken note", "good note"]).await;
+
+        let results = service.search(semantic_query("note")).await.unwrap();
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["Doc 1"]);
+
+        // 手动补齐时仍然报告错误
+        let error = service.index_pending(None).await.unwrap_err();
+        assert!(matches!(&error, AppError::Ai(message) if message.contains("model crashed")));
+    }
+
+    #[tokio::test]
+    async fn hashing_backend_indexes_everything_before_query() {
+        let service = hashing_service(memory_pool().await);
+        let texts: Vec<String> = (0..QUERY_CATCH_UP_FILES + 2)
+            .map(|i| format!("note number {}", i))
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        documents(&service.pool, &texts).await;
+
+        service.search(semantic_query("note")).await.unwrap();
+        assert_eq!(indexed_files(&service.pool).await, QUERY_CATCH_UP_FILES + 2);
+    }
+
+    fn spans(text: &str) -> Vec<(usize, usize)> {
+        chunk_spans(&text.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn chunk_spans_group_short_paragraphs() {
+        assert!(spans("").is_empty());
+        assert!(spans(" \n\n \n").is_empty());
+        assert_eq!(spans("a\n\n  b  \n"), [(0, 6)]);
+
+        let text = format!("{}\n{}", "x".repeat(500), "y".repeat(500));
+        assert_eq!(spans(&text), [(0, 500), (501, 1001)]);
+
+        let text = format!("{}\n{}", "x".repeat(400), "y".repeat(399));
+        assert_eq!(spans(&text), [(0, CHUNK_CHARS)]);
+    }
+
+    #[test]
+    fn chunk_spans_window_long_paragraphs() {
+        let text = format!("short\n{}", "中".repeat(2000));
+        assert_eq!(
+            spans(&text),
+            [
+                (0, 5),
+                (6, 6 + CHUNK_CHARS),
+                (
+                    6 + CHUNK_CHARS - CHUNK_OVERLAP,
+                    6 + 2 * CHUNK_CHARS - CHUNK_OVERLAP
+                ),
+                (6 + 2 * CHUNK_CHARS - 2 * CHUNK_OVERLAP, 2006),
+            ]
+        );
+    }
+
+    #[test]
+    fn hashing_embed_reflects_shared_words() {
+        let rust = hashing_embed("Rust async runtime");
+        assert_eq!(rust.len(), HASHING_DIM);
+        let norm: f32 = rust.iter().map(|v| v * v).sum();
+        assert!((norm - 1.0).abs() < 1e-5);
+
+        // 不区分大小写，忽略标点
+        assert_eq!(rust, hashing_embed("rust, ASYNC runtime!"));
+
+        let related = dot(&rust, &hashing_embed("async rust")).unwrap();
+        let unrelated = dot(&rust, &hashing_embed("python pandas")).unwrap();
+        assert!(related > 0.5, "{}", related);
+        assert!(related > unrelated);
+
+        // 中文按单字和相邻二字组合
+        let cjk = dot(&hashing_embed("中文搜索"), &hashing_embed("搜索引擎")).unwrap();
+        assert!(cjk > 0.0);
+        assert!(dot(&hashing_embed("中文搜索"), &hashing_embed("中文搜索")).unwrap() > 0.999);
+
+        assert!(hashing_embed("").iter().all(|v| *v == 0.0));
+        assert_eq!(dot(&rust, &[]), None);
+    }
+
+    fn hashing_service(pool: Pool<Sqlite>) -> EmbeddingService {
+        let key_path = std::env::temp_dir().join(format!("embedding-{}.key", Uuid::new_v4()));
+        EmbeddingService::new(pool, key_path)
+    }
+
+    fn retrieval_query(query: &str) -> RetrievalQuery {
+        RetrievalQuery {
+            query: query.to_string(),
+            workspace_id: None,
+            file_type: None,
+            tags: None,
+            limit: None,
+        }
+    }
+
+    async fn retrieved_titles(service: &EmbeddingService, query: RetrievalQuery) -> Vec<String> {
+        let mut titles: Vec<String> = service
+            .retrieve_context(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|chunk| chunk.title)
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[tokio::test]
+    async fn chunks_found_by_both_rankings_come_first() {
+        let service = hashing_service(memory_pool().await);
+        // Doc 0 同时被全文和向量命中；Doc 1 只有前缀匹配，没有相同的词；
+        // Doc 2 只有相同的单字，没有相邻二字组成的短语
+        documents(&service.pool, &["rust 搜索 tools", "rusty crates", "索 搜"]).await;
+
+        let results = service
+            .retrieve_context(retrieval_query("rust 搜索"))
+            .await
+            .unwrap();
+        let mut ranks: Vec<(&str, bool, bool)> = results
+            .iter()
+            .map(|r| {
+                (
+                    r.title.as_str(),
+                    r.keyword_rank.is_some(),
+                    r.vector_rank.is_some(),
+                )
+            })
+            .collect();
+        // 两个单路命中的分数相同，按文件 id 排序，这里不比较先后
+        ranks[1..].sort();
+        assert_eq!(
+            ranks,
+            [
+                ("Doc 0", true, true),
+                ("Doc 1", true, false),
+                ("Doc 2", false, true)
+            ]
+        );
+
+        let best = &results[0];
+        assert_eq!(best.keyword_rank, Some(1));
+        assert_eq!(best.vector_rank, Some(1));
+        assert!((best.score - 2.0 / (RRF_K + 1.0)).abs() < 1e-9);
+        assert!(results[1..].iter().all(|r| r.score < best.score));
+        assert_eq!((best.start_offset, best.end_offset), (0, 13));
+    }
+
+    #[tokio::test]
+    async fn retrieval_honors_scope_and_skips_trash() {
+        let pool = memory_pool().await;
+        let service = hashing_service(pool.clone());
+        let first = documents(&pool, &["shared topic alpha", "shared topic beta"]).await;
+        let second = documents(&pool, &["shared topic gamma"]).await;
+
+        let files = FileService::new(pool.clone());
+        let all = files.list_by_workspace(&first).await.unwrap();
+        let alpha = all.iter().find(|f| f.title == "Doc 0").unwrap();
+        let beta = all.iter().find(|f| f.title == "Doc 1").unwrap();
+        let tag = TagService::new(pool.clone())
+            .create(CreateTag {
+                name: "keep".to_string(),
+                color: None,
+            })
+            .await
+            .unwrap();
+        files.add_tag(&alpha.id, &tag.id).await.unwrap();
+        files
+            .create(CreateFile {
+                workspace_id: first.clone(),
+                file_type: "note".to_string(),
+                title: "Note".to_string(),
+                content: Some("<p>shared topic delta</p>".to_string()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+                folder_id: None,
+            })
+            .await
+            .unwrap();
+
+        let query =
+            |workspace_id: Option<&str>, file_type: Option<&str>, tags: &[&str]| RetrievalQuery {
+                workspace_id: workspace_id.map(str::to_string),
+                file_type: file_type.map(str::to_string),
+                tags: (!tags.is_empty()).then(|| tags.iter().map(|t| t.to_string()).collect()),
+                ..retrieval_query("shared topic")
+            };
+
+        assert_eq!(
+            retrieved_titles(&service, query(None, None, &[])).await,
+            ["Doc 0", "Doc 0", "Doc 1", "Note"]
+        );
+        assert_eq!(
+            retrieved_titles(&service, query(Some(&second), None, &[])).await,
+            ["Doc 0"]
+        );
+        assert_eq!(
+            retrieved_titles(&service, query(Some(&first), Some("document"), &[])).await,
+            ["Doc 0", "Doc 1"]
+        );
+        assert_eq!(
+            retrieved_titles(&service, query(Some(&first), None, &["keep"])).await,
+            ["Doc 0"]
+        );
+        assert!(
+            retrieved_titles(&service, query(Some(&first), None, &["keep", "missing"]))
+                .await
+                .is_empty()
+        );
+
+        // 已索引的分块在文件移入回收站后不再返回
+        files.delete(&beta.id).await.unwrap();
+        assert_eq!(
+            retrieved_titles(&service, query(Some(&first), Some("document"), &[])).await,
+            ["Doc 0"]
+        );
+    }
+
+    #[test]
+    fn keyword_match_expr_builds_fts_terms() {
+        assert_eq!(keyword_match_expr(""), None);
+        assert_eq!(keyword_match_expr("  ?! \"\" "), None);
+
+        // 引号等标点只作为分隔符，不会破坏表达式；较长的词使用前缀匹配
+        assert_eq!(
+            keyword_match_expr(r#"say "Hello" to it's"#).as_deref(),
+            Some(r#""hello"* OR "it" OR "s" OR "say"* OR "to""#)
+        );
+
+        // 中日韩文字按相邻二字组成短语，单个字单独使用
+        assert_eq!(
+            keyword_match_expr("中文搜索").as_deref(),
+            Some(r#""中 文" OR "搜 索" OR "文 搜""#)
+        );
+        assert_eq!(
+            keyword_match_expr("Rust中文 和 AI").as_deref(),
+            Some(r#""ai" OR "rust"* OR "中 文" OR "和""#)
+        );
+    }
+}
@@ -1,47 +1,81 @@
-use crate::models::{CreateFile, File, Tag, UpdateFile};
-use anyhow::Result;
+use super::revision::RevisionService;
+use super::search::index_file;
+use crate::error::{AppError, Result};
+use crate::models::{
+    CreateFile, File, FilePage, FileRevision, FileSortField, FileSummary, ListFilesQuery,
+    SortDirection, Tag, UpdateFile,
+};
+use crate::sanitize::sanitize_html;
+use crate::text::document_stats;
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Row, Sqlite, SqliteConnection};
 use uuid::Uuid;
 
 pub struct FileService {
     pool: Pool<Sqlite>,
+    revisions: RevisionService,
 }
 
 impl FileService {
     pub fn new(pool: Pool<Sqlite>) -> Self {
-        Self { pool }
+        Self {
+            revisions: RevisionService::new(pool.clone()),
+            pool,
+        }
     }
 
     pub async fn create(&self, data: CreateFile) -> Result<File> {
         let id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
+        // 正文先按编辑器白名单清理，纯文本和统计信息由清理后的 HTML 生成
+        let content = data.content.as_deref().map(sanitize_html);
+
+        if let Some(folder_id) = &data.folder_id {
+            let folder: Option<(String,)> =
+                sqlx::query_as("SELECT workspace_id FROM folders WHERE id = ?")
+                    .bind(folder_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+            match folder {
+                None => {
+                    return Err(AppError::NotFound(format!(
+                        "Folder not found: {}",
+                        folder_id
+                    )))
+                }
+                Some((workspace_id,)) if workspace_id != data.workspace_id => {
+                    return Err(AppError::Validation(
+                        "Folder belongs to another workspace".to_string(),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
 
-        let file = sqlx::query_as::<_, File>(
+        sqlx::query(
             r#"
             INSERT INTO files (
-                id, workspace_id, file_type, title, content, content_plain,
-                file_path, file_size, mime_type, created_at, updated_at
+                id, workspace_id, file_type, title, content,
+                file_path, file_size, mime_type, created_at, updated_at, folder_id
             )
             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            RETURNING *
             "#,
         )
         .bind(&id)
         .bind(&data.workspace_id)
         .bind(&data.file_type)
         .bind(&data.title)
-        .bind(&data.content)
-        .bind(&data.content_plain)
+        .bind(&content)
         .bind(&data.file_path)
-        .bind(&data.file_size)
+        .bind(data.file_size)
         .bind(&data.mime_type)
         .bind(&now)
         .bind(&now)
-        .fetch_one(&self.pool)
+        .bind(&data.folder_id)
+        .execute(&self.pool)
         .await?;
 
-        Ok(file)
+        self.refresh(&id, true).await
     }
 
     pub async fn get(&self, id: &str) -> Result<Option<File>> {
@@ -53,9 +87,18 @@ impl FileService {
         Ok(file)
     }
 
+    /// 不在回收站中的文件，回收站中的文件需要先恢复才能修改
+    async fn active(&self, id: &str) -> Result<File> {
+        sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("File not found: {}", id)))
+    }
+
     pub async fn list_by_workspace(&self, workspace_id: &str) -> Result<Vec<File>> {
         let files = sqlx::query_as::<_, File>(
-            "SELECT * FROM files WHERE workspace_id = ? ORDER BY updated_at DESC",
+            "SELECT * FROM files WHERE workspace_id = ? AND deleted_at IS NULL ORDER BY updated_at DESC",
         )
         .bind(workspace_id)
         .fetch_all(&self.pool)
@@ -66,7 +109,11 @@ impl FileService {
 
     pub async fn list_by_type(&self, workspace_id: &str, file_type: &str) -> Result<Vec<File>> {
         let files = sqlx::query_as::<_, File>(
-            "SELECT * FROM files WHERE workspace_id = ? AND file_type = ? ORDER BY updated_at DESC",
+            r#"
+            SELECT * FROM files
+            WHERE workspace_id = ? AND file_type = ? AND deleted_at IS NULL
+            ORDER BY updated_at DESC
+            "#,
         )
         .bind(workspace_id)
         .bind(file_type)
@@ -76,7 +123,129 @@ impl FileService {
         Ok(files)
     }
 
+    /// 分页列出文件摘要（不含正文）
+    pub async fn list(&self, query: ListFilesQuery) -> Result<FilePage> {
+        let offset = query.offset.unwrap_or(0).max(0);
+        let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+        let mut filter = String::from("WHERE f.workspace_id = ? AND f.deleted_at IS NULL");
+        let mut params: Vec<String> = vec![query.workspace_id];
+
+        if let Some(file_type) = query.file_type {
+            filter.push_str(" AND f.file_type = ?");
+            params.push(file_type);
+        }
+
+        let count_sql = format!("SELECT COUNT(*) FROM files f {}", filter);
+        let mut count_query = sqlx::query_as::<_, (i64,)>(&count_sql);
+        for param in &params {
+            count_query = count_query.bind(param);
+        }
+        let (total,) = count_query.fetch_one(&self.pool).await?;
+
+        let sort_column = match query.sort_by.unwrap_or_default() {
+            FileSortField::Title => "f.title COLLATE NOCASE",
+            FileSortField::Created => "f.created_at",
+            FileSortField::Updated => "f.updated_at",
+            FileSortField::Size => "size",
+        };
+        let direction = match query.direction.unwrap_or_default() {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+
+        let sql = format!(
+            r#"
+            SELECT
+                f.id,
+                f.workspace_id,
+                f.file_type,
+                f.title,
+                COALESCE(f.file_size, LENGTH(CAST(f.content AS BLOB)), 0) as size,
+                COALESCE(f.word_count, 0) as word_count,
+                COALESCE(f.char_count, 0) as char_count,
+                COALESCE(f.paragraph_count, 0) as paragraph_count,
+                COALESCE(f.reading_minutes, 0) as reading_minutes,
+                f.folder_id,
+                (
+                    SELECT json_group_array(t.name)
+                    FROM file_tags ft
+                    INNER JOIN tags t ON ft.tag_id = t.id
+                    WHERE ft.file_id = f.id
+                ) as tags,
+                f.created_at,
+                f.updated_at
+            FROM files f
+            {}
+            ORDER BY {} {}, f.id {}
+            LIMIT ? OFFSET ?
+            "#,
+            filter, sort_column, direction, direction
+        );
+
+        let mut q = sqlx::query(&sql);
+        for param in params {
+            q = q.bind(param);
+        }
+        let rows = q.bind(limit).bind(offset).fetch_all(&self.pool).await?;
+
+        let items: Vec<FileSummary> = rows
+            .iter()
+            .map(|row| {
+                let tags: String = row.get("tags");
+                FileSummary {
+                    id: row.get("id"),
+                    workspace_id: row.get("workspace_id"),
+                    file_type: row.get("file_type"),
+                    title: row.get("title"),
+                    size: row.get("size"),
+                    word_count: row.get("word_count"),
+                    char_count: row.get("char_count"),
+                    paragraph_count: row.get("paragraph_count"),
+                    reading_minutes: row.get("reading_minutes"),
+                    folder_id: row.get("folder_id"),
+                    tags: serde_json::from_str(&tags).unwrap_or_default(),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            })
+            .collect();
+
+        Ok(FilePage {
+            has_more: offset + (items.len() as i64) < total,
+            items,
+            total,
+            offset,
+            limit,
+        })
+    }
+
+    /// 为缺少统计信息的文档（升级前创建的）重新生成纯文本和统计信息
+    pub async fn backfill_stats(&self) -> Result<()> {
+        let ids: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM files WHERE content IS NOT NULL AND char_count IS NULL")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut conn = self.pool.acquire().await?;
+        for (id,) in ids {
+            update_document_stats(&mut conn, &id).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn update(&self, id: &str, data: UpdateFile) -> Result<File> {
+        let content = data.content.as_deref().map(sanitize_html);
+
+        // 内容发生变化时，先为旧内容写入快照（自动保存按时间窗口节流）
+        let current = self.active(id).await?;
+        if let Some(content) = &content {
+            if current.content.as_ref() != Some(content) {
+                self.revisions.snapshot(&current, "autosave", false).await?;
+            }
+        }
+
         let now = Utc::now().to_rfc3339();
 
         let mut query = String::from("UPDATE files SET updated_at = ?");
@@ -87,16 +256,12 @@ impl FileService {
             params.push(title);
         }
 
-        if let Some(content) = data.content {
+        let content_changed = content.is_some();
+        if let Some(content) = content {
             query.push_str(", content = ?");
             params.push(content);
         }
 
-        if let Some(content_plain) = data.content_plain {
-            query.push_str(", content_plain = ?");
-            params.push(content_plain);
-        }
-
         if let Some(file_path) = data.file_path {
             query.push_str(", file_path = ?");
             params.push(file_path);
@@ -112,26 +277,63 @@ impl FileService {
             params.push(mime_type);
         }
 
-        query.push_str(" WHERE id = ? RETURNING *");
+        query.push_str(" WHERE id = ? AND deleted_at IS NULL");
         params.push(id.to_string());
 
-        let mut q = sqlx::query_as::<_, File>(&query);
+        let mut q = sqlx::query(&query);
         for param in params {
             q = q.bind(param);
         }
 
-        let file = q.fetch_one(&self.pool).await?;
+        if q.execute(&self.pool).await?.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("File not found: {}", id)));
+        }
 
-        Ok(file)
+        self.refresh(id, content_changed).await
     }
 
+    /// 手动保存一个版本快照
+    pub async fn create_revision(&self, id: &str) -> Result<Option<FileRevision>> {
+        let file = self.active(id).await?;
+
+        self.revisions.snapshot(&file, "manual", true).await
+    }
+
+    /// 恢复到指定版本，恢复前会为当前内容保存快照
+    pub async fn restore_revision(&self, revision_id: &str) -> Result<File> {
+        let revision =
+            self.revisions.get(revision_id).await?.ok_or_else(|| {
+                AppError::NotFound(format!("Revision not found: {}", revision_id))
+            })?;
+
+        let current = self.active(&revision.file_id).await?;
+        self.revisions.snapshot(&current, "restore", true).await?;
+
+        self.update(
+            &revision.file_id,
+            UpdateFile {
+                title: Some(revision.title),
+                content: Some(revision.content.unwrap_or_default()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+            },
+        )
+        .await
+    }
+
+    /// 移入回收站，彻底删除见 `TrashService`
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM files WHERE id = ?")
+        let now = Utc::now().to_rfc3339();
+
+        sqlx::query("UPDATE files SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(&now)
             .bind(id)
             .execute(&self.pool)
             .await?;
 
-        Ok(())
+        // 回收站中的文件不参与全文索引
+        self.reindex(id).await
     }
 
     // 标签相关操作
@@ -148,7 +350,7 @@ impl FileService {
         .await?;
 
         // 更新 FTS 表的标签
-        self.update_fts_tags(file_id).await?;
+        self.reindex(file_id).await?;
 
         Ok(())
     }
@@ -161,7 +363,7 @@ impl FileService {
             .await?;
 
         // 更新 FTS 表的标签
-        self.update_fts_tags(file_id).await?;
+        self.reindex(file_id).await?;
 
         Ok(())
     }
@@ -182,22 +384,131 @@ impl FileService {
         Ok(tags)
     }
 
-    async fn update_fts_tags(&self, file_id: &str) -> Result<()> {
-        // 获取文件的所有标签
-        let tags = self.get_tags(file_id).await?;
-        let tags_str = tags
-            .iter()
-            .map(|t| t.name.clone())
-            .collect::<Vec<_>>()
-            .join(" ");
+    async fn reindex(&self, file_id: &str) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        index_file(&mut conn, file_id).await
+    }
 
-        // 更新 FTS 表
-        sqlx::query("UPDATE files_fts SET tags = ? WHERE file_id = ?")
-            .bind(&tags_str)
+    /// 正文变化时重新计算统计信息，然后刷新全文索引并返回最新的文件
+    async fn refresh(&self, file_id: &str, content_changed: bool) -> Result<File> {
+        let mut conn = self.pool.acquire().await?;
+        if content_changed {
+            update_document_stats(&mut conn, file_id).await?;
+        }
+        index_file(&mut conn, file_id).await?;
+
+        let file = sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = ?")
             .bind(file_id)
-            .execute(&self.pool)
+            .fetch_one(&mut *conn)
             .await?;
 
-        Ok(())
+        Ok(file)
+    }
+}
+
+/// 根据文档正文重新生成 `content_plain` 和统计信息（字数、段落、阅读时间、大纲）
+///
+/// 所有写入正文的地方都应在写入后调用，保证列表、搜索和编辑器看到的统计一致。
+pub async fn update_document_stats(conn: &mut SqliteConnection, file_id: &str) -> Result<()> {
+    let content: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT content FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    let Some((Some(content),)) = content else {
+        return Ok(());
+    };
+
+    let stats = document_stats(&content);
+    let outline = serde_json::to_string(&stats.outline)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize outline: {}", e)))?;
+
+    sqlx::query(
+        r#"
+        UPDATE files
+        SET content_plain = ?, word_count = ?, char_count = ?, paragraph_count = ?,
+            reading_minutes = ?, outline = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&stats.plain)
+    .bind(stats.word_count)
+    .bind(stats.char_count)
+    .bind(stats.paragraph_count)
+    .bind(stats.reading_minutes)
+    .bind(&outline)
+    .bind(file_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::CreateWorkspace;
+    use crate::services::workspace::WorkspaceService;
+
+    fn content(text: &str) -> UpdateFile {
+        UpdateFile {
+            title: None,
+            content: Some(format!("<p>{}</p>", text)),
+            file_path: None,
+            file_size: None,
+            mime_type: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn trashed_files_cannot_be_edited() {
+        let pool = memory_pool().await;
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Files".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+        let service = FileService::new(pool.clone());
+        let file = service
+            .create(CreateFile {
+                workspace_id: workspace.id.clone(),
+                file_type: "document".to_string(),
+                title: "Doc".to_string(),
+                content: Some("<p>first</p>".to_string()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+                folder_id: None,
+            })
+            .await
+            .unwrap();
+        service.update(&file.id, content("second")).await.unwrap();
+        let revision = service.create_revision(&file.id).await.unwrap().unwrap();
+        let revisions = service.revisions.list(&file.id).await.unwrap().len();
+
+        service.delete(&file.id).await.unwrap();
+
+        for result in [
+            service.update(&file.id, content("third")).await,
+            service.restore_revision(&revision.id).await,
+        ] {
+            assert!(matches!(result, Err(AppError::NotFound(_))), "{:?}", result);
+        }
+        assert!(matches!(
+            service.create_revision(&file.id).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        // 内容和版本都没有变化
+        let trashed = service.get(&file.id).await.unwrap().unwrap();
+        assert_eq!(trashed.content.as_deref(), Some("<p>second</p>"));
+        assert!(trashed.deleted_at.is_some());
+        assert_eq!(
+            service.revisions.list(&file.id).await.unwrap().len(),
+            revisions
+        );
     }
 }
@@ -0,0 +1,517 @@
+use crate::error::{AppError, Result};
+use crate::models::{
+    CreateFolder, Folder, FolderDeleteMode, FolderNode, FolderTree, FolderTreeFile,
+};
+use chrono::Utc;
+use sqlx::error::ErrorKind;
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 按路径过滤文件的 SQL 条件（包含子文件夹），参数见 [`folder_filter_params`]
+///
+/// 路径为空时表示根目录，只匹配不在任何文件夹中的文件。路径只在指定的工作空间中解析，
+/// 未指定工作空间时在文件所属的工作空间中解析。
+pub fn folder_filter_sql(path: &str, workspace_id: Option<&str>) -> String {
+    if path.is_empty() {
+        return "f.folder_id IS NULL".to_string();
+    }
+    let workspace = if workspace_id.is_some() {
+        "?"
+    } else {
+        "f.workspace_id"
+    };
+    format!(
+        r#"f.folder_id IN (
+            WITH RECURSIVE folder_paths(id, path) AS (
+                SELECT id, name FROM folders WHERE parent_id IS NULL AND workspace_id = {}
+                UNION ALL
+                SELECT c.id, p.path || '/' || c.name
+                FROM folders c INNER JOIN folder_paths p ON c.parent_id = p.id
+            )
+            SELECT id FROM folder_paths WHERE path = ? OR substr(path, 1, length(?)) = ?
+        )"#,
+        workspace
+    )
+}
+
+pub fn folder_filter_params(path: &str, workspace_id: Option<&str>) -> Vec<String> {
+    if path.is_empty() {
+        return vec![];
+    }
+    let prefix = format!("{}/", path);
+    workspace_id
+        .map(str::to_string)
+        .into_iter()
+        .chain([path.to_string(), prefix.clone(), prefix])
+        .collect()
+}
+
+/// 规范化文件夹路径：去掉首尾和多余的 `/` 以及各级名称两侧的空白
+pub fn normalize_folder_path(path: &str) -> String {
+    path.split('/')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub struct FolderService {
+    pool: Pool<Sqlite>,
+}
+
+impl FolderService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, data: CreateFolder) -> Result<Folder> {
+        let name = validate_name(&data.name)?;
+        if let Some(parent_id) = &data.parent_id {
+            let parent = self.require(parent_id).await?;
+            if parent.workspace_id != data.workspace_id {
+                return Err(AppError::Validation(
+                    "Parent folder belongs to another workspace".to_string(),
+                ));
+            }
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+
+        let folder = sqlx::query_as::<_, Folder>(
+            r#"
+            INSERT INTO folders (id, workspace_id, parent_id, name, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(&id)
+        .bind(&data.workspace_id)
+        .bind(&data.parent_id)
+        .bind(name)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| name_conflict(e, name))?;
+
+        Ok(folder)
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Folder>> {
+        let folder = sqlx::query_as::<_, Folder>("SELECT * FROM folders WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(folder)
+    }
+
+    pub async fn rename(&self, id: &str, name: &str) -> Result<Folder> {
+        let name = validate_name(name)?;
+        let now = Utc::now().to_rfc3339();
+
+        let folder = sqlx::query_as::<_, Folder>(
+            "UPDATE folders SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+        )
+        .bind(name)
+        .bind(&now)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| name_conflict(e, name))?;
+
+        folder.ok_or_else(|| AppError::NotFound(format!("Folder not found: {}", id)))
+    }
+
+    /// 移动文件夹，`parent_id` 为 None 时移到根目录
+    pub async fn move_to(&self, id: &str, parent_id: Option<&str>) -> Result<Folder> {
+        let folder = self.require(id).await?;
+
+        if let Some(parent_id) = parent_id {
+            let parent = self.require(parent_id).await?;
+            if parent.workspace_id != folder.workspace_id {
+                return Err(AppError::Validation(
+                    "Cannot move a folder to another workspace".to_string(),
+                ));
+            }
+
+            let mut conn = self.pool.acquire().await?;
+            if subtree_ids(&mut conn, id)
+                .await?
+                .iter()
+                .any(|f| f == parent_id)
+            {
+                return Err(AppError::Validation(
+                    "Cannot move a folder into itself or one of its subfolders".to_string(),
+                ));
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+
+        let folder = sqlx::query_as::<_, Folder>(
+            "UPDATE folders SET parent_id = ?, updated_at = ? WHERE id = ? RETURNING *",
+        )
+        .bind(parent_id)
+        .bind(&now)
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| name_conflict(e, &folder.name))?;
+
+        Ok(folder)
+    }
+
+    /// 删除文件夹
+    ///
+    /// `Cascade` 删除子文件夹并把其中的文件移入回收站；`Reparent` 把直接包含的
+    /// 子文件夹和文件移到被删除文件夹的上一级。
+    pub async fn delete(&self, id: &str, mode: FolderDeleteMode) -> Result<()> {
+        let folder = self.require(id).await?;
+        let mut tx = self.pool.begin().await?;
+
+        match mode {
+            FolderDeleteMode::Cascade => {
+                // 文件移入回收站，文件夹删除后 folder_id 置空，恢复时回到根目录
+                let ids = subtree_ids(&mut tx, id).await?;
+                let placeholders = vec!["?"; ids.len()].join(",");
+                let now = Utc::now().to_rfc3339();
+
+                let sql = format!(
+                    "UPDATE files SET deleted_at = ? WHERE deleted_at IS NULL AND folder_id IN ({})",
+                    placeholders
+                );
+                let mut q = sqlx::query(&sql).bind(&now);
+                for id in &ids {
+                    q = q.bind(id);
+                }
+                q.execute(&mut *tx).await?;
+
+                let sql = format!(
+                    "DELETE FROM files_fts WHERE file_id IN (SELECT id FROM files WHERE folder_id IN ({}))",
+                    placeholders
+                );
+                let mut q = sqlx::query(&sql);
+                for id in &ids {
+                    q = q.bind(id);
+                }
+                q.execute(&mut *tx).await?;
+            }
+            FolderDeleteMode::Reparent => {
+                sqlx::query("UPDATE folders SET parent_id = ? WHERE parent_id = ?")
+                    .bind(&folder.parent_id)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        sibling_conflict(
+                            e,
+                            "A subfolder has the same name as a folder in the parent folder"
+                                .to_string(),
+                        )
+                    })?;
+
+                sqlx::query("UPDATE files SET folder_id = ? WHERE folder_id = ?")
+                    .bind(&folder.parent_id)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        // 子文件夹由外键级联删除
+        sqlx::query("DELETE FROM folders WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 把文件移到指定文件夹，`folder_id` 为 None 时移到根目录
+    pub async fn move_files(&self, file_ids: &[String], folder_id: Option<&str>) -> Result<()> {
+        if file_ids.is_empty() {
+            return Ok(());
+        }
+
+        let workspace_id = match folder_id {
+            Some(folder_id) => Some(self.require(folder_id).await?.workspace_id),
+            None => None,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        for file_id in file_ids {
+            let file: Option<(String,)> =
+                sqlx::query_as("SELECT workspace_id FROM files WHERE id = ?")
+                    .bind(file_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            let Some((file_workspace_id,)) = file else {
+                return Err(AppError::NotFound(format!("File not found: {}", file_id)));
+            };
+            if workspace_id
+                .as_ref()
+                .is_some_and(|w| *w != file_workspace_id)
+            {
+                return Err(AppError::Validation(format!(
+                    "File {} belongs to another workspace",
+                    file_id
+                )));
+            }
+
+            sqlx::query("UPDATE files SET folder_id = ? WHERE id = ?")
+                .bind(folder_id)
+                .bind(file_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 获取工作空间的完整目录树（文件夹和文件按名称排序）
+    pub async fn tree(&self, workspace_id: &str) -> Result<FolderTree> {
+        let folders = sqlx::query_as::<_, Folder>(
+            "SELECT * FROM folders WHERE workspace_id = ? ORDER BY name COLLATE NOCASE, id",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let files = sqlx::query_as::<_, FolderTreeFile>(
+            r#"
+            SELECT id, folder_id, file_type, title, updated_at
+            FROM files
+            WHERE workspace_id = ? AND deleted_at IS NULL
+            ORDER BY title COLLATE NOCASE, id
+            "#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut child_folders: HashMap<Option<String>, Vec<Folder>> = HashMap::new();
+        for folder in folders {
+            child_folders
+                .entry(folder.parent_id.clone())
+                .or_default()
+                .push(folder);
+        }
+        let mut folder_files: HashMap<Option<String>, Vec<FolderTreeFile>> = HashMap::new();
+        for file in files {
+            folder_files
+                .entry(file.folder_id.clone())
+                .or_default()
+                .push(file);
+        }
+
+        Ok(FolderTree {
+            folders: build_nodes(None, "", &mut child_folders, &mut folder_files),
+            files: folder_files.remove(&None).unwrap_or_default(),
+        })
+    }
+
+    async fn require(&self, id: &str) -> Result<Folder> {
+        self.get(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Folder not found: {}", id)))
+    }
+}
+
+fn build_nodes(
+    parent_id: Option<String>,
+    parent_path: &str,
+    child_folders: &mut HashMap<Option<String>, Vec<Folder>>,
+    folder_files: &mut HashMap<Option<String>, Vec<FolderTreeFile>>,
+) -> Vec<FolderNode> {
+    child_folders
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|folder| {
+            let path = if parent_path.is_empty() {
+                folder.name.clone()
+            } else {
+                format!("{}/{}", parent_path, folder.name)
+            };
+            let id = Some(folder.id.clone());
+            FolderNode {
+                children: build_nodes(id.clone(), &path, child_folders, folder_files),
+                files: folder_files.remove(&id).unwrap_or_default(),
+                folder,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// 文件夹自身及其所有子孙文件夹的 id
+async fn subtree_ids(conn: &mut SqliteConnection, id: &str) -> Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE subtree(id) AS (
+            SELECT ?
+            UNION ALL
+            SELECT f.id FROM folders f INNER JOIN subtree s ON f.parent_id = s.id
+        )
+        SELECT id FROM subtree
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+fn validate_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation(
+            "Folder name cannot be empty".to_string(),
+        ));
+    }
+    // `/` 用作路径分隔符
+    if name.contains('/') {
+        return Err(AppError::Validation(
+            "Folder name cannot contain '/'".to_string(),
+        ));
+    }
+    Ok(name)
+}
+
+fn name_conflict(e: sqlx::Error, name: &str) -> AppError {
+    sibling_conflict(e, format!("A folder named '{}' already exists here", name))
+}
+
+/// 同一目录下重名时返回可读的冲突信息，其他错误原样转换
+fn sibling_conflict(e: sqlx::Error, message: String) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.kind() == ErrorKind::UniqueViolation => {
+            AppError::Conflict(message)
+        }
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::workspace::WorkspaceService;
+
+    /// 在工作空间中创建 `Notes/Daily` 文件夹和其中的一个文档，返回 (工作空间 id, 文档 id)
+    async fn seed(pool: &Pool<Sqlite>, folders: &FolderService, name: &str) -> (String, String) {
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: name.to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+        let notes = folders
+            .create(CreateFolder {
+                workspace_id: workspace.id.clone(),
+                parent_id: None,
+                name: "Notes".to_string(),
+            })
+            .await
+            .unwrap();
+        let daily = folders
+            .create(CreateFolder {
+                workspace_id: workspace.id.clone(),
+                parent_id: Some(notes.id),
+                name: "Daily".to_string(),
+            })
+            .await
+            .unwrap();
+        let file = FileService::new(pool.clone())
+            .create(CreateFile {
+                workspace_id: workspace.id.clone(),
+                file_type: "document".to_string(),
+                title: name.to_string(),
+                content: Some(String::new()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+                folder_id: Some(daily.id),
+            })
+            .await
+            .unwrap();
+
+        (workspace.id, file.id)
+    }
+
+    async fn filter(pool: &Pool<Sqlite>, path: &str, workspace_id: Option<&str>) -> Vec<String> {
+        let sql = format!(
+            "SELECT f.id FROM files f WHERE {} ORDER BY f.id",
+            folder_filter_sql(path, workspace_id)
+        );
+        let mut query = sqlx::query_as::<_, (String,)>(&sql);
+        for param in folder_filter_params(path, workspace_id) {
+            query = query.bind(param);
+        }
+        let rows = query.fetch_all(pool).await.unwrap();
+        rows.into_iter().map(|(id,)| id).collect()
+    }
+
+    #[tokio::test]
+    async fn folder_filter_is_scoped_to_workspace() {
+        let pool = memory_pool().await;
+        let folders = FolderService::new(pool.clone());
+        let (first_workspace, first_file) = seed(&pool, &folders, "First").await;
+        let (_, second_file) = seed(&pool, &folders, "Second").await;
+
+        assert_eq!(
+            filter(&pool, "Notes", Some(&first_workspace)).await,
+            vec![first_file.clone()]
+        );
+        assert_eq!(
+            filter(&pool, "Notes/Daily", Some(&first_workspace)).await,
+            vec![first_file.clone()]
+        );
+        assert!(filter(&pool, "Daily", Some(&first_workspace))
+            .await
+            .is_empty());
+
+        let mut all = vec![first_file, second_file];
+        all.sort();
+        assert_eq!(filter(&pool, "Notes", None).await, all);
+    }
+
+    #[tokio::test]
+    async fn rename_missing_folder_is_not_found() {
+        let pool = memory_pool().await;
+        let folders = FolderService::new(pool);
+
+        let result = folders.rename("missing", "Renamed").await;
+
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn rename_to_sibling_name_conflicts() {
+        let pool = memory_pool().await;
+        let folders = FolderService::new(pool.clone());
+        let (workspace_id, _) = seed(&pool, &folders, "Workspace").await;
+        let other = folders
+            .create(CreateFolder {
+                workspace_id,
+                parent_id: None,
+                name: "Other".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let result = folders.rename(&other.id, "Notes").await;
+
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+    }
+}
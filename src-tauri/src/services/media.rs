@@ -1,16 +1,211 @@
-use crate::models::{CreateMediaAsset, MediaAsset};
-use anyhow::Result;
-use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use super::archive::rewrite_media_refs;
+use super::blocking;
+use crate::error::{AppError, Result};
+use crate::models::{
+    BeginMediaUpload, CreateMediaAsset, MediaAsset, MediaFailure, MediaGcReport,
+    MediaUploadProgress, ThumbnailBackfillReport, ThumbnailSize,
+};
+use chrono::{Duration, Utc};
+use ring::digest::{Context, SHA256};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use uuid::Uuid;
 
+pub mod probe;
+mod thumbnail;
+mod upload;
+
+use probe::ImageInfo;
+use upload::{Uploads, PROBE_HEAD_BYTES};
+
+/// 文档内容中的媒体引用前缀（`media://<id>`）
+pub const MEDIA_SCHEME: &str = "media://";
+
+/// 上传后还没保存进文档的媒体在这段时间内不会被清理
+const GC_GRACE_HOURS: i64 = 24;
+
 pub struct MediaService {
     pool: Pool<Sqlite>,
+    media_dir: PathBuf,
+    uploads: Uploads,
 }
 
 impl MediaService {
-    pub fn new(pool: Pool<Sqlite>) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool<Sqlite>, media_dir: PathBuf) -> Self {
+        Self {
+            pool,
+            media_dir,
+            uploads: Uploads::default(),
+        }
+    }
+
+    /// 把文件内容写入媒体目录并创建媒体记录，文件名为内容的 SHA-256
+    ///
+    /// 同一工作空间中已有相同内容的媒体时直接返回已有的记录。
+    pub async fn store(
+        &self,
+        workspace_id: &str,
+        file_name: String,
+        mime_type: String,
+        bytes: &[u8],
+    ) -> Result<MediaAsset> {
+        let stored = write_media_file(&self.media_dir, &file_name, &mut &bytes[..])?;
+        self.register(workspace_id, file_name, mime_type, stored, bytes)
+            .await
+    }
+
+    /// 开始分块上传，进度通过 `on_progress` 推送，返回上传 id
+    ///
+    /// 工作空间和要关联的文档必须存在，且文档属于该工作空间。
+    pub async fn begin_upload(
+        &self,
+        data: BeginMediaUpload,
+        on_progress: impl Fn(MediaUploadProgress) + Send + Sync + 'static,
+    ) -> Result<String> {
+        let workspace: Option<(String,)> = sqlx::query_as("SELECT id FROM workspaces WHERE id = ?")
+            .bind(&data.workspace_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if workspace.is_none() {
+            return Err(AppError::NotFound(format!(
+                "Workspace not found: {}",
+                data.workspace_id
+            )));
+        }
+
+        let file: Option<(String,)> =
+            sqlx::query_as("SELECT workspace_id FROM files WHERE id = ? AND deleted_at IS NULL")
+                .bind(&data.file_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match file {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "File not found: {}",
+                    data.file_id
+                )))
+            }
+            Some((workspace_id,)) if workspace_id != data.workspace_id => {
+                return Err(AppError::Validation(
+                    "File belongs to another workspace".to_string(),
+                ))
+            }
+            Some(_) => {}
+        }
+
+        self.uploads.begin(&self.media_dir, data, on_progress).await
+    }
+
+    /// 追加上传分块，`offset` 必须等于已接收的字节数，返回已接收的字节数
+    pub async fn append_upload(&self, upload_id: &str, offset: i64, chunk: Vec<u8>) -> Result<i64> {
+        self.uploads.append(upload_id, offset, chunk).await
+    }
+
+    /// 完成分块上传：校验大小和哈希后创建媒体记录并关联到文档
+    ///
+    /// 与 `store` 相同，同一工作空间中已有相同内容的媒体时返回已有的记录。
+    pub async fn finish_upload(
+        &self,
+        upload_id: &str,
+        expected_hash: Option<&str>,
+    ) -> Result<MediaAsset> {
+        let upload = self.uploads.finish(upload_id, expected_hash).await?;
+        let data = upload.data;
+        let stored = commit_temp_file(
+            &self.media_dir,
+            &upload.temp_path,
+            &data.file_name,
+            upload.hash,
+            data.size,
+        )?;
+
+        let asset = self
+            .register(
+                &data.workspace_id,
+                data.file_name,
+                data.mime_type,
+                stored,
+                &upload.head,
+            )
+            .await?;
+        self.link_to_file(&data.file_id, &asset.id).await?;
+
+        Ok(asset)
+    }
+
+    /// 取消分块上传并删除已接收的内容，返回上传是否存在
+    pub async fn abort_upload(&self, upload_id: &str) -> bool {
+        self.uploads.abort(upload_id).await
+    }
+
+    /// 为写入媒体目录的文件创建媒体记录，`head` 为文件开头的内容，用于识别图片
+    async fn register(
+        &self,
+        workspace_id: &str,
+        file_name: String,
+        mime_type: String,
+        stored: StoredFile,
+        head: &[u8],
+    ) -> Result<MediaAsset> {
+        if let Some(existing) = self.find_by_hash(workspace_id, &stored.hash).await? {
+            self.discard(&stored).await?;
+            return Ok(existing);
+        }
+
+        // 按内容识别图片，未声明类型的图片补上 MIME 类型；尺寸按 EXIF 方向摆正
+        let info = probe::probe(head);
+        let mime_type = match &info {
+            Some(info) if mime_type.is_empty() || mime_type == "application/octet-stream" => {
+                info.format.mime_type().to_string()
+            }
+            _ => mime_type,
+        };
+        let (width, height) = match info.filter(|_| mime_type.starts_with("image/")) {
+            Some(info) => {
+                let (width, height) = info.display_size();
+                (i32::try_from(width).ok(), i32::try_from(height).ok())
+            }
+            None => (None, None),
+        };
+
+        let result = self
+            .create(CreateMediaAsset {
+                workspace_id: workspace_id.to_string(),
+                file_name,
+                file_path: stored.path.to_string_lossy().to_string(),
+                file_size: stored.size,
+                mime_type,
+                width,
+                height,
+                content_hash: Some(stored.hash.clone()),
+            })
+            .await;
+
+        match result {
+            Ok(asset) => {
+                // 在后台生成缩略图，失败时在获取缩略图时重试
+                if thumbnail::is_supported(&asset.mime_type) {
+                    let source = stored.path.clone();
+                    let media_dir = self.media_dir.clone();
+                    let hash = stored.hash.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let _ =
+                            thumbnail::generate(&source, &media_dir, &hash, &ThumbnailSize::ALL);
+                    });
+                }
+                Ok(asset)
+            }
+            Err(e) => {
+                // 同时上传相同内容时唯一索引冲突，使用先创建的记录
+                let existing = self.find_by_hash(workspace_id, &stored.hash).await?;
+                self.discard(&stored).await?;
+                existing.ok_or(e)
+            }
+        }
     }
 
     pub async fn create(&self, data: CreateMediaAsset) -> Result<MediaAsset> {
@@ -21,9 +216,9 @@ impl MediaService {
             r#"
             INSERT INTO media_assets (
                 id, workspace_id, file_name, file_path, file_size, 
-                mime_type, width, height, created_at
+                mime_type, width, height, created_at, content_hash
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING *
             "#,
         )
@@ -31,11 +226,12 @@ impl MediaService {
         .bind(&data.workspace_id)
         .bind(&data.file_name)
         .bind(&data.file_path)
-        .bind(&data.file_size)
+        .bind(data.file_size)
         .bind(&data.mime_type)
-        .bind(&data.width)
-        .bind(&data.height)
+        .bind(data.width)
+        .bind(data.height)
         .bind(&now)
+        .bind(&data.content_hash)
         .fetch_one(&self.pool)
         .await?;
 
@@ -62,12 +258,186 @@ impl MediaService {
         Ok(assets)
     }
 
+    /// 删除媒体记录，磁盘文件没有被其他记录使用时一并删除
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM media_assets WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
+        let deleted: Option<(String,)> =
+            sqlx::query_as("DELETE FROM media_assets WHERE id = ? RETURNING file_path")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        if let Some((path,)) = deleted {
+            remove_unreferenced_files(&self.pool, &self.media_dir, [path]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 读取图片媒体的格式、尺寸、方向、颜色类型和帧数，不是图片类型或无法识别时返回 `None`
+    pub async fn image_info(&self, id: &str) -> Result<Option<ImageInfo>> {
+        let asset = self
+            .get(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Media not found: {}", id)))?;
+        if !asset.mime_type.starts_with("image/") {
+            return Ok(None);
+        }
+
+        // 动画格式的帧数散布在整个文件中，其他格式的元数据都在文件开头
+        let whole_file = matches!(
+            asset.mime_type.as_str(),
+            "image/gif" | "image/webp" | "image/avif"
+        );
+        blocking(move || {
+            let data = if whole_file {
+                fs::read(&asset.file_path)?
+            } else {
+                let mut head = Vec::new();
+                fs::File::open(&asset.file_path)?
+                    .take(PROBE_HEAD_BYTES as u64)
+                    .read_to_end(&mut head)?;
+                head
+            };
+            Ok(probe::probe(&data))
+        })
+        .await
+    }
+
+    /// 获取媒体缩略图的路径，缩略图不存在时生成
+    ///
+    /// SVG、不超过缩略图尺寸的图片和磁盘文件已丢失的媒体返回原图路径。
+    pub async fn get_thumbnail(&self, id: &str, size: ThumbnailSize) -> Result<String> {
+        let asset = self
+            .get(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Media not found: {}", id)))?;
+        if !asset.mime_type.starts_with("image/") {
+            return Err(AppError::Validation(format!(
+                "Media is not an image: {}",
+                asset.mime_type
+            )));
+        }
+
+        let hash = match &asset.content_hash {
+            Some(hash) if thumbnail::is_supported(&asset.mime_type) => hash,
+            _ => return Ok(asset.file_path),
+        };
+        let path = thumbnail::thumbnail_path(&self.media_dir, hash, size);
+        if !path.exists() && thumbnail::is_needed(asset.width, asset.height, size) {
+            self.generate_thumbnails(&asset, &[size]).await?;
+        }
+
+        if path.exists() {
+            Ok(path.to_string_lossy().to_string())
+        } else {
+            Ok(asset.file_path)
+        }
+    }
+
+    /// 为已有的图片媒体补生成缺少的缩略图
+    pub async fn backfill_thumbnails(&self) -> Result<ThumbnailBackfillReport> {
+        let assets = sqlx::query_as::<_, MediaAsset>(
+            r#"
+            SELECT * FROM media_assets
+            WHERE mime_type LIKE 'image/%' AND content_hash IS NOT NULL
+            ORDER BY created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut report = ThumbnailBackfillReport::default();
+        // 内容相同的媒体共用缩略图
+        let mut seen: HashSet<String> = HashSet::new();
+        for asset in assets {
+            let Some(hash) = asset.content_hash.clone() else {
+                continue;
+            };
+            let sizes: Vec<ThumbnailSize> = ThumbnailSize::ALL
+                .into_iter()
+                .filter(|&size| {
+                    thumbnail::is_needed(asset.width, asset.height, size)
+                        && !thumbnail::thumbnail_path(&self.media_dir, &hash, size).exists()
+                })
+                .collect();
+            if !thumbnail::is_supported(&asset.mime_type) || sizes.is_empty() || !seen.insert(hash)
+            {
+                report.skipped += 1;
+                continue;
+            }
+
+            match self.generate_thumbnails(&asset, &sizes).await {
+                Ok(0) => report.skipped += 1,
+                Ok(generated) => report.generated += generated as i64,
+                Err(e) => report.failed.push(MediaFailure {
+                    media_id: asset.id,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// 为旧媒体计算哈希并改用哈希文件名，同一工作空间中内容相同的媒体合并为一个
+    pub async fn backfill_hashes(&self) -> Result<()> {
+        let assets = sqlx::query_as::<_, MediaAsset>(
+            "SELECT * FROM media_assets WHERE content_hash IS NULL ORDER BY created_at",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for asset in assets {
+            // 磁盘文件丢失的媒体保持原样
+            let Ok(mut file) = fs::File::open(&asset.file_path) else {
+                continue;
+            };
+            let hash = hash_reader(&mut file)?;
+            drop(file);
+
+            let mut tx = self.pool.begin().await?;
+            let existing = sqlx::query_as::<_, MediaAsset>(
+                "SELECT * FROM media_assets WHERE workspace_id = ? AND content_hash = ?",
+            )
+            .bind(&asset.workspace_id)
+            .bind(&hash)
+            .fetch_optional(&mut *tx)
             .await?;
 
+            if let Some(existing) = existing {
+                merge_media(&mut tx, &asset.id, &existing.id).await?;
+                tx.commit().await?;
+                remove_unreferenced_files(&self.pool, &self.media_dir, [asset.file_path]).await?;
+                continue;
+            }
+
+            // 相同内容的文件已存在（其他工作空间的媒体）时共用，否则重命名
+            let current = PathBuf::from(&asset.file_path);
+            let target = self
+                .media_dir
+                .join(hashed_file_name(&hash, &asset.file_name));
+            let path = if target == current
+                || target.exists()
+                || (current.starts_with(&self.media_dir) && fs::rename(&current, &target).is_ok())
+            {
+                target
+            } else {
+                current.clone()
+            };
+
+            sqlx::query("UPDATE media_assets SET content_hash = ?, file_path = ? WHERE id = ?")
+                .bind(&hash)
+                .bind(path.to_string_lossy().to_string())
+                .bind(&asset.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            if path != current && current.exists() {
+                remove_unreferenced_files(&self.pool, &self.media_dir, [asset.file_path]).await?;
+            }
+        }
+
         Ok(())
     }
 
@@ -98,6 +468,243 @@ impl MediaService {
         Ok(())
     }
 
+    /// 清理无用的媒体
+    ///
+    /// 按文档内容（包括回收站中的文档和历史版本）中的 `media://` 引用修正 `file_media`
+    /// 关联，删除没有任何引用的媒体记录和文件，以及媒体目录中没有记录的文件。
+    /// `dry_run` 时只返回报告。最近上传的媒体和文件不会被处理。
+    pub async fn collect_garbage(&self, dry_run: bool) -> Result<MediaGcReport> {
+        let grace_cutoff = Utc::now() - Duration::hours(GC_GRACE_HOURS);
+        let grace_cutoff_str = grace_cutoff.to_rfc3339();
+        let mut report = MediaGcReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        // 开始事务前在阻塞线程池中列出目录；之后写入的文件不在列表中，不会被误删
+        let media_dir = self.media_dir.clone();
+        let thumbnail_dir = self.media_dir.join(thumbnail::THUMBNAIL_DIR);
+        let (media_files, thumbnail_files) = blocking(move || {
+            let thumbnail_files = if thumbnail_dir.is_dir() {
+                list_files(&thumbnail_dir)?
+            } else {
+                Vec::new()
+            };
+            Ok((list_files(&media_dir)?, thumbnail_files))
+        })
+        .await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let assets = sqlx::query_as::<_, MediaAsset>("SELECT * FROM media_assets")
+            .fetch_all(&mut *tx)
+            .await?;
+        let asset_ids: HashSet<&str> = assets.iter().map(|a| a.id.as_str()).collect();
+
+        // 文档当前内容中的引用决定关联，历史版本中的引用只用于保留媒体
+        let documents: Vec<(String, String)> = sqlx::query_as(
+            "SELECT id, content FROM files WHERE content IS NOT NULL AND instr(content, ?) > 0",
+        )
+        .bind(MEDIA_SCHEME)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut wanted_links: HashSet<(&str, &str)> = HashSet::new();
+        let mut referenced: HashSet<String> = HashSet::new();
+        for (file_id, content) in &documents {
+            for media_id in media_ref_ids(content) {
+                if asset_ids.contains(media_id) {
+                    wanted_links.insert((file_id.as_str(), media_id));
+                    referenced.insert(media_id.to_string());
+                }
+            }
+        }
+
+        let revisions: Vec<(String,)> = sqlx::query_as(
+            "SELECT content FROM file_revisions WHERE content IS NOT NULL AND instr(content, ?) > 0",
+        )
+        .bind(MEDIA_SCHEME)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (content,) in &revisions {
+            referenced.extend(media_ref_ids(content).into_iter().map(str::to_string));
+        }
+
+        let links: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT file_id, media_id, created_at FROM file_media")
+                .fetch_all(&mut *tx)
+                .await?;
+        let existing_links: HashSet<(&str, &str)> = links
+            .iter()
+            .map(|(file_id, media_id, _)| (file_id.as_str(), media_id.as_str()))
+            .collect();
+
+        let now = Utc::now().to_rfc3339();
+        for (file_id, media_id) in wanted_links.difference(&existing_links) {
+            report.links_added += 1;
+            if !dry_run {
+                sqlx::query(
+                    "INSERT INTO file_media (file_id, media_id, created_at) VALUES (?, ?, ?)",
+                )
+                .bind(file_id)
+                .bind(media_id)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        for (file_id, media_id, created_at) in &links {
+            if wanted_links.contains(&(file_id.as_str(), media_id.as_str()))
+                || *created_at >= grace_cutoff_str
+            {
+                continue;
+            }
+            report.links_removed += 1;
+            if !dry_run {
+                sqlx::query("DELETE FROM file_media WHERE file_id = ? AND media_id = ?")
+                    .bind(file_id)
+                    .bind(media_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        let file_paths: Vec<(String,)> =
+            sqlx::query_as("SELECT file_path FROM files WHERE file_path IS NOT NULL")
+                .fetch_all(&mut *tx)
+                .await?;
+        let (orphans, kept): (Vec<&MediaAsset>, Vec<&MediaAsset>) =
+            assets.iter().partition(|asset| {
+                !referenced.contains(&asset.id) && asset.created_at < grace_cutoff_str
+            });
+
+        // 相同内容的媒体共用文件，仍被保留的记录使用的文件不会删除
+        let in_use: HashSet<PathBuf> = kept
+            .iter()
+            .map(|asset| PathBuf::from(&asset.file_path))
+            .chain(file_paths.into_iter().map(|(path,)| PathBuf::from(path)))
+            .collect();
+        let kept_files: Vec<(String, String)> = kept
+            .iter()
+            .map(|asset| (asset.id.clone(), asset.file_path.clone()))
+            .collect();
+        report.missing_assets = blocking(move || {
+            Ok(kept_files
+                .into_iter()
+                .filter(|(_, path)| !Path::new(path).exists())
+                .map(|(id, _)| id)
+                .collect())
+        })
+        .await?;
+
+        let mut freed: HashSet<PathBuf> = HashSet::new();
+        for asset in orphans {
+            let path = PathBuf::from(&asset.file_path);
+            if !in_use.contains(&path) && freed.insert(path) {
+                report.freed_bytes += asset.file_size;
+            }
+            report.orphan_assets.push(asset.clone());
+            if !dry_run {
+                // 关联由外键级联删除
+                sqlx::query("DELETE FROM media_assets WHERE id = ?")
+                    .bind(&asset.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        // 媒体目录中既不是媒体记录也不是文件记录的文件
+        let grace_time = SystemTime::from(grace_cutoff);
+        let expired = |file: &ListedFile| file.modified.is_some_and(|t| t < grace_time);
+        for file in &media_files {
+            if in_use.contains(&file.path) || freed.contains(&file.path) || !expired(file) {
+                continue;
+            }
+            report.freed_bytes += file.size as i64;
+            report
+                .orphan_files
+                .push(file.path.to_string_lossy().to_string());
+        }
+
+        // 原图已不再使用的缩略图
+        let kept_hashes: HashSet<&str> = kept
+            .iter()
+            .filter_map(|asset| asset.content_hash.as_deref())
+            .collect();
+        for file in &thumbnail_files {
+            if thumbnail::source_hash(&file.path).is_some_and(|h| kept_hashes.contains(h))
+                || !expired(file)
+            {
+                continue;
+            }
+            report.freed_bytes += file.size as i64;
+            report
+                .orphan_files
+                .push(file.path.to_string_lossy().to_string());
+        }
+
+        if dry_run {
+            return Ok(report);
+        }
+        tx.commit().await?;
+
+        let media_dir = self.media_dir.clone();
+        let orphan_files = report.orphan_files.clone();
+        blocking(move || {
+            for path in &orphan_files {
+                remove_media_file(&media_dir, Path::new(path));
+            }
+            Ok(())
+        })
+        .await?;
+        let orphan_paths: Vec<String> = report
+            .orphan_assets
+            .iter()
+            .map(|a| a.file_path.clone())
+            .collect();
+        remove_unreferenced_files(&self.pool, &self.media_dir, orphan_paths).await?;
+
+        Ok(report)
+    }
+
+    async fn find_by_hash(&self, workspace_id: &str, hash: &str) -> Result<Option<MediaAsset>> {
+        let asset = sqlx::query_as::<_, MediaAsset>(
+            "SELECT * FROM media_assets WHERE workspace_id = ? AND content_hash = ?",
+        )
+        .bind(workspace_id)
+        .bind(hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(asset)
+    }
+
+    /// 在阻塞线程中解码原图并生成缩略图，返回新生成的数量
+    async fn generate_thumbnails(
+        &self,
+        asset: &MediaAsset,
+        sizes: &[ThumbnailSize],
+    ) -> Result<usize> {
+        let Some(hash) = asset.content_hash.clone() else {
+            return Ok(0);
+        };
+        let source = PathBuf::from(&asset.file_path);
+        let media_dir = self.media_dir.clone();
+        let sizes = sizes.to_vec();
+
+        tokio::task::spawn_blocking(move || thumbnail::generate(&source, &media_dir, &hash, &sizes))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+    }
+
+    /// 去掉本次新写入但没有用上的文件
+    async fn discard(&self, stored: &StoredFile) -> Result<()> {
+        if stored.created {
+            let path = stored.path.to_string_lossy().to_string();
+            remove_unreferenced_files(&self.pool, &self.media_dir, [path]).await?;
+        }
+        Ok(())
+    }
+
     // 获取文档关联的所有媒体
     pub async fn get_file_media(&self, file_id: &str) -> Result<Vec<MediaAsset>> {
         let assets = sqlx::query_as::<_, MediaAsset>(
@@ -115,3 +722,899 @@ impl MediaService {
         Ok(assets)
     }
 }
+
+/// 内容中 `media://<id>` 引用的媒体 id（可能重复）
+pub fn media_ref_ids(content: &str) -> Vec<&str> {
+    let mut ids = Vec::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find(MEDIA_SCHEME) {
+        let after = &rest[start + MEDIA_SCHEME.len()..];
+        let id_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(after.len());
+        if id_len > 0 {
+            ids.push(&after[..id_len]);
+        }
+        rest = &after[id_len..];
+    }
+
+    ids
+}
+
+/// 写入媒体目录的文件
+pub(crate) struct StoredFile {
+    pub path: PathBuf,
+    pub hash: String,
+    pub size: i64,
+    pub created: bool, // 为 false 时相同内容的文件已存在
+}
+
+/// 把内容写入媒体目录，文件名为内容的 SHA-256 加原扩展名
+///
+/// 先写入临时文件并同时计算哈希，相同的文件已存在时（如其他工作空间中的同一图片）不再保存。
+pub(crate) fn write_media_file(
+    media_dir: &Path,
+    file_name: &str,
+    reader: &mut impl Read,
+) -> Result<StoredFile> {
+    let temp_path = media_dir.join(format!(".{}.tmp", Uuid::new_v4()));
+    let written = fs::File::create(&temp_path)
+        .map_err(Into::into)
+        .and_then(|mut file| copy_hashed(reader, &mut file));
+    let (hash, size) = match written {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+
+    commit_temp_file(media_dir, &temp_path, file_name, hash, size)
+}
+
+/// 把已计算哈希的临时文件改名为哈希文件名，相同的文件已存在时删除临时文件
+fn commit_temp_file(
+    media_dir: &Path,
+    temp_path: &Path,
+    file_name: &str,
+    hash: String,
+    size: i64,
+) -> Result<StoredFile> {
+    let path = media_dir.join(hashed_file_name(&hash, file_name));
+    let created = !path.exists();
+    if created {
+        if let Err(e) = fs::rename(temp_path, &path) {
+            let _ = fs::remove_file(temp_path);
+            return Err(e.into());
+        }
+    } else {
+        let _ = fs::remove_file(temp_path);
+    }
+
+    Ok(StoredFile {
+        path,
+        hash,
+        size,
+        created,
+    })
+}
+
+/// 计算内容的 SHA-256（十六进制）
+pub(crate) fn hash_reader(reader: &mut impl Read) -> Result<String> {
+    copy_hashed(reader, &mut io::sink()).map(|(hash, _)| hash)
+}
+
+/// 复制内容并计算 SHA-256，返回哈希和字节数
+fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> Result<(String, i64)> {
+    let mut context = Context::new(&SHA256);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        context.update(&buffer[..n]);
+        writer.write_all(&buffer[..n])?;
+        size += n as i64;
+    }
+    writer.flush()?;
+
+    Ok((hex_digest(context), size))
+}
+
+/// SHA-256 结果的十六进制表示
+fn hex_digest(context: Context) -> String {
+    context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 哈希文件名，保留原文件的扩展名
+fn hashed_file_name(hash: &str, file_name: &str) -> String {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    match extension {
+        Some(ext) => format!("{}.{}", hash, ext.to_ascii_lowercase()),
+        None => hash.to_string(),
+    }
+}
+
+/// 删除没有被任何媒体或文件记录使用的磁盘文件
+///
+/// 内容相同的媒体在不同工作空间中共用一个文件，删除记录后需要确认没有其他记录还在使用。
+pub(crate) async fn remove_unreferenced_files(
+    pool: &Pool<Sqlite>,
+    media_dir: &Path,
+    paths: impl IntoIterator<Item = String>,
+) -> Result<()> {
+    for path in paths {
+        let (in_use,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM media_assets WHERE file_path = ?)
+                OR EXISTS (SELECT 1 FROM files WHERE file_path = ?)
+            "#,
+        )
+        .bind(&path)
+        .bind(&path)
+        .fetch_one(pool)
+        .await?;
+
+        if !in_use {
+            remove_media_file(media_dir, Path::new(&path));
+        }
+    }
+
+    Ok(())
+}
+
+/// 目录中的普通文件及其大小和修改时间
+struct ListedFile {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+fn list_files(dir: &Path) -> io::Result<Vec<ListedFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push(ListedFile {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// 把媒体 `from` 的关联和文档（含历史版本）中的引用改为 `to`，然后删除 `from`
+async fn merge_media(conn: &mut SqliteConnection, from: &str, to: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO file_media (file_id, media_id, created_at)
+        SELECT file_id, ?, created_at FROM file_media WHERE media_id = ?
+        "#,
+    )
+    .bind(to)
+    .bind(from)
+    .execute(&mut *conn)
+    .await?;
+
+    let reference = format!("{}{}", MEDIA_SCHEME, from);
+    for table in ["files", "file_revisions"] {
+        let sql = format!(
+            "SELECT id, content FROM {} WHERE content IS NOT NULL AND instr(content, ?) > 0",
+            table
+        );
+        let rows: Vec<(String, String)> = sqlx::query_as(&sql)
+            .bind(&reference)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let sql = format!("UPDATE {} SET content = ? WHERE id = ?", table);
+        for (id, content) in rows {
+            let content = rewrite_media_refs(&content, |id| {
+                (id == from).then(|| format!("{}{}", MEDIA_SCHEME, to))
+            });
+            sqlx::query(&sql)
+                .bind(content)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    // 关联由外键级联删除
+    sqlx::query("DELETE FROM media_assets WHERE id = ?")
+        .bind(from)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// 删除媒体目录中的文件，不在媒体目录中的路径（如外部文件）不会被删除
+///
+/// 删除的是哈希命名的原图时一并删除它的缩略图。
+fn remove_media_file(media_dir: &Path, path: &Path) {
+    if !path.starts_with(media_dir) {
+        return;
+    }
+    let _ = fs::remove_file(path);
+
+    let hash = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| stem.len() == 64 && stem.bytes().all(|b| b.is_ascii_hexdigit()));
+    if let (Some(hash), Some(parent)) = (hash, path.parent()) {
+        if parent == media_dir {
+            thumbnail::remove(media_dir, hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateMediaAsset, CreateWorkspace, UpdateFile, Workspace};
+    use crate::services::file::FileService;
+    use crate::services::workspace::WorkspaceService;
+
+    async fn workspace(pool: &Pool<Sqlite>, name: &str) -> Workspace {
+        WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: name.to_string(),
+                description: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    async fn document(pool: &Pool<Sqlite>, workspace_id: &str) -> String {
+        FileService::new(pool.clone())
+            .create(CreateFile {
+                workspace_id: workspace_id.to_string(),
+                file_type: "document".to_string(),
+                title: "Document".to_string(),
+                content: Some(String::new()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+                folder_id: None,
+            })
+            .await
+            .unwrap()
+            .id
+    }
+
+    fn upload(workspace_id: &str, file_id: &str, size: i64) -> BeginMediaUpload {
+        BeginMediaUpload {
+            workspace_id: workspace_id.to_string(),
+            file_id: file_id.to_string(),
+            file_name: "notes.txt".to_string(),
+            mime_type: "text/plain".to_string(),
+            size,
+        }
+    }
+
+    #[tokio::test]
+    async fn begin_upload_validates_workspace_and_file() {
+        let pool = memory_pool().await;
+        let media_dir = std::env::temp_dir().join(format!("media-{}", Uuid::new_v4()));
+        fs::create_dir_all(&media_dir).unwrap();
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        let first = workspace(&pool, "First").await;
+        let second = workspace(&pool, "Second").await;
+        let file_id = document(&pool, &first.id).await;
+
+        let result = media
+            .begin_upload(upload("missing", &file_id, 1), |_| {})
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let result = media
+            .begin_upload(upload(&first.id, "missing", 1), |_| {})
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let result = media
+            .begin_upload(upload(&second.id, &file_id, 1), |_| {})
+            .await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+
+        // 没有创建任何临时文件
+        assert_eq!(fs::read_dir(&media_dir).unwrap().count(), 0);
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn finished_upload_is_linked_to_file() {
+        let pool = memory_pool().await;
+        let media_dir = std::env::temp_dir().join(format!("media-{}", Uuid::new_v4()));
+        fs::create_dir_all(&media_dir).unwrap();
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        let workspace = workspace(&pool, "Workspace").await;
+        let file_id = document(&pool, &workspace.id).await;
+
+        let id = media
+            .begin_upload(upload(&workspace.id, &file_id, 5), |_| {})
+            .await
+            .unwrap();
+        media
+            .append_upload(&id, 0, b"hello".to_vec())
+            .await
+            .unwrap();
+        let asset = media.finish_upload(&id, None).await.unwrap();
+
+        assert_eq!(fs::read(&asset.file_path).unwrap(), b"hello");
+        let linked: Vec<MediaAsset> = media.get_file_media(&file_id).await.unwrap();
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].id, asset.id);
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    /// 临时媒体目录和服务
+    async fn gc_setup() -> (Pool<Sqlite>, PathBuf, MediaService) {
+        let pool = memory_pool().await;
+        let media_dir = std::env::temp_dir().join(format!("media-{}", Uuid::new_v4()));
+        fs::create_dir_all(&media_dir).unwrap();
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        (pool, media_dir, media)
+    }
+
+    async fn store(
+        media: &MediaService,
+        workspace_id: &str,
+        name: &str,
+        bytes: &[u8],
+    ) -> MediaAsset {
+        media
+            .store(
+                workspace_id,
+                name.to_string(),
+                "text/plain".to_string(),
+                bytes,
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn document_with(pool: &Pool<Sqlite>, workspace_id: &str, content: String) -> String {
+        let files = FileService::new(pool.clone());
+        let id = document(pool, workspace_id).await;
+        files
+            .update(
+                &id,
+                UpdateFile {
+                    title: None,
+                    content: Some(content),
+                    file_path: None,
+                    file_size: None,
+                    mime_type: None,
+                },
+            )
+            .await
+            .unwrap();
+        id
+    }
+
+    fn image(asset: &MediaAsset) -> String {
+        format!("<img data-media-src=\"{}{}\">", MEDIA_SCHEME, asset.id)
+    }
+
+    /// 把所有媒体记录和关联的创建时间移到保留期之前
+    async fn age_records(pool: &Pool<Sqlite>) {
+        let old = (Utc::now() - Duration::hours(GC_GRACE_HOURS + 1)).to_rfc3339();
+        for sql in [
+            "UPDATE media_assets SET created_at = ?",
+            "UPDATE file_media SET created_at = ?",
+        ] {
+            sqlx::query(sql).bind(&old).execute(pool).await.unwrap();
+        }
+    }
+
+    /// 把文件的修改时间移到保留期之前
+    fn age_file(path: &Path) {
+        let old =
+            SystemTime::now() - std::time::Duration::from_secs(GC_GRACE_HOURS as u64 * 3600 + 60);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+    }
+
+    async fn count(pool: &Pool<Sqlite>, table: &str) -> i64 {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    fn ids(assets: &[MediaAsset]) -> Vec<&str> {
+        let mut ids: Vec<&str> = assets.iter().map(|a| a.id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn gc_dry_run_reports_without_changes() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let workspace = workspace(&pool, "Workspace").await;
+        let used = store(&media, &workspace.id, "used.txt", b"used").await;
+        let orphan = store(&media, &workspace.id, "orphan.txt", b"orphan").await;
+        let doc = document_with(&pool, &workspace.id, image(&used)).await;
+        // 文档中不再引用的旧关联
+        media.link_to_file(&doc, &orphan.id).await.unwrap();
+        age_records(&pool).await;
+        age_file(Path::new(&used.file_path));
+        age_file(Path::new(&orphan.file_path));
+
+        let report = media.collect_garbage(true).await.unwrap();
+        assert!(report.dry_run);
+        assert_eq!(ids(&report.orphan_assets), [orphan.id.as_str()]);
+        assert_eq!((report.links_added, report.links_removed), (1, 1));
+        assert_eq!(report.freed_bytes, orphan.file_size);
+        assert!(report.orphan_files.is_empty());
+
+        assert_eq!(count(&pool, "media_assets").await, 2);
+        assert_eq!(count(&pool, "file_media").await, 1);
+        assert!(Path::new(&orphan.file_path).exists());
+
+        // 实际执行时与预览的结果一致
+        let report = media.collect_garbage(false).await.unwrap();
+        assert_eq!(ids(&report.orphan_assets), [orphan.id.as_str()]);
+        assert_eq!(count(&pool, "media_assets").await, 1);
+        let linked = media.get_file_media(&doc).await.unwrap();
+        assert_eq!(ids(&linked), [used.id.as_str()]);
+        assert!(!Path::new(&orphan.file_path).exists());
+        assert!(Path::new(&used.file_path).exists());
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_recent_assets_links_and_files() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let workspace = workspace(&pool, "Workspace").await;
+        let doc = document(&pool, &workspace.id).await;
+        // 刚上传、还没有写进文档的媒体和关联，以及正在上传的临时文件
+        let fresh = store(&media, &workspace.id, "fresh.txt", b"fresh").await;
+        media.link_to_file(&doc, &fresh.id).await.unwrap();
+        fs::write(media_dir.join(".upload-new.part"), b"partial").unwrap();
+
+        let report = media.collect_garbage(false).await.unwrap();
+        assert!(report.orphan_assets.is_empty());
+        assert!(report.orphan_files.is_empty());
+        assert_eq!((report.links_added, report.links_removed), (0, 0));
+        assert_eq!(report.freed_bytes, 0);
+
+        assert_eq!(count(&pool, "media_assets").await, 1);
+        assert_eq!(count(&pool, "file_media").await, 1);
+        assert!(Path::new(&fresh.file_path).exists());
+        assert!(media_dir.join(".upload-new.part").exists());
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_media_referenced_by_revisions_and_trash() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let files = FileService::new(pool.clone());
+        let workspace = workspace(&pool, "Workspace").await;
+        let in_revision = store(&media, &workspace.id, "revision.txt", b"revision").await;
+        let in_trash = store(&media, &workspace.id, "trash.txt", b"trash").await;
+
+        // 只有历史版本引用
+        let doc = document_with(&pool, &workspace.id, image(&in_revision)).await;
+        files.create_revision(&doc).await.unwrap().unwrap();
+        files
+            .update(
+                &doc,
+                UpdateFile {
+                    title: None,
+                    content: Some("<p>image removed</p>".to_string()),
+                    file_path: None,
+                    file_size: None,
+                    mime_type: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        // 只有回收站中的文档引用
+        let trashed = document_with(&pool, &workspace.id, image(&in_trash)).await;
+        files.delete(&trashed).await.unwrap();
+
+        age_records(&pool).await;
+        age_file(Path::new(&in_revision.file_path));
+        age_file(Path::new(&in_trash.file_path));
+
+        let report = media.collect_garbage(false).await.unwrap();
+        assert!(report.orphan_assets.is_empty());
+        assert!(report.orphan_files.is_empty());
+        assert_eq!(count(&pool, "media_assets").await, 2);
+        assert!(Path::new(&in_revision.file_path).exists());
+        assert!(Path::new(&in_trash.file_path).exists());
+        // 回收站中的文档仍保留关联，恢复后图片可以直接显示
+        assert_eq!(
+            ids(&media.get_file_media(&trashed).await.unwrap()),
+            [in_trash.id.as_str()]
+        );
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_files_shared_with_other_workspaces() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let first = workspace(&pool, "First").await;
+        let second = workspace(&pool, "Second").await;
+        let orphan = store(&media, &first.id, "a.txt", b"same bytes").await;
+        let used = store(&media, &second.id, "b.txt", b"same bytes").await;
+        assert_eq!(orphan.file_path, used.file_path);
+        document_with(&pool, &second.id, image(&used)).await;
+        age_records(&pool).await;
+        age_file(Path::new(&used.file_path));
+
+        let report = media.collect_garbage(false).await.unwrap();
+        assert_eq!(ids(&report.orphan_assets), [orphan.id.as_str()]);
+        assert_eq!(report.freed_bytes, 0);
+        assert!(report.orphan_files.is_empty());
+
+        assert!(media.list_by_workspace(&first.id).await.unwrap().is_empty());
+        assert_eq!(media.list_by_workspace(&second.id).await.unwrap().len(), 1);
+        assert_eq!(fs::read(&used.file_path).unwrap(), b"same bytes");
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gc_reports_stale_uploads_and_thumbnails() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let workspace = workspace(&pool, "Workspace").await;
+        let kept = store(&media, &workspace.id, "kept.txt", b"kept").await;
+        document_with(&pool, &workspace.id, image(&kept)).await;
+        age_records(&pool).await;
+        age_file(Path::new(&kept.file_path));
+
+        let part = media_dir.join(".upload-stale.part");
+        fs::write(&part, b"partial").unwrap();
+        let thumbnails = media_dir.join(thumbnail::THUMBNAIL_DIR);
+        fs::create_dir_all(&thumbnails).unwrap();
+        let kept_thumbnail = thumbnail::thumbnail_path(
+            &media_dir,
+            kept.content_hash.as_deref().unwrap(),
+            ThumbnailSize::Small,
+        );
+        let stale_thumbnail =
+            thumbnail::thumbnail_path(&media_dir, &"0".repeat(64), ThumbnailSize::Large);
+        fs::write(&kept_thumbnail, b"thumb").unwrap();
+        fs::write(&stale_thumbnail, b"stale thumb").unwrap();
+        for path in [&part, &kept_thumbnail, &stale_thumbnail] {
+            age_file(path);
+        }
+
+        let report = media.collect_garbage(true).await.unwrap();
+        let mut orphan_files = report.orphan_files.clone();
+        orphan_files.sort();
+        let mut expected = vec![
+            part.to_string_lossy().to_string(),
+            stale_thumbnail.to_string_lossy().to_string(),
+        ];
+        expected.sort();
+        assert_eq!(orphan_files, expected);
+        assert_eq!(report.freed_bytes, 7 + 11);
+        assert!(report.orphan_assets.is_empty());
+        assert!(part.exists() && stale_thumbnail.exists());
+
+        media.collect_garbage(false).await.unwrap();
+        assert!(!part.exists());
+        assert!(!stale_thumbnail.exists());
+        assert!(kept_thumbnail.exists());
+        assert!(Path::new(&kept.file_path).exists());
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn storing_same_bytes_reuses_asset_per_workspace() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let first = workspace(&pool, "First").await;
+        let second = workspace(&pool, "Second").await;
+
+        let original = store(&media, &first.id, "a.txt", b"same bytes").await;
+        let again = store(&media, &first.id, "b.txt", b"same bytes").await;
+        assert_eq!(again.id, original.id);
+        assert_eq!(again.file_name, "a.txt");
+
+        // 其他工作空间有自己的记录，共用磁盘文件
+        let other = store(&media, &second.id, "c.txt", b"same bytes").await;
+        assert_ne!(other.id, original.id);
+        assert_eq!(other.file_path, original.file_path);
+        assert_eq!(other.content_hash, original.content_hash);
+
+        let different = store(&media, &first.id, "d.txt", b"other bytes").await;
+        assert_ne!(different.file_path, original.file_path);
+
+        let found = media
+            .find_by_hash(&first.id, original.content_hash.as_deref().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, original.id);
+        assert!(media
+            .find_by_hash(&first.id, &"0".repeat(64))
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(fs::read_dir(&media_dir).unwrap().count(), 2);
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn backfill_merges_duplicates_within_a_workspace() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let files = FileService::new(pool.clone());
+        let first = workspace(&pool, "First").await;
+        let second = workspace(&pool, "Second").await;
+
+        // 升级前的媒体：没有哈希，文件名不是内容哈希
+        let legacy = |workspace_id: &str, name: &str| {
+            let path = media_dir.join(name);
+            CreateMediaAsset {
+                workspace_id: workspace_id.to_string(),
+                file_name: name.to_string(),
+                file_path: path.to_string_lossy().to_string(),
+                file_size: 10,
+                mime_type: "text/plain".to_string(),
+                width: None,
+                height: None,
+                content_hash: None,
+            }
+        };
+        fs::write(media_dir.join("one.txt"), b"same bytes").unwrap();
+        fs::write(media_dir.join("two.txt"), b"same bytes").unwrap();
+        let survivor = media.create(legacy(&first.id, "one.txt")).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        let loser = media.create(legacy(&first.id, "two.txt")).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        // 另一个工作空间的媒体使用同一个旧文件
+        let shared = media.create(legacy(&second.id, "two.txt")).await.unwrap();
+
+        let first_doc = document_with(&pool, &first.id, image(&survivor)).await;
+        let second_doc = document_with(&pool, &first.id, image(&loser)).await;
+        files.create_revision(&second_doc).await.unwrap().unwrap();
+        let other_doc = document_with(&pool, &second.id, image(&shared)).await;
+        for (doc, asset) in [
+            (&first_doc, &survivor),
+            (&second_doc, &loser),
+            (&other_doc, &shared),
+        ] {
+            media.link_to_file(doc, &asset.id).await.unwrap();
+        }
+
+        media.backfill_hashes().await.unwrap();
+
+        let assets = media.list_by_workspace(&first.id).await.unwrap();
+        assert_eq!(ids(&assets), [survivor.id.as_str()]);
+        let merged = &assets[0];
+        let hash = merged.content_hash.clone().unwrap();
+        assert_eq!(
+            merged.file_path,
+            media_dir
+                .join(hashed_file_name(&hash, "one.txt"))
+                .to_string_lossy()
+        );
+
+        // 两篇文档（包括历史版本）的引用和关联都指向保留的记录
+        let reference = format!("{}{}", MEDIA_SCHEME, survivor.id);
+        for doc in [&first_doc, &second_doc] {
+            let content = files.get(doc).await.unwrap().unwrap().content.unwrap();
+            assert!(content.contains(&reference), "{}", content);
+            assert_eq!(
+                ids(&media.get_file_media(doc).await.unwrap()),
+                [survivor.id.as_str()]
+            );
+        }
+        let (revision,): (String,) =
+            sqlx::query_as("SELECT content FROM file_revisions WHERE file_id = ?")
+                .bind(&second_doc)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(revision.contains(&reference) && !revision.contains(&loser.id));
+
+        // 其他工作空间不合并，但共用同一个文件
+        let other = media.list_by_workspace(&second.id).await.unwrap();
+        assert_eq!(ids(&other), [shared.id.as_str()]);
+        assert_eq!(other[0].content_hash.as_deref(), Some(hash.as_str()));
+        assert_eq!(other[0].file_path, merged.file_path);
+        assert_eq!(
+            ids(&media.get_file_media(&other_doc).await.unwrap()),
+            [shared.id.as_str()]
+        );
+
+        // 旧文件在没有记录使用后才删除
+        let mut names: Vec<String> = fs::read_dir(&media_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, [hashed_file_name(&hash, "one.txt")]);
+        assert_eq!(fs::read(&merged.file_path).unwrap(), b"same bytes");
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    /// 在媒体目录中生成图片并创建记录，`known_size` 为 false 时不记录尺寸
+    async fn image_asset(
+        media: &MediaService,
+        workspace_id: &str,
+        hash: &str,
+        size: (u32, u32),
+        known_size: bool,
+    ) -> MediaAsset {
+        let path = media.media_dir.join(hashed_file_name(hash, "image.png"));
+        if !path.exists() {
+            image::RgbImage::from_pixel(size.0, size.1, image::Rgb([200, 30, 30]))
+                .save(&path)
+                .unwrap();
+        }
+        let dimension = |value: u32| known_size.then_some(value as i32);
+        media
+            .create(CreateMediaAsset {
+                workspace_id: workspace_id.to_string(),
+                file_name: "image.png".to_string(),
+                file_path: path.to_string_lossy().to_string(),
+                file_size: fs::metadata(&path).unwrap().len() as i64,
+                mime_type: "image/png".to_string(),
+                width: dimension(size.0),
+                height: dimension(size.1),
+                content_hash: Some(hash.to_string()),
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_thumbnail_generates_on_demand() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let workspace = workspace(&pool, "Workspace").await;
+        let large = image_asset(&media, &workspace.id, &"a".repeat(64), (600, 300), true).await;
+        let small = image_asset(&media, &workspace.id, &"b".repeat(64), (100, 80), true).await;
+
+        let path = media
+            .get_thumbnail(&large.id, ThumbnailSize::Small)
+            .await
+            .unwrap();
+        let expected = thumbnail::thumbnail_path(&media_dir, &"a".repeat(64), ThumbnailSize::Small);
+        assert_eq!(path, expected.to_string_lossy());
+        let thumbnail = image::open(&path).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+
+        // 原图不超过缩略图尺寸时直接使用原图
+        let path = media
+            .get_thumbnail(&large.id, ThumbnailSize::Large)
+            .await
+            .unwrap();
+        assert_eq!(path, large.file_path);
+        let path = media
+            .get_thumbnail(&small.id, ThumbnailSize::Small)
+            .await
+            .unwrap();
+        assert_eq!(path, small.file_path);
+        let thumbnails = media_dir.join(thumbnail::THUMBNAIL_DIR);
+        assert_eq!(fs::read_dir(&thumbnails).unwrap().count(), 1);
+
+        let text = store(&media, &workspace.id, "notes.txt", b"notes").await;
+        let result = media.get_thumbnail(&text.id, ThumbnailSize::Small).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+        let result = media.get_thumbnail("missing", ThumbnailSize::Small).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn backfill_thumbnails_reports_counts() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let first = workspace(&pool, "First").await;
+        let second = workspace(&pool, "Second").await;
+        let large = "a".repeat(64);
+        image_asset(&media, &first.id, &large, (1200, 900), true).await;
+        // 另一个工作空间中内容相同的媒体共用缩略图
+        image_asset(&media, &second.id, &large, (1200, 900), true).await;
+        image_asset(&media, &first.id, &"b".repeat(64), (100, 80), true).await;
+        // 尺寸未知时解码后才发现原图足够小
+        image_asset(&media, &first.id, &"c".repeat(64), (100, 80), false).await;
+        let broken = image_asset(&media, &first.id, &"d".repeat(64), (10, 10), false).await;
+        fs::write(&broken.file_path, b"not an image").unwrap();
+
+        let report = media.backfill_thumbnails().await.unwrap();
+        assert_eq!(report.generated, 2);
+        assert_eq!(report.skipped, 3);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].media_id, broken.id);
+        for size in ThumbnailSize::ALL {
+            assert!(thumbnail::thumbnail_path(&media_dir, &large, size).exists());
+        }
+
+        // 再次运行时没有需要生成的缩略图
+        let report = media.backfill_thumbnails().await.unwrap();
+        assert_eq!(report.generated, 0);
+        assert_eq!(report.skipped, 4);
+        assert_eq!(report.failed.len(), 1);
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn image_info_reads_only_image_media() {
+        let (pool, media_dir, media) = gc_setup().await;
+        let workspace = workspace(&pool, "Workspace").await;
+        let png = image_asset(&media, &workspace.id, &"a".repeat(64), (300, 200), true).await;
+
+        let info = media.image_info(&png.id).await.unwrap().unwrap();
+        assert_eq!(info.format, probe::ImageFormat::Png);
+        assert_eq!((info.width, info.height), (300, 200));
+
+        // 非图片类型不读取文件
+        let text = store(
+            &media,
+            &workspace.id,
+            "image.txt",
+            &fs::read(&png.file_path).unwrap(),
+        )
+        .await;
+        fs::remove_file(&text.file_path).unwrap();
+        assert!(media.image_info(&text.id).await.unwrap().is_none());
+
+        // 动画 GIF 读取整个文件统计帧数
+        let path = media_dir.join("animation.gif");
+        let mut encoder = image::codecs::gif::GifEncoder::new(fs::File::create(&path).unwrap());
+        for shade in [0, 128, 255] {
+            let frame = image::RgbaImage::from_pixel(40, 30, image::Rgba([shade, 0, 0, 255]));
+            encoder.encode_frame(image::Frame::new(frame)).unwrap();
+        }
+        drop(encoder);
+        let gif = media
+            .create(CreateMediaAsset {
+                workspace_id: workspace.id.clone(),
+                file_name: "animation.gif".to_string(),
+                file_path: path.to_string_lossy().to_string(),
+                file_size: fs::metadata(&path).unwrap().len() as i64,
+                mime_type: "image/gif".to_string(),
+                width: None,
+                height: None,
+                content_hash: None,
+            })
+            .await
+            .unwrap();
+        let info = media.image_info(&gif.id).await.unwrap().unwrap();
+        assert_eq!((info.width, info.height, info.frame_count), (40, 30, 3));
+
+        let result = media.image_info("missing").await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        fs::remove_dir_all(media_dir).unwrap();
+    }
+}
@@ -0,0 +1,1274 @@
+//! 图片元数据探测
+//!
+//! 只读取文件头部和必要的结构，不解码像素。支持 PNG（含 APNG）、JPEG（含渐进式）、
+//! GIF、WebP、BMP、SVG 和 AVIF。所有读取都经过边界检查，截断或损坏的文件返回
+//! `None` 或能确定的部分信息，不会 panic。
+
+use serde::Serialize;
+
+/// 图片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+    Bmp,
+    Svg,
+    Avif,
+}
+
+impl ImageFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::WebP => "image/webp",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Svg => "image/svg+xml",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// 像素的颜色类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    Indexed,
+    IndexedAlpha,
+    Cmyk,
+}
+
+/// 探测结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    /// 文件中存储的尺寸，未按方向旋转
+    pub width: u32,
+    pub height: u32,
+    /// EXIF 方向（1-8），没有方向信息时为 1
+    pub orientation: u8,
+    /// 无法确定时（如 SVG）为空
+    pub color_type: Option<ColorType>,
+    /// 动画帧数，静态图片为 1
+    pub frame_count: u32,
+}
+
+impl ImageInfo {
+    /// 按 EXIF 方向摆正后的显示尺寸
+    pub fn display_size(&self) -> (u32, u32) {
+        if (5..=8).contains(&self.orientation) {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// 按文件内容识别格式并读取元数据，不是支持的图片或无法读出尺寸时返回 `None`
+pub fn probe(data: &[u8]) -> Option<ImageInfo> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        probe_png(data)
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        probe_jpeg(data)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        probe_gif(data)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        probe_webp(data)
+    } else if data.starts_with(b"BM") {
+        probe_bmp(data)
+    } else if data.get(4..8) == Some(b"ftyp") {
+        probe_avif(data)
+    } else {
+        probe_svg(data)
+    }
+}
+
+/// 带边界检查的字节读取
+#[derive(Clone, Copy)]
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn slice(self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        self.0.get(offset..offset.checked_add(len)?)
+    }
+
+    fn u8(self, offset: usize) -> Option<u8> {
+        self.0.get(offset).copied()
+    }
+
+    fn u16_be(self, offset: usize) -> Option<u16> {
+        self.slice(offset, 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u16_le(self, offset: usize) -> Option<u16> {
+        self.slice(offset, 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u24_le(self, offset: usize) -> Option<u32> {
+        self.slice(offset, 3)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]))
+    }
+
+    fn u32_be(self, offset: usize) -> Option<u32> {
+        self.slice(offset, 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u32_le(self, offset: usize) -> Option<u32> {
+        self.slice(offset, 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64_be(self, offset: usize) -> Option<u64> {
+        self.slice(offset, 8).map(|b| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(b);
+            u64::from_be_bytes(bytes)
+        })
+    }
+}
+
+fn image_info(format: ImageFormat, width: u32, height: u32) -> Option<ImageInfo> {
+    (width > 0 && height > 0).then_some(ImageInfo {
+        format,
+        width,
+        height,
+        orientation: 1,
+        color_type: None,
+        frame_count: 1,
+    })
+}
+
+// ============ PNG ============
+
+fn probe_png(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    if bytes.slice(12, 4)? != b"IHDR" {
+        return None;
+    }
+    let mut info = image_info(ImageFormat::Png, bytes.u32_be(16)?, bytes.u32_be(20)?)?;
+    let color = bytes.u8(25);
+    info.color_type = match color {
+        Some(0) => Some(ColorType::Gray),
+        Some(2) => Some(ColorType::Rgb),
+        Some(3) => Some(ColorType::Indexed),
+        Some(4) => Some(ColorType::GrayAlpha),
+        Some(6) => Some(ColorType::Rgba),
+        _ => None,
+    };
+
+    // 图像数据之前的辅助块：acTL（APNG 帧数）、eXIf（方向）、tRNS（透明色）
+    let mut offset = 8;
+    while let (Some(len), Some(kind)) = (bytes.u32_be(offset), bytes.slice(offset + 4, 4)) {
+        let body = offset + 8;
+        match kind {
+            b"IDAT" | b"IEND" => break,
+            b"acTL" => {
+                if let Some(frames) = bytes.u32_be(body).filter(|&n| n > 0) {
+                    info.frame_count = frames;
+                }
+            }
+            b"eXIf" => {
+                if let Some(orientation) =
+                    bytes.slice(body, len as usize).and_then(exif_orientation)
+                {
+                    info.orientation = orientation;
+                }
+            }
+            b"tRNS" => {
+                info.color_type = match info.color_type {
+                    Some(ColorType::Gray) => Some(ColorType::GrayAlpha),
+                    Some(ColorType::Rgb) => Some(ColorType::Rgba),
+                    Some(ColorType::Indexed) => Some(ColorType::IndexedAlpha),
+                    other => other,
+                };
+            }
+            _ => {}
+        }
+        // 块长度 + 类型 + 数据 + CRC
+        offset = match body
+            .checked_add(len as usize)
+            .and_then(|n| n.checked_add(4))
+        {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    Some(info)
+}
+
+// ============ JPEG ============
+
+fn probe_jpeg(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    let mut orientation = 1;
+    let mut offset = 2;
+
+    loop {
+        if bytes.u8(offset)? != 0xFF {
+            return None;
+        }
+        // 标记前可以有任意个填充的 0xFF
+        let mut marker = bytes.u8(offset + 1)?;
+        while marker == 0xFF {
+            offset += 1;
+            marker = bytes.u8(offset + 1)?;
+        }
+        offset += 2;
+
+        // 没有长度字段的标记
+        if marker == 0x01 || (0xD0..=0xD8).contains(&marker) {
+            continue;
+        }
+        // 扫描数据或图像结束之前没有遇到帧头
+        if marker == 0xD9 || marker == 0xDA {
+            return None;
+        }
+
+        let len = bytes.u16_be(offset)? as usize;
+        if len < 2 {
+            return None;
+        }
+        let segment = offset + 2;
+
+        match marker {
+            // APP1 中的 EXIF
+            0xE1 => {
+                let body = bytes.slice(segment, len - 2).unwrap_or(&[]);
+                if let Some(tiff) = body.strip_prefix(b"Exif\0\0") {
+                    orientation = exif_orientation(tiff).unwrap_or(orientation);
+                }
+            }
+            // SOF0-SOF15，除去 DHT（C4）、JPG（C8）和 DAC（CC）
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = bytes.u16_be(segment + 1)? as u32;
+                let width = bytes.u16_be(segment + 3)? as u32;
+                let mut info = image_info(ImageFormat::Jpeg, width, height)?;
+                info.orientation = orientation;
+                info.color_type = match bytes.u8(segment + 5) {
+                    Some(1) => Some(ColorType::Gray),
+                    Some(3) => Some(ColorType::Rgb),
+                    Some(4) => Some(ColorType::Cmyk),
+                    _ => None,
+                };
+                return Some(info);
+            }
+            _ => {}
+        }
+
+        offset = offset.checked_add(len)?;
+    }
+}
+
+// ============ GIF ============
+
+fn probe_gif(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    let mut info = image_info(
+        ImageFormat::Gif,
+        bytes.u16_le(6)? as u32,
+        bytes.u16_le(8)? as u32,
+    )?;
+    info.color_type = Some(ColorType::Indexed);
+    info.frame_count = 0;
+
+    let mut offset = 13;
+    let flags = bytes.u8(10)?;
+    if flags & 0x80 != 0 {
+        offset += color_table_len(flags);
+    }
+
+    // 截断的文件按已读到的帧计数
+    while let Some(block) = bytes.u8(offset) {
+        match block {
+            // 图像描述符
+            0x2C => {
+                info.frame_count += 1;
+                let Some(flags) = bytes.u8(offset + 9) else {
+                    break;
+                };
+                offset += 10;
+                if flags & 0x80 != 0 {
+                    offset += color_table_len(flags);
+                }
+                // LZW 最小码长，然后是数据子块
+                match skip_sub_blocks(bytes, offset + 1) {
+                    Some(next) => offset = next,
+                    None => break,
+                }
+            }
+            // 扩展块，图形控制扩展中的透明色标记
+            0x21 => {
+                let label = bytes.u8(offset + 1);
+                if label == Some(0xF9) && bytes.u8(offset + 3).is_some_and(|f| f & 0x01 != 0) {
+                    info.color_type = Some(ColorType::IndexedAlpha);
+                }
+                match skip_sub_blocks(bytes, offset + 2) {
+                    Some(next) => offset = next,
+                    None => break,
+                }
+            }
+            _ => break, // 0x3B 结束，其他值为损坏的数据
+        }
+    }
+
+    info.frame_count = info.frame_count.max(1);
+    Some(info)
+}
+
+fn color_table_len(flags: u8) -> usize {
+    3 << ((flags & 0x07) + 1)
+}
+
+/// 跳过以 0 长度结束的子块序列，返回之后的位置
+fn skip_sub_blocks(bytes: Bytes<'_>, mut offset: usize) -> Option<usize> {
+    loop {
+        let len = bytes.u8(offset)? as usize;
+        offset += 1;
+        if len == 0 {
+            return Some(offset);
+        }
+        offset += len;
+    }
+}
+
+// ============ WebP ============
+
+fn probe_webp(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    let mut info: Option<ImageInfo> = None;
+    let mut has_alpha = false;
+    let mut frames = 0;
+    let mut orientation = 1;
+
+    let mut offset = 12;
+    while let (Some(kind), Some(len)) = (bytes.slice(offset, 4), bytes.u32_le(offset + 4)) {
+        let body = offset + 8;
+        match kind {
+            // 有损
+            b"VP8 " => {
+                if bytes.slice(body + 3, 3)? != [0x9D, 0x01, 0x2A] {
+                    return None;
+                }
+                let width = (bytes.u16_le(body + 6)? & 0x3FFF) as u32;
+                let height = (bytes.u16_le(body + 8)? & 0x3FFF) as u32;
+                info = info.or(image_info(ImageFormat::WebP, width, height));
+            }
+            // 无损
+            b"VP8L" => {
+                if bytes.u8(body)? != 0x2F {
+                    return None;
+                }
+                let bits = bytes.u32_le(body + 1)?;
+                let width = (bits & 0x3FFF) + 1;
+                let height = ((bits >> 14) & 0x3FFF) + 1;
+                has_alpha |= bits & (1 << 28) != 0;
+                info = info.or(image_info(ImageFormat::WebP, width, height));
+            }
+            // 扩展格式：画布尺寸和特性标记
+            b"VP8X" => {
+                let flags = bytes.u8(body)?;
+                has_alpha |= flags & 0x10 != 0;
+                let width = bytes.u24_le(body + 4)? + 1;
+                let height = bytes.u24_le(body + 7)? + 1;
+                info = image_info(ImageFormat::WebP, width, height);
+            }
+            b"ALPH" => has_alpha = true,
+            b"ANMF" => frames += 1,
+            b"EXIF" => {
+                let tiff = bytes.slice(body, len as usize).unwrap_or(&[]);
+                let tiff = tiff.strip_prefix(b"Exif\0\0").unwrap_or(tiff);
+                orientation = exif_orientation(tiff).unwrap_or(orientation);
+            }
+            _ => {}
+        }
+        // 块数据按偶数字节对齐
+        let padded = (len as usize).checked_add(len as usize & 1);
+        offset = match padded.and_then(|n| body.checked_add(n)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    let mut info = info?;
+    info.color_type = Some(if has_alpha {
+        ColorType::Rgba
+    } else {
+        ColorType::Rgb
+    });
+    info.frame_count = frames.max(1);
+    info.orientation = orientation;
+    Some(info)
+}
+
+// ============ BMP ============
+
+fn probe_bmp(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    let header_len = bytes.u32_le(14)?;
+
+    let (width, height, bits) = if header_len == 12 {
+        // BITMAPCOREHEADER
+        (
+            bytes.u16_le(18)? as u32,
+            bytes.u16_le(20)? as u32,
+            bytes.u16_le(24)?,
+        )
+    } else if header_len >= 40 {
+        // BITMAPINFOHEADER 及之后的版本，高度为负表示自上而下存储
+        let width = bytes.u32_le(18)? as i32;
+        let height = bytes.u32_le(22)? as i32;
+        (
+            width.unsigned_abs(),
+            height.unsigned_abs(),
+            bytes.u16_le(28)?,
+        )
+    } else {
+        return None;
+    };
+
+    let mut info = image_info(ImageFormat::Bmp, width, height)?;
+    info.color_type = match bits {
+        1 | 2 | 4 | 8 => Some(ColorType::Indexed),
+        16 | 24 => Some(ColorType::Rgb),
+        32 => Some(ColorType::Rgba),
+        _ => None,
+    };
+    Some(info)
+}
+
+// ============ SVG ============
+
+/// 只读取根元素的 `width`、`height` 和 `viewBox`
+///
+/// 带单位的尺寸按 CSS 像素换算，百分比等相对尺寸改用 `viewBox`。
+fn probe_svg(data: &[u8]) -> Option<ImageInfo> {
+    let head = &data[..data.len().min(64 * 1024)];
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if !text.starts_with('<') {
+        return None;
+    }
+
+    let start = text.find("<svg")?;
+    let rest = &text[start + 4..];
+    if !rest.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+        return None;
+    }
+    let tag = &rest[..rest.find('>')?];
+
+    let view_box: Option<Vec<f64>> = svg_attribute(tag, "viewBox").map(|value| {
+        value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| s.parse().ok())
+            .collect()
+    });
+    let view_box = view_box.filter(|v| v.len() == 4);
+    let width = svg_attribute(tag, "width")
+        .and_then(svg_length)
+        .or_else(|| view_box.as_ref().map(|v| v[2]));
+    let height = svg_attribute(tag, "height")
+        .and_then(svg_length)
+        .or_else(|| view_box.as_ref().map(|v| v[3]));
+
+    let to_pixels = |v: f64| {
+        (0.5..=u32::MAX as f64)
+            .contains(&v)
+            .then(|| v.round() as u32)
+    };
+    image_info(ImageFormat::Svg, to_pixels(width?)?, to_pixels(height?)?)
+}
+
+/// 读取标签中的属性值，属性名前必须是空白以免匹配到 `stroke-width` 等
+fn svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut search = tag;
+    while let Some(pos) = search.find(name) {
+        let before = search[..pos].chars().next_back();
+        let after = search[pos + name.len()..].trim_start();
+        search = &search[pos + name.len()..];
+
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value = &after[1..];
+        return Some(&value[..value.find(quote)?]);
+    }
+    None
+}
+
+/// SVG 长度换算为像素，相对单位返回 `None`
+fn svg_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let scale = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+// ============ AVIF ============
+
+/// 解析 ISOBMFF 盒结构
+///
+/// 尺寸取 `ispe` 中最大的一个（主图像，透明通道等辅助图像尺寸相同或更小），方向由
+/// `irot`/`imir` 换算为 EXIF 方向，图像序列的帧数取第一个轨道的 `stsz` 样本数。
+fn probe_avif(data: &[u8]) -> Option<ImageInfo> {
+    let bytes = Bytes(data);
+    let (ftyp_start, ftyp_end) = next_box(bytes, 0, data.len(), b"ftyp")?;
+    let brands: Vec<&[u8]> = std::iter::once(ftyp_start)
+        .chain((ftyp_start + 8..ftyp_end).step_by(4))
+        .filter_map(|offset| bytes.slice(offset, 4))
+        .collect();
+    if !brands.iter().any(|b| *b == b"avif" || *b == b"avis") {
+        return None;
+    }
+
+    let mut width = 0;
+    let mut height = 0;
+    let mut rotation = 0;
+    let mut mirror = None;
+    let mut channels = None;
+    let mut has_alpha = false;
+
+    // meta 是 FullBox，内容前有 4 字节的版本和标记
+    let ipco = next_box(bytes, 0, data.len(), b"meta")
+        .and_then(|(start, end)| next_box(bytes, start + 4, end, b"iprp"))
+        .and_then(|(start, end)| next_box(bytes, start, end, b"ipco"));
+    if let Some((start, end)) = ipco {
+        for (kind, body, body_end) in boxes(bytes, start, end) {
+            match kind {
+                b"ispe" => {
+                    let (Some(w), Some(h)) = (bytes.u32_be(body + 4), bytes.u32_be(body + 8))
+                    else {
+                        continue;
+                    };
+                    if w as u64 * h as u64 > width as u64 * height as u64 {
+                        width = w;
+                        height = h;
+                    }
+                }
+                b"irot" => rotation = bytes.u8(body).map_or(0, |a| a & 0x03),
+                b"imir" => mirror = bytes.u8(body).map(|a| a & 0x01),
+                b"pixi" => {
+                    channels = channels.max(bytes.u8(body + 4));
+                }
+                b"auxC" => {
+                    let urn = bytes.slice(body + 4, body_end.saturating_sub(body + 4));
+                    has_alpha |= urn.is_some_and(|u| {
+                        u.starts_with(b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha")
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut info = image_info(ImageFormat::Avif, width, height)?;
+    info.orientation = heif_orientation(rotation, mirror);
+    info.color_type = match (channels, has_alpha) {
+        (Some(1), false) => Some(ColorType::Gray),
+        (Some(1), true) => Some(ColorType::GrayAlpha),
+        (_, true) => Some(ColorType::Rgba),
+        _ => Some(ColorType::Rgb),
+    };
+
+    if brands.iter().any(|b| *b == b"avis") {
+        let frames = ["moov", "trak", "mdia", "minf", "stbl", "stsz"]
+            .iter()
+            .try_fold((0, data.len()), |(start, end), name| {
+                next_box(bytes, start, end, name.as_bytes())
+            })
+            .and_then(|(body, _)| bytes.u32_be(body + 8));
+        if let Some(frames) = frames.filter(|&n| n > 0) {
+            info.frame_count = frames;
+        }
+    }
+
+    Some(info)
+}
+
+/// 依次列出 `[start, end)` 范围内的盒：(类型, 内容起点, 内容终点)
+fn boxes(
+    bytes: Bytes<'_>,
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = (&[u8], usize, usize)> {
+    let end = end.min(bytes.0.len());
+    let mut offset = start;
+
+    std::iter::from_fn(move || {
+        if offset >= end {
+            return None;
+        }
+        let size = bytes.u32_be(offset)? as u64;
+        let kind = bytes.slice(offset + 4, 4)?;
+        let (header, size) = match size {
+            0 => (8, (end - offset) as u64), // 延伸到末尾
+            1 => (16, bytes.u64_be(offset + 8)?),
+            _ => (8, size),
+        };
+        if size < header as u64 {
+            return None;
+        }
+        let box_end = offset.checked_add(usize::try_from(size).ok()?)?.min(end);
+        let body = offset + header;
+        offset = box_end;
+        Some((kind, body.min(box_end), box_end))
+    })
+}
+
+fn next_box(bytes: Bytes<'_>, start: usize, end: usize, kind: &[u8]) -> Option<(usize, usize)> {
+    boxes(bytes, start, end)
+        .find(|(k, _, _)| *k == kind)
+        .map(|(_, body, body_end)| (body, body_end))
+}
+
+/// `irot`（逆时针旋转 90° 的次数）和 `imir`（0 左右翻转，1 上下翻转）换算为 EXIF 方向
+///
+/// 先旋转后翻转；上下翻转等于旋转 180° 后左右翻转。
+fn heif_orientation(rotation: u8, mirror: Option<u8>) -> u8 {
+    match mirror {
+        None => [1, 8, 3, 6][rotation as usize & 3],
+        Some(axis) => {
+            let rotation = (rotation + axis * 2) as usize & 3;
+            [2, 7, 4, 5][rotation]
+        }
+    }
+}
+
+// ============ EXIF ============
+
+/// 从 TIFF 结构的 IFD0 中读取方向标签（0x0112）
+fn exif_orientation(tiff: &[u8]) -> Option<u8> {
+    let bytes = Bytes(tiff);
+    let little_endian = match bytes.slice(0, 4)? {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return None,
+    };
+    let u16_at = |offset| {
+        if little_endian {
+            bytes.u16_le(offset)
+        } else {
+            bytes.u16_be(offset)
+        }
+    };
+    let u32_at = |offset| {
+        if little_endian {
+            bytes.u32_le(offset)
+        } else {
+            bytes.u32_be(offset)
+        }
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count)
+        .filter_map(|i| ifd.checked_add(2 + i * 12))
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|value| (1..=8).contains(value))
+        .map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::metadata::Orientation;
+    use image::{DynamicImage, Frame, GrayImage, RgbImage, RgbaImage};
+    use std::io::Cursor;
+
+    // ============ 样本构造 ============
+
+    fn encode(image: DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    fn rgba(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 128])
+        }))
+    }
+
+    fn rgb(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([x as u8, y as u8, 7])
+        }))
+    }
+
+    /// 只含方向标签的 TIFF 结构
+    fn tiff(little_endian: bool, orientation: u16) -> Vec<u8> {
+        let u16_bytes = |v: u16| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let u32_bytes = |v: u32| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let mut out = if little_endian {
+            b"II*\0".to_vec()
+        } else {
+            b"MM\0*".to_vec()
+        };
+        out.extend(u32_bytes(8));
+        out.extend(u16_bytes(1));
+        out.extend(u16_bytes(0x0112));
+        out.extend(u16_bytes(3));
+        out.extend(u32_bytes(1));
+        out.extend(u16_bytes(orientation));
+        out.extend([0, 0]);
+        out.extend(u32_bytes(0));
+        out
+    }
+
+    fn png_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = (body.len() as u32).to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(body);
+        out.extend([0; 4]); // CRC 不参与探测
+        out
+    }
+
+    /// 在 IHDR 之后插入辅助块
+    fn png_with(image: DynamicImage, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = encode(image, image::ImageFormat::Png);
+        let ihdr_end = 8 + 25;
+        for chunk in chunks.iter().rev() {
+            data.splice(ihdr_end..ihdr_end, chunk.iter().copied());
+        }
+        data
+    }
+
+    fn jpeg_segment(marker: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![0xFF, marker];
+        out.extend((body.len() as u16 + 2).to_be_bytes());
+        out.extend(body);
+        out
+    }
+
+    /// 带 EXIF 的渐进式 JPEG 头部（SOF2），扫描数据只是占位
+    fn progressive_jpeg(width: u16, height: u16, orientation: u16) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(jpeg_segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"));
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend(tiff(false, orientation));
+        data.extend(jpeg_segment(0xE1, &exif));
+        data.extend(jpeg_segment(0xDB, &[0; 65]));
+        // 填充的 0xFF 之后是帧头
+        data.push(0xFF);
+        let mut sof = vec![8];
+        sof.extend(height.to_be_bytes());
+        sof.extend(width.to_be_bytes());
+        sof.extend([3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        data.extend(jpeg_segment(0xC2, &sof));
+        data.extend(jpeg_segment(0xC4, &[0; 20]));
+        data.extend(jpeg_segment(0xDA, &[1, 1, 0, 0, 63, 0]));
+        data.extend([0x12, 0x34, 0xFF, 0x00, 0x56]);
+        data.extend([0xFF, 0xD9]);
+        data
+    }
+
+    fn animated_gif(width: u32, height: u32, frames: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut out);
+            let frames = (0..frames).map(|i| {
+                Frame::new(RgbaImage::from_pixel(
+                    width,
+                    height,
+                    image::Rgba([i as u8 * 40, 0, 0, 255]),
+                ))
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+        out
+    }
+
+    /// 1×1 透明 GIF
+    const TRANSPARENT_GIF: &[u8] = b"GIF89a\x01\0\x01\0\x80\0\0\xff\xff\xff\0\0\0\
+        !\xf9\x04\x01\0\0\0\0,\0\0\0\0\x01\0\x01\0\0\x02\x02D\x01\0;";
+
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut out = b"RIFF".to_vec();
+        out.extend((body.len() as u32 + 4).to_le_bytes());
+        out.extend(b"WEBP");
+        out.extend(body);
+        out
+    }
+
+    fn webp_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = kind.to_vec();
+        out.extend((body.len() as u32).to_le_bytes());
+        out.extend(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    /// 有损 WebP（VP8），只有关键帧头部
+    fn lossy_webp(width: u16, height: u16) -> Vec<u8> {
+        let mut vp8 = vec![0x50, 0x02, 0x00, 0x9D, 0x01, 0x2A];
+        vp8.extend(width.to_le_bytes());
+        vp8.extend(height.to_le_bytes());
+        vp8.extend([0; 7]);
+        riff(&[webp_chunk(b"VP8 ", &vp8)])
+    }
+
+    /// 扩展格式的动画 WebP，带透明通道和 EXIF
+    fn animated_webp(width: u32, height: u32, frames: usize, orientation: u16) -> Vec<u8> {
+        let mut vp8x = vec![0x10 | 0x08 | 0x02, 0, 0, 0];
+        vp8x.extend(&(width - 1).to_le_bytes()[..3]);
+        vp8x.extend(&(height - 1).to_le_bytes()[..3]);
+        let mut chunks = vec![webp_chunk(b"VP8X", &vp8x), webp_chunk(b"ANIM", &[0; 6])];
+        for _ in 0..frames {
+            chunks.push(webp_chunk(b"ANMF", &[0; 17]));
+        }
+        chunks.push(webp_chunk(b"EXIF", &tiff(true, orientation)));
+        riff(&chunks)
+    }
+
+    /// 自上而下存储的 32 位 BMP（BITMAPINFOHEADER，高度为负）
+    fn top_down_bmp(width: i32, height: i32) -> Vec<u8> {
+        let mut data = b"BM".to_vec();
+        data.extend([0; 12]);
+        data.extend(40u32.to_le_bytes());
+        data.extend(width.to_le_bytes());
+        data.extend((-height).to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(32u16.to_le_bytes());
+        data.extend([0; 24]);
+        data
+    }
+
+    /// OS/2 的 BITMAPCOREHEADER
+    fn core_bmp(width: u16, height: u16) -> Vec<u8> {
+        let mut data = b"BM".to_vec();
+        data.extend([0; 12]);
+        data.extend(12u32.to_le_bytes());
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(8u16.to_le_bytes());
+        data
+    }
+
+    fn isobmff(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = (body.len() as u32 + 8).to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(body);
+        out
+    }
+
+    fn full_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        isobmff(kind, &[&[0, 0, 0, 0], body].concat())
+    }
+
+    fn ispe(width: u32, height: u32) -> Vec<u8> {
+        full_box(
+            b"ispe",
+            &[width.to_be_bytes(), height.to_be_bytes()].concat(),
+        )
+    }
+
+    /// AVIF：主图像、较小的透明通道图像和可选的旋转、翻转属性
+    fn avif(width: u32, height: u32, rotation: Option<u8>, mirror: Option<u8>) -> Vec<u8> {
+        let mut ipco = [
+            ispe(width, height),
+            full_box(b"pixi", &[3, 8, 8, 8]),
+            ispe(width / 2, height / 2),
+            full_box(b"auxC", b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha\0"),
+        ]
+        .concat();
+        if let Some(rotation) = rotation {
+            ipco.extend(isobmff(b"irot", &[rotation]));
+        }
+        if let Some(mirror) = mirror {
+            ipco.extend(isobmff(b"imir", &[mirror]));
+        }
+
+        let meta = full_box(
+            b"meta",
+            &[
+                full_box(b"hdlr", &[0; 20]),
+                isobmff(b"iprp", &isobmff(b"ipco", &ipco)),
+            ]
+            .concat(),
+        );
+        [isobmff(b"ftyp", b"avif\0\0\0\0mif1miaf"), meta].concat()
+    }
+
+    /// AVIF 图像序列，帧数来自 `stsz`
+    fn avif_sequence(width: u32, height: u32, frames: u32) -> Vec<u8> {
+        let ipco = ispe(width, height);
+        let meta = full_box(b"meta", &isobmff(b"iprp", &isobmff(b"ipco", &ipco)));
+        let stsz = full_box(
+            b"stsz",
+            &[0u32.to_be_bytes(), frames.to_be_bytes()].concat(),
+        );
+        let moov = ["trak", "mdia", "minf", "stbl"]
+            .iter()
+            .rev()
+            .fold(stsz, |inner, kind| {
+                isobmff(kind.as_bytes().try_into().unwrap(), &inner)
+            });
+        [
+            isobmff(b"ftyp", b"avis\0\0\0\0avifmsf1"),
+            meta,
+            isobmff(b"moov", &moov),
+        ]
+        .concat()
+    }
+
+    /// 期望的探测结果：(名称, 数据, 格式, 宽, 高, 方向, 颜色类型, 帧数)
+    type Sample = (
+        &'static str,
+        Vec<u8>,
+        ImageFormat,
+        u32,
+        u32,
+        u8,
+        Option<ColorType>,
+        u32,
+    );
+
+    fn samples() -> Vec<Sample> {
+        use ColorType::*;
+        use ImageFormat::*;
+
+        let gray = DynamicImage::ImageLuma8(GrayImage::new(9, 4));
+        vec![
+            ("png", encode(rgba(37, 21), image::ImageFormat::Png), Png, 37, 21, 1, Some(Rgba), 1),
+            ("png rgb", encode(rgb(5, 300), image::ImageFormat::Png), Png, 5, 300, 1, Some(Rgb), 1),
+            (
+                "png gray + tRNS",
+                png_with(gray, &[png_chunk(b"tRNS", &[0, 0])]),
+                Png, 9, 4, 1, Some(GrayAlpha), 1,
+            ),
+            (
+                "apng + eXIf",
+                png_with(
+                    rgba(20, 10),
+                    &[
+                        png_chunk(b"acTL", &[0, 0, 0, 3, 0, 0, 0, 0]),
+                        png_chunk(b"eXIf", &tiff(false, 6)),
+                    ],
+                ),
+                Png, 20, 10, 6, Some(Rgba), 3,
+            ),
+            ("jpeg", encode(rgb(64, 48), image::ImageFormat::Jpeg), Jpeg, 64, 48, 1, Some(Rgb), 1),
+            (
+                "jpeg gray",
+                encode(DynamicImage::ImageLuma8(GrayImage::new(16, 3)), image::ImageFormat::Jpeg),
+                Jpeg, 16, 3, 1, Some(Gray), 1,
+            ),
+            ("jpeg progressive + exif", progressive_jpeg(400, 300, 6), Jpeg, 400, 300, 6, Some(Rgb), 1),
+            ("gif animated", animated_gif(20, 10, 3), Gif, 20, 10, 1, None, 3),
+            ("gif transparent", TRANSPARENT_GIF.to_vec(), Gif, 1, 1, 1, Some(IndexedAlpha), 1),
+            ("webp lossless", encode(rgba(33, 17), image::ImageFormat::WebP), WebP, 33, 17, 1, Some(Rgba), 1),
+            ("webp lossy", lossy_webp(640, 480), WebP, 640, 480, 1, Some(Rgb), 1),
+            ("webp animated + exif", animated_webp(100, 50, 2, 3), WebP, 100, 50, 3, Some(Rgba), 2),
+            ("bmp", encode(rgb(13, 7), image::ImageFormat::Bmp), Bmp, 13, 7, 1, Some(Rgb), 1),
+            ("bmp top-down", top_down_bmp(6, 8), Bmp, 6, 8, 1, Some(Rgba), 1),
+            ("bmp core header", core_bmp(3, 2), Bmp, 3, 2, 1, Some(Indexed), 1),
+            (
+                "svg",
+                br#"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="80"><rect/></svg>"#.to_vec(),
+                Svg, 120, 80, 1, None, 1,
+            ),
+            (
+                "svg units + prolog",
+                "\u{feff}<?xml version=\"1.0\"?>\n<svg stroke-width=\"3\" width='2in' height=\"1.5in\"></svg>"
+                    .as_bytes()
+                    .to_vec(),
+                Svg, 192, 144, 1, None, 1,
+            ),
+            (
+                "svg viewBox",
+                br#"<svg viewBox="0 0 64.4 32" width="100%"/>"#.to_vec(),
+                Svg, 64, 32, 1, None, 1,
+            ),
+            ("avif", avif(1920, 1080, Some(1), None), Avif, 1920, 1080, 8, Some(Rgba), 1),
+            ("avif mirrored", avif(30, 40, None, Some(0)), Avif, 30, 40, 2, Some(Rgba), 1),
+            ("avif sequence", avif_sequence(64, 64, 24), Avif, 64, 64, 1, Some(Rgb), 24),
+        ]
+    }
+
+    // ============ 测试 ============
+
+    #[test]
+    fn probes_valid_images() {
+        for (name, data, format, width, height, orientation, color_type, frames) in samples() {
+            let info = probe(&data).unwrap_or_else(|| panic!("{}: not recognized", name));
+            assert_eq!(info.format, format, "{}", name);
+            assert_eq!((info.width, info.height), (width, height), "{}", name);
+            assert_eq!(info.orientation, orientation, "{}", name);
+            if color_type.is_some() {
+                assert_eq!(info.color_type, color_type, "{}", name);
+            }
+            assert_eq!(info.frame_count, frames, "{}", name);
+        }
+    }
+
+    #[test]
+    fn display_size_follows_orientation() {
+        let info = probe(&progressive_jpeg(400, 300, 6)).unwrap();
+        assert_eq!(info.display_size(), (300, 400));
+
+        let info = probe(&animated_webp(100, 50, 1, 3)).unwrap();
+        assert_eq!(info.display_size(), (100, 50));
+    }
+
+    #[test]
+    fn empty_and_unknown_input() {
+        assert_eq!(probe(&[]), None);
+        assert_eq!(probe(b"plain text"), None);
+        assert_eq!(probe(b"<html><body></body></html>"), None);
+        assert_eq!(probe(b"<svgfoo width=\"1\" height=\"1\">"), None);
+        assert_eq!(probe(&[0; 64]), None);
+
+        // 只有文件签名
+        for magic in [
+            &b"\x89PNG\r\n\x1a\n"[..],
+            &[0xFF, 0xD8, 0xFF],
+            b"GIF89a",
+            b"RIFF\0\0\0\0WEBP",
+            b"BM",
+            b"\0\0\0\x0cftypavif",
+        ] {
+            assert_eq!(probe(magic), None, "{:?}", magic);
+        }
+    }
+
+    #[test]
+    fn zero_dimensions_are_rejected() {
+        assert_eq!(probe(&lossy_webp(0, 10)), None);
+        assert_eq!(probe(&top_down_bmp(0, 4)), None);
+        assert_eq!(probe(br#"<svg width="0" height="10"/>"#), None);
+        assert_eq!(probe(&avif(0, 0, None, None)), None);
+
+        let mut png = encode(rgb(2, 2), image::ImageFormat::Png);
+        png[16..20].copy_from_slice(&[0; 4]);
+        assert_eq!(probe(&png), None);
+    }
+
+    #[test]
+    fn corrupted_structures() {
+        // IHDR 不是第一个块
+        let mut png = encode(rgb(2, 2), image::ImageFormat::Png);
+        png[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(probe(&png), None);
+
+        // 标记之间出现了非 0xFF 字节
+        let mut jpeg = progressive_jpeg(10, 10, 1);
+        jpeg[2] = 0x00;
+        assert_eq!(probe(&jpeg), None);
+
+        // 帧头之前就开始扫描数据
+        let jpeg = [&[0xFF, 0xD8][..], &jpeg_segment(0xDA, &[0; 6])].concat();
+        assert_eq!(probe(&jpeg), None);
+
+        // 段长度小于 2
+        assert_eq!(probe(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x01]), None);
+
+        // VP8 关键帧起始码错误
+        let mut webp = lossy_webp(10, 10);
+        webp[23] = 0;
+        assert_eq!(probe(&webp), None);
+
+        // 不支持的 BMP 信息头
+        let mut bmp = top_down_bmp(4, 4);
+        bmp[14] = 20;
+        assert_eq!(probe(&bmp), None);
+
+        // 不是 AVIF 的 ISOBMFF（如 MP4）
+        assert_eq!(probe(&isobmff(b"ftyp", b"isom\0\0\0\0mp41")), None);
+
+        // 盒长度超出文件
+        let mut avif = avif(8, 8, None, None);
+        let len = avif.len();
+        avif[len - 4..].copy_from_slice(&[0xFF; 4]);
+        assert!(probe(&avif).is_none_or(|info| info.width == 8 && info.height == 8));
+
+        // EXIF 方向超出范围时忽略
+        let info = probe(&progressive_jpeg(10, 10, 9)).unwrap();
+        assert_eq!(info.orientation, 1);
+    }
+
+    #[test]
+    fn truncated_input_returns_none_or_correct_dimensions() {
+        for (name, data, _, width, height, ..) in samples() {
+            for len in 0..data.len() {
+                if let Some(info) = probe(&data[..len]) {
+                    assert_eq!(
+                        (info.width, info.height),
+                        (width, height),
+                        "{} truncated to {} bytes",
+                        name,
+                        len
+                    );
+                }
+            }
+        }
+    }
+
+    fn assert_sane(info: Option<ImageInfo>) {
+        if let Some(info) = info {
+            assert!(info.width > 0 && info.height > 0);
+            assert!((1..=8).contains(&info.orientation));
+            assert!(info.frame_count >= 1);
+        }
+    }
+
+    #[test]
+    fn single_byte_corruption_does_not_panic() {
+        for (_, data, ..) in samples() {
+            for i in 0..data.len() {
+                for value in [0x00, 0xFF, data[i] ^ 0x01, data[i] ^ 0x80] {
+                    let mut corrupted = data.clone();
+                    corrupted[i] = value;
+                    assert_sane(probe(&corrupted));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_corruption_does_not_panic() {
+        // 固定种子的 xorshift，结果可以复现
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for (_, data, ..) in samples() {
+            for _ in 0..2000 {
+                let mut corrupted = data.clone();
+                for _ in 0..1 + next() % 8 {
+                    let i = next() as usize % corrupted.len();
+                    corrupted[i] = next() as u8;
+                }
+                // 偶尔截断或在末尾追加垃圾数据
+                match next() % 4 {
+                    0 => corrupted.truncate(next() as usize % corrupted.len()),
+                    1 => corrupted.extend((0..next() % 64).map(|_| next() as u8)),
+                    _ => {}
+                }
+                assert_sane(probe(&corrupted));
+            }
+        }
+    }
+
+    #[test]
+    fn exif_orientation_in_both_byte_orders() {
+        for orientation in 1..=8 {
+            assert_eq!(
+                exif_orientation(&tiff(true, orientation)),
+                Some(orientation as u8)
+            );
+            assert_eq!(
+                exif_orientation(&tiff(false, orientation)),
+                Some(orientation as u8)
+            );
+        }
+        assert_eq!(exif_orientation(&tiff(true, 0)), None);
+        assert_eq!(exif_orientation(b"XX*\0"), None);
+        assert_eq!(exif_orientation(&tiff(true, 6)[..12]), None);
+    }
+
+    /// 把 HEIF 的变换直接作用在图片上，与按换算出的 EXIF 方向摆正的结果比较
+    #[test]
+    fn heif_orientation_matches_transforms() {
+        let source = DynamicImage::ImageRgb8(RgbImage::from_fn(3, 2, |x, y| {
+            image::Rgb([(y * 3 + x) as u8, 0, 0])
+        }));
+
+        for rotation in 0..4u8 {
+            for mirror in [None, Some(0), Some(1)] {
+                // irot 逆时针旋转，然后 imir 按竖直（0）或水平（1）轴翻转
+                let mut expected = source.clone();
+                for _ in 0..rotation {
+                    expected = expected.rotate270();
+                }
+                expected = match mirror {
+                    Some(0) => expected.fliph(),
+                    Some(_) => expected.flipv(),
+                    None => expected,
+                };
+
+                let orientation = heif_orientation(rotation, mirror);
+                let mut actual = source.clone();
+                actual.apply_orientation(Orientation::from_exif(orientation).unwrap());
+
+                assert_eq!(
+                    actual.to_rgb8().into_raw(),
+                    expected.to_rgb8().into_raw(),
+                    "irot {} imir {:?} -> orientation {}",
+                    rotation,
+                    mirror,
+                    orientation
+                );
+                assert_eq!(actual.width(), expected.width());
+            }
+        }
+    }
+
+    #[test]
+    fn heif_orientation_table() {
+        assert_eq!(heif_orientation(0, None), 1);
+        assert_eq!(heif_orientation(1, None), 8);
+        assert_eq!(heif_orientation(2, None), 3);
+        assert_eq!(heif_orientation(3, None), 6);
+        assert_eq!(heif_orientation(0, Some(0)), 2);
+        assert_eq!(heif_orientation(0, Some(1)), 4);
+        assert_eq!(heif_orientation(1, Some(0)), 7);
+        assert_eq!(heif_orientation(1, Some(1)), 5);
+        // irot 只使用低 2 位
+        assert_eq!(heif_orientation(5, None), 8);
+    }
+}
@@ -0,0 +1,263 @@
+//! 图片缩略图
+//!
+//! 缩略图按原图内容的哈希保存为 `media/thumbnails/<hash>-<像素>.jpg`，不同工作空间中
+//! 内容相同的媒体共用同一组缩略图。原图不超过缩略图尺寸时不生成，直接使用原图。
+
+use crate::error::Result;
+use crate::models::ThumbnailSize;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgb, RgbImage};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// 媒体目录下保存缩略图的子目录
+pub const THUMBNAIL_DIR: &str = "thumbnails";
+
+const JPEG_QUALITY: u8 = 80;
+
+/// 缩略图文件路径
+pub fn thumbnail_path(media_dir: &Path, hash: &str, size: ThumbnailSize) -> PathBuf {
+    media_dir
+        .join(THUMBNAIL_DIR)
+        .join(format!("{}-{}.jpg", hash, size.pixels()))
+}
+
+/// 是否能为该类型的媒体生成缩略图（SVG 是矢量图，直接使用原图）
+pub fn is_supported(mime_type: &str) -> bool {
+    mime_type.starts_with("image/") && mime_type != "image/svg+xml"
+}
+
+/// 按已知的原图尺寸判断是否需要该尺寸的缩略图，尺寸未知时需要
+pub fn is_needed(width: Option<i32>, height: Option<i32>, size: ThumbnailSize) -> bool {
+    match (width, height) {
+        (Some(w), Some(h)) => w.max(h) > size.pixels() as i32,
+        _ => true,
+    }
+}
+
+/// 解码原图并生成缺少的缩略图，已存在的缩略图不会重新生成
+///
+/// 图片按 EXIF 方向摆正，透明部分填充为白色。返回新生成的缩略图数量。
+pub fn generate(
+    source: &Path,
+    media_dir: &Path,
+    hash: &str,
+    sizes: &[ThumbnailSize],
+) -> Result<usize> {
+    let missing: Vec<ThumbnailSize> = sizes
+        .iter()
+        .copied()
+        .filter(|&size| !thumbnail_path(media_dir, hash, size).exists())
+        .collect();
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    let mut decoder = ImageReader::open(source)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    fs::create_dir_all(media_dir.join(THUMBNAIL_DIR))?;
+    let mut generated = 0;
+    for size in missing {
+        let pixels = size.pixels();
+        if image.width().max(image.height()) <= pixels {
+            continue;
+        }
+        let thumbnail = flatten(image.thumbnail(pixels, pixels));
+        write_jpeg(&thumbnail, &thumbnail_path(media_dir, hash, size))?;
+        generated += 1;
+    }
+
+    Ok(generated)
+}
+
+/// 删除原图的所有缩略图
+pub fn remove(media_dir: &Path, hash: &str) {
+    for size in ThumbnailSize::ALL {
+        let _ = fs::remove_file(thumbnail_path(media_dir, hash, size));
+    }
+}
+
+/// 从缩略图文件名中取出原图哈希
+pub fn source_hash(path: &Path) -> Option<&str> {
+    let stem = path.file_stem()?.to_str()?;
+    let (hash, pixels) = stem.rsplit_once('-')?;
+    ThumbnailSize::ALL
+        .iter()
+        .any(|size| size.pixels().to_string() == pixels)
+        .then_some(hash)
+}
+
+/// 透明部分与白色背景混合
+fn flatten(image: DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.into_rgb8();
+    }
+
+    let rgba = image.into_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// 先写入临时文件再重命名，避免读到写了一半的缩略图
+fn write_jpeg(image: &RgbImage, path: &Path) -> Result<()> {
+    let temp_path = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+    let written = encode_jpeg(image, &temp_path)
+        .and_then(|()| fs::rename(&temp_path, path).map_err(Into::into));
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+fn encode_jpeg(image: &RgbImage, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY).encode_image(image)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::png::PngEncoder;
+    use image::{ExtendedColorType, ImageEncoder, RgbaImage};
+
+    const HASH: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("thumbnail-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 左半红色、右半蓝色的 JPEG
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, 90)
+            .encode_image(&image)
+            .unwrap();
+        bytes
+    }
+
+    /// 在 SOI 之后插入只有方向标签的 EXIF 段
+    fn with_orientation(jpeg: &[u8], orientation: u8) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08".to_vec();
+        exif.extend_from_slice(&[0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1]);
+        bytes.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        bytes.extend_from_slice(&exif);
+        bytes.extend_from_slice(&jpeg[2..]);
+        bytes
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 0]));
+        let mut bytes = Vec::new();
+        PngEncoder::new(&mut bytes)
+            .write_image(image.as_raw(), width, height, ExtendedColorType::Rgba8)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn exif_orientation_is_applied() {
+        let dir = temp_dir();
+        let source = dir.join("source.jpg");
+        // 横图按 EXIF 顺时针旋转 90 度后为竖图，左侧的红色到了上方
+        fs::write(&source, with_orientation(&jpeg(600, 300), 6)).unwrap();
+
+        let generated = generate(&source, &dir, HASH, &[ThumbnailSize::Small]).unwrap();
+        assert_eq!(generated, 1);
+
+        let thumbnail = image::open(thumbnail_path(&dir, HASH, ThumbnailSize::Small))
+            .unwrap()
+            .into_rgb8();
+        assert_eq!(thumbnail.dimensions(), (128, 256));
+        let [r, _, b] = thumbnail.get_pixel(64, 10).0;
+        assert!(r > 200 && b < 60, "top is {:?}", (r, b));
+        let [r, _, b] = thumbnail.get_pixel(64, 246).0;
+        assert!(b > 200 && r < 60, "bottom is {:?}", (r, b));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn generates_only_missing_sizes_larger_than_source() {
+        let dir = temp_dir();
+        let source = dir.join("source.png");
+        fs::write(&source, png(512, 300)).unwrap();
+
+        // 原图不超过 1024px，只生成小尺寸
+        let generated = generate(&source, &dir, HASH, &ThumbnailSize::ALL).unwrap();
+        assert_eq!(generated, 1);
+        let small = thumbnail_path(&dir, HASH, ThumbnailSize::Small);
+        assert!(small.exists());
+        assert!(!thumbnail_path(&dir, HASH, ThumbnailSize::Large).exists());
+
+        // 透明部分填充为白色
+        let pixel = image::open(&small).unwrap().into_rgb8().get_pixel(10, 10).0;
+        assert!(pixel.iter().all(|&c| c > 240), "{:?}", pixel);
+
+        // 已存在的缩略图不再解码原图
+        fs::write(&source, b"not an image").unwrap();
+        assert_eq!(
+            generate(&source, &dir, HASH, &[ThumbnailSize::Small]).unwrap(),
+            0
+        );
+        assert!(generate(&source, &dir, HASH, &[ThumbnailSize::Large]).is_err());
+
+        // 没有留下临时文件
+        let names: Vec<String> = fs::read_dir(dir.join(THUMBNAIL_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, [format!("{}-256.jpg", HASH)]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn is_needed_compares_longest_side() {
+        assert!(!is_needed(Some(256), Some(100), ThumbnailSize::Small));
+        assert!(is_needed(Some(100), Some(257), ThumbnailSize::Small));
+        assert!(!is_needed(Some(1024), Some(1024), ThumbnailSize::Large));
+        assert!(is_needed(Some(2000), Some(10), ThumbnailSize::Large));
+        assert!(is_needed(None, Some(10), ThumbnailSize::Small));
+        assert!(is_needed(None, None, ThumbnailSize::Large));
+    }
+
+    #[test]
+    fn source_hash_reads_thumbnail_names() {
+        let dir = Path::new("/media");
+        for size in ThumbnailSize::ALL {
+            assert_eq!(source_hash(&thumbnail_path(dir, HASH, size)), Some(HASH));
+        }
+        assert_eq!(
+            source_hash(Path::new("/media/thumbnails/abc-512.jpg")),
+            None
+        );
+        assert_eq!(source_hash(Path::new("/media/thumbnails/abc.jpg")), None);
+        assert_eq!(source_hash(Path::new("/media/thumbnails/.tmp")), None);
+    }
+}
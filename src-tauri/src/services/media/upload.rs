@@ -0,0 +1,434 @@
+//! 分块上传
+//!
+//! 前端按顺序发送原始字节分块，内容追加到媒体目录中的临时文件并同时计算 SHA-256，
+//! 完成时校验大小和哈希后再转为媒体文件。长时间没有新分块的上传在下次开始上传时丢弃，
+//! 残留的临时文件由媒体清理删除。
+//!
+//! 全局表只在查找和增删上传时短暂加锁；每个上传有自己的锁，文件读写在阻塞线程池中
+//! 进行，不同上传的分块可以同时写入，进度在释放锁之后推送。
+
+use super::hex_digest;
+use crate::error::{AppError, Result};
+use crate::models::{BeginMediaUpload, MediaUploadProgress};
+use crate::services::blocking;
+use ring::digest::{Context, SHA256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 超过这段时间没有新分块的上传视为已放弃
+const UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// 保留在内存中用于识别图片的文件开头字节数
+pub(super) const PROBE_HEAD_BYTES: usize = 1024 * 1024;
+
+type ProgressFn = Arc<dyn Fn(MediaUploadProgress) + Send + Sync>;
+
+/// 进行中的上传
+struct UploadSession {
+    data: BeginMediaUpload,
+    received: i64,
+    temp_path: PathBuf,
+    writer: BufWriter<fs::File>,
+    context: Context,
+    head: Vec<u8>,
+    last_active: Instant,
+    on_progress: ProgressFn,
+    /// 已完成或已取消，之后到达的分块不再写入
+    closed: bool,
+}
+
+/// 已接收完整并通过校验的上传，临时文件由调用方转为媒体文件或删除
+pub struct CompletedUpload {
+    pub data: BeginMediaUpload,
+    pub hash: String,
+    pub temp_path: PathBuf,
+    pub head: Vec<u8>,
+}
+
+type SharedSession = Arc<Mutex<UploadSession>>;
+
+#[derive(Default)]
+pub struct Uploads {
+    sessions: Mutex<HashMap<String, SharedSession>>,
+}
+
+impl Uploads {
+    /// 创建临时文件并登记上传，返回上传 id
+    pub async fn begin(
+        &self,
+        media_dir: &Path,
+        data: BeginMediaUpload,
+        on_progress: impl Fn(MediaUploadProgress) + Send + Sync + 'static,
+    ) -> Result<String> {
+        if data.size < 0 {
+            return Err(AppError::Validation(format!(
+                "Invalid upload size: {}",
+                data.size
+            )));
+        }
+        self.drop_idle().await;
+
+        let id = Uuid::new_v4().to_string();
+        let temp_path = media_dir.join(format!(".upload-{}.part", id));
+        let file = blocking({
+            let temp_path = temp_path.clone();
+            move || fs::File::create(temp_path).map_err(Into::into)
+        })
+        .await?;
+
+        let session = UploadSession {
+            data,
+            received: 0,
+            temp_path,
+            writer: BufWriter::new(file),
+            context: Context::new(&SHA256),
+            head: Vec::new(),
+            last_active: Instant::now(),
+            on_progress: Arc::new(on_progress),
+            closed: false,
+        };
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Arc::new(Mutex::new(session)));
+
+        Ok(id)
+    }
+
+    /// 追加分块，`offset` 必须等于已接收的字节数，返回已接收的字节数
+    pub async fn append(&self, id: &str, offset: i64, chunk: Vec<u8>) -> Result<i64> {
+        let session = self.get(id)?;
+
+        let written = blocking({
+            let session = session.clone();
+            let id = id.to_string();
+            move || {
+                let mut session = session.lock().unwrap();
+                write_chunk(&mut session, &id, offset, &chunk)
+                    .map(|progress| (progress, session.on_progress.clone()))
+            }
+        })
+        .await;
+
+        match written {
+            Ok((progress, on_progress)) => {
+                let received = progress.received;
+                on_progress(progress);
+                Ok(received)
+            }
+            Err(e @ AppError::Io(_)) => {
+                // 写入失败后临时文件的内容已不可信，放弃这次上传
+                self.remove(id, &session);
+                close(session).await;
+                Err(e)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 结束上传并校验大小和哈希（`expected_hash` 为空时不校验哈希），校验失败时删除临时文件
+    pub async fn finish(&self, id: &str, expected_hash: Option<&str>) -> Result<CompletedUpload> {
+        let session = self.take(id)?;
+        let expected_hash = expected_hash.map(str::to_string);
+
+        blocking(move || {
+            let mut session = session.lock().unwrap();
+            session.closed = true;
+
+            let completed = complete(&mut session, expected_hash.as_deref());
+            if completed.is_err() {
+                let _ = fs::remove_file(&session.temp_path);
+            }
+            completed
+        })
+        .await
+    }
+
+    /// 取消上传并删除临时文件，返回上传是否存在
+    pub async fn abort(&self, id: &str) -> bool {
+        match self.take(id) {
+            Ok(session) => {
+                close(session).await;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn get(&self, id: &str) -> Result<SharedSession> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| upload_not_found(id))
+    }
+
+    fn take(&self, id: &str) -> Result<SharedSession> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| upload_not_found(id))
+    }
+
+    /// 只在表中仍是同一个上传时移除
+    fn remove(&self, id: &str, session: &SharedSession) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.get(id).is_some_and(|s| Arc::ptr_eq(s, session)) {
+            sessions.remove(id);
+        }
+    }
+
+    async fn drop_idle(&self) {
+        let idle: Vec<SharedSession> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            // 正在写入的上传持有锁，显然不是闲置的
+            let ids: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| {
+                    session
+                        .try_lock()
+                        .is_ok_and(|s| s.last_active.elapsed() > UPLOAD_IDLE_TIMEOUT)
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| sessions.remove(id)).collect()
+        };
+
+        for session in idle {
+            close(session).await;
+        }
+    }
+}
+
+/// 校验偏移和大小后写入分块并更新哈希，返回要推送的进度
+fn write_chunk(
+    session: &mut UploadSession,
+    id: &str,
+    offset: i64,
+    chunk: &[u8],
+) -> Result<MediaUploadProgress> {
+    if session.closed {
+        return Err(upload_not_found(id));
+    }
+    if offset != session.received {
+        return Err(AppError::Conflict(format!(
+            "Expected chunk at offset {}, got {}",
+            session.received, offset
+        )));
+    }
+    if session.received + chunk.len() as i64 > session.data.size {
+        return Err(AppError::Validation(format!(
+            "Upload exceeds declared size of {} bytes",
+            session.data.size
+        )));
+    }
+
+    session.writer.write_all(chunk)?;
+    session.context.update(chunk);
+    if session.head.len() < PROBE_HEAD_BYTES {
+        let take = chunk.len().min(PROBE_HEAD_BYTES - session.head.len());
+        session.head.extend_from_slice(&chunk[..take]);
+    }
+    session.received += chunk.len() as i64;
+    session.last_active = Instant::now();
+
+    Ok(MediaUploadProgress {
+        upload_id: id.to_string(),
+        received: session.received,
+        total: session.data.size,
+    })
+}
+
+fn complete(session: &mut UploadSession, expected_hash: Option<&str>) -> Result<CompletedUpload> {
+    if session.received != session.data.size {
+        return Err(AppError::Validation(format!(
+            "Upload incomplete: received {} of {} bytes",
+            session.received, session.data.size
+        )));
+    }
+
+    session.writer.flush()?;
+
+    let hash = hex_digest(session.context.clone());
+    if let Some(expected) = expected_hash {
+        if !expected.eq_ignore_ascii_case(&hash) {
+            return Err(AppError::Validation(format!(
+                "Upload hash mismatch: expected {}, got {}",
+                expected, hash
+            )));
+        }
+    }
+
+    Ok(CompletedUpload {
+        data: session.data.clone(),
+        hash,
+        temp_path: session.temp_path.clone(),
+        head: std::mem::take(&mut session.head),
+    })
+}
+
+/// 标记上传已结束并删除临时文件，等待正在进行的写入完成
+async fn close(session: SharedSession) {
+    let _ = blocking(move || {
+        let mut session = session.lock().unwrap();
+        session.closed = true;
+        let _ = fs::remove_file(&session.temp_path);
+        Ok(())
+    })
+    .await;
+}
+
+fn upload_not_found(id: &str) -> AppError {
+    AppError::NotFound(format!("Upload not found: {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uploads-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn data(size: i64) -> BeginMediaUpload {
+        BeginMediaUpload {
+            workspace_id: "workspace".to_string(),
+            file_id: "file".to_string(),
+            file_name: "photo.png".to_string(),
+            mime_type: "image/png".to_string(),
+            size,
+        }
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        let mut context = Context::new(&SHA256);
+        context.update(bytes);
+        hex_digest(context)
+    }
+
+    #[tokio::test]
+    async fn chunks_are_written_in_order() {
+        let dir = temp_dir();
+        let uploads = Uploads::default();
+        let progress = Arc::new(AtomicUsize::new(0));
+        let id = uploads
+            .begin(&dir, data(6), {
+                let progress = progress.clone();
+                move |p| {
+                    assert_eq!(p.total, 6);
+                    progress.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(uploads.append(&id, 0, b"abc".to_vec()).await.unwrap(), 3);
+        assert!(matches!(
+            uploads.append(&id, 0, b"abc".to_vec()).await,
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            uploads.append(&id, 3, b"defg".to_vec()).await,
+            Err(AppError::Validation(_))
+        ));
+        assert_eq!(uploads.append(&id, 3, b"def".to_vec()).await.unwrap(), 6);
+        assert_eq!(progress.load(Ordering::SeqCst), 2);
+
+        let completed = uploads
+            .finish(&id, Some(&sha256(b"abcdef").to_uppercase()))
+            .await
+            .unwrap();
+        assert_eq!(completed.hash, sha256(b"abcdef"));
+        assert_eq!(completed.head, b"abcdef");
+        assert_eq!(fs::read(&completed.temp_path).unwrap(), b"abcdef");
+
+        // 已完成的上传不能再追加
+        assert!(matches!(
+            uploads.append(&id, 6, Vec::new()).await,
+            Err(AppError::NotFound(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_finish_removes_temp_file() {
+        let dir = temp_dir();
+        let uploads = Uploads::default();
+
+        let id = uploads.begin(&dir, data(4), |_| {}).await.unwrap();
+        uploads.append(&id, 0, b"ab".to_vec()).await.unwrap();
+        assert!(matches!(
+            uploads.finish(&id, None).await,
+            Err(AppError::Validation(_))
+        ));
+
+        let id = uploads.begin(&dir, data(2), |_| {}).await.unwrap();
+        uploads.append(&id, 0, b"ab".to_vec()).await.unwrap();
+        assert!(matches!(
+            uploads.finish(&id, Some("00")).await,
+            Err(AppError::Validation(_))
+        ));
+
+        assert!(matches!(
+            uploads.finish(&id, None).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn abort_removes_temp_file() {
+        let dir = temp_dir();
+        let uploads = Uploads::default();
+
+        let id = uploads.begin(&dir, data(4), |_| {}).await.unwrap();
+        uploads.append(&id, 0, b"ab".to_vec()).await.unwrap();
+
+        assert!(uploads.abort(&id).await);
+        assert!(!uploads.abort(&id).await);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn uploads_are_written_concurrently() {
+        let dir = temp_dir();
+        let uploads = Arc::new(Uploads::default());
+        let chunk = vec![7u8; 64 * 1024];
+        let chunks = 16;
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let uploads = uploads.clone();
+                let dir = dir.clone();
+                let chunk = chunk.clone();
+                tokio::spawn(async move {
+                    let size = (chunk.len() * chunks) as i64;
+                    let id = uploads.begin(&dir, data(size), |_| {}).await.unwrap();
+                    for i in 0..chunks {
+                        let offset = (i * chunk.len()) as i64;
+                        uploads.append(&id, offset, chunk.clone()).await.unwrap();
+                    }
+                    uploads.finish(&id, None).await.unwrap()
+                })
+            })
+            .collect();
+
+        let expected = sha256(&chunk.repeat(chunks));
+        for task in tasks {
+            assert_eq!(task.await.unwrap().hash, expected);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
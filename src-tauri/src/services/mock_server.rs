@@ -0,0 +1,151 @@
+//! 测试用的 OpenAI 兼容接口模拟服务器
+//!
+//! 每个连接只处理一个请求，响应后关闭连接，因此重试时客户端会重新连接。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 流式响应中相邻片段之间的间隔，使每个片段由客户端单独读到
+const PART_INTERVAL_MS: u64 = 20;
+
+/// 一次响应：响应头之后逐个写出正文片段
+#[derive(Clone)]
+pub struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    parts: Vec<Vec<u8>>,
+    /// 写完正文后保持连接的时间
+    hold: Duration,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Content-Length".to_string(), body.len().to_string()),
+            ],
+            parts: vec![body.as_bytes().to_vec()],
+            hold: Duration::ZERO,
+        }
+    }
+
+    /// SSE 响应，没有 Content-Length，正文到连接关闭为止
+    pub fn sse(parts: &[&[u8]]) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+            parts: parts.iter().map(|part| part.to_vec()).collect(),
+            hold: Duration::ZERO,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn hold(mut self, duration: Duration) -> Self {
+        self.hold = duration;
+        self
+    }
+}
+
+pub struct MockServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// 启动服务器，`handler` 根据请求序号和请求体生成响应
+    pub async fn start(handler: impl Fn(usize, &str) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let handler = Arc::new(handler);
+        let count = Arc::new(AtomicUsize::new(0));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let count = count.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, |body| {
+                        received.lock().unwrap().push(body.to_string());
+                        handler(count.fetch_add(1, Ordering::SeqCst), body)
+                    })
+                    .await;
+                });
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    /// 按顺序返回 `replies`，请求数超出时重复最后一个
+    pub async fn sequence(replies: Vec<Reply>) -> Self {
+        Self::start(move |index, _| replies[index.min(replies.len() - 1)].clone()).await
+    }
+
+    /// 已收到的请求体
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn serve(mut stream: TcpStream, respond: impl FnOnce(&str) -> Reply) -> std::io::Result<()> {
+    let body = read_request(&mut stream).await?;
+    let reply = respond(&body);
+
+    let mut head = format!("HTTP/1.1 {} Mock\r\nConnection: close\r\n", reply.status);
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.flush().await?;
+
+    for part in &reply.parts {
+        tokio::time::sleep(Duration::from_millis(PART_INTERVAL_MS)).await;
+        stream.write_all(part).await?;
+        stream.flush().await?;
+    }
+
+    tokio::time::sleep(reply.hold).await;
+    stream.shutdown().await
+}
+
+/// 读取请求头和 Content-Length 指定长度的请求体
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&buffer[..end]).to_lowercase();
+        let length: usize = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0);
+        if buffer.len() >= end + 4 + length {
+            return Ok(String::from_utf8_lossy(&buffer[end + 4..end + 4 + length]).to_string());
+        }
+    }
+
+    Ok(String::new())
+}
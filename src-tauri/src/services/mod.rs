@@ -1,11 +1,42 @@
+pub mod ai;
+pub mod archive;
+pub mod embedding;
 pub mod file;
+pub mod folder;
 pub mod media;
+#[cfg(test)]
+pub(crate) mod mock_server;
+pub mod provider;
+pub mod revision;
 pub mod search;
+pub mod settings;
 pub mod tag;
+pub mod trash;
+pub mod vault;
 pub mod workspace;
 
+pub use ai::AiService;
+pub use archive::ArchiveService;
+pub use embedding::EmbeddingService;
 pub use file::FileService;
+pub use folder::FolderService;
 pub use media::MediaService;
+pub use provider::ProviderService;
+pub use revision::RevisionService;
 pub use search::SearchService;
+pub use settings::SettingsService;
 pub use tag::TagService;
+pub use trash::TrashService;
+pub use vault::VaultService;
 pub use workspace::WorkspaceService;
+
+use crate::error::{AppError, Result};
+
+/// 在阻塞线程池中执行文件操作，避免占用异步运行时的工作线程
+pub(crate) async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
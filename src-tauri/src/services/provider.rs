@@ -0,0 +1,548 @@
+use crate::error::{AppError, Result};
+use crate::models::{AiProvider, CreateAiProvider, ProviderConnection, UpdateAiProvider};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::{Pool, Sqlite};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 查询提供商时返回的列（不包含密文）
+const PROVIDER_COLUMNS: &str = "id, name, base_url, model, enabled, is_default, \
+     api_key_encrypted IS NOT NULL AS has_api_key, created_at, updated_at";
+
+/// 密文格式版本前缀
+const CIPHER_PREFIX: &str = "v1:";
+
+const TEST_TIMEOUT_SECS: u64 = 15;
+
+/// 调用提供商接口所需的连接信息（包含解密后的 API Key，只在后端使用）
+#[derive(Debug, Clone)]
+pub struct ProviderEndpoint {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+/// AI 提供商管理
+///
+/// API Key 使用 AES-256-GCM 加密后保存，密钥是应用数据目录中首次使用时随机生成的 `provider.key`；
+/// 以提供商 id 作为附加数据，密文不能挪用到其他提供商。
+pub struct ProviderService {
+    pool: Pool<Sqlite>,
+    key_path: PathBuf,
+}
+
+impl ProviderService {
+    pub fn new(pool: Pool<Sqlite>, key_path: PathBuf) -> Self {
+        Self { pool, key_path }
+    }
+
+    pub async fn create(&self, data: CreateAiProvider) -> Result<AiProvider> {
+        validate_base_url(&data.base_url)?;
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let api_key = match data.api_key.as_deref().map(str::trim) {
+            Some(key) if !key.is_empty() => Some(self.encrypt(&id, key)?),
+            _ => None,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_providers (
+                id, name, base_url, model, enabled, is_default, api_key_encrypted,
+                created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, 0, ?, ?, ?)
+            "#,
+        )
+        .bind(&id)
+        .bind(&data.name)
+        .bind(data.base_url.trim_end_matches('/'))
+        .bind(&data.model)
+        .bind(data.enabled.unwrap_or(true))
+        .bind(api_key)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        self.require(&id).await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<AiProvider>> {
+        let provider = sqlx::query_as::<_, AiProvider>(&format!(
+            "SELECT {} FROM ai_providers WHERE id = ?",
+            PROVIDER_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(provider)
+    }
+
+    pub async fn list(&self) -> Result<Vec<AiProvider>> {
+        let providers = sqlx::query_as::<_, AiProvider>(&format!(
+            "SELECT {} FROM ai_providers ORDER BY created_at, name",
+            PROVIDER_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(providers)
+    }
+
+    /// 更新提供商
+    ///
+    /// 修改 `base_url` 而没有同时提供 `api_key` 时清除已保存的 Key，避免把它发送到新的地址。
+    pub async fn update(&self, id: &str, data: UpdateAiProvider) -> Result<AiProvider> {
+        let current = self.require(id).await?;
+
+        let now = Utc::now().to_rfc3339();
+
+        // enabled 按布尔值绑定，未提供时保持不变
+        let mut query =
+            String::from("UPDATE ai_providers SET updated_at = ?, enabled = COALESCE(?, enabled)");
+        let mut params: Vec<Option<String>> = Vec::new();
+
+        if let Some(name) = data.name {
+            query.push_str(", name = ?");
+            params.push(Some(name));
+        }
+
+        if let Some(base_url) = data.base_url {
+            validate_base_url(&base_url)?;
+            let base_url = base_url.trim_end_matches('/').to_string();
+            if base_url != current.base_url && data.api_key.is_none() {
+                query.push_str(", api_key_encrypted = NULL");
+            }
+            query.push_str(", base_url = ?");
+            params.push(Some(base_url));
+        }
+
+        if let Some(model) = data.model {
+            query.push_str(", model = ?");
+            params.push(Some(model));
+        }
+
+        if let Some(api_key) = data.api_key {
+            query.push_str(", api_key_encrypted = ?");
+            let api_key = api_key.trim();
+            params.push(if api_key.is_empty() {
+                None
+            } else {
+                Some(self.encrypt(id, api_key)?)
+            });
+        }
+
+        query.push_str(" WHERE id = ?");
+        params.push(Some(id.to_string()));
+
+        let mut q = sqlx::query(&query).bind(now).bind(data.enabled);
+        for param in params {
+            q = q.bind(param);
+        }
+        q.execute(&self.pool).await?;
+
+        self.require(id).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM ai_providers WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 设置默认提供商，`id` 为空时取消默认
+    pub async fn set_default(&self, id: Option<&str>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE ai_providers SET is_default = 0 WHERE is_default = 1")
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(id) = id {
+            let updated = sqlx::query("UPDATE ai_providers SET is_default = 1 WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Err(AppError::NotFound(format!("Provider not found: {}", id)));
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 取得调用接口所需的连接信息
+    ///
+    /// `id` 为空时使用默认提供商；没有已启用的默认提供商时使用第一个已启用且有 API Key 的提供商。
+    pub async fn resolve(&self, id: Option<&str>) -> Result<ProviderEndpoint> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                let row: Option<(String,)> = sqlx::query_as(
+                    r#"
+                    SELECT id FROM ai_providers
+                    WHERE enabled = 1 AND (is_default = 1 OR api_key_encrypted IS NOT NULL)
+                    ORDER BY is_default DESC, created_at
+                    LIMIT 1
+                    "#,
+                )
+                .fetch_optional(&self.pool)
+                .await?;
+
+                row.ok_or_else(|| AppError::Validation("No AI provider is enabled".to_string()))?
+                    .0
+            }
+        };
+
+        let (enabled, endpoint) = self.endpoint(&id).await?;
+        if !enabled {
+            return Err(AppError::Validation(format!(
+                "Provider is disabled: {}",
+                id
+            )));
+        }
+
+        Ok(endpoint)
+    }
+
+    /// 测试连接：请求提供商的 `/models` 接口
+    pub async fn test_connection(&self, id: &str) -> Result<ProviderConnection> {
+        let (_, endpoint) = self.endpoint(id).await?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(TEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let mut request = client.get(format!("{}/models", endpoint.base_url));
+        if let Some(api_key) = &endpoint.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let started = Instant::now();
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::Ai(format!("Connection failed: {}", e)))?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Ai(format!(
+                "Connection failed ({}): {}",
+                status,
+                body.trim()
+            )));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::Ai(format!("Invalid /models response: {}", e)))?;
+        let models = body["data"]
+            .as_array()
+            .map(|data| {
+                data.iter()
+                    .filter_map(|m| m["id"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(ProviderConnection { models, latency_ms })
+    }
+
+    async fn require(&self, id: &str) -> Result<AiProvider> {
+        self.get(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Provider not found: {}", id)))
+    }
+
+    /// 读取连接信息并解密 API Key，同时返回是否启用
+    async fn endpoint(&self, id: &str) -> Result<(bool, ProviderEndpoint)> {
+        let row: Option<(String, String, bool, Option<String>)> = sqlx::query_as(
+            "SELECT base_url, model, enabled, api_key_encrypted FROM ai_providers WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((base_url, model, enabled, api_key)) = row else {
+            return Err(AppError::NotFound(format!("Provider not found: {}", id)));
+        };
+
+        let api_key = match api_key {
+            Some(cipher) => Some(self.decrypt(id, &cipher)?),
+            None => None,
+        };
+
+        Ok((
+            enabled,
+            ProviderEndpoint {
+                base_url,
+                api_key,
+                model,
+            },
+        ))
+    }
+
+    fn encrypt(&self, id: &str, plain: &str) -> Result<String> {
+        let key = self.load_key()?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::Internal("Failed to generate nonce".to_string()))?;
+
+        let mut data = plain.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(id.as_bytes()),
+            &mut data,
+        )
+        .map_err(|_| AppError::Internal("Failed to encrypt API key".to_string()))?;
+
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&data);
+
+        Ok(format!("{}{}", CIPHER_PREFIX, STANDARD.encode(out)))
+    }
+
+    fn decrypt(&self, id: &str, cipher: &str) -> Result<String> {
+        let invalid =
+            || AppError::Internal("Failed to decrypt API key, please enter it again".to_string());
+
+        let encoded = cipher.strip_prefix(CIPHER_PREFIX).ok_or_else(invalid)?;
+        let mut data = STANDARD.decode(encoded).map_err(|_| invalid())?;
+        if data.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+
+        let key = self.load_key()?;
+        let (nonce, sealed) = data.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| invalid())?;
+        let plain = key
+            .open_in_place(nonce, Aad::from(id.as_bytes()), sealed)
+            .map_err(|_| invalid())?;
+
+        String::from_utf8(plain.to_vec()).map_err(|_| invalid())
+    }
+
+    /// 读取密钥文件，不存在时随机生成（仅当前用户可读）
+    fn load_key(&self) -> Result<LessSafeKey> {
+        let bytes = match std::fs::read(&self.key_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => self.create_key()?,
+            Err(e) => return Err(e.into()),
+        };
+
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| AppError::Internal("Invalid provider key file".to_string()))?;
+
+        Ok(LessSafeKey::new(key))
+    }
+
+    fn create_key(&self) -> Result<Vec<u8>> {
+        let mut key = vec![0u8; AES_256_GCM.key_len()];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| AppError::Internal("Failed to generate provider key".to_string()))?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        match options.open(&self.key_path) {
+            Ok(mut file) => {
+                file.write_all(&key)?;
+                file.sync_all()?;
+                Ok(key)
+            }
+            // 并发创建时使用先写入的密钥
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                Ok(std::fs::read(&self.key_path)?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn validate_base_url(base_url: &str) -> Result<()> {
+    if base_url.starts_with("http://") || base_url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Invalid provider base URL: {}",
+            base_url
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+
+    async fn service() -> ProviderService {
+        let key_path = std::env::temp_dir().join(format!("provider-{}.key", Uuid::new_v4()));
+        ProviderService::new(memory_pool().await, key_path)
+    }
+
+    fn update(base_url: Option<&str>, api_key: Option<&str>) -> UpdateAiProvider {
+        UpdateAiProvider {
+            name: None,
+            base_url: base_url.map(str::to_string),
+            model: None,
+            enabled: None,
+            api_key: api_key.map(str::to_string),
+        }
+    }
+
+    async fn create(service: &ProviderService) -> AiProvider {
+        service
+            .create(CreateAiProvider {
+                name: "Test".to_string(),
+                base_url: "https://api.example.com/v1".to_string(),
+                model: "model".to_string(),
+                enabled: Some(true),
+                api_key: Some("sk-secret".to_string()),
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn changing_base_url_clears_api_key() {
+        let service = service().await;
+        let provider = create(&service).await;
+        assert!(provider.has_api_key);
+
+        let updated = service
+            .update(
+                &provider.id,
+                update(Some("https://evil.example.com/v1"), None),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(updated.base_url, "https://evil.example.com/v1");
+        assert!(!updated.has_api_key);
+        assert_eq!(
+            service.endpoint(&provider.id).await.unwrap().1.api_key,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unchanged_base_url_keeps_api_key() {
+        let service = service().await;
+        let provider = create(&service).await;
+
+        let updated = service
+            .update(
+                &provider.id,
+                update(Some("https://api.example.com/v1/"), None),
+            )
+            .await
+            .unwrap();
+
+        assert!(updated.has_api_key);
+        let endpoint = service.endpoint(&provider.id).await.unwrap().1;
+        assert_eq!(endpoint.api_key.as_deref(), Some("sk-secret"));
+    }
+
+    #[tokio::test]
+    async fn changing_base_url_with_new_api_key() {
+        let service = service().await;
+        let provider = create(&service).await;
+
+        service
+            .update(
+                &provider.id,
+                update(Some("https://other.example.com"), Some("sk-other")),
+            )
+            .await
+            .unwrap();
+
+        let endpoint = service.endpoint(&provider.id).await.unwrap().1;
+        assert_eq!(endpoint.base_url, "https://other.example.com");
+        assert_eq!(endpoint.api_key.as_deref(), Some("sk-other"));
+    }
+
+    #[tokio::test]
+    async fn enabled_is_stored_as_integer() {
+        let service = service().await;
+        let provider = create(&service).await;
+
+        for enabled in [false, true] {
+            let updated = service
+                .update(
+                    &provider.id,
+                    UpdateAiProvider {
+                        enabled: Some(enabled),
+                        ..update(None, None)
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(updated.enabled, enabled);
+
+            let (stored,): (String,) = sqlx::query_as(
+                "SELECT typeof(enabled) || ':' || enabled FROM ai_providers WHERE id = ?",
+            )
+            .bind(&provider.id)
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+            assert_eq!(stored, format!("integer:{}", enabled as i32));
+        }
+
+        // 未提供时保持不变
+        let updated = service
+            .update(&provider.id, update(None, None))
+            .await
+            .unwrap();
+        assert!(updated.enabled);
+    }
+
+    #[tokio::test]
+    async fn seeded_providers_use_rfc3339_timestamps() {
+        let service = service().await;
+        let seeded: Vec<(String, String)> = sqlx::query_as(
+            "SELECT created_at, updated_at FROM ai_providers WHERE id LIKE '%-default'",
+        )
+        .fetch_all(&service.pool)
+        .await
+        .unwrap();
+
+        assert_eq!(seeded.len(), 2);
+        for (created_at, updated_at) in seeded {
+            assert!(
+                chrono::DateTime::parse_from_rfc3339(&created_at).is_ok(),
+                "{}",
+                created_at
+            );
+            assert!(
+                chrono::DateTime::parse_from_rfc3339(&updated_at).is_ok(),
+                "{}",
+                updated_at
+            );
+        }
+    }
+}
@@ -0,0 +1,519 @@
+use crate::error::{AppError, Result};
+use crate::models::{
+    DiffGranularity, DiffOp, DiffSegment, File, FileRevision, FileRevisionSummary, RevisionDiff,
+};
+use crate::text::is_cjk;
+use chrono::{DateTime, Utc};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use sqlx::{Pool, Sqlite};
+use uuid::Uuid;
+
+/// 自动保存时两次快照之间的最短间隔（秒）
+const AUTOSAVE_INTERVAL_SECS: i64 = 300;
+
+/// 每个文档最多保留的历史版本数
+const MAX_REVISIONS_PER_FILE: i64 = 100;
+
+pub struct RevisionService {
+    pool: Pool<Sqlite>,
+}
+
+impl RevisionService {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    /// 为文档当前内容写入快照
+    ///
+    /// `force` 为 false 时（自动保存）按时间窗口节流，窗口内的多次保存只保留第一次之前的内容；
+    /// 与最近一次快照内容相同时始终跳过。
+    pub async fn snapshot(
+        &self,
+        file: &File,
+        reason: &str,
+        force: bool,
+    ) -> Result<Option<FileRevision>> {
+        if !force
+            && file
+                .content_plain
+                .as_deref()
+                .is_none_or(|s| s.trim().is_empty())
+        {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+
+        if let Some(latest) = self.latest(&file.id).await? {
+            if latest.content == file.content {
+                return Ok(None);
+            }
+            if !force && !interval_elapsed(&latest.created_at, now) {
+                return Ok(None);
+            }
+        }
+
+        let revision = sqlx::query_as::<_, FileRevision>(
+            r#"
+            INSERT INTO file_revisions (
+                id, file_id, title, content, content_plain, reason, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&file.id)
+        .bind(&file.title)
+        .bind(&file.content)
+        .bind(&file.content_plain)
+        .bind(reason)
+        .bind(now.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.prune(&file.id).await?;
+
+        Ok(Some(revision))
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<FileRevision>> {
+        let revision =
+            sqlx::query_as::<_, FileRevision>("SELECT * FROM file_revisions WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(revision)
+    }
+
+    pub async fn list(&self, file_id: &str) -> Result<Vec<FileRevisionSummary>> {
+        let revisions = sqlx::query_as::<_, FileRevisionSummary>(
+            r#"
+            SELECT id, file_id, title, reason,
+                   LENGTH(COALESCE(content_plain, '')) AS char_count,
+                   created_at
+            FROM file_revisions
+            WHERE file_id = ?
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(file_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    /// 对比两个版本的纯文本；`to_id` 为空时与文档当前内容对比
+    pub async fn diff(
+        &self,
+        from_id: &str,
+        to_id: Option<&str>,
+        granularity: DiffGranularity,
+    ) -> Result<RevisionDiff> {
+        let from = self
+            .get(from_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Revision not found: {}", from_id)))?;
+
+        let new_text = match to_id {
+            Some(to_id) => {
+                let to = self
+                    .get(to_id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("Revision not found: {}", to_id)))?;
+                if to.file_id != from.file_id {
+                    return Err(AppError::Validation(
+                        "Revisions belong to different files".to_string(),
+                    ));
+                }
+                to.content_plain
+            }
+            None => {
+                let current: (Option<String>,) =
+                    sqlx::query_as("SELECT content_plain FROM files WHERE id = ?")
+                        .bind(&from.file_id)
+                        .fetch_one(&self.pool)
+                        .await?;
+                current.0
+            }
+        };
+
+        let old_text = from.content_plain.unwrap_or_default();
+        let new_text = new_text.unwrap_or_default();
+
+        let segments = diff_text(&old_text, &new_text, granularity);
+        let count = |op: DiffOp| {
+            segments
+                .iter()
+                .filter(|s| s.op == op)
+                .map(|s| count_units(&s.text, granularity))
+                .sum()
+        };
+
+        Ok(RevisionDiff {
+            from_id: from.id,
+            to_id: to_id.map(str::to_string),
+            granularity,
+            insertions: count(DiffOp::Insert),
+            deletions: count(DiffOp::Delete),
+            segments,
+        })
+    }
+
+    async fn latest(&self, file_id: &str) -> Result<Option<FileRevision>> {
+        let revision = sqlx::query_as::<_, FileRevision>(
+            "SELECT * FROM file_revisions WHERE file_id = ? ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(file_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(revision)
+    }
+
+    /// 只保留最近的若干个版本
+    async fn prune(&self, file_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM file_revisions
+            WHERE file_id = ? AND id NOT IN (
+                SELECT id FROM file_revisions
+                WHERE file_id = ?
+                ORDER BY created_at DESC
+                LIMIT ?
+            )
+            "#,
+        )
+        .bind(file_id)
+        .bind(file_id)
+        .bind(MAX_REVISIONS_PER_FILE)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn interval_elapsed(created_at: &str, now: DateTime<Utc>) -> bool {
+    match DateTime::parse_from_rfc3339(created_at) {
+        Ok(t) => (now - t.with_timezone(&Utc)).num_seconds() >= AUTOSAVE_INTERVAL_SECS,
+        Err(_) => true,
+    }
+}
+
+/// 计算结构化差异
+///
+/// 段落模式下每个段落是一个片段；词模式下相邻的同类片段会合并。
+pub fn diff_text(old: &str, new: &str, granularity: DiffGranularity) -> Vec<DiffSegment> {
+    let (old_units, new_units) = match granularity {
+        DiffGranularity::Paragraph => (split_paragraphs(old), split_paragraphs(new)),
+        DiffGranularity::Word => (split_words(old), split_words(new)),
+    };
+
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    let mut push = |op: DiffOp, units: &[&str]| {
+        for unit in units {
+            match segments.last_mut() {
+                Some(last) if granularity == DiffGranularity::Word && last.op == op => {
+                    last.text.push_str(unit);
+                }
+                _ => segments.push(DiffSegment {
+                    op,
+                    text: unit.to_string(),
+                }),
+            }
+        }
+    };
+
+    for op in capture_diff_slices(Algorithm::Myers, &old_units, &new_units) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => push(DiffOp::Equal, &old_units[old_range]),
+            DiffTag::Delete => push(DiffOp::Delete, &old_units[old_range]),
+            DiffTag::Insert => push(DiffOp::Insert, &new_units[new_range]),
+            DiffTag::Replace => {
+                push(DiffOp::Delete, &old_units[old_range]);
+                push(DiffOp::Insert, &new_units[new_range]);
+            }
+        }
+    }
+
+    segments
+}
+
+fn split_paragraphs(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// 按词切分：连续的字母数字为一个词，中日韩字符逐字切分，空白和标点单独成词
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut start_is_space = false;
+
+    for (i, c) in text.char_indices() {
+        let joinable = c.is_alphanumeric() && !is_cjk(c);
+        let is_space = c.is_whitespace();
+
+        if let Some(s) = start {
+            let continues = if start_is_space { is_space } else { joinable };
+            if continues {
+                continue;
+            }
+            words.push(&text[s..i]);
+            start = None;
+        }
+
+        if joinable || is_space {
+            start = Some(i);
+            start_is_space = is_space;
+        } else {
+            words.push(&text[i..i + c.len_utf8()]);
+        }
+    }
+
+    if let Some(s) = start {
+        words.push(&text[s..]);
+    }
+
+    words
+}
+
+fn count_units(text: &str, granularity: DiffGranularity) -> usize {
+    match granularity {
+        DiffGranularity::Paragraph => 1,
+        DiffGranularity::Word => split_words(text)
+            .iter()
+            .filter(|w| !w.trim().is_empty())
+            .count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::workspace::WorkspaceService;
+
+    async fn document(pool: &Pool<Sqlite>) -> File {
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Revisions".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+
+        FileService::new(pool.clone())
+            .create(CreateFile {
+                workspace_id: workspace.id,
+                file_type: "document".to_string(),
+                title: "Doc".to_string(),
+                content: Some("<p>v0</p>".to_string()),
+                file_path: None,
+                file_size: None,
+                mime_type: None,
+                folder_id: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    /// 只改动内容的文档副本，快照只读取这些字段
+    fn edited(file: &File, text: &str) -> File {
+        File {
+            content: Some(format!("<p>{}</p>", text)),
+            content_plain: Some(text.to_string()),
+            ..file.clone()
+        }
+    }
+
+    async fn save(
+        service: &RevisionService,
+        file: &File,
+        text: &str,
+        force: bool,
+    ) -> Option<FileRevision> {
+        let reason = if force { "manual" } else { "autosave" };
+        service
+            .snapshot(&edited(file, text), reason, force)
+            .await
+            .unwrap()
+    }
+
+    /// 把所有快照的时间提前，模拟自动保存窗口已经过去
+    async fn age_revisions(pool: &Pool<Sqlite>, secs: i64) {
+        let created_at = (Utc::now() - chrono::Duration::seconds(secs)).to_rfc3339();
+        sqlx::query("UPDATE file_revisions SET created_at = ?")
+            .bind(created_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn autosave_is_throttled_within_interval() {
+        let pool = memory_pool().await;
+        let service = RevisionService::new(pool.clone());
+        let file = document(&pool).await;
+
+        assert!(save(&service, &file, "v1", false).await.is_some());
+        // 窗口内的自动保存不写入
+        assert!(save(&service, &file, "v2", false).await.is_none());
+
+        age_revisions(&pool, AUTOSAVE_INTERVAL_SECS - 10).await;
+        assert!(save(&service, &file, "v3", false).await.is_none());
+
+        age_revisions(&pool, AUTOSAVE_INTERVAL_SECS).await;
+        assert!(save(&service, &file, "v4", false).await.is_some());
+
+        assert_eq!(service.list(&file.id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn autosave_skips_empty_documents() {
+        let pool = memory_pool().await;
+        let service = RevisionService::new(pool.clone());
+        let file = document(&pool).await;
+
+        assert!(save(&service, &file, "  ", false).await.is_none());
+        assert!(save(&service, &file, "  ", true).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn force_bypasses_throttle_but_not_identical_content() {
+        let pool = memory_pool().await;
+        let service = RevisionService::new(pool.clone());
+        let file = document(&pool).await;
+
+        assert!(save(&service, &file, "v1", false).await.is_some());
+        let manual = service
+            .snapshot(&edited(&file, "v2"), "manual", true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(manual.reason, "manual");
+        assert_eq!(manual.content_plain.as_deref(), Some("v2"));
+
+        // 与最近一次快照相同时即使强制也跳过
+        assert!(save(&service, &file, "v2", true).await.is_none());
+
+        assert_eq!(service.list(&file.id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_latest_revisions() {
+        let pool = memory_pool().await;
+        let service = RevisionService::new(pool.clone());
+        let file = document(&pool).await;
+
+        for i in 0..MAX_REVISIONS_PER_FILE + 5 {
+            assert!(save(&service, &file, &format!("v{}", i), true)
+                .await
+                .is_some());
+        }
+
+        let revisions = service.list(&file.id).await.unwrap();
+        assert_eq!(revisions.len() as i64, MAX_REVISIONS_PER_FILE);
+
+        let kept: Vec<String> = sqlx::query_as::<_, (String,)>(
+            "SELECT content_plain FROM file_revisions WHERE file_id = ? ORDER BY created_at",
+        )
+        .bind(&file.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(text,)| text)
+        .collect();
+        assert_eq!(kept.first().map(String::as_str), Some("v5"));
+        assert_eq!(
+            kept.last().cloned(),
+            Some(format!("v{}", MAX_REVISIONS_PER_FILE + 4))
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_against_current_content() {
+        let pool = memory_pool().await;
+        let service = RevisionService::new(pool.clone());
+        let file = document(&pool).await;
+
+        let revision = service
+            .snapshot(&edited(&file, "old text"), "manual", true)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let diff = service
+            .diff(&revision.id, None, DiffGranularity::Word)
+            .await
+            .unwrap();
+        assert_eq!(diff.to_id, None);
+        assert_eq!((diff.insertions, diff.deletions), (1, 2));
+    }
+
+    fn ops(segments: &[DiffSegment]) -> Vec<(DiffOp, &str)> {
+        segments.iter().map(|s| (s.op, s.text.as_str())).collect()
+    }
+
+    #[test]
+    fn paragraph_diff_compares_whole_lines() {
+        let segments = diff_text(
+            "first\n\nsecond\nthird",
+            "first\n  second changed  \nthird\nfourth",
+            DiffGranularity::Paragraph,
+        );
+        assert_eq!(
+            ops(&segments),
+            [
+                (DiffOp::Equal, "first"),
+                (DiffOp::Delete, "second"),
+                (DiffOp::Insert, "second changed"),
+                (DiffOp::Equal, "third"),
+                (DiffOp::Insert, "fourth"),
+            ]
+        );
+    }
+
+    #[test]
+    fn word_diff_merges_adjacent_segments() {
+        let segments = diff_text("the quick fox", "the slow brown fox", DiffGranularity::Word);
+        assert_eq!(
+            ops(&segments),
+            [
+                (DiffOp::Equal, "the "),
+                (DiffOp::Delete, "quick"),
+                (DiffOp::Insert, "slow brown"),
+                (DiffOp::Equal, " fox"),
+            ]
+        );
+        assert_eq!(count_units("slow brown", DiffGranularity::Word), 2);
+    }
+
+    #[test]
+    fn word_diff_splits_cjk_per_character() {
+        assert_eq!(
+            split_words("中文abc 1,2"),
+            ["中", "文", "abc", " ", "1", ",", "2"]
+        );
+
+        let segments = diff_text("我喜欢苹果", "我喜欢香蕉", DiffGranularity::Word);
+        assert_eq!(
+            ops(&segments),
+            [
+                (DiffOp::Equal, "我喜欢"),
+                (DiffOp::Delete, "苹果"),
+                (DiffOp::Insert, "香蕉"),
+            ]
+        );
+        assert!(diff_text("", "", DiffGranularity::Word).is_empty());
+    }
+}
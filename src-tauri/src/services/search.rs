@@ -1,6 +1,26 @@
-use crate::models::{SearchQuery, SearchResult};
-use anyhow::Result;
-use sqlx::{Pool, Row, Sqlite};
+use super::folder::{folder_filter_params, folder_filter_sql, normalize_folder_path};
+use crate::error::{AppError, Result};
+use crate::models::{MatchRange, SearchQuery, SearchResult, SearchWeights};
+use crate::text::{segment, SEGMENT_SEPARATOR};
+use sqlx::{sqlite::SqliteRow, Pool, Row, Sqlite, SqliteConnection};
+
+mod query;
+
+/// 索引格式版本，分词规则变化时递增以触发重建
+const SEARCH_INDEX_VERSION: &str = "2";
+
+/// 默认摘录长度（词数）
+const DEFAULT_SNIPPET_TOKENS: i64 = 24;
+
+/// 更新时间加成的半衰期（天）：更新于该天数之前的文档获得一半的加成
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// 非全文搜索结果的摘录长度（字符数）
+const EXCERPT_CHARS: i64 = 120;
+
+/// highlight()/snippet() 使用的匹配起止标记，解析后从文本中移除
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
 
 pub struct SearchService {
     pool: Pool<Sqlite>,
@@ -11,32 +31,132 @@ impl SearchService {
         Self { pool }
     }
 
-    /// 全文搜索（使用 FTS5）
-    pub async fn search(&self, query: SearchQuery) -> Result<Vec<SearchResult>> {
-        let limit = query.limit.unwrap_or(50);
+    /// 索引版本与当前分词规则不一致时重建全文索引
+    pub async fn ensure_index(&self) -> Result<()> {
+        let version: Option<(String,)> =
+            sqlx::query_as("SELECT value FROM app_meta WHERE key = 'search_index_version'")
+                .fetch_optional(&self.pool)
+                .await?;
 
-        // 构建 FTS 查询
-        let fts_query = self.build_fts_query(&query.query);
+        if version.as_ref().map(|v| v.0.as_str()) == Some(SEARCH_INDEX_VERSION) {
+            return Ok(());
+        }
 
-        let mut sql = String::from(
-            r#"
-            SELECT 
-                f.id,
-                f.workspace_id,
-                f.file_type,
-                f.title,
-                f.content,
-                f.file_path,
-                f.created_at,
-                f.updated_at,
-                fts.rank as rank
-            FROM files f
-            INNER JOIN files_fts fts ON f.id = fts.file_id
-            WHERE files_fts MATCH ?
-            "#,
-        );
+        self.rebuild_index().await
+    }
 
-        let mut params: Vec<String> = vec![fts_query];
+    /// 按当前分词规则重建所有文件和分块的全文索引
+    pub async fn rebuild_index(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM files_fts")
+            .execute(&mut *tx)
+            .await?;
+
+        let ids: Vec<(String,)> = sqlx::query_as("SELECT id FROM files")
+            .fetch_all(&mut *tx)
+            .await?;
+        for (id,) in ids {
+            index_file(&mut tx, &id).await?;
+        }
+
+        sqlx::query("DELETE FROM file_chunks_fts")
+            .execute(&mut *tx)
+            .await?;
+
+        let chunks: Vec<(String, String)> = sqlx::query_as("SELECT id, content FROM file_chunks")
+            .fetch_all(&mut *tx)
+            .await?;
+        for (id, content) in chunks {
+            sqlx::query("INSERT INTO file_chunks_fts (chunk_id, content) VALUES (?, ?)")
+                .bind(id)
+                .bind(segment(&content))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO app_meta (key, value) VALUES ('search_index_version', ?)",
+        )
+        .bind(SEARCH_INDEX_VERSION)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 全文搜索（使用 FTS5）
+    ///
+    /// `query.query` 支持短语、排除、布尔运算和字段过滤，语法见 [`query`] 模块。
+    pub async fn search(&self, query: SearchQuery) -> Result<Vec<SearchResult>> {
+        let limit = query.limit.unwrap_or(50);
+        let snippet_tokens = query
+            .snippet_tokens
+            .unwrap_or(DEFAULT_SNIPPET_TOKENS)
+            .clamp(1, 64);
+
+        // 解析查询语法
+        let parsed = query::parse(&query.query)?;
+        let score = score_sql(
+            query.weights.unwrap_or_default(),
+            query.recency_boost.unwrap_or(0.0),
+        )?;
+
+        let ranked = parsed.fts.is_some();
+        let mut params: Vec<String> = Vec::new();
+
+        let mut sql = if let Some(fts) = parsed.fts {
+            params.push(fts);
+
+            // 标题整体高亮，正文只取包含匹配的片段
+            format!(
+                r#"
+                SELECT
+                    f.id,
+                    f.workspace_id,
+                    f.file_type,
+                    highlight(files_fts, 1, char(2), char(3)) as title_marked,
+                    snippet(files_fts, 2, char(2), char(3), '…', {}) as snippet_marked,
+                    f.file_path,
+                    f.created_at,
+                    f.updated_at,
+                    {} as rank
+                FROM files f
+                INNER JOIN files_fts fts ON f.id = fts.file_id
+                WHERE files_fts MATCH ? AND f.deleted_at IS NULL
+                "#,
+                snippet_tokens, score
+            )
+        } else {
+            // 只有过滤条件或排除项时不使用全文匹配
+            format!(
+                r#"
+                SELECT
+                    f.id,
+                    f.workspace_id,
+                    f.file_type,
+                    f.title as title_marked,
+                    {} as snippet_marked,
+                    f.file_path,
+                    f.created_at,
+                    f.updated_at,
+                    0.0 as rank
+                FROM files f
+                WHERE f.deleted_at IS NULL
+                "#,
+                excerpt_sql()
+            )
+        };
+
+        // 排除项
+        if let Some(exclude_fts) = parsed.exclude_fts {
+            sql.push_str(
+                " AND f.id NOT IN (SELECT file_id FROM files_fts WHERE files_fts MATCH ?)",
+            );
+            params.push(exclude_fts);
+        }
 
         // 添加工作空间过滤
         if let Some(workspace_id) = &query.workspace_id {
@@ -50,36 +170,95 @@ impl SearchService {
             params.push(file_type.clone());
         }
 
-        // 添加标签过滤
-        if let Some(tags) = &query.tags {
-            if !tags.is_empty() {
-                sql.push_str(
-                    r#"
-                    AND f.id IN (
-                        SELECT ft.file_id FROM file_tags ft
-                        INNER JOIN tags t ON ft.tag_id = t.id
-                        WHERE t.name IN (
-                    "#,
-                );
-
-                let placeholders = tags.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-                sql.push_str(&placeholders);
-                sql.push_str(")");
-
-                // 如果需要匹配所有标签，使用 GROUP BY 和 HAVING
-                sql.push_str(&format!(
-                    " GROUP BY ft.file_id HAVING COUNT(DISTINCT t.id) = {}",
-                    tags.len()
-                ));
-                sql.push_str(")");
-
-                for tag in tags {
-                    params.push(tag.clone());
-                }
-            }
+        if !parsed.file_types.is_empty() {
+            sql.push_str(&format!(
+                " AND f.file_type IN ({})",
+                placeholders(parsed.file_types.len())
+            ));
+            params.extend(parsed.file_types);
+        }
+
+        if !parsed.exclude_file_types.is_empty() {
+            sql.push_str(&format!(
+                " AND f.file_type NOT IN ({})",
+                placeholders(parsed.exclude_file_types.len())
+            ));
+            params.extend(parsed.exclude_file_types);
+        }
+
+        // 添加文件夹过滤（包含子文件夹）
+        let mut folders = parsed.folders;
+        if let Some(folder) = &query.folder {
+            folders.push(normalize_folder_path(folder));
+        }
+
+        let workspace_id = query.workspace_id.as_deref();
+        for path in folders {
+            sql.push_str(&format!(" AND {}", folder_filter_sql(&path, workspace_id)));
+            params.extend(folder_filter_params(&path, workspace_id));
+        }
+
+        // 根目录下文件的 folder_id 为 NULL，IN 的结果也是 NULL，需要按不匹配处理
+        for path in parsed.exclude_folders {
+            sql.push_str(&format!(
+                " AND NOT COALESCE({}, 0)",
+                folder_filter_sql(&path, workspace_id)
+            ));
+            params.extend(folder_filter_params(&path, workspace_id));
+        }
+
+        // 添加标签过滤（需要匹配所有标签）
+        let mut tags = query.tags.unwrap_or_default();
+        tags.extend(parsed.tags);
+        tags.sort();
+        tags.dedup();
+
+        if !tags.is_empty() {
+            sql.push_str(&format!(
+                r#"
+                AND f.id IN (
+                    SELECT ft.file_id FROM file_tags ft
+                    INNER JOIN tags t ON ft.tag_id = t.id
+                    WHERE t.name IN ({})
+                    GROUP BY ft.file_id HAVING COUNT(DISTINCT t.id) = {}
+                )
+                "#,
+                placeholders(tags.len()),
+                tags.len()
+            ));
+            params.extend(tags);
+        }
+
+        if !parsed.exclude_tags.is_empty() {
+            sql.push_str(&format!(
+                r#"
+                AND f.id NOT IN (
+                    SELECT ft.file_id FROM file_tags ft
+                    INNER JOIN tags t ON ft.tag_id = t.id
+                    WHERE t.name IN ({})
+                )
+                "#,
+                placeholders(parsed.exclude_tags.len())
+            ));
+            params.extend(parsed.exclude_tags);
         }
 
-        sql.push_str(" ORDER BY rank DESC LIMIT ?");
+        // 日期过滤（按更新时间，before 不含当天，after 含当天）
+        if let Some(before) = parsed.before {
+            sql.push_str(" AND f.updated_at < ?");
+            params.push(before.format("%Y-%m-%d").to_string());
+        }
+
+        if let Some(after) = parsed.after {
+            sql.push_str(" AND f.updated_at >= ?");
+            params.push(after.format("%Y-%m-%d").to_string());
+        }
+
+        if ranked {
+            sql.push_str(" ORDER BY rank DESC LIMIT ?");
+        } else {
+            sql.push_str(" ORDER BY f.updated_at DESC LIMIT ?");
+        }
         params.push(limit.to_string());
 
         // 执行查询
@@ -90,50 +269,11 @@ impl SearchService {
 
         let rows = q.fetch_all(&self.pool).await?;
 
-        let results: Vec<SearchResult> = rows
-            .iter()
-            .map(|row| SearchResult {
-                id: row.get("id"),
-                workspace_id: row.get("workspace_id"),
-                file_type: row.get("file_type"),
-                title: row.get("title"),
-                content: row.get("content"),
-                file_path: row.get("file_path"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                rank: row.get("rank"),
-            })
-            .collect();
+        let results: Vec<SearchResult> = rows.iter().map(map_search_row).collect();
 
         Ok(results)
     }
 
-    /// 构建 FTS5 查询字符串
-    fn build_fts_query(&self, query: &str) -> String {
-        // 简单的查询处理：
-        // 1. 分词
-        // 2. 添加通配符支持前缀匹配
-        // 3. 使用 OR 连接多个词
-
-        let terms: Vec<String> = query
-            .split_whitespace()
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                // 转义特殊字符
-                let escaped = s.replace('"', "\"\"");
-                // 添加通配符支持前缀匹配
-                format!("\"{}\"*", escaped)
-            })
-            .collect();
-
-        if terms.is_empty() {
-            return String::from("*");
-        }
-
-        // 使用 OR 连接，这样任何一个词匹配都会返回结果
-        terms.join(" OR ")
-    }
-
     /// 按标签搜索
     pub async fn search_by_tags(
         &self,
@@ -145,14 +285,14 @@ impl SearchService {
             return Ok(vec![]);
         }
 
-        let mut sql = String::from(
+        let mut sql = format!(
             r#"
-            SELECT 
+            SELECT
                 f.id,
                 f.workspace_id,
                 f.file_type,
-                f.title,
-                f.content,
+                f.title as title_marked,
+                {} as snippet_marked,
                 f.file_path,
                 f.created_at,
                 f.updated_at,
@@ -160,13 +300,14 @@ impl SearchService {
             FROM files f
             INNER JOIN file_tags ft ON f.id = ft.file_id
             INNER JOIN tags t ON ft.tag_id = t.id
-            WHERE t.name IN (
+            WHERE f.deleted_at IS NULL AND t.name IN (
             "#,
+            excerpt_sql()
         );
 
         let placeholders = tag_names.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         sql.push_str(&placeholders);
-        sql.push_str(")");
+        sql.push(')');
 
         let mut params: Vec<String> = tag_names.clone();
 
@@ -195,70 +336,508 @@ impl SearchService {
 
         let rows = q.fetch_all(&self.pool).await?;
 
-        let results: Vec<SearchResult> = rows
-            .iter()
-            .map(|row| SearchResult {
-                id: row.get("id"),
-                workspace_id: row.get("workspace_id"),
-                file_type: row.get("file_type"),
-                title: row.get("title"),
-                content: row.get("content"),
-                file_path: row.get("file_path"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                rank: row.get("rank"),
-            })
-            .collect();
+        let results: Vec<SearchResult> = rows.iter().map(map_search_row).collect();
 
         Ok(results)
     }
 
-    /// 相似文档推荐（基于标签）
+    /// 相似文档推荐（基于共同标签，用于没有可比较内容的文档）
     pub async fn find_similar(&self, file_id: &str, limit: i64) -> Result<Vec<SearchResult>> {
-        let sql = r#"
+        let sql = format!(
+            r#"
             SELECT DISTINCT
                 f.id,
                 f.workspace_id,
                 f.file_type,
-                f.title,
-                f.content,
+                f.title as title_marked,
+                {} as snippet_marked,
                 f.file_path,
                 f.created_at,
                 f.updated_at,
-                COUNT(DISTINCT ft2.tag_id) as rank
+                CAST(COUNT(DISTINCT ft2.tag_id) AS REAL) as rank
             FROM files f
             INNER JOIN file_tags ft2 ON f.id = ft2.file_id
             WHERE ft2.tag_id IN (
                 SELECT tag_id FROM file_tags WHERE file_id = ?
             )
-            AND f.id != ?
+            AND f.id != ? AND f.deleted_at IS NULL
             GROUP BY f.id
             ORDER BY rank DESC, f.updated_at DESC
             LIMIT ?
-        "#;
+            "#,
+            excerpt_sql()
+        );
 
-        let rows = sqlx::query(sql)
+        let rows = sqlx::query(&sql)
             .bind(file_id)
             .bind(file_id)
             .bind(limit)
             .fetch_all(&self.pool)
             .await?;
 
-        let results: Vec<SearchResult> = rows
-            .iter()
-            .map(|row| SearchResult {
-                id: row.get("id"),
-                workspace_id: row.get("workspace_id"),
-                file_type: row.get("file_type"),
-                title: row.get("title"),
-                content: row.get("content"),
-                file_path: row.get("file_path"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                rank: row.get::<i64, _>("rank") as f64,
+        let results: Vec<SearchResult> = rows.iter().map(map_search_row).collect();
+
+        Ok(results)
+    }
+}
+
+/// 刷新单个文件的全文索引（标题、正文和标签都经过分词后写入，回收站中的文件只删除索引）
+pub async fn index_file(conn: &mut SqliteConnection, file_id: &str) -> Result<()> {
+    let row: Option<(String, Option<String>, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT
+            f.title,
+            f.content_plain,
+            (
+                SELECT group_concat(t.name, ' ')
+                FROM file_tags ft
+                INNER JOIN tags t ON ft.tag_id = t.id
+                WHERE ft.file_id = f.id
+            ) as tags
+        FROM files f
+        WHERE f.id = ? AND f.deleted_at IS NULL
+        "#,
+    )
+    .bind(file_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    sqlx::query("DELETE FROM files_fts WHERE file_id = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some((title, content_plain, tags)) = row {
+        sqlx::query("INSERT INTO files_fts (file_id, title, content, tags) VALUES (?, ?, ?, ?)")
+            .bind(file_id)
+            .bind(segment(&title))
+            .bind(segment(content_plain.as_deref().unwrap_or_default()))
+            .bind(segment(tags.as_deref().unwrap_or_default()))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn map_search_row(row: &SqliteRow) -> SearchResult {
+    let (title, title_ranges) = extract_marks(row.get("title_marked"));
+    let (snippet, snippet_ranges) = extract_marks(row.get("snippet_marked"));
+
+    SearchResult {
+        id: row.get("id"),
+        workspace_id: row.get("workspace_id"),
+        file_type: row.get("file_type"),
+        title,
+        title_ranges,
+        snippet,
+        snippet_ranges,
+        file_path: row.get("file_path"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        rank: row.get("rank"),
+    }
+}
+
+/// 相关度分数：BM25 取反（越大越相关），可选按更新时间加成
+///
+/// 加成系数为 `1 + boost / (1 + 距今天数 / 半衰期)`，刚更新的文档最多乘以 `1 + boost`。
+fn score_sql(weights: SearchWeights, recency_boost: f64) -> Result<String> {
+    let values = [weights.title, weights.content, weights.tags, recency_boost];
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err(AppError::Validation(
+            "Search weights and recency boost must be non-negative numbers".to_string(),
+        ));
+    }
+
+    // file_id 列不参与排名
+    let mut score = format!(
+        "-bm25(files_fts, 0.0, {:?}, {:?}, {:?})",
+        weights.title, weights.content, weights.tags
+    );
+
+    if recency_boost > 0.0 {
+        score = format!(
+            "{} * (1.0 + {:?} / (1.0 + MAX(julianday('now') - julianday(f.updated_at), 0.0) / {:?}))",
+            score, recency_boost, RECENCY_HALF_LIFE_DAYS
+        );
+    }
+
+    Ok(score)
+}
+
+pub(super) fn placeholders(n: usize) -> String {
+    vec!["?"; n].join(",")
+}
+
+/// 取正文开头作为摘录（用于没有全文匹配的查询）
+pub(super) fn excerpt_sql() -> String {
+    format!(
+        "CASE WHEN length(COALESCE(f.content_plain, '')) > {n} \
+         THEN substr(f.content_plain, 1, {n}) || '…' \
+         ELSE COALESCE(f.content_plain, '') END",
+        n = EXCERPT_CHARS
+    )
+}
+
+/// 去除匹配标记和分词分隔符，返回纯文本和匹配位置
+fn extract_marks(marked: String) -> (String, Vec<MatchRange>) {
+    let mut text = String::with_capacity(marked.len());
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    let mut start = None;
+
+    for c in marked.chars() {
+        match c {
+            SEGMENT_SEPARATOR => {}
+            MARK_START => start = Some(pos),
+            MARK_END => {
+                if let Some(start) = start.take() {
+                    ranges.push(MatchRange { start, end: pos });
+                }
+            }
+            _ => {
+                text.push(c);
+                pos += 1;
+            }
+        }
+    }
+
+    (text, ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateFolder, CreateTag, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::folder::FolderService;
+    use crate::services::tag::TagService;
+    use crate::services::workspace::WorkspaceService;
+
+    const DOCUMENTS: [(&str, &str); 4] = [
+        ("Rust 编程指南", "<p>学习Rust编程语言的基础知识</p>"),
+        (
+            "Python notes",
+            "<p>Python数据分析入门，包括pandas的用法</p>",
+        ),
+        ("中文搜索", "<p>全文搜索支持中英文混合 mixed text</p>"),
+        ("Meeting", "<p>讨论了下个版本的发布计划 release plan</p>"),
+    ];
+
+    async fn seed() -> SearchService {
+        seed_with(&DOCUMENTS).await
+    }
+
+    async fn seed_with(documents: &[(&str, &str)]) -> SearchService {
+        let pool = memory_pool().await;
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Workspace".to_string(),
+                description: None,
             })
+            .await
+            .unwrap();
+        let files = FileService::new(pool.clone());
+        for (title, content) in documents {
+            files
+                .create(CreateFile {
+                    workspace_id: workspace.id.clone(),
+                    file_type: "document".to_string(),
+                    title: title.to_string(),
+                    content: Some(content.to_string()),
+                    file_path: None,
+                    file_size: None,
+                    mime_type: None,
+                    folder_id: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        SearchService::new(pool)
+    }
+
+    fn search_query(query: &str) -> SearchQuery {
+        SearchQuery {
+            query: query.to_string(),
+            workspace_id: None,
+            file_type: None,
+            tags: None,
+            folder: None,
+            limit: None,
+            snippet_tokens: None,
+            weights: None,
+            recency_boost: None,
+        }
+    }
+
+    async fn search(service: &SearchService, query: &str) -> Vec<SearchResult> {
+        service.search(search_query(query)).await.unwrap()
+    }
+
+    /// 按排名顺序的标题
+    async fn ranked(service: &SearchService, query: SearchQuery) -> Vec<String> {
+        service
+            .search(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.title)
+            .collect()
+    }
+
+    async fn titles(service: &SearchService, query: &str) -> Vec<String> {
+        let mut titles: Vec<String> = search(service, query)
+            .await
+            .into_iter()
+            .map(|r| r.title)
             .collect();
+        titles.sort();
+        titles
+    }
 
-        Ok(results)
+    /// 按匹配位置取出的文本
+    fn marked(text: &str, ranges: &[MatchRange]) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        ranges
+            .iter()
+            .map(|r| chars[r.start..r.end].iter().collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn mixed_cjk_and_latin_search() {
+        let service = seed().await;
+
+        // 句子中间的中文词
+        assert_eq!(titles(&service, "编程").await, ["Rust 编程指南"]);
+        assert_eq!(titles(&service, "数据分析").await, ["Python notes"]);
+        // 中文与英文相连
+        assert_eq!(titles(&service, "Rust编程").await, ["Rust 编程指南"]);
+        assert_eq!(titles(&service, "pandas").await, ["Python notes"]);
+        // 英文前缀
+        assert_eq!(titles(&service, "mix").await, ["中文搜索"]);
+        // 中英文混合的多个词
+        assert_eq!(titles(&service, "搜索 mixed").await, ["中文搜索"]);
+        assert_eq!(titles(&service, "release 版本").await, ["Meeting"]);
+        // 字序不同不是同一个词
+        assert!(titles(&service, "程编").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn mixed_search_operators() {
+        let service = seed().await;
+
+        assert_eq!(
+            titles(&service, "编程 OR 发布").await,
+            ["Meeting", "Rust 编程指南"]
+        );
+        assert_eq!(titles(&service, "title:中文").await, ["中文搜索"]);
+        assert!(titles(&service, "title:数据").await.is_empty());
+        assert_eq!(
+            titles(&service, "-Python -搜索").await,
+            ["Meeting", "Rust 编程指南"]
+        );
+        assert_eq!(titles(&service, "\"中英文\" -python").await, ["中文搜索"]);
+    }
+
+    #[tokio::test]
+    async fn query_filters_restrict_results() {
+        let service = seed().await;
+        let pool = &service.pool;
+        let id = |title: &'static str| async move {
+            sqlx::query_as::<_, (String, String)>(
+                "SELECT id, workspace_id FROM files WHERE title = ?",
+            )
+            .bind(title)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+        };
+        let (rust, workspace_id) = id("Rust 编程指南").await;
+        let (meeting, _) = id("Meeting").await;
+        let (python, _) = id("Python notes").await;
+
+        let files = FileService::new(pool.clone());
+        let tag = TagService::new(pool.clone())
+            .create(CreateTag {
+                name: "work".to_string(),
+                color: None,
+            })
+            .await
+            .unwrap();
+        files.add_tag(&rust, &tag.id).await.unwrap();
+        files.add_tag(&meeting, &tag.id).await.unwrap();
+
+        let folders = FolderService::new(pool.clone());
+        let projects = folders
+            .create(CreateFolder {
+                workspace_id: workspace_id.clone(),
+                parent_id: None,
+                name: "Projects".to_string(),
+            })
+            .await
+            .unwrap();
+        let web = folders
+            .create(CreateFolder {
+                workspace_id: workspace_id.clone(),
+                parent_id: Some(projects.id),
+                name: "Web".to_string(),
+            })
+            .await
+            .unwrap();
+        folders
+            .move_files(&[python], Some(web.id.as_str()))
+            .await
+            .unwrap();
+
+        sqlx::query("UPDATE files SET updated_at = '2023-06-01T00:00:00+00:00' WHERE id = ?")
+            .bind(&meeting)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            titles(&service, "tag:work").await,
+            ["Meeting", "Rust 编程指南"]
+        );
+        assert_eq!(titles(&service, "编程 tag:work").await, ["Rust 编程指南"]);
+        assert_eq!(
+            titles(&service, "-tag:work").await,
+            ["Python notes", "中文搜索"]
+        );
+        assert_eq!(titles(&service, "folder:Projects").await, ["Python notes"]);
+        assert_eq!(
+            titles(&service, "folder:Projects/Web").await,
+            ["Python notes"]
+        );
+        assert_eq!(
+            titles(&service, "folder:/").await,
+            ["Meeting", "Rust 编程指南", "中文搜索"]
+        );
+        assert_eq!(titles(&service, "before:2024-01-01").await, ["Meeting"]);
+        assert_eq!(
+            titles(&service, "after:2024-01-01 -tag:work").await,
+            ["Python notes", "中文搜索"]
+        );
+        assert_eq!(titles(&service, "type:document").await.len(), 4);
+        assert!(titles(&service, "type:image").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn weights_change_ranking() {
+        let service = seed_with(&[
+            ("alpha report", "<p>quarterly numbers for the review</p>"),
+            ("beta report", "<p>alpha numbers for the review</p>"),
+        ])
+        .await;
+
+        // 默认标题权重更高
+        assert_eq!(
+            ranked(&service, search_query("alpha")).await,
+            ["alpha report", "beta report"]
+        );
+
+        let swapped = SearchQuery {
+            weights: Some(SearchWeights {
+                title: 1.0,
+                content: 10.0,
+                tags: 5.0,
+            }),
+            ..search_query("alpha")
+        };
+        assert_eq!(
+            ranked(&service, swapped).await,
+            ["beta report", "alpha report"]
+        );
+    }
+
+    #[tokio::test]
+    async fn recency_boost_prefers_recent_documents() {
+        let service = seed_with(&[
+            ("old note", "<p>shared topic text</p>"),
+            ("new note", "<p>shared topic text</p>"),
+        ])
+        .await;
+        sqlx::query("UPDATE files SET updated_at = '2020-01-01T00:00:00+00:00' WHERE title = ?")
+            .bind("old note")
+            .execute(&service.pool)
+            .await
+            .unwrap();
+
+        let results = search(&service, "shared").await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].rank, results[1].rank);
+
+        let boosted = service
+            .search(SearchQuery {
+                recency_boost: Some(1.0),
+                ..search_query("shared")
+            })
+            .await
+            .unwrap();
+        assert_eq!(boosted[0].title, "new note");
+        assert!(boosted[0].rank > boosted[1].rank);
+    }
+
+    #[tokio::test]
+    async fn invalid_weights_are_rejected() {
+        let service = seed().await;
+
+        let weights = |title: f64| SearchWeights {
+            title,
+            ..SearchWeights::default()
+        };
+        for query in [
+            SearchQuery {
+                weights: Some(weights(-1.0)),
+                ..search_query("rust")
+            },
+            SearchQuery {
+                weights: Some(weights(f64::NAN)),
+                ..search_query("rust")
+            },
+            SearchQuery {
+                recency_boost: Some(-0.5),
+                ..search_query("rust")
+            },
+            SearchQuery {
+                recency_boost: Some(f64::INFINITY),
+                ..search_query("rust")
+            },
+        ] {
+            assert!(matches!(
+                service.search(query).await,
+                Err(AppError::Validation(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_are_reported_without_separators() {
+        let service = seed().await;
+
+        let results = search(&service, "编程").await;
+        let result = &results[0];
+
+        assert_eq!(result.title, "Rust 编程指南");
+        assert_eq!(result.snippet, "学习Rust编程语言的基础知识");
+        assert_eq!(marked(&result.title, &result.title_ranges).concat(), "编程");
+        assert_eq!(
+            marked(&result.snippet, &result.snippet_ranges).concat(),
+            "编程"
+        );
+        assert!(result.rank > 0.0);
+    }
+
+    #[tokio::test]
+    async fn rebuild_index_keeps_results() {
+        let service = seed().await;
+
+        service.rebuild_index().await.unwrap();
+
+        assert_eq!(titles(&service, "分析 Python").await, ["Python notes"]);
     }
 }
@@ -0,0 +1,663 @@
+//! 搜索查询语言
+//!
+//! 支持的语法：
+//! - `word`：前缀匹配，多个词默认需要同时满足
+//! - `"exact phrase"`：短语匹配
+//! - `-word`、`-"phrase"`：排除
+//! - `a OR b`、`a AND b`、`(a OR b) c`：布尔组合（运算符需大写）
+//! - `title:word`、`title:"phrase"`：只匹配标题
+//! - `tag:name`、`type:document`：按标签、文件类型过滤（可用 `-` 排除）
+//! - `folder:projects/web`、`folder:"my notes"`：按文件夹路径过滤，包含子文件夹（可用 `-` 排除，`folder:/` 表示根目录）
+//! - `before:2024-01-31`、`after:2024-01-01`：按更新日期过滤
+//!
+//! 解析结果编译为 FTS5 MATCH 表达式和 SQL 过滤条件，用户输入只会以带引号的
+//! 短语形式进入 MATCH，不会产生 FTS5 语法错误。
+
+use crate::error::{AppError, Result};
+use crate::services::folder::normalize_folder_path;
+use crate::text::segment;
+use chrono::NaiveDate;
+
+const FILE_TYPES: [&str; 4] = ["document", "image", "audio", "video"];
+
+/// 编译后的查询
+#[derive(Debug, Default)]
+pub struct ParsedQuery {
+    /// 结果必须匹配的 FTS5 表达式
+    pub fts: Option<String>,
+    /// 结果不能匹配的 FTS5 表达式（查询只有排除项时使用）
+    pub exclude_fts: Option<String>,
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub file_types: Vec<String>,
+    pub exclude_file_types: Vec<String>,
+    pub folders: Vec<String>,
+    pub exclude_folders: Vec<String>,
+    pub before: Option<NaiveDate>,
+    pub after: Option<NaiveDate>,
+}
+
+/// 解析查询字符串
+pub fn parse(input: &str) -> Result<ParsedQuery> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+
+    let node = parser.parse_or()?;
+    if let Some((_, pos)) = parser.peek() {
+        return Err(syntax_error(format!(
+            "Unmatched ')' at position {}",
+            pos + 1
+        )));
+    }
+
+    compile_query(node)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(Field),
+    Minus,
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Title,
+    Tag,
+    Type,
+    Folder,
+    Before,
+    After,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "title" => Some(Field::Title),
+            "tag" => Some(Field::Tag),
+            "type" => Some(Field::Type),
+            "folder" => Some(Field::Folder),
+            "before" => Some(Field::Before),
+            "after" => Some(Field::After),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Tag => "tag",
+            Field::Type => "type",
+            Field::Folder => "folder",
+            Field::Before => "before",
+            Field::After => "after",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Filter {
+    Tag(String),
+    Type(String),
+    Folder(String),
+    Before(NaiveDate),
+    After(NaiveDate),
+}
+
+#[derive(Debug)]
+enum Node {
+    Term {
+        text: String,
+        phrase: bool,
+        title_only: bool,
+    },
+    Filter(Filter),
+    Not(Box<Node>),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+}
+
+/// 词法分析，返回 (词元, 字符位置)
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '(' => {
+                tokens.push((Token::LParen, i));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::RParen, i));
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .map(|p| i + 1 + p)
+                    .ok_or_else(|| {
+                        syntax_error(format!("Unterminated quote at position {}", start + 1))
+                    })?;
+                let text: String = chars[start + 1..end].iter().collect();
+                tokens.push((Token::Phrase(text), start));
+                i = end + 1;
+            }
+            '-' => {
+                // 只有紧跟内容的 - 才表示排除，单独的 - 忽略
+                if chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) {
+                    tokens.push((Token::Minus, i));
+                }
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"()\"".contains(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                let field = word
+                    .split_once(':')
+                    .and_then(|(name, rest)| Field::from_name(name).map(|f| (f, rest)));
+
+                match (word.as_str(), field) {
+                    ("AND", _) => tokens.push((Token::And, start)),
+                    ("OR", _) => tokens.push((Token::Or, start)),
+                    (_, Some((field, rest))) => {
+                        tokens.push((Token::Field(field), start));
+                        if !rest.is_empty() {
+                            let offset = start + field.name().chars().count() + 1;
+                            tokens.push((Token::Word(rest.to_string()), offset));
+                        }
+                    }
+                    _ => tokens.push((Token::Word(word), start)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Token, usize)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// 当前位置是否可以开始一个新的词项
+    fn at_operand(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some((
+                Token::Word(_) | Token::Phrase(_) | Token::Field(_) | Token::Minus | Token::LParen,
+                _
+            ))
+        )
+    }
+
+    fn parse_or(&mut self) -> Result<Node> {
+        let mut nodes = vec![self.parse_and()?];
+
+        while let Some((Token::Or, pos)) = self.peek().cloned() {
+            if is_empty(&nodes[nodes.len() - 1]) {
+                return Err(syntax_error(format!(
+                    "Expected a term before OR at position {}",
+                    pos + 1
+                )));
+            }
+            self.next();
+            if !self.at_operand() {
+                return Err(syntax_error(format!(
+                    "Expected a term after OR at position {}",
+                    pos + 1
+                )));
+            }
+            nodes.push(self.parse_and()?);
+        }
+
+        Ok(if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            Node::Or(nodes)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+
+        while let Some((token, pos)) = self.peek().cloned() {
+            match token {
+                Token::Or | Token::RParen => break,
+                Token::And => {
+                    self.next();
+                    if nodes.is_empty() || !self.at_operand() {
+                        return Err(syntax_error(format!(
+                            "AND needs a term on both sides (position {})",
+                            pos + 1
+                        )));
+                    }
+                }
+                _ => nodes.push(self.parse_unary()?),
+            }
+        }
+
+        Ok(if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            Node::And(nodes)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Node> {
+        if let Some((Token::Minus, pos)) = self.peek().cloned() {
+            self.next();
+            if !self.at_operand() || matches!(self.peek(), Some((Token::Minus, _))) {
+                return Err(syntax_error(format!(
+                    "Expected a term after '-' at position {}",
+                    pos + 1
+                )));
+            }
+            return Ok(Node::Not(Box::new(self.parse_primary()?)));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Node> {
+        let (token, pos) = self
+            .next()
+            .ok_or_else(|| syntax_error("Unexpected end of query".to_string()))?;
+
+        match token {
+            Token::LParen => {
+                let node = self.parse_or()?;
+                match self.next() {
+                    Some((Token::RParen, _)) => {}
+                    _ => {
+                        return Err(syntax_error(format!(
+                            "Missing ')' for '(' at position {}",
+                            pos + 1
+                        )))
+                    }
+                }
+                if is_empty(&node) {
+                    return Err(syntax_error(format!(
+                        "Empty parentheses at position {}",
+                        pos + 1
+                    )));
+                }
+                Ok(node)
+            }
+            Token::Word(text) => Ok(Node::Term {
+                text,
+                phrase: false,
+                title_only: false,
+            }),
+            Token::Phrase(text) => {
+                if text.trim().is_empty() {
+                    return Err(syntax_error(format!(
+                        "Empty phrase at position {}",
+                        pos + 1
+                    )));
+                }
+                Ok(Node::Term {
+                    text,
+                    phrase: true,
+                    title_only: false,
+                })
+            }
+            Token::Field(field) => self.parse_field(field, pos),
+            Token::RParen => Err(syntax_error(format!(
+                "Unmatched ')' at position {}",
+                pos + 1
+            ))),
+            Token::And | Token::Or | Token::Minus => Err(syntax_error(format!(
+                "Expected a term at position {}",
+                pos + 1
+            ))),
+        }
+    }
+
+    fn parse_field(&mut self, field: Field, pos: usize) -> Result<Node> {
+        let (value, phrase) = match self.peek().cloned() {
+            Some((Token::Word(text), _)) => (text, false),
+            Some((Token::Phrase(text), _)) if !text.trim().is_empty() => (text, true),
+            _ => {
+                return Err(syntax_error(format!(
+                    "Expected a value after '{}:' at position {}",
+                    field.name(),
+                    pos + 1
+                )))
+            }
+        };
+        self.next();
+
+        let node = match field {
+            Field::Title => Node::Term {
+                text: value,
+                phrase,
+                title_only: true,
+            },
+            Field::Tag => Node::Filter(Filter::Tag(value)),
+            Field::Type => {
+                let value = value.to_lowercase();
+                if !FILE_TYPES.contains(&value.as_str()) {
+                    return Err(syntax_error(format!(
+                        "Unknown file type '{}' (expected one of: {})",
+                        value,
+                        FILE_TYPES.join(", ")
+                    )));
+                }
+                Node::Filter(Filter::Type(value))
+            }
+            Field::Folder => Node::Filter(Filter::Folder(normalize_folder_path(&value))),
+            Field::Before | Field::After => {
+                let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| {
+                    syntax_error(format!(
+                        "Invalid date '{}' for '{}:' (expected YYYY-MM-DD)",
+                        value,
+                        field.name()
+                    ))
+                })?;
+                if field == Field::Before {
+                    Node::Filter(Filter::Before(date))
+                } else {
+                    Node::Filter(Filter::After(date))
+                }
+            }
+        };
+
+        Ok(node)
+    }
+}
+
+fn is_empty(node: &Node) -> bool {
+    matches!(node, Node::And(nodes) if nodes.is_empty())
+}
+
+fn contains_filter(node: &Node) -> bool {
+    match node {
+        Node::Filter(_) => true,
+        Node::Term { .. } => false,
+        Node::Not(inner) => contains_filter(inner),
+        Node::And(nodes) | Node::Or(nodes) => nodes.iter().any(contains_filter),
+    }
+}
+
+fn misplaced_filter() -> AppError {
+    syntax_error(
+        "Filters (tag:, type:, folder:, before:, after:) can only be used at the top level, \
+         not inside OR or parentheses"
+            .to_string(),
+    )
+}
+
+/// 顶层的过滤条件转为 SQL 条件，其余部分编译为 FTS5 表达式
+fn compile_query(node: Node) -> Result<ParsedQuery> {
+    let nodes = match node {
+        Node::And(nodes) => nodes,
+        node => vec![node],
+    };
+
+    let mut query = ParsedQuery::default();
+    let mut positives = Vec::new();
+    let mut negatives = Vec::new();
+
+    for node in nodes {
+        match node {
+            Node::Filter(Filter::Tag(tag)) => query.tags.push(tag),
+            Node::Filter(Filter::Type(file_type)) => query.file_types.push(file_type),
+            Node::Filter(Filter::Folder(path)) => query.folders.push(path),
+            Node::Filter(Filter::Before(date)) => query.before = Some(date),
+            Node::Filter(Filter::After(date)) => query.after = Some(date),
+            Node::Not(inner) => match *inner {
+                Node::Filter(Filter::Tag(tag)) => query.exclude_tags.push(tag),
+                Node::Filter(Filter::Type(file_type)) => query.exclude_file_types.push(file_type),
+                Node::Filter(Filter::Folder(path)) => query.exclude_folders.push(path),
+                Node::Filter(_) => {
+                    return Err(syntax_error(
+                        "before:/after: filters cannot be negated".to_string(),
+                    ))
+                }
+                inner if contains_filter(&inner) => return Err(misplaced_filter()),
+                inner => negatives.push(inner),
+            },
+            node if contains_filter(&node) => return Err(misplaced_filter()),
+            node => positives.push(node),
+        }
+    }
+
+    if !positives.is_empty() {
+        positives.extend(negatives.into_iter().map(|n| Node::Not(Box::new(n))));
+        query.fts = Some(compile(&Node::And(positives))?);
+    } else if !negatives.is_empty() {
+        query.exclude_fts = Some(compile(&Node::Or(negatives))?);
+    }
+
+    Ok(query)
+}
+
+fn compile(node: &Node) -> Result<String> {
+    match node {
+        Node::Term {
+            text,
+            phrase,
+            title_only,
+        } => {
+            let mut term = format!("\"{}\"", segment(text).replace('"', "\"\""));
+            if !phrase {
+                term.push('*');
+            }
+            if *title_only {
+                term = format!("title : {}", term);
+            }
+            Ok(term)
+        }
+        Node::And(nodes) => {
+            let mut positives = Vec::new();
+            let mut negatives = Vec::new();
+            for node in nodes {
+                match node {
+                    Node::Not(inner) => negatives.push(compile(inner)?),
+                    node => positives.push(compile(node)?),
+                }
+            }
+
+            if positives.is_empty() {
+                return Err(syntax_error(
+                    "A group with '-' exclusions needs at least one term to match".to_string(),
+                ));
+            }
+
+            let mut expr = positives.join(" AND ");
+            if !negatives.is_empty() {
+                expr = format!("({}) NOT ({})", expr, negatives.join(" OR "));
+            }
+            Ok(format!("({})", expr))
+        }
+        Node::Or(nodes) => {
+            let parts = nodes
+                .iter()
+                .map(|node| match node {
+                    Node::Not(_) => Err(syntax_error(
+                        "Cannot use '-' directly in an OR; group it with a term, e.g. (a -b) OR c"
+                            .to_string(),
+                    )),
+                    node => compile(node),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("({})", parts.join(" OR ")))
+        }
+        Node::Not(_) => Err(syntax_error(
+            "A query cannot consist of a single '-' group".to_string(),
+        )),
+        Node::Filter(_) => Err(misplaced_filter()),
+    }
+}
+
+fn syntax_error(message: String) -> AppError {
+    AppError::Validation(format!("Invalid search query: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::SEGMENT_SEPARATOR;
+
+    /// 编译结果中的分隔符替换为 `|`，便于阅读
+    fn fts(input: &str) -> String {
+        parse(input)
+            .unwrap()
+            .fts
+            .unwrap()
+            .replace(SEGMENT_SEPARATOR, "|")
+    }
+
+    fn error(input: &str) -> String {
+        match parse(input) {
+            Err(AppError::Validation(message)) => message,
+            other => panic!(
+                "expected a validation error for {:?}, got {:?}",
+                input, other
+            ),
+        }
+    }
+
+    #[test]
+    fn words_are_prefix_matches_joined_with_and() {
+        assert_eq!(fts("rust"), r#"("rust"*)"#);
+        assert_eq!(fts("rust async"), r#"("rust"* AND "async"*)"#);
+        assert_eq!(fts("rust AND async"), r#"("rust"* AND "async"*)"#);
+    }
+
+    #[test]
+    fn phrases_are_exact() {
+        assert_eq!(fts(r#""exact phrase""#), r#"("exact phrase")"#);
+        assert_eq!(
+            fts(r#"title:"release notes""#),
+            r#"(title : "release notes")"#
+        );
+        assert_eq!(fts("title:draft"), r#"(title : "draft"*)"#);
+    }
+
+    #[test]
+    fn cjk_terms_are_segmented() {
+        assert_eq!(fts("中文"), r#"("中|文"*)"#);
+        assert_eq!(fts("Rust编程"), r#"("Rust|编|程"*)"#);
+        assert_eq!(fts(r#""全文 搜索""#), r#"("全|文| |搜|索")"#);
+        assert_eq!(fts("编程 language"), r#"("编|程"* AND "language"*)"#);
+    }
+
+    #[test]
+    fn boolean_groups() {
+        assert_eq!(fts("a OR b"), r#"(("a"* OR "b"*))"#);
+        assert_eq!(fts("(a OR b) c"), r#"(("a"* OR "b"*) AND "c"*)"#);
+        assert_eq!(fts("a b OR c"), r#"((("a"* AND "b"*) OR "c"*))"#);
+        assert_eq!(fts("(a -b) OR c"), r#"(((("a"*) NOT ("b"*)) OR "c"*))"#);
+    }
+
+    #[test]
+    fn exclusions() {
+        assert_eq!(fts("a -b"), r#"(("a"*) NOT ("b"*))"#);
+        assert_eq!(fts(r#"a -b -"c d""#), r#"(("a"*) NOT ("b"* OR "c d"))"#);
+
+        let parsed = parse("-draft -中文").unwrap();
+        assert!(parsed.fts.is_none());
+        assert_eq!(
+            parsed.exclude_fts.unwrap().replace(SEGMENT_SEPARATOR, "|"),
+            r#"("draft"* OR "中|文"*)"#
+        );
+    }
+
+    #[test]
+    fn lone_minus_is_ignored() {
+        assert_eq!(fts("a - b"), r#"("a"* AND "b"*)"#);
+    }
+
+    #[test]
+    fn operator_characters_stay_inside_quotes() {
+        assert_eq!(fts("NEAR a* ^b"), r#"("NEAR"* AND "a*"* AND "^b"*)"#);
+        assert_eq!(fts("col:value"), r#"("col:value"*)"#);
+        assert_eq!(fts("and or"), r#"("and"* AND "or"*)"#);
+    }
+
+    #[test]
+    fn filters() {
+        let parsed = parse(
+            r#"notes tag:work -tag:old type:Document -type:image folder:"my notes/ 2024 " -folder:/ after:2024-01-01 before:2024-12-31"#,
+        )
+        .unwrap();
+
+        assert_eq!(parsed.fts.as_deref(), Some(r#"("notes"*)"#));
+        assert_eq!(parsed.tags, ["work"]);
+        assert_eq!(parsed.exclude_tags, ["old"]);
+        assert_eq!(parsed.file_types, ["document"]);
+        assert_eq!(parsed.exclude_file_types, ["image"]);
+        assert_eq!(parsed.folders, ["my notes/2024"]);
+        assert_eq!(parsed.exclude_folders, [""]);
+        assert_eq!(parsed.after, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(parsed.before, NaiveDate::from_ymd_opt(2024, 12, 31));
+    }
+
+    #[test]
+    fn filters_only() {
+        let parsed = parse("tag:work").unwrap();
+        assert!(parsed.fts.is_none());
+        assert!(parsed.exclude_fts.is_none());
+        assert_eq!(parsed.tags, ["work"]);
+    }
+
+    #[test]
+    fn empty_query() {
+        let parsed = parse("   ").unwrap();
+        assert!(parsed.fts.is_none());
+        assert!(parsed.exclude_fts.is_none());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(error(r#""open"#).contains("Unterminated quote at position 1"));
+        assert!(error("a )").contains("Unmatched ')' at position 3"));
+        assert!(error("(a").contains("Missing ')'"));
+        assert!(error("()").contains("Empty parentheses"));
+        assert!(error(r#""""#).contains("Empty phrase"));
+        assert!(error("OR a").contains("Expected a term before OR"));
+        assert!(error("a OR").contains("Expected a term after OR"));
+        assert!(error("AND a").contains("AND needs a term on both sides"));
+        assert!(error("--a").contains("Expected a term after '-'"));
+        assert!(error("tag:").contains("Expected a value after 'tag:'"));
+        assert!(error("type:pdf").contains("Unknown file type 'pdf'"));
+        assert!(error("before:yesterday").contains("Invalid date"));
+        assert!(error("-before:2024-01-01").contains("cannot be negated"));
+        assert!(error("a OR tag:x").contains("only be used at the top level"));
+        assert!(error("-a OR b").contains("Cannot use '-' directly in an OR"));
+        assert!(error("(-a -b) c").contains("needs at least one term"));
+    }
+}
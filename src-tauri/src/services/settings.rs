@@ -0,0 +1,164 @@
+use crate::error::{AppError, Result};
+use crate::models::Settings;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+
+/// 设置的结构版本
+///
+/// 新增字段不需要递增（反序列化时取默认值）；字段改名或含义变化时递增，并在 `migrate` 中加入对应的升级步骤。
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// 行高的允许范围
+const LINE_HEIGHT_RANGE: std::ops::RangeInclusive<f64> = 1.0..=3.0;
+
+/// 应用设置，以 JSON 保存在 `app_meta` 表中
+pub struct SettingsService {
+    pool: SqlitePool,
+    settings: RwLock<Settings>,
+}
+
+impl SettingsService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            settings: RwLock::new(Settings {
+                version: SETTINGS_VERSION,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 从数据库读取保存的设置；未保存过或无法解析时使用默认值
+    pub async fn load(&self) -> Result<()> {
+        let saved: Option<(String,)> =
+            sqlx::query_as("SELECT value FROM app_meta WHERE key = 'settings'")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        let Some((json,)) = saved else {
+            return Ok(());
+        };
+
+        let Ok(mut value) = serde_json::from_str::<Value>(&json) else {
+            return Ok(());
+        };
+        // 加入版本号之前保存的设置没有 version 字段，视为版本 0
+        let from_version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        migrate(&mut value, from_version);
+
+        if let Ok(settings) = serde_json::from_value::<Settings>(value) {
+            *self.settings.write().await = settings;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(&self) -> Settings {
+        self.settings.read().await.clone()
+    }
+
+    /// 校验并保存完整的设置，返回保存后的结果
+    pub async fn update(&self, mut settings: Settings) -> Result<Settings> {
+        let appearance = &mut settings.appearance;
+        if !LINE_HEIGHT_RANGE.contains(&appearance.line_height) {
+            return Err(AppError::Validation(format!(
+                "Line height must be between {} and {}",
+                LINE_HEIGHT_RANGE.start(),
+                LINE_HEIGHT_RANGE.end()
+            )));
+        }
+        appearance.font_family = appearance.font_family.trim().to_string();
+        if appearance.font_family.is_empty() {
+            return Err(AppError::Validation(
+                "Font family must not be empty".to_string(),
+            ));
+        }
+        settings.version = SETTINGS_VERSION;
+
+        let json = serde_json::to_string(&settings)
+            .map_err(|e| AppError::Internal(format!("Failed to save settings: {}", e)))?;
+        sqlx::query("INSERT OR REPLACE INTO app_meta (key, value) VALUES ('settings', ?)")
+            .bind(json)
+            .execute(&self.pool)
+            .await?;
+
+        *self.settings.write().await = settings.clone();
+
+        Ok(settings)
+    }
+}
+
+/// 把旧版本保存的设置逐个版本升级到当前结构
+///
+/// 比当前版本更新的设置（来自更新版本的应用）不做改动，未知字段在反序列化时忽略。
+fn migrate(settings: &mut Value, from_version: u32) {
+    for version in from_version..SETTINGS_VERSION {
+        match version {
+            // v0 -> v1：只是加入了版本号，字段没有变化
+            0 => {}
+            _ => unreachable!("missing settings migration from version {}", version),
+        }
+    }
+
+    if from_version < SETTINGS_VERSION {
+        settings["version"] = Value::from(SETTINGS_VERSION);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_sets_current_version() {
+        let mut value = json!({ "appearance": { "lineHeight": 2.2 } });
+        migrate(&mut value, 0);
+
+        assert_eq!(value["version"], json!(SETTINGS_VERSION));
+        assert_eq!(value["appearance"]["lineHeight"], json!(2.2));
+    }
+
+    #[test]
+    fn migrate_keeps_newer_version() {
+        let mut value = json!({ "version": SETTINGS_VERSION + 1 });
+        migrate(&mut value, SETTINGS_VERSION + 1);
+
+        assert_eq!(value["version"], json!(SETTINGS_VERSION + 1));
+    }
+
+    #[tokio::test]
+    async fn load_migrates_unversioned_settings() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO app_meta (key, value) VALUES ('settings', ?)")
+            .bind(r#"{"appearance":{"theme":"dark","lineHeight":2.2}}"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let service = SettingsService::new(pool);
+        service.load().await.unwrap();
+        let settings = service.get().await;
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.appearance.theme, crate::models::ThemeMode::Dark);
+        assert_eq!(settings.appearance.line_height, 2.2);
+        assert_eq!(settings.appearance.font_family, "system-ui");
+    }
+
+    #[tokio::test]
+    async fn load_ignores_unreadable_settings() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO app_meta (key, value) VALUES ('settings', 'not json')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let service = SettingsService::new(pool);
+        service.load().await.unwrap();
+
+        assert_eq!(service.get().await.version, SETTINGS_VERSION);
+    }
+}
@@ -1,7 +1,8 @@
+use super::search::index_file;
+use crate::error::Result;
 use crate::models::{CreateTag, Tag};
-use anyhow::Result;
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteConnection};
 use uuid::Uuid;
 
 pub struct TagService {
@@ -57,11 +58,26 @@ impl TagService {
     }
 
     pub async fn delete(&self, id: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 记录受影响的文件，删除后刷新它们的索引标签
+        let file_ids: Vec<(String,)> =
+            sqlx::query_as("SELECT file_id FROM file_tags WHERE tag_id = ?")
+                .bind(id)
+                .fetch_all(&mut *tx)
+                .await?;
+
         sqlx::query("DELETE FROM tags WHERE id = ?")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
 
+        for (file_id,) in file_ids {
+            index_file(&mut tx, &file_id).await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
@@ -74,3 +90,30 @@ impl TagService {
         Ok(count.0)
     }
 }
+
+/// 按名称查找标签，不存在时新建，返回标签 id（用于导入时与已有标签合并）
+pub async fn find_or_create(
+    conn: &mut SqliteConnection,
+    name: &str,
+    color: Option<&str>,
+) -> Result<String> {
+    let existing: Option<(String,)> = sqlx::query_as("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    if let Some((id,)) = existing {
+        return Ok(id);
+    }
+
+    let id = Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO tags (id, name, color, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&id)
+        .bind(name)
+        .bind(color)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(id)
+}
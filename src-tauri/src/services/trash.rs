@@ -0,0 +1,503 @@
+use super::media::remove_unreferenced_files;
+use super::search::index_file;
+use crate::error::{AppError, Result};
+use crate::models::{
+    File, TrashConfig, TrashContents, TrashPurgeResult, TrashedFile, TrashedWorkspace, Workspace,
+};
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Sqlite};
+use std::path::PathBuf;
+
+const MAX_RETENTION_DAYS: u32 = 3650;
+
+/// 回收站：恢复软删除的文件和工作空间，或彻底删除它们及磁盘上的媒体文件
+pub struct TrashService {
+    pool: Pool<Sqlite>,
+    media_dir: PathBuf,
+}
+
+impl TrashService {
+    pub fn new(pool: Pool<Sqlite>, media_dir: PathBuf) -> Self {
+        Self { pool, media_dir }
+    }
+
+    pub async fn get_config(&self) -> Result<TrashConfig> {
+        let saved: Option<(String,)> =
+            sqlx::query_as("SELECT value FROM app_meta WHERE key = 'trash_config'")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(saved
+            .and_then(|(json,)| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    pub async fn set_config(&self, config: TrashConfig) -> Result<()> {
+        if config.retention_days > MAX_RETENTION_DAYS {
+            return Err(AppError::Validation(format!(
+                "Retention days must be at most {}",
+                MAX_RETENTION_DAYS
+            )));
+        }
+
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Internal(format!("Failed to save trash config: {}", e)))?;
+        sqlx::query("INSERT OR REPLACE INTO app_meta (key, value) VALUES ('trash_config', ?)")
+            .bind(json)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 列出回收站内容（按删除时间倒序）
+    ///
+    /// 随工作空间一起删除的文件只计入工作空间，不单独列出。
+    pub async fn list(&self) -> Result<TrashContents> {
+        let workspaces = sqlx::query_as::<_, TrashedWorkspace>(
+            r#"
+            SELECT
+                w.id,
+                w.name,
+                (
+                    SELECT COUNT(*) FROM files f
+                    WHERE f.workspace_id = w.id AND f.deleted_at = w.deleted_at
+                ) as file_count,
+                w.deleted_at
+            FROM workspaces w
+            WHERE w.deleted_at IS NOT NULL
+            ORDER BY w.deleted_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let files = sqlx::query_as::<_, TrashedFile>(
+            r#"
+            SELECT
+                f.id,
+                f.workspace_id,
+                w.name as workspace_name,
+                f.file_type,
+                f.title,
+                f.deleted_at
+            FROM files f
+            INNER JOIN workspaces w ON w.id = f.workspace_id
+            WHERE f.deleted_at IS NOT NULL AND w.deleted_at IS NULL
+            ORDER BY f.deleted_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(TrashContents { workspaces, files })
+    }
+
+    /// 从回收站恢复文件，所在工作空间也在回收站中时需要先恢复工作空间
+    pub async fn restore_file(&self, id: &str) -> Result<File> {
+        let workspace_deleted: Option<(Option<String>,)> = sqlx::query_as(
+            r#"
+            SELECT w.deleted_at FROM files f
+            INNER JOIN workspaces w ON w.id = f.workspace_id
+            WHERE f.id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match workspace_deleted {
+            None => return Err(AppError::NotFound(format!("File not found: {}", id))),
+            Some((Some(_),)) => {
+                return Err(AppError::Validation(
+                    "The file's workspace is in the trash; restore the workspace first".to_string(),
+                ))
+            }
+            Some((None,)) => {}
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("UPDATE files SET deleted_at = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        index_file(&mut conn, id).await?;
+
+        let file = sqlx::query_as::<_, File>("SELECT * FROM files WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(file)
+    }
+
+    /// 恢复工作空间及随它一起删除的文件，之前单独删除的文件仍留在回收站
+    pub async fn restore_workspace(&self, id: &str) -> Result<Workspace> {
+        let workspace = sqlx::query_as::<_, Workspace>("SELECT * FROM workspaces WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Workspace not found: {}", id)))?;
+        let Some(deleted_at) = &workspace.deleted_at else {
+            return Ok(workspace);
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        let restored: Vec<(String,)> = sqlx::query_as(
+            r#"
+            UPDATE files SET deleted_at = NULL
+            WHERE workspace_id = ? AND deleted_at = ?
+            RETURNING id
+            "#,
+        )
+        .bind(id)
+        .bind(deleted_at)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (file_id,) in &restored {
+            index_file(&mut tx, file_id).await?;
+        }
+
+        let workspace = sqlx::query_as::<_, Workspace>(
+            "UPDATE workspaces SET deleted_at = NULL WHERE id = ? RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(workspace)
+    }
+
+    /// 清空回收站
+    pub async fn empty(&self) -> Result<TrashPurgeResult> {
+        self.purge(None).await
+    }
+
+    /// 彻底删除超过保留天数的项目（启动时调用），保留天数为 0 时不处理
+    pub async fn purge_expired(&self) -> Result<TrashPurgeResult> {
+        let config = self.get_config().await?;
+        if config.retention_days == 0 {
+            return Ok(TrashPurgeResult::default());
+        }
+
+        let cutoff = Utc::now() - Duration::days(i64::from(config.retention_days));
+        self.purge(Some(&cutoff.to_rfc3339())).await
+    }
+
+    /// 彻底删除在 `before` 之前（不传时为全部）移入回收站的工作空间和文件
+    ///
+    /// 数据库记录删除后再删除媒体目录中不再被使用的文件。
+    async fn purge(&self, before: Option<&str>) -> Result<TrashPurgeResult> {
+        let cutoff_sql = if before.is_some() {
+            " AND deleted_at < ?"
+        } else {
+            ""
+        };
+        let mut tx = self.pool.begin().await?;
+        let mut paths: Vec<String> = Vec::new();
+
+        let sql = format!(
+            "SELECT id FROM workspaces WHERE deleted_at IS NOT NULL{}",
+            cutoff_sql
+        );
+        let mut q = sqlx::query_as(&sql);
+        if let Some(before) = before {
+            q = q.bind(before);
+        }
+        let workspace_ids: Vec<(String,)> = q.fetch_all(&mut *tx).await?;
+
+        for (workspace_id,) in &workspace_ids {
+            let media: Vec<(String,)> = sqlx::query_as(
+                r#"
+                SELECT file_path FROM media_assets WHERE workspace_id = ?
+                UNION
+                SELECT file_path FROM files WHERE workspace_id = ? AND file_path IS NOT NULL
+                "#,
+            )
+            .bind(workspace_id)
+            .bind(workspace_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            paths.extend(media.into_iter().map(|(path,)| path));
+
+            // 文件、文件夹、媒体记录和关联由外键级联删除
+            sqlx::query("DELETE FROM workspaces WHERE id = ?")
+                .bind(workspace_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let sql = format!(
+            "DELETE FROM files WHERE deleted_at IS NOT NULL{} RETURNING file_path",
+            cutoff_sql
+        );
+        let mut q = sqlx::query_as(&sql);
+        if let Some(before) = before {
+            q = q.bind(before);
+        }
+        let files: Vec<(Option<String>,)> = q.fetch_all(&mut *tx).await?;
+        paths.extend(files.iter().filter_map(|(path,)| path.clone()));
+
+        tx.commit().await?;
+
+        remove_unreferenced_files(&self.pool, &self.media_dir, paths).await?;
+
+        Ok(TrashPurgeResult {
+            workspaces: workspace_ids.len() as i64,
+            files: files.len() as i64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::{CreateFile, CreateWorkspace};
+    use crate::services::file::FileService;
+    use crate::services::media::MediaService;
+    use crate::services::workspace::WorkspaceService;
+    use std::fs;
+    use std::path::Path;
+    use uuid::Uuid;
+
+    async fn workspace(pool: &Pool<Sqlite>, name: &str) -> Workspace {
+        WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: name.to_string(),
+                description: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    async fn file(pool: &Pool<Sqlite>, workspace_id: &str, title: &str) -> File {
+        file_at(pool, workspace_id, title, None).await
+    }
+
+    async fn file_at(
+        pool: &Pool<Sqlite>,
+        workspace_id: &str,
+        title: &str,
+        file_path: Option<&Path>,
+    ) -> File {
+        FileService::new(pool.clone())
+            .create(CreateFile {
+                workspace_id: workspace_id.to_string(),
+                file_type: if file_path.is_some() {
+                    "pdf"
+                } else {
+                    "document"
+                }
+                .to_string(),
+                title: title.to_string(),
+                content: file_path.is_none().then(|| format!("<p>{}</p>", title)),
+                file_path: file_path.map(|path| path.to_string_lossy().to_string()),
+                file_size: None,
+                mime_type: None,
+                folder_id: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    fn is_trashed(file: &File) -> bool {
+        file.deleted_at.is_some()
+    }
+
+    async fn reload(pool: &Pool<Sqlite>, id: &str) -> File {
+        FileService::new(pool.clone())
+            .get(id)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    fn days_ago(days: i64) -> String {
+        (Utc::now() - Duration::days(days)).to_rfc3339()
+    }
+
+    /// 把回收站中文件的删除时间改为 `days` 天前
+    async fn age_file(pool: &Pool<Sqlite>, id: &str, days: i64) {
+        sqlx::query("UPDATE files SET deleted_at = ? WHERE id = ?")
+            .bind(days_ago(days))
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    /// 把回收站中的工作空间和随它一起删除的文件的删除时间改为 `days` 天前
+    async fn age_workspace(pool: &Pool<Sqlite>, id: &str, days: i64) {
+        let deleted_at = days_ago(days);
+        for sql in [
+            "UPDATE files SET deleted_at = ? WHERE workspace_id = ? AND deleted_at IS NOT NULL",
+            "UPDATE workspaces SET deleted_at = ? WHERE id = ?",
+        ] {
+            sqlx::query(sql)
+                .bind(&deleted_at)
+                .bind(id)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn files_in_a_trashed_workspace_cannot_be_restored_alone() {
+        let pool = memory_pool().await;
+        let trash = TrashService::new(pool.clone(), std::env::temp_dir());
+        let workspace = workspace(&pool, "Notes").await;
+        let doc = file(&pool, &workspace.id, "Doc").await;
+
+        WorkspaceService::new(pool.clone())
+            .delete(&workspace.id)
+            .await
+            .unwrap();
+        let result = trash.restore_file(&doc.id).await;
+        assert!(
+            matches!(result, Err(AppError::Validation(_))),
+            "{:?}",
+            result
+        );
+        assert!(is_trashed(&reload(&pool, &doc.id).await));
+
+        let result = trash.restore_file("missing").await;
+        assert!(matches!(result, Err(AppError::NotFound(_))), "{:?}", result);
+
+        trash.restore_workspace(&workspace.id).await.unwrap();
+        assert!(!is_trashed(&reload(&pool, &doc.id).await));
+    }
+
+    #[tokio::test]
+    async fn restoring_a_workspace_keeps_files_deleted_before_it() {
+        let pool = memory_pool().await;
+        let trash = TrashService::new(pool.clone(), std::env::temp_dir());
+        let files = FileService::new(pool.clone());
+        let workspace = workspace(&pool, "Notes").await;
+        let earlier = file(&pool, &workspace.id, "Deleted earlier").await;
+        let kept = file(&pool, &workspace.id, "Deleted with workspace").await;
+
+        files.delete(&earlier.id).await.unwrap();
+        WorkspaceService::new(pool.clone())
+            .delete(&workspace.id)
+            .await
+            .unwrap();
+
+        // 随工作空间删除的文件只计入工作空间
+        let contents = trash.list().await.unwrap();
+        assert_eq!(contents.workspaces.len(), 1);
+        assert_eq!(contents.workspaces[0].file_count, 1);
+        assert!(contents.files.is_empty());
+
+        let restored = trash.restore_workspace(&workspace.id).await.unwrap();
+        assert_eq!(restored.deleted_at, None);
+        assert!(!is_trashed(&reload(&pool, &kept.id).await));
+        assert!(is_trashed(&reload(&pool, &earlier.id).await));
+
+        let contents = trash.list().await.unwrap();
+        let titles: Vec<&str> = contents.files.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, ["Deleted earlier"]);
+
+        trash.restore_file(&earlier.id).await.unwrap();
+        assert!(!is_trashed(&reload(&pool, &earlier.id).await));
+    }
+
+    #[tokio::test]
+    async fn purge_honors_cutoff_and_removes_unreferenced_media() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("trash-{}", Uuid::new_v4()));
+        let media_dir = temp.join("media");
+        fs::create_dir_all(&media_dir).unwrap();
+        let trash = TrashService::new(pool.clone(), media_dir.clone());
+        let media = MediaService::new(pool.clone(), media_dir.clone());
+        let workspaces = WorkspaceService::new(pool.clone());
+        let files = FileService::new(pool.clone());
+
+        // 过期的工作空间：独有的媒体和与其他工作空间共用的媒体
+        let expired = workspace(&pool, "Expired").await;
+        let only = media
+            .store(
+                &expired.id,
+                "only.png".to_string(),
+                "image/png".to_string(),
+                b"only",
+            )
+            .await
+            .unwrap();
+        let shared = media
+            .store(
+                &expired.id,
+                "shared.png".to_string(),
+                "image/png".to_string(),
+                b"shared",
+            )
+            .await
+            .unwrap();
+        file(&pool, &expired.id, "Old note").await;
+        workspaces.delete(&expired.id).await.unwrap();
+        age_workspace(&pool, &expired.id, 40).await;
+
+        let live = workspace(&pool, "Live").await;
+        let copy = media
+            .store(
+                &live.id,
+                "copy.png".to_string(),
+                "image/png".to_string(),
+                b"shared",
+            )
+            .await
+            .unwrap();
+        assert_eq!(copy.file_path, shared.file_path);
+
+        // 过期和未过期的单个文件，内容保存在媒体目录中
+        let old_path = media_dir.join("old.pdf");
+        let recent_path = media_dir.join("recent.pdf");
+        fs::write(&old_path, b"old").unwrap();
+        fs::write(&recent_path, b"recent").unwrap();
+        let old = file_at(&pool, &live.id, "Old", Some(&old_path)).await;
+        let recent = file_at(&pool, &live.id, "Recent", Some(&recent_path)).await;
+        files.delete(&old.id).await.unwrap();
+        files.delete(&recent.id).await.unwrap();
+        age_file(&pool, &old.id, 40).await;
+        age_file(&pool, &recent.id, 10).await;
+
+        // 保留天数为 0 时不处理
+        trash
+            .set_config(TrashConfig { retention_days: 0 })
+            .await
+            .unwrap();
+        assert_eq!(trash.purge_expired().await.unwrap().files, 0);
+
+        trash
+            .set_config(TrashConfig { retention_days: 30 })
+            .await
+            .unwrap();
+        let result = trash.purge_expired().await.unwrap();
+        assert_eq!((result.workspaces, result.files), (1, 1));
+
+        let remaining: Vec<(String,)> = sqlx::query_as("SELECT name FROM workspaces")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, [("Live".to_string(),)]);
+        assert!(files.get(&old.id).await.unwrap().is_none());
+        assert!(is_trashed(&reload(&pool, &recent.id).await));
+
+        assert!(!Path::new(&only.file_path).exists());
+        assert!(Path::new(&shared.file_path).exists());
+        assert!(!old_path.exists());
+        assert!(recent_path.exists());
+
+        // 清空回收站不看保留天数
+        let result = trash.empty().await.unwrap();
+        assert_eq!((result.workspaces, result.files), (0, 1));
+        assert!(!recent_path.exists());
+        assert!(Path::new(&copy.file_path).exists());
+
+        fs::remove_dir_all(&temp).unwrap();
+    }
+}
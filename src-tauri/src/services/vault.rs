@@ -0,0 +1,859 @@
+use super::blocking;
+use super::file::update_document_stats;
+use super::media::{MediaService, MEDIA_SCHEME};
+use super::search::index_file;
+use super::tag::find_or_create;
+use crate::error::{AppError, Result};
+use crate::markdown::{
+    markdown_image_sources, markdown_to_html_with, split_front_matter, LinkResolver,
+};
+use crate::models::{
+    ArchiveProgress, ArchiveStage, VaultImportIssue, VaultImportReport, VaultImportedFile,
+};
+use crate::sanitize::sanitize_html;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/// 文档之间链接的前缀（`doc://<file_id>`）
+pub const DOC_SCHEME: &str = "doc://";
+
+/// Obsidian / Logseq 风格的 Markdown 笔记库导入
+///
+/// 子目录映射为标签（如 `projects/web`），front matter 中的 `tags` 写入标签表；
+/// 笔记引用的图片复制到媒体目录并关联到文档，`[[wiki link]]` 改写为 `doc://<file_id>`。
+pub struct VaultService {
+    pool: Pool<Sqlite>,
+    media: MediaService,
+}
+
+struct Note {
+    path: PathBuf,
+    relative: String,
+    id: String,
+    markdown: String,
+    created_at: String,
+    updated_at: String,
+    images: Vec<(String, PathBuf, &'static str)>, // 引用的图片：地址、笔记库中的文件和 MIME 类型
+}
+
+/// 遍历笔记库的结果
+struct Scan {
+    root: PathBuf,
+    notes: Vec<Note>,
+    attachments: Vec<PathBuf>,
+    unreadable: Vec<VaultImportIssue>, // 读取失败的笔记
+}
+
+impl VaultService {
+    pub fn new(pool: Pool<Sqlite>, media_dir: PathBuf) -> Self {
+        Self {
+            media: MediaService::new(pool.clone(), media_dir),
+            pool,
+        }
+    }
+
+    /// 导入 `root` 下的所有笔记，单个笔记失败时记录原因并继续
+    ///
+    /// 失败的笔记不会留下文档或只被它用到的媒体，其他笔记中指向它的链接只保留文字。
+    pub async fn import_vault(
+        &self,
+        workspace_id: &str,
+        root: &Path,
+        mut on_progress: impl FnMut(ArchiveProgress),
+    ) -> Result<VaultImportReport> {
+        let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM workspaces WHERE id = ?")
+            .bind(workspace_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(AppError::NotFound(format!(
+                "Workspace not found: {}",
+                workspace_id
+            )));
+        }
+
+        let root = root.to_path_buf();
+        let Scan {
+            root,
+            notes,
+            attachments,
+            unreadable,
+        } = blocking(move || scan_vault(&root)).await?;
+
+        let mut report = VaultImportReport {
+            imported: Vec::new(),
+            skipped: Vec::new(),
+            failed: unreadable,
+            media: 0,
+        };
+
+        // wiki link 按相对路径或文件名查找笔记，不区分大小写；重名时取路径排序靠前的
+        let mut note_ids = HashMap::new();
+        for note in &notes {
+            let key = note_key(&note.relative);
+            let name = key.rsplit('/').next().unwrap_or(&key).to_string();
+            note_ids.entry(key).or_insert_with(|| note.id.clone());
+            note_ids.entry(name).or_insert_with(|| note.id.clone());
+        }
+
+        // 导入前工作空间中已有的媒体，笔记失败时不会被删除
+        let existing_media: HashSet<String> = self
+            .media
+            .list_by_workspace(workspace_id)
+            .await?
+            .into_iter()
+            .map(|asset| asset.id)
+            .collect();
+
+        let mut stored = HashMap::new();
+        let mut imported = Vec::new();
+        let mut failed_ids = HashSet::new();
+        let total = notes.len() as i64;
+
+        for (index, note) in notes.iter().enumerate() {
+            let links = VaultLinks {
+                root: &root,
+                note_dir: note.path.parent().unwrap_or(&root),
+                note_ids: &note_ids,
+            };
+
+            let mut new_media = Vec::new();
+            match self
+                .import_note(workspace_id, note, &links, &mut stored, &mut new_media)
+                .await
+            {
+                Ok(imported_note) => {
+                    report.imported.push(VaultImportedFile {
+                        path: note.relative.clone(),
+                        file_id: note.id.clone(),
+                        title: imported_note.title.clone(),
+                    });
+                    imported.push((note, imported_note));
+                }
+                Err(e) => {
+                    // 文档没有写入，删除只被这篇笔记用到的新媒体
+                    for path in new_media {
+                        if let Some(media_id) = stored.remove(&path) {
+                            if !existing_media.contains(&media_id) {
+                                self.media.delete(&media_id).await?;
+                            }
+                        }
+                    }
+                    failed_ids.insert(note.id.clone());
+                    report.failed.push(VaultImportIssue {
+                        path: note.relative.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+
+            on_progress(ArchiveProgress {
+                stage: ArchiveStage::Documents,
+                completed: index as i64 + 1,
+                total,
+            });
+        }
+
+        // 已导入的笔记中指向失败笔记的链接改为只保留文字
+        if !failed_ids.is_empty() {
+            note_ids.retain(|_, id| !failed_ids.contains(id));
+            for (note, imported_note) in &imported {
+                let dangling = failed_ids.iter().any(|id| {
+                    imported_note
+                        .content
+                        .contains(&format!("{}{}", DOC_SCHEME, id))
+                });
+                if !dangling {
+                    continue;
+                }
+
+                let links = VaultLinks {
+                    root: &root,
+                    note_dir: note.path.parent().unwrap_or(&root),
+                    note_ids: &note_ids,
+                };
+                let (_, body) = split_front_matter(&note.markdown);
+                let content = render_note(body, &links, &imported_note.images);
+                self.update_content(&note.id, &content).await?;
+            }
+        }
+
+        // 没有被笔记引用的附件不导入
+        for path in &attachments {
+            if !stored.contains_key(path) {
+                report.skipped.push(VaultImportIssue {
+                    path: relative_path(&root, path),
+                    reason: "Not a Markdown note or an embedded image".to_string(),
+                });
+            }
+        }
+        report.media = stored.len() as i64;
+
+        on_progress(ArchiveProgress {
+            stage: ArchiveStage::Done,
+            completed: total,
+            total,
+        });
+
+        Ok(report)
+    }
+
+    /// 在一个事务中导入单个笔记
+    ///
+    /// `stored` 记录已复制的图片，多个笔记引用同一图片时只复制一次；这篇笔记新复制的图片
+    /// 追加到 `new_media`，导入失败时由调用方删除。
+    async fn import_note(
+        &self,
+        workspace_id: &str,
+        note: &Note,
+        links: &VaultLinks<'_>,
+        stored: &mut HashMap<PathBuf, String>,
+        new_media: &mut Vec<PathBuf>,
+    ) -> Result<ImportedNote> {
+        let (front_matter, body) = split_front_matter(&note.markdown);
+
+        let title = front_matter.title.clone().unwrap_or_else(|| {
+            note.path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        let mut images = HashMap::new();
+        for (src, path, mime_type) in &note.images {
+            let media_id = match stored.get(path) {
+                Some(id) => id.clone(),
+                None => {
+                    let bytes = blocking({
+                        let path = path.clone();
+                        move || Ok(fs::read(path)?)
+                    })
+                    .await?;
+                    let file_name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let asset = self
+                        .media
+                        .store(workspace_id, file_name, mime_type.to_string(), &bytes)
+                        .await?;
+                    stored.insert(path.clone(), asset.id.clone());
+                    new_media.push(path.clone());
+                    asset.id
+                }
+            };
+            images.insert(src.clone(), media_id);
+        }
+
+        let content = render_note(body, links, &images);
+
+        // 子目录作为标签，放在 front matter 的标签之前
+        let mut tags = front_matter.tags;
+        if let Some((folder, _)) = note.relative.rsplit_once('/') {
+            tags.insert(0, folder.to_string());
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO files (id, workspace_id, file_type, title, content, created_at, updated_at)
+            VALUES (?, ?, 'document', ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&note.id)
+        .bind(workspace_id)
+        .bind(&title)
+        .bind(&content)
+        .bind(&note.created_at)
+        .bind(&note.updated_at)
+        .execute(&mut *tx)
+        .await?;
+        update_document_stats(&mut tx, &note.id).await?;
+
+        let now = Utc::now().to_rfc3339();
+        for tag in &tags {
+            let tag_id = find_or_create(&mut tx, tag, None).await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO file_tags (file_id, tag_id, created_at) VALUES (?, ?, ?)",
+            )
+            .bind(&note.id)
+            .bind(&tag_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for media_id in images.values() {
+            sqlx::query(
+                "INSERT OR IGNORE INTO file_media (file_id, media_id, created_at) VALUES (?, ?, ?)",
+            )
+            .bind(&note.id)
+            .bind(media_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        index_file(&mut tx, &note.id).await?;
+
+        tx.commit().await?;
+
+        Ok(ImportedNote {
+            title,
+            content,
+            images,
+        })
+    }
+
+    /// 替换已导入文档的内容并刷新统计信息和全文索引，不改变更新时间
+    async fn update_content(&self, file_id: &str, content: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE files SET content = ? WHERE id = ?")
+            .bind(content)
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        update_document_stats(&mut tx, file_id).await?;
+        index_file(&mut tx, file_id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+/// 已导入的笔记：标题、转换后的内容和图片地址到媒体 id 的映射
+struct ImportedNote {
+    title: String,
+    content: String,
+    images: HashMap<String, String>,
+}
+
+/// 把笔记正文转换为清理后的 HTML
+fn render_note(body: &str, links: &VaultLinks<'_>, images: &HashMap<String, String>) -> String {
+    sanitize_html(&markdown_to_html_with(body, &NoteLinks { links, images }))
+}
+
+/// 笔记库中链接的解析
+struct VaultLinks<'a> {
+    root: &'a Path,
+    note_dir: &'a Path,
+    note_ids: &'a HashMap<String, String>,
+}
+
+impl VaultLinks<'_> {
+    fn note_url(&self, key: &str) -> Option<String> {
+        let name = key.rsplit('/').next().unwrap_or(key);
+        self.note_ids
+            .get(key)
+            .or_else(|| self.note_ids.get(name))
+            .map(|id| format!("{}{}", DOC_SCHEME, id))
+    }
+}
+
+/// 单个笔记转换时的链接改写，`images` 为图片地址到媒体 id 的映射
+struct NoteLinks<'a> {
+    links: &'a VaultLinks<'a>,
+    images: &'a HashMap<String, String>,
+}
+
+impl LinkResolver for NoteLinks<'_> {
+    fn image(&self, src: &str) -> Option<String> {
+        self.images
+            .get(src)
+            .map(|id| format!("{}{}", MEDIA_SCHEME, id))
+    }
+
+    /// 指向笔记库中其他 `.md` 文件的相对链接
+    fn link(&self, href: &str) -> Option<String> {
+        if has_scheme(href) || href.starts_with('#') {
+            return None;
+        }
+
+        let path = percent_decode(href.split('#').next().unwrap_or_default());
+        if !is_markdown(Path::new(&path)) {
+            return None;
+        }
+
+        let dir = self.links.note_dir.strip_prefix(self.links.root).ok()?;
+        let relative = normalize(&dir.join(path))?;
+        self.links
+            .note_ids
+            .get(&note_key(&relative))
+            .map(|id| format!("{}{}", DOC_SCHEME, id))
+    }
+
+    /// `[[note]]`、`[[folder/note]]`、`[[note#heading]]`
+    fn wiki_link(&self, target: &str) -> Option<String> {
+        let target = target.split('#').next().unwrap_or_default().trim();
+        if target.is_empty() {
+            return None;
+        }
+
+        self.links.note_url(&note_key(&target.replace('\\', "/")))
+    }
+}
+
+/// 收集并读取笔记库中的文件，解析笔记引用的图片（在阻塞线程池中调用）
+///
+/// 读取失败的笔记不参与链接解析，指向它的链接只保留文字。
+fn scan_vault(root: &Path) -> Result<Scan> {
+    if !root.is_dir() {
+        return Err(AppError::Validation(format!(
+            "Not a directory: {}",
+            root.display()
+        )));
+    }
+    let root = root.canonicalize()?;
+
+    let mut paths = Vec::new();
+    collect_files(&root, &root, &mut paths)?;
+
+    let (note_paths, attachments): (Vec<PathBuf>, Vec<PathBuf>) =
+        paths.into_iter().partition(|path| is_markdown(path));
+
+    // `![[image.png]]` 只写文件名，按文件名在整个笔记库中查找
+    let mut attachment_names = HashMap::new();
+    for path in &attachments {
+        if let Some(name) = path.file_name() {
+            let name = name.to_string_lossy().to_lowercase();
+            attachment_names.entry(name).or_insert_with(|| path.clone());
+        }
+    }
+
+    let mut notes = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in note_paths {
+        let relative = relative_path(&root, &path);
+        let markdown = match fs::read_to_string(&path) {
+            Ok(markdown) => markdown,
+            Err(e) => {
+                unreadable.push(VaultImportIssue {
+                    path: relative,
+                    reason: AppError::from(e).to_string(),
+                });
+                continue;
+            }
+        };
+
+        let note_dir = path.parent().unwrap_or(&root);
+        let (_, body) = split_front_matter(&markdown);
+        let images = markdown_image_sources(body)
+            .into_iter()
+            .filter_map(|src| {
+                let (path, mime_type) = find_attachment(&root, note_dir, &attachment_names, &src)?;
+                Some((src, path, mime_type))
+            })
+            .collect();
+        let (created_at, updated_at) = file_times(&path);
+
+        notes.push(Note {
+            path,
+            relative,
+            id: Uuid::new_v4().to_string(),
+            markdown,
+            created_at,
+            updated_at,
+            images,
+        });
+    }
+
+    Ok(Scan {
+        root,
+        notes,
+        attachments,
+        unreadable,
+    })
+}
+
+/// 图片地址对应的笔记库中的文件及其 MIME 类型；先按笔记所在目录、再按根目录、最后按文件名查找
+fn find_attachment(
+    root: &Path,
+    note_dir: &Path,
+    attachment_names: &HashMap<String, PathBuf>,
+    src: &str,
+) -> Option<(PathBuf, &'static str)> {
+    if has_scheme(src) {
+        return None;
+    }
+
+    let decoded = percent_decode(src);
+    let candidates = [
+        note_dir.join(&decoded),
+        root.join(decoded.trim_start_matches('/')),
+    ];
+
+    let path = candidates
+        .iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.canonicalize().ok())
+        .find(|path| path.starts_with(root))
+        .or_else(|| {
+            let name = Path::new(&decoded).file_name()?;
+            let name = name.to_string_lossy().to_lowercase();
+            attachment_names.get(&name).cloned()
+        })?;
+
+    image_mime_type(&path).map(|mime_type| (path, mime_type))
+}
+
+/// 递归收集笔记库中的文件（按路径排序），跳过隐藏文件和 Logseq 的配置目录
+fn collect_files(root: &Path, dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || (dir == root && name == "logseq") {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(root, &entry.path(), paths)?;
+        } else if file_type.is_file() {
+            paths.push(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"))
+}
+
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let mime_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        _ => return None,
+    };
+    Some(mime_type)
+}
+
+/// 相对根目录的路径，使用 `/` 分隔
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 笔记的查找键：去掉 `.md` 扩展名并转为小写
+fn note_key(relative: &str) -> String {
+    let lower = relative.to_lowercase();
+    lower
+        .strip_suffix(".md")
+        .or_else(|| lower.strip_suffix(".markdown"))
+        .unwrap_or(&lower)
+        .to_string()
+}
+
+/// 处理路径中的 `.` 和 `..`，超出根目录或为绝对路径时返回 None
+fn normalize(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// `https://`、`data:` 等带协议的地址（排除 Windows 盘符 `C:`）
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// 笔记文件的创建和修改时间，无法读取时使用当前时间
+fn file_times(path: &Path) -> (String, String) {
+    let metadata = fs::metadata(path).ok();
+    let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+    let created = metadata
+        .as_ref()
+        .and_then(|m| m.created().ok())
+        .or(modified);
+
+    let format = |time: Option<std::time::SystemTime>| {
+        time.map(DateTime::<Utc>::from)
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339()
+    };
+
+    (format(created), format(modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_pool;
+    use crate::models::CreateWorkspace;
+    use crate::services::file::FileService;
+    use crate::services::workspace::WorkspaceService;
+
+    fn write(dir: &Path, name: &str, content: &[u8]) {
+        fs::write(dir.join(name), content).unwrap();
+    }
+
+    fn media_files(media_dir: &Path) -> usize {
+        fs::read_dir(media_dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_file())
+            .count()
+    }
+
+    #[tokio::test]
+    async fn failed_notes_leave_no_media_or_dangling_links() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("vault-{}", Uuid::new_v4()));
+        let vault = temp.join("vault");
+        let media_dir = temp.join("media");
+        fs::create_dir_all(&vault).unwrap();
+        fs::create_dir_all(&media_dir).unwrap();
+
+        write(&vault, "Broken.md", b"![[only-broken.png]] ![[shared.png]]");
+        write(
+            &vault,
+            "Home.md",
+            b"See [[Broken]], [[bad]] and [[Other]].\n\n![[shared.png]]",
+        );
+        write(&vault, "Other.md", b"Back to [[Broken]] or [[Home]]");
+        write(&vault, "bad.md", b"invalid \xff\xfe utf-8");
+        write(&vault, "only-broken.png", b"only used by the broken note");
+        write(&vault, "shared.png", b"shared image");
+
+        // 标题为 Broken 的笔记写入数据库时失败
+        sqlx::query(
+            r#"
+            CREATE TRIGGER fail_broken_note BEFORE INSERT ON files WHEN NEW.title = 'Broken'
+            BEGIN SELECT RAISE(ABORT, 'broken note'); END
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Vault".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+        let service = VaultService::new(pool.clone(), media_dir.clone());
+        let report = service
+            .import_vault(&workspace.id, &vault, |_| {})
+            .await
+            .unwrap();
+
+        let imported: Vec<&str> = report.imported.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(imported, ["Home.md", "Other.md"]);
+        let mut failed: Vec<&str> = report.failed.iter().map(|f| f.path.as_str()).collect();
+        failed.sort();
+        assert_eq!(failed, ["Broken.md", "bad.md"]);
+        assert_eq!(report.media, 1);
+
+        // 只剩下 Home 引用的图片
+        let assets: Vec<(String,)> =
+            sqlx::query_as("SELECT file_name FROM media_assets WHERE workspace_id = ?")
+                .bind(&workspace.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(assets, [("shared.png".to_string(),)]);
+        assert_eq!(media_files(&media_dir), 1);
+
+        let content = |file_id: String| {
+            let pool = pool.clone();
+            async move {
+                let (content,): (String,) =
+                    sqlx::query_as("SELECT content FROM files WHERE id = ?")
+                        .bind(file_id)
+                        .fetch_one(&pool)
+                        .await
+                        .unwrap();
+                content
+            }
+        };
+        let home_id = report.imported[0].file_id.clone();
+        let other_id = report.imported[1].file_id.clone();
+
+        // 指向失败笔记的链接只保留文字，指向已导入笔记的链接保留
+        let home = content(home_id.clone()).await;
+        assert_eq!(home.matches(DOC_SCHEME).count(), 1);
+        assert!(home.contains(&format!("{}{}", DOC_SCHEME, other_id)));
+        assert!(home.contains("Broken") && home.contains("bad"));
+
+        let other = content(other_id).await;
+        assert_eq!(other.matches(DOC_SCHEME).count(), 1);
+        assert!(other.contains(&format!("{}{}", DOC_SCHEME, home_id)));
+        assert!(other.contains("Back to Broken"));
+
+        fs::remove_dir_all(temp).unwrap();
+    }
+
+    #[tokio::test]
+    async fn tags_and_links_are_resolved() {
+        let pool = memory_pool().await;
+        let temp = std::env::temp_dir().join(format!("vault-{}", Uuid::new_v4()));
+        let vault = temp.join("vault");
+        let nested = vault.join("projects").join("web");
+        fs::create_dir_all(nested.join("images")).unwrap();
+        fs::create_dir_all(vault.join("assets")).unwrap();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        fs::create_dir_all(temp.join("media")).unwrap();
+
+        write(
+            &nested,
+            "Plan.md",
+            b"---\ntitle: Web plan\ntags: [rust, ideas]\n---\n\
+              Back to [[Home]] or [[projects/web/Plan#Goals|this plan]] or [home](../../Home.md).\n\n\
+              ![[logo.png]]\n\n![diagram](images/pic.jpg)\n",
+        );
+        write(
+            &vault,
+            "Home.md",
+            b"---\ntags:\n  - ideas\n---\nSee [[plan]] and [[Missing]].\n\n![[logo.png]]\n",
+        );
+        write(&vault.join("assets"), "logo.png", b"logo bytes");
+        write(&nested.join("images"), "pic.jpg", b"picture bytes");
+        write(&vault, "notes.txt", b"not a note");
+        write(&vault.join(".obsidian"), "app.json", b"{}");
+
+        let workspace = WorkspaceService::new(pool.clone())
+            .create(CreateWorkspace {
+                name: "Vault".to_string(),
+                description: None,
+            })
+            .await
+            .unwrap();
+        let service = VaultService::new(pool.clone(), temp.join("media"));
+        let report = service
+            .import_vault(&workspace.id, &vault, |_| {})
+            .await
+            .unwrap();
+
+        let imported: Vec<(&str, &str)> = report
+            .imported
+            .iter()
+            .map(|f| (f.path.as_str(), f.title.as_str()))
+            .collect();
+        assert_eq!(
+            imported,
+            [("Home.md", "Home"), ("projects/web/Plan.md", "Web plan")]
+        );
+        let skipped: Vec<&str> = report.skipped.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(skipped, ["notes.txt"]);
+        assert!(report.failed.is_empty());
+        // 两篇笔记引用同一张图片只导入一次
+        assert_eq!(report.media, 2);
+
+        let (home_id, plan_id) = (&report.imported[0].file_id, &report.imported[1].file_id);
+        let files = FileService::new(pool.clone());
+        let tag_names = |file_id: &str| {
+            let files = &files;
+            let file_id = file_id.to_string();
+            async move {
+                let mut names: Vec<String> = files
+                    .get_tags(&file_id)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.name)
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+        // 子目录映射为标签，与 front matter 中的标签合并
+        assert_eq!(tag_names(plan_id).await, ["ideas", "projects/web", "rust"]);
+        assert_eq!(tag_names(home_id).await, ["ideas"]);
+
+        let media: HashMap<String, String> =
+            sqlx::query_as("SELECT file_name, id FROM media_assets WHERE workspace_id = ?")
+                .bind(&workspace.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .collect();
+        let media_url = |name: &str| format!("{}{}", MEDIA_SCHEME, media[name]);
+
+        let plan = files.get(plan_id).await.unwrap().unwrap().content.unwrap();
+        let home_url = format!("{}{}", DOC_SCHEME, home_id);
+        let plan_url = format!("{}{}", DOC_SCHEME, plan_id);
+        assert_eq!(plan.matches(&home_url).count(), 2, "{}", plan);
+        assert!(plan.contains(&plan_url), "{}", plan);
+        assert!(plan.contains(&media_url("logo.png")), "{}", plan);
+        assert!(plan.contains(&media_url("pic.jpg")), "{}", plan);
+
+        // 按文件名查找不区分大小写，找不到的链接只保留文字
+        let home = files.get(home_id).await.unwrap().unwrap().content.unwrap();
+        assert!(home.contains(&plan_url), "{}", home);
+        assert!(home.contains(&media_url("logo.png")), "{}", home);
+        assert_eq!(home.matches(DOC_SCHEME).count(), 1);
+        assert!(home.contains("Missing"));
+
+        let linked: Vec<(String,)> =
+            sqlx::query_as("SELECT media_id FROM file_media WHERE file_id = ? ORDER BY media_id")
+                .bind(plan_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(linked.len(), 2);
+
+        fs::remove_dir_all(temp).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
+use crate::error::Result;
 use crate::models::{CreateWorkspace, UpdateWorkspace, Workspace};
-use anyhow::Result;
 use chrono::Utc;
 use sqlx::{Pool, Sqlite};
 use uuid::Uuid;
@@ -45,10 +45,11 @@ impl WorkspaceService {
     }
 
     pub async fn list(&self) -> Result<Vec<Workspace>> {
-        let workspaces =
-            sqlx::query_as::<_, Workspace>("SELECT * FROM workspaces ORDER BY updated_at DESC")
-                .fetch_all(&self.pool)
-                .await?;
+        let workspaces = sqlx::query_as::<_, Workspace>(
+            "SELECT * FROM workspaces WHERE deleted_at IS NULL ORDER BY updated_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
         Ok(workspaces)
     }
@@ -83,20 +84,49 @@ impl WorkspaceService {
         Ok(workspace)
     }
 
+    /// 移入回收站，其中未删除的文件使用相同的删除时间，恢复时一并恢复
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM workspaces WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        let result =
+            sqlx::query("UPDATE workspaces SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+                .bind(&now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            "UPDATE files SET deleted_at = ? WHERE workspace_id = ? AND deleted_at IS NULL",
+        )
+        .bind(&now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        // 回收站中的文件不参与全文索引
+        sqlx::query(
+            "DELETE FROM files_fts WHERE file_id IN (SELECT id FROM files WHERE workspace_id = ?)",
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
 
         Ok(())
     }
 
     pub async fn get_file_count(&self, workspace_id: &str) -> Result<i64> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM files WHERE workspace_id = ?")
-            .bind(workspace_id)
-            .fetch_one(&self.pool)
-            .await?;
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM files WHERE workspace_id = ? AND deleted_at IS NULL",
+        )
+        .bind(workspace_id)
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(count.0)
     }
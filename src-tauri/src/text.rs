@@ -0,0 +1,260 @@
+//! 文本处理工具：中日韩字符识别、全文索引分词、HTML 纯文本提取与文档统计
+
+use crate::models::OutlineHeading;
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+
+/// 分词时插入的分隔符（HAIR SPACE）
+///
+/// FTS5 的 unicode61 分词器把它当作空白处理，而普通文本中几乎不会出现，
+/// 因此从索引读出的文本（摘录、高亮）可以无损地去掉它。
+pub const SEGMENT_SEPARATOR: char = '\u{200A}';
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}' // CJK 扩展 A
+        | '\u{4E00}'..='\u{9FFF}' // CJK 统一表意文字
+        | '\u{AC00}'..='\u{D7AF}' // 韩文音节
+        | '\u{F900}'..='\u{FAFF}' // CJK 兼容表意文字
+        | '\u{20000}'..='\u{2FA1F}' // CJK 扩展 B 及之后
+    )
+}
+
+/// 为全文索引切分文本
+///
+/// unicode61 分词器会把连续的中文当作一个词，导致句子中间的词无法命中。
+/// 这里在每个中日韩字符两侧插入分隔符，使其成为独立的词；
+/// 查询时同样切分，多字词即变为逐字相邻的短语匹配。
+pub fn segment(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 2);
+    let mut prev_cjk = false;
+
+    for c in text.chars() {
+        let cjk = is_cjk(c);
+        if (cjk || prev_cjk) && !out.is_empty() {
+            out.push(SEGMENT_SEPARATOR);
+        }
+        out.push(c);
+        prev_cjk = cjk;
+    }
+
+    out
+}
+
+/// 统计词数：中日韩字符每个字计一个词，其他文字按连续的字母数字计词
+pub fn count_words(text: &str) -> i64 {
+    let mut count = 0;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                count += 1;
+                in_word = true;
+            }
+        } else {
+            in_word = false;
+        }
+    }
+
+    count
+}
+
+/// 中文每分钟阅读字数
+const CJK_CHARS_PER_MINUTE: f64 = 300.0;
+/// 其他文字每分钟阅读词数
+const WORDS_PER_MINUTE: f64 = 200.0;
+
+/// 文档正文的纯文本与统计信息
+#[derive(Debug, Clone, Default)]
+pub struct DocumentStats {
+    pub plain: String,
+    pub char_count: i64,      // 字符数（不含空白）
+    pub word_count: i64,      // 词数（中日韩字符按字计）
+    pub paragraph_count: i64, // 非空段落数
+    pub reading_minutes: i64, // 预计阅读时间（分钟，向上取整）
+    pub outline: Vec<OutlineHeading>,
+}
+
+/// 从文档 HTML 计算纯文本和统计信息
+pub fn document_stats(html: &str) -> DocumentStats {
+    let fragment = Html::parse_fragment(html);
+    let plain = fragment_text(&fragment);
+
+    let mut paragraph_count = 0;
+    let mut outline = Vec::new();
+    for element in fragment
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let name = element.value().name();
+        if name == "p" {
+            if element.text().any(|text| !text.trim().is_empty()) {
+                paragraph_count += 1;
+            }
+        } else if let Some(level) = heading_level(name) {
+            let text = element.text().collect::<String>();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                outline.push(OutlineHeading { level, text });
+            }
+        }
+    }
+
+    let cjk_chars = plain.chars().filter(|c| is_cjk(*c)).count();
+    let word_count = count_words(&plain);
+    let other_words = word_count as usize - cjk_chars;
+    // 中文按字、其他文字按词分别估算
+    let reading_minutes =
+        cjk_chars as f64 / CJK_CHARS_PER_MINUTE + other_words as f64 / WORDS_PER_MINUTE;
+
+    DocumentStats {
+        char_count: plain.chars().filter(|c| !c.is_whitespace()).count() as i64,
+        word_count,
+        paragraph_count,
+        reading_minutes: reading_minutes.ceil() as i64,
+        outline,
+        plain,
+    }
+}
+
+fn heading_level(name: &str) -> Option<i64> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// 从 HTML 提取纯文本（用于 `content_plain`）
+///
+/// 与编辑器的 `getText()` 一致：块级元素之间空一行，`<br>` 换行，图片等没有文本的节点忽略。
+fn fragment_text(fragment: &Html) -> String {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+
+    collect_text(*fragment.root_element(), false, &mut blocks, &mut current);
+    flush_block(&mut current, &mut blocks);
+
+    blocks.join("\n\n")
+}
+
+fn collect_text(
+    node: NodeRef<Node>,
+    preformatted: bool,
+    blocks: &mut Vec<String>,
+    current: &mut String,
+) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) if preformatted => current.push_str(text),
+            Node::Text(text) => {
+                // 按 HTML 规则合并连续空白
+                for c in text.chars() {
+                    if c.is_whitespace() && c != '\u{a0}' {
+                        if !current.is_empty() && !current.ends_with([' ', '\n']) {
+                            current.push(' ');
+                        }
+                    } else {
+                        current.push(c);
+                    }
+                }
+            }
+            Node::Element(element) => match element.name() {
+                "br" => {
+                    let trimmed = current.trim_end_matches(' ').len();
+                    current.truncate(trimmed);
+                    current.push('\n');
+                }
+                "script" | "style" | "template" => {}
+                "p" | "div" | "section" | "article" | "header" | "footer" | "h1" | "h2" | "h3"
+                | "h4" | "h5" | "h6" | "blockquote" | "ul" | "ol" | "li" | "table" | "tr"
+                | "td" | "th" | "hr" | "figure" | "figcaption" => {
+                    flush_block(current, blocks);
+                    collect_text(child, preformatted, blocks, current);
+                    flush_block(current, blocks);
+                }
+                "pre" => {
+                    flush_block(current, blocks);
+                    collect_text(child, true, blocks, current);
+                    flush_block(current, blocks);
+                }
+                _ => collect_text(child, preformatted, blocks, current),
+            },
+            _ => {}
+        }
+    }
+}
+
+fn flush_block(current: &mut String, blocks: &mut Vec<String>) {
+    let text = current.trim_matches(' ').trim_end_matches('\n');
+    if !text.trim().is_empty() {
+        blocks.push(text.to_string());
+    }
+    current.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 用 `|` 表示分隔符，便于阅读
+    fn segmented(text: &str) -> String {
+        segment(text).replace(SEGMENT_SEPARATOR, "|")
+    }
+
+    #[test]
+    fn segment_leaves_latin_text_unchanged() {
+        assert_eq!(segment(""), "");
+        assert_eq!(segment("hello world"), "hello world");
+        assert_eq!(segment("café déjà-vu 42"), "café déjà-vu 42");
+    }
+
+    #[test]
+    fn segment_splits_cjk_characters() {
+        assert_eq!(segmented("中文"), "中|文");
+        assert_eq!(segmented("全文搜索"), "全|文|搜|索");
+        assert_eq!(segmented("ひらがなカタカナ"), "ひ|ら|が|な|カ|タ|カ|ナ");
+        assert_eq!(segmented("한국어"), "한|국|어");
+        assert_eq!(segmented("𠀀𠀁"), "𠀀|𠀁");
+    }
+
+    #[test]
+    fn segment_separates_cjk_from_adjacent_text() {
+        assert_eq!(segmented("Rust编程"), "Rust|编|程");
+        assert_eq!(segmented("学习Rust语言"), "学|习|Rust|语|言");
+        assert_eq!(segmented("版本2.0发布"), "版|本|2.0|发|布");
+        assert_eq!(segmented("中 文"), "中| |文");
+        assert_eq!(segmented("你好，世界"), "你|好|，|世|界");
+    }
+
+    #[test]
+    fn segment_does_not_add_leading_or_trailing_separators() {
+        let text = segment("中文");
+        assert!(!text.starts_with(SEGMENT_SEPARATOR));
+        assert!(!text.ends_with(SEGMENT_SEPARATOR));
+    }
+
+    #[test]
+    fn segmented_text_restores_by_removing_separators() {
+        let text = "混合 mixed 文本 text，标点。";
+        assert_eq!(segment(text).replace(SEGMENT_SEPARATOR, ""), text);
+    }
+
+    #[test]
+    fn count_words_counts_cjk_per_character() {
+        assert_eq!(count_words("hello world"), 2);
+        assert_eq!(count_words("中文搜索"), 4);
+        assert_eq!(count_words("学习Rust语言 2024"), 6);
+        assert_eq!(count_words(""), 0);
+    }
+}
@@ -93,9 +93,13 @@ function DocumentList() {
 
 // 设置面板组件
 function SettingsPanel({ onClose }: { onClose: () => void }) {
-  const { aiProviders, updateProvider, setDefaultProvider, defaultProviderId } = useSettingsStore()
+  const { aiProviders, loadProviders, updateProvider, setDefaultProvider } = useSettingsStore()
   const [showKey, setShowKey] = useState<string | null>(null)
 
+  useEffect(() => {
+    loadProviders()
+  }, [loadProviders])
+
   return (
     <div className="fixed inset-0 bg-black/40 backdrop-blur-sm flex items-center justify-center z-50 animate-fadeIn">
       <div className="bg-white/95 backdrop-blur-xl rounded-xl shadow-2xl w-full max-w-lg max-h-[70vh] overflow-hidden animate-slideIn">
@@ -125,12 +129,12 @@ function SettingsPanel({ onClose }: { onClose: () => void }) {
                   </label>
                   <button
                     onClick={() => setDefaultProvider(provider.id)}
-                    className={`text-[10px] px-2 py-0.5 rounded-full transition-all ${defaultProviderId === provider.id
+                    className={`text-[10px] px-2 py-0.5 rounded-full transition-all ${provider.is_default
                       ? 'bg-blue-500 text-white shadow-sm'
                       : 'bg-gray-200 text-gray-600 hover:bg-gray-300'
                       }`}
                   >
-                    {defaultProviderId === provider.id ? '默认' : '设为默认'}
+                    {provider.is_default ? '默认' : '设为默认'}
                   </button>
                 </div>
               </div>
@@ -141,10 +145,10 @@ function SettingsPanel({ onClose }: { onClose: () => void }) {
                   <div className="flex gap-1.5">
                     <input
                       type={showKey === provider.id ? 'text' : 'password'}
-                      value={provider.apiKey}
-                      onChange={(e) => updateProvider(provider.id, { apiKey: e.target.value })}
+                      defaultValue=""
+                      onBlur={(e) => e.target.value && updateProvider(provider.id, { api_key: e.target.value })}
                       className="flex-1 px-2.5 py-1.5 border border-gray-300/60 rounded-md text-xs bg-white/80 focus:outline-none focus:ring-1 focus:ring-blue-500"
-                      placeholder="输入 API Key"
+                      placeholder={provider.has_api_key ? '已保存' : '输入 API Key'}
                     />
                     <button
                       onClick={() => setShowKey(showKey === provider.id ? null : provider.id)}
@@ -159,8 +163,8 @@ function SettingsPanel({ onClose }: { onClose: () => void }) {
                   <label className="text-[11px] text-gray-500 mb-1 block">Base URL</label>
                   <input
                     type="text"
-                    value={provider.baseUrl}
-                    onChange={(e) => updateProvider(provider.id, { baseUrl: e.target.value })}
+                    defaultValue={provider.base_url}
+                    onBlur={(e) => updateProvider(provider.id, { base_url: e.target.value })}
                     className="w-full px-2.5 py-1.5 border border-gray-300/60 rounded-md text-xs bg-white/80 focus:outline-none focus:ring-1 focus:ring-blue-500"
                   />
                 </div>
@@ -169,8 +173,8 @@ function SettingsPanel({ onClose }: { onClose: () => void }) {
                   <label className="text-[11px] text-gray-500 mb-1 block">模型</label>
                   <input
                     type="text"
-                    value={provider.model}
-                    onChange={(e) => updateProvider(provider.id, { model: e.target.value })}
+                    defaultValue={provider.model}
+                    onBlur={(e) => updateProvider(provider.id, { model: e.target.value })}
                     className="w-full px-2.5 py-1.5 border border-gray-300/60 rounded-md text-xs bg-white/80 focus:outline-none focus:ring-1 focus:ring-blue-500"
                   />
                 </div>
@@ -196,20 +200,13 @@ function AIEditDialog({
   const [instruction, setInstruction] = useState('')
   const [result, setResult] = useState('')
   const [loading, setLoading] = useState(false)
-  const { getActiveProvider } = useSettingsStore()
 
   const quickPrompts = ['修正语法', '改写简洁', '翻译成英文', '扩展内容', '总结要点']
 
   const handleSubmit = async () => {
-    const provider = getActiveProvider()
-    if (!provider) {
-      alert('请先在设置中配置并启用 AI 提供商')
-      return
-    }
-
     setLoading(true)
     try {
-      const response = await callAI(provider, {
+      const response = await callAI({
         instruction,
         selectedText,
       })
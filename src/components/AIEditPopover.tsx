@@ -1,5 +1,5 @@
 import { useState, useEffect, useRef } from 'react'
-import { useSettingsStore } from '../store/settings'
+import { formatError } from '../services/database'
 import { callAI } from '../services/ai'
 
 export function AIEditPopover({
@@ -16,7 +16,6 @@ export function AIEditPopover({
     const [instruction, setInstruction] = useState('')
     const [result, setResult] = useState('')
     const [loading, setLoading] = useState(false)
-    const { getActiveProvider } = useSettingsStore()
     const popoverRef = useRef<HTMLDivElement>(null)
 
     const quickPrompts = ['修正语法', '改写简洁', '翻译成英文', '扩展内容', '总结要点']
@@ -29,26 +28,22 @@ export function AIEditPopover({
     }, [])
 
     const handleSubmit = async () => {
-        const provider = getActiveProvider()
-        if (!provider) {
-            alert('请先在设置中配置并启用 AI 提供商')
-            return
-        }
-
         if (!instruction.trim()) {
             alert('请输入编辑指令')
             return
         }
 
         setLoading(true)
+        setResult('')
         try {
-            const response = await callAI(provider, {
-                instruction,
-                selectedText,
-            })
+            // 未配置可用的提供商时后端返回 VALIDATION 错误
+            const response = await callAI(
+                { instruction, selectedText },
+                { onDelta: (content) => setResult((prev) => prev + content) }
+            )
             setResult(response)
         } catch (error) {
-            alert('AI 请求失败: ' + (error as Error).message)
+            alert('AI 请求失败: ' + formatError(error))
         } finally {
             setLoading(false)
         }
@@ -6,16 +6,17 @@ import EditorWindow from './pages/EditorWindow'
 import SettingsWindow from './pages/SettingsWindow'
 import { useSettingsStore, applyTheme } from './store/settings'
 import { listen } from '@tauri-apps/api/event'
-import type { AppearanceSettings } from './store/settings'
+import type { Settings } from './services/database'
 import './index.css'
 
 // 应用初始化组件
 function AppInit({ children }: { children: React.ReactNode }) {
-  const { appearance, setAppearance } = useSettingsStore()
+  const { appearance, setAppearance, syncSettings } = useSettingsStore()
 
   useEffect(() => {
-    // 应用保存的主题设置
+    // 应用本地缓存的主题设置，再从后端读取最新设置
     applyTheme(appearance)
+    syncSettings().catch((e) => console.error('Failed to load settings:', e))
 
     // 监听系统主题变化
     const mediaQuery = window.matchMedia('(prefers-color-scheme: dark)')
@@ -26,8 +27,8 @@ function AppInit({ children }: { children: React.ReactNode }) {
     }
     mediaQuery.addEventListener('change', handleSystemChange)
 
-    // 监听来自其他窗口的设置变更
-    const unlisten = listen<{ appearance: AppearanceSettings }>('settings-changed', (event) => {
+    // 监听后端广播的设置变更
+    const unlisten = listen<Settings>('settings-changed', (event) => {
       console.log('Received settings-changed event:', event.payload)
       const newAppearance = event.payload.appearance
       setAppearance(newAppearance, false)
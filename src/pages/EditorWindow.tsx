@@ -4,7 +4,7 @@ import { EditorContent, useEditor } from '@tiptap/react'
 import StarterKit from '@tiptap/starter-kit'
 import Placeholder from '@tiptap/extension-placeholder'
 import CharacterCount from '@tiptap/extension-character-count'
-import { fileAPI, type File } from '../services/database'
+import { fileAPI, formatError, type File } from '../services/database'
 import { SlashCommand } from '../extensions/SlashCommand'
 import { AIEditPopover } from '../components/AIEditPopover'
 import { MediaImage, handleImageUpload } from '../extensions/MediaImage'
@@ -280,7 +280,7 @@ export default function EditorWindow() {
                 }
             }
         } catch (error) {
-            alert('加载失败：' + error)
+            alert('加载失败：' + formatError(error))
         } finally {
             setLoading(false)
         }
@@ -291,9 +291,8 @@ export default function EditorWindow() {
 
         try {
             setSaving(true)
-            // 提取纯文本用于全文索引
-            const contentPlain = editor.getText()
-            await fileAPI.update(file.id, { content, content_plain: contentPlain })
+            // 纯文本由后端从清理后的 HTML 生成
+            await fileAPI.update(file.id, { content })
         } catch (error) {
             console.error('保存失败：', error)
         } finally {
@@ -308,7 +307,7 @@ export default function EditorWindow() {
             await fileAPI.update(file.id, { title })
             setFile({ ...file, title })
         } catch (error) {
-            alert('更新失败：' + error)
+            alert('更新失败：' + formatError(error))
         }
     }
 
@@ -354,7 +353,7 @@ export default function EditorWindow() {
                     })
             }
         } catch (error) {
-            alert('上传失败：' + error)
+            alert('上传失败：' + formatError(error))
         } finally {
             setUploading(false)
             // 清空 input 以便重复选择同一文件
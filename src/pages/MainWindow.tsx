@@ -4,11 +4,12 @@ import {
     fileAPI,
     searchAPI,
     windowAPI,
+    formatError,
     type Workspace,
     type File,
 } from '../services/database'
 import { invoke } from '@tauri-apps/api/core'
-import { migrateFromLocalStorage } from '../utils/migration'
+import { migrateFromLocalStorage, migrateProvidersToBackend } from '../utils/migration'
 import { WorkspaceDialog } from '../components/WorkspaceDialog'
 import { FileDialog } from '../components/FileDialog'
 import { WelcomeModal } from '../components/WelcomeModal'
@@ -31,6 +32,7 @@ export default function MainWindow() {
             if (migrated) {
                 alert('已成功从旧版本迁移数据！')
             }
+            await migrateProvidersToBackend()
             await loadWorkspaces()
             await loadAllRecentFiles()
         }
@@ -100,7 +102,7 @@ export default function MainWindow() {
             setCurrentWorkspace(ws)
             setShowWorkspaceDialog(false)
         } catch (error) {
-            alert('创建失败：' + error)
+            alert('创建失败：' + formatError(error))
         }
     }
 
@@ -119,7 +121,7 @@ export default function MainWindow() {
             setFiles([file, ...files])
             setShowFileDialog(false)
         } catch (error) {
-            alert('创建失败：' + error)
+            alert('创建失败：' + formatError(error))
         }
     }
 
@@ -127,7 +129,7 @@ export default function MainWindow() {
         try {
             await invoke('open_editor_window', { fileId: file.id })
         } catch (error) {
-            alert('打开失败：' + error)
+            alert('打开失败：' + formatError(error))
         }
     }
 
@@ -137,7 +139,7 @@ export default function MainWindow() {
             await fileAPI.delete(fileId)
             setFiles(files.filter((f) => f.id !== fileId))
         } catch (error) {
-            alert('删除失败：' + error)
+            alert('删除失败：' + formatError(error))
         }
     }
 
@@ -150,9 +152,10 @@ export default function MainWindow() {
                 workspace_id: currentWorkspace.id,
                 limit: 50,
             })
-            setFiles(results as any)
+            // 搜索结果只带摘录，用摘录作为卡片预览
+            setFiles(results.map((r) => ({ ...r, content: r.snippet })) as any)
         } catch (error) {
-            alert('搜索失败：' + error)
+            alert('搜索失败：' + formatError(error))
         } finally {
             setLoading(false)
         }
@@ -1,4 +1,5 @@
-import { useState } from 'react'
+import { useState, useEffect } from 'react'
+import { formatError, providerAPI, type AiProvider, type UpdateAiProvider } from '../services/database'
 import {
     useSettingsStore,
     applyTheme,
@@ -200,9 +201,24 @@ function AppearanceSettings() {
 // ============ AI 设置 ============
 
 function AISettings() {
-    const { aiProviders, updateProvider, setDefaultProvider, defaultProviderId, addProvider, removeProvider } =
-        useSettingsStore()
-    const [showKey, setShowKey] = useState<string | null>(null)
+    const { aiProviders, loadProviders, addProvider } = useSettingsStore()
+
+    useEffect(() => {
+        loadProviders().catch((e) => alert('加载 AI 提供商失败: ' + formatError(e)))
+    }, [loadProviders])
+
+    const handleAdd = async () => {
+        try {
+            await addProvider({
+                name: '自定义',
+                base_url: 'https://api.example.com/v1',
+                model: 'gpt-3.5-turbo',
+                enabled: false,
+            })
+        } catch (e) {
+            alert('添加失败: ' + formatError(e))
+        }
+    }
 
     return (
         <div className="max-w-2xl">
@@ -212,124 +228,182 @@ function AISettings() {
 
             <div className="space-y-4">
                 {aiProviders.map((provider) => (
-                    <div
-                        key={provider.id}
-                        className="bg-white dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded-xl p-4"
-                    >
-                        <div className="flex items-center justify-between mb-4">
-                            <div className="flex items-center gap-3">
-                                <span className="font-medium text-gray-800 dark:text-gray-200">
-                                    {provider.name}
-                                </span>
-                                {defaultProviderId === provider.id && (
-                                    <span className="px-2 py-0.5 bg-blue-100 dark:bg-blue-900 text-blue-600 dark:text-blue-400 text-xs rounded-full">
-                                        默认
-                                    </span>
-                                )}
-                            </div>
-                            <div className="flex items-center gap-2">
-                                <label className="flex items-center gap-2 text-sm">
-                                    <input
-                                        type="checkbox"
-                                        checked={provider.enabled}
-                                        onChange={(e) =>
-                                            updateProvider(provider.id, { enabled: e.target.checked })
-                                        }
-                                        className="rounded accent-blue-500"
-                                    />
-                                    <span className="text-gray-600 dark:text-gray-400">启用</span>
-                                </label>
-                                {defaultProviderId !== provider.id && (
-                                    <button
-                                        onClick={() => setDefaultProvider(provider.id)}
-                                        className="text-xs px-2 py-1 text-blue-600 hover:bg-blue-50 dark:hover:bg-blue-900/20 rounded"
-                                    >
-                                        设为默认
-                                    </button>
-                                )}
-                            </div>
-                        </div>
+                    <ProviderCard key={provider.id} provider={provider} />
+                ))}
 
-                        <div className="grid gap-3">
-                            <div>
-                                <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
-                                    API Key
-                                </label>
-                                <div className="flex gap-2">
-                                    <input
-                                        type={showKey === provider.id ? 'text' : 'password'}
-                                        value={provider.apiKey}
-                                        onChange={(e) =>
-                                            updateProvider(provider.id, { apiKey: e.target.value })
-                                        }
-                                        className="flex-1 px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
-                                        placeholder="sk-..."
-                                    />
-                                    <button
-                                        onClick={() =>
-                                            setShowKey(showKey === provider.id ? null : provider.id)
-                                        }
-                                        className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm hover:bg-gray-50 dark:hover:bg-gray-700"
-                                    >
-                                        {showKey === provider.id ? '隐藏' : '显示'}
-                                    </button>
-                                </div>
-                            </div>
-
-                            <div>
-                                <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
-                                    Base URL
-                                </label>
-                                <input
-                                    type="text"
-                                    value={provider.baseUrl}
-                                    onChange={(e) =>
-                                        updateProvider(provider.id, { baseUrl: e.target.value })
-                                    }
-                                    className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
-                                />
-                            </div>
-
-                            <div>
-                                <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
-                                    模型
-                                </label>
-                                <input
-                                    type="text"
-                                    value={provider.model}
-                                    onChange={(e) =>
-                                        updateProvider(provider.id, { model: e.target.value })
-                                    }
-                                    className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
-                                />
-                            </div>
-                        </div>
+                <button
+                    onClick={handleAdd}
+                    className="w-full py-3 border-2 border-dashed border-gray-300 dark:border-gray-600 rounded-xl text-gray-500 dark:text-gray-400 hover:border-blue-400 hover:text-blue-500 transition-colors"
+                >
+                    + 添加提供商
+                </button>
+            </div>
+        </div>
+    )
+}
+
+// 单个提供商：文本框失焦时保存，API Key 只写不读
+function ProviderCard({ provider }: { provider: AiProvider }) {
+    const { updateProvider, setDefaultProvider, removeProvider } = useSettingsStore()
+    const [baseUrl, setBaseUrl] = useState(provider.base_url)
+    const [model, setModel] = useState(provider.model)
+    const [apiKey, setApiKey] = useState('')
+    const [showKey, setShowKey] = useState(false)
+    const [testing, setTesting] = useState(false)
+    const [testResult, setTestResult] = useState<string | null>(null)
+
+    const save = async (updates: UpdateAiProvider) => {
+        try {
+            await updateProvider(provider.id, updates)
+        } catch (e) {
+            alert('保存失败: ' + formatError(e))
+            setBaseUrl(provider.base_url)
+            setModel(provider.model)
+        }
+    }
+
+    const saveKey = async () => {
+        if (!apiKey) return
+        await save({ api_key: apiKey })
+        setApiKey('')
+    }
+
+    const handleTest = async () => {
+        setTesting(true)
+        setTestResult(null)
+        try {
+            const result = await providerAPI.testConnection(provider.id)
+            setTestResult(`连接成功（${result.latency_ms} ms，${result.models.length} 个模型）`)
+        } catch (e) {
+            setTestResult(formatError(e))
+        } finally {
+            setTesting(false)
+        }
+    }
+
+    const handleRemove = async () => {
+        try {
+            await removeProvider(provider.id)
+        } catch (e) {
+            alert('删除失败: ' + formatError(e))
+        }
+    }
+
+    return (
+        <div className="bg-white dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded-xl p-4">
+            <div className="flex items-center justify-between mb-4">
+                <div className="flex items-center gap-3">
+                    <span className="font-medium text-gray-800 dark:text-gray-200">
+                        {provider.name}
+                    </span>
+                    {provider.is_default && (
+                        <span className="px-2 py-0.5 bg-blue-100 dark:bg-blue-900 text-blue-600 dark:text-blue-400 text-xs rounded-full">
+                            默认
+                        </span>
+                    )}
+                </div>
+                <div className="flex items-center gap-2">
+                    <label className="flex items-center gap-2 text-sm">
+                        <input
+                            type="checkbox"
+                            checked={provider.enabled}
+                            onChange={(e) => save({ enabled: e.target.checked })}
+                            className="rounded accent-blue-500"
+                        />
+                        <span className="text-gray-600 dark:text-gray-400">启用</span>
+                    </label>
+                    {!provider.is_default && (
+                        <button
+                            onClick={() =>
+                                setDefaultProvider(provider.id).catch((e) =>
+                                    alert('设置失败: ' + formatError(e))
+                                )
+                            }
+                            className="text-xs px-2 py-1 text-blue-600 hover:bg-blue-50 dark:hover:bg-blue-900/20 rounded"
+                        >
+                            设为默认
+                        </button>
+                    )}
+                </div>
+            </div>
 
-                        {!provider.id.includes('default') && (
+            <div className="grid gap-3">
+                <div>
+                    <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
+                        API Key
+                    </label>
+                    <div className="flex gap-2">
+                        <input
+                            type={showKey ? 'text' : 'password'}
+                            value={apiKey}
+                            onChange={(e) => setApiKey(e.target.value)}
+                            onBlur={saveKey}
+                            className="flex-1 px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
+                            placeholder={provider.has_api_key ? '已保存，输入新的 Key 可替换' : 'sk-...'}
+                        />
+                        <button
+                            onClick={() => setShowKey(!showKey)}
+                            className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm hover:bg-gray-50 dark:hover:bg-gray-700"
+                        >
+                            {showKey ? '隐藏' : '显示'}
+                        </button>
+                        {provider.has_api_key && (
                             <button
-                                onClick={() => removeProvider(provider.id)}
-                                className="mt-3 text-xs text-red-500 hover:text-red-600"
+                                onClick={() => save({ api_key: '' })}
+                                className="px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm text-red-500 hover:bg-gray-50 dark:hover:bg-gray-700"
                             >
-                                删除此提供商
+                                清除
                             </button>
                         )}
                     </div>
-                ))}
+                </div>
+
+                <div>
+                    <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
+                        Base URL
+                    </label>
+                    <input
+                        type="text"
+                        value={baseUrl}
+                        onChange={(e) => setBaseUrl(e.target.value)}
+                        onBlur={() => baseUrl !== provider.base_url && save({ base_url: baseUrl })}
+                        className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
+                    />
+                </div>
 
+                <div>
+                    <label className="text-xs text-gray-500 dark:text-gray-400 mb-1 block">
+                        模型
+                    </label>
+                    <input
+                        type="text"
+                        value={model}
+                        onChange={(e) => setModel(e.target.value)}
+                        onBlur={() => model !== provider.model && save({ model })}
+                        className="w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg text-sm bg-white dark:bg-gray-700 text-gray-800 dark:text-gray-200"
+                    />
+                </div>
+            </div>
+
+            <div className="mt-3 flex items-center gap-3">
                 <button
-                    onClick={() =>
-                        addProvider({
-                            name: '自定义',
-                            apiKey: '',
-                            baseUrl: 'https://api.example.com/v1',
-                            model: 'gpt-3.5-turbo',
-                            enabled: false,
-                        })
-                    }
-                    className="w-full py-3 border-2 border-dashed border-gray-300 dark:border-gray-600 rounded-xl text-gray-500 dark:text-gray-400 hover:border-blue-400 hover:text-blue-500 transition-colors"
+                    onClick={handleTest}
+                    disabled={testing}
+                    className="text-xs px-2 py-1 border border-gray-300 dark:border-gray-600 rounded text-gray-600 dark:text-gray-300 hover:bg-gray-50 dark:hover:bg-gray-700 disabled:opacity-50"
                 >
-                    + 添加提供商
+                    {testing ? '测试中...' : '测试连接'}
                 </button>
+                {testResult && (
+                    <span className="text-xs text-gray-500 dark:text-gray-400">{testResult}</span>
+                )}
+                {!provider.id.includes('default') && (
+                    <button
+                        onClick={handleRemove}
+                        className="ml-auto text-xs text-red-500 hover:text-red-600"
+                    >
+                        删除此提供商
+                    </button>
+                )}
             </div>
         </div>
     )
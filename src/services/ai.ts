@@ -1,4 +1,4 @@
-import { AIProvider } from '../store/settings'
+import { invoke, Channel } from '@tauri-apps/api/core'
 
 export interface AIEditRequest {
     instruction: string
@@ -6,11 +6,38 @@ export interface AIEditRequest {
     context?: string
 }
 
+export interface ChatMessage {
+    role: 'system' | 'user' | 'assistant'
+    content: string
+}
+
+/**
+ * 流式补全过程中后端推送的事件
+ */
+export type ChatEvent =
+    | { type: 'delta'; content: string }
+    | { type: 'retry'; attempt: number; delay_ms: number }
+
+export interface CallAIOptions {
+    /** 使用的提供商，不指定时使用默认提供商 */
+    providerId?: string
+    /** 每收到一段新内容时调用 */
+    onDelta?: (content: string) => void
+    /** 中止时取消后端请求，Promise 以 code 为 CANCELLED 的 AppError 拒绝 */
+    signal?: AbortSignal
+}
+
+/**
+ * 调用 AI 编辑文本
+ *
+ * 请求由 Rust 后端发出并流式返回，完成后得到完整回复。
+ * 提供商的地址和 API Key 由后端读取，不经过前端。
+ */
 export async function callAI(
-    provider: AIProvider,
-    request: AIEditRequest
+    request: AIEditRequest,
+    options: CallAIOptions = {}
 ): Promise<string> {
-    const messages = [
+    const messages: ChatMessage[] = [
         {
             role: 'system',
             content: '你是一个专业的文本编辑助手。根据用户的指令编辑文本，直接返回编辑后的结果，不要添加任何解释。',
@@ -27,24 +54,31 @@ export async function callAI(
 
     messages.push({ role: 'user', content: userMessage })
 
-    const response = await fetch(`${provider.baseUrl}/chat/completions`, {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-            'Authorization': `Bearer ${provider.apiKey}`,
-        },
-        body: JSON.stringify({
-            model: provider.model,
-            messages,
-            temperature: 0.7,
-            max_tokens: 2000,
-        }),
-    })
-
-    if (!response.ok) {
-        throw new Error(`API 请求失败: ${response.statusText}`)
+    const requestId = crypto.randomUUID()
+    const onEvent = new Channel<ChatEvent>()
+    onEvent.onmessage = (event) => {
+        if (event.type === 'delta') {
+            options.onDelta?.(event.content)
+        }
+    }
+
+    const cancel = () => {
+        invoke('cancel_chat_completion', { requestId }).catch(() => {})
     }
+    options.signal?.addEventListener('abort', cancel, { once: true })
 
-    const data = await response.json()
-    return data.choices[0]?.message?.content || ''
-}
\ No newline at end of file
+    try {
+        return await invoke<string>('chat_completion', {
+            requestId,
+            request: {
+                provider_id: options.providerId ?? null,
+                messages,
+                temperature: 0.7,
+                max_tokens: 2000,
+            },
+            onEvent,
+        })
+    } finally {
+        options.signal?.removeEventListener('abort', cancel)
+    }
+}
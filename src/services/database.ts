@@ -1,4 +1,5 @@
-import { invoke } from '@tauri-apps/api/core'
+import { invoke, Channel } from '@tauri-apps/api/core'
+import type { AppearanceSettings } from '../store/settings'
 
 // ============ 窗口管理 API ============
 
@@ -12,14 +13,58 @@ export const windowAPI = {
     },
 }
 
+// ============ 设置 API ============
+
+/**
+ * 所有窗口共享的应用设置，保存在后端
+ */
+export interface Settings {
+    /** 结构版本，由后端写入 */
+    version: number
+    appearance: AppearanceSettings
+}
+
+export const settingsAPI = {
+    async get(): Promise<Settings> {
+        return invoke('get_settings')
+    },
+
+    /**
+     * 保存设置，后端会向所有窗口广播 settings-changed 事件
+     */
+    async update(settings: Omit<Settings, 'version'>): Promise<Settings> {
+        return invoke('update_settings', { settings })
+    },
+}
+
 // ============ 类型定义 ============
 
+/**
+ * 后端命令返回的结构化错误
+ */
+export interface AppError {
+    code: 'NOT_FOUND' | 'CONFLICT' | 'VALIDATION' | 'IO' | 'DATABASE' | 'AI' | 'CANCELLED' | 'INTERNAL'
+    message: string
+}
+
+export function isAppError(error: unknown): error is AppError {
+    return typeof error === 'object' && error !== null && 'code' in error && 'message' in error
+}
+
+/**
+ * 将命令错误转换为可展示的文本
+ */
+export function formatError(error: unknown): string {
+    return isAppError(error) ? error.message : String(error)
+}
+
 export interface Workspace {
     id: string
     name: string
     description?: string
     created_at: string
     updated_at: string
+    deleted_at?: string // 在回收站中时为删除时间
 }
 
 export interface File {
@@ -34,6 +79,109 @@ export interface File {
     mime_type?: string
     created_at: string
     updated_at: string
+    word_count?: number
+    char_count?: number // 不含空白
+    paragraph_count?: number
+    reading_minutes?: number
+    outline?: OutlineHeading[]
+    folder_id?: string
+    deleted_at?: string // 在回收站中时为删除时间
+}
+
+/**
+ * 文档大纲中的标题
+ */
+export interface OutlineHeading {
+    level: number
+    text: string
+}
+
+/**
+ * 文件列表项（不含正文）
+ */
+export interface FileSummary {
+    id: string
+    workspace_id: string
+    file_type: 'document' | 'image' | 'audio' | 'video'
+    title: string
+    size: number
+    word_count: number
+    char_count: number
+    paragraph_count: number
+    reading_minutes: number
+    folder_id?: string
+    tags: string[]
+    created_at: string
+    updated_at: string
+}
+
+export interface FilePage {
+    items: FileSummary[]
+    total: number
+    offset: number
+    limit: number
+    has_more: boolean
+}
+
+export interface Folder {
+    id: string
+    workspace_id: string
+    parent_id?: string
+    name: string
+    created_at: string
+    updated_at: string
+}
+
+/**
+ * 目录树中的文件（不含正文）
+ */
+export interface FolderTreeFile {
+    id: string
+    folder_id?: string
+    file_type: 'document' | 'image' | 'audio' | 'video'
+    title: string
+    updated_at: string
+}
+
+export interface FolderNode extends Folder {
+    path: string
+    children: FolderNode[]
+    files: FolderTreeFile[]
+}
+
+export interface FolderTree {
+    folders: FolderNode[]
+    files: FolderTreeFile[]
+}
+
+export interface TrashedWorkspace {
+    id: string
+    name: string
+    file_count: number // 随工作空间一起删除的文件数
+    deleted_at: string
+}
+
+export interface TrashedFile {
+    id: string
+    workspace_id: string
+    workspace_name: string
+    file_type: 'document' | 'image' | 'audio' | 'video'
+    title: string
+    deleted_at: string
+}
+
+export interface TrashContents {
+    workspaces: TrashedWorkspace[]
+    files: TrashedFile[]
+}
+
+export interface TrashPurgeResult {
+    workspaces: number
+    files: number
+}
+
+export interface TrashConfig {
+    retention_days: number // 0 表示不自动清理
 }
 
 export interface Tag {
@@ -43,18 +191,78 @@ export interface Tag {
     created_at: string
 }
 
+/**
+ * 匹配位置（按字符计算的左闭右开区间，可配合 Array.from(text) 使用）
+ */
+export interface MatchRange {
+    start: number
+    end: number
+}
+
 export interface SearchResult {
     id: string
     workspace_id: string
     file_type: string
     title: string
-    content?: string
+    title_ranges: MatchRange[]
+    snippet: string
+    snippet_ranges: MatchRange[]
     file_path?: string
     created_at: string
     updated_at: string
+    /** 相关度分数，越大越相关 */
     rank: number
 }
 
+/**
+ * BM25 列权重，默认 title 10、content 1、tags 5
+ */
+export interface SearchWeights {
+    title: number
+    content: number
+    tags: number
+}
+
+/**
+ * 语义搜索命中的文档片段，偏移为 content_plain 中的字符位置
+ */
+export interface ChunkMatch {
+    file_id: string
+    workspace_id: string
+    title: string
+    chunk_index: number
+    content: string
+    start_offset: number
+    end_offset: number
+    /** 余弦相似度 */
+    score: number
+}
+
+/**
+ * 检索到的上下文片段，score 为全文与向量两路排名融合后的分数
+ */
+export interface ContextChunk {
+    file_id: string
+    workspace_id: string
+    title: string
+    chunk_index: number
+    content: string
+    start_offset: number
+    end_offset: number
+    score: number
+    /** 全文检索中的名次（从 1 开始），未命中时为 null */
+    keyword_rank: number | null
+    /** 向量检索中的名次（从 1 开始），未命中时为 null */
+    vector_rank: number | null
+}
+
+/**
+ * 向量化后端：内置哈希向量（离线）或已配置 AI 提供商的 /embeddings 接口
+ */
+export type EmbeddingConfig =
+    | { backend: 'hashing' }
+    | { backend: 'provider'; provider_id: string; model: string }
+
 // ============ 工作空间 API ============
 
 export const workspaceAPI = {
@@ -79,6 +287,103 @@ export const workspaceAPI = {
     },
 }
 
+// ============ 导入导出 API ============
+
+/**
+ * 导入导出过程中后端推送的进度
+ */
+export interface ArchiveProgress {
+    stage: 'documents' | 'media' | 'done'
+    completed: number
+    total: number
+}
+
+export interface ExportSummary {
+    documents: number
+    media: number
+    /** 数据库中有记录但文件已丢失的媒体 */
+    missing_media: number
+    /** 归档大小（字节） */
+    size: number
+}
+
+export interface VaultImportedFile {
+    /** 相对笔记库根目录的路径 */
+    path: string
+    file_id: string
+    title: string
+}
+
+export interface VaultImportIssue {
+    path: string
+    reason: string
+}
+
+export interface VaultImportReport {
+    imported: VaultImportedFile[]
+    skipped: VaultImportIssue[]
+    failed: VaultImportIssue[]
+    /** 复制到媒体目录的图片数 */
+    media: number
+}
+
+export const archiveAPI = {
+    /**
+     * 导出工作空间为 zip 归档（manifest.json、每篇文档的 HTML 和 Markdown、媒体文件）
+     * @param destPath 归档的保存路径
+     */
+    async exportWorkspace(
+        workspaceId: string,
+        destPath: string,
+        onProgress?: (progress: ArchiveProgress) => void
+    ): Promise<ExportSummary> {
+        const channel = new Channel<ArchiveProgress>()
+        channel.onmessage = (progress) => onProgress?.(progress)
+        return invoke('export_workspace', { workspaceId, destPath, onProgress: channel })
+    },
+
+    /**
+     * 从 zip 归档导入为新的工作空间，标签按名称与已有标签合并
+     * @param archivePath 归档文件路径
+     */
+    async importWorkspace(
+        archivePath: string,
+        onProgress?: (progress: ArchiveProgress) => void
+    ): Promise<Workspace> {
+        const channel = new Channel<ArchiveProgress>()
+        channel.onmessage = (progress) => onProgress?.(progress)
+        return invoke('import_workspace', { archivePath, onProgress: channel })
+    },
+
+    /**
+     * 把 Markdown 文件批量导入为文档，标题取第一个标题，没有标题时使用文件名
+     */
+    async importMarkdownFiles(workspaceId: string, paths: string[]): Promise<File[]> {
+        return invoke('import_markdown_files', { workspaceId, paths })
+    },
+
+    /**
+     * 把文档导出为 Markdown 文件，引用的图片复制到同目录的 `<文件名>.assets/` 下
+     */
+    async exportFileAsMarkdown(fileId: string, destPath: string): Promise<void> {
+        return invoke('export_file_as_markdown', { fileId, destPath })
+    },
+
+    /**
+     * 导入 Obsidian / Logseq 风格的 Markdown 笔记库
+     * 子目录和 front matter 中的 tags 作为标签，图片复制到媒体目录，[[wiki link]] 改写为 doc:// 链接
+     */
+    async importVault(
+        workspaceId: string,
+        rootPath: string,
+        onProgress?: (progress: ArchiveProgress) => void
+    ): Promise<VaultImportReport> {
+        const channel = new Channel<ArchiveProgress>()
+        channel.onmessage = (progress) => onProgress?.(progress)
+        return invoke('import_vault', { workspaceId, rootPath, onProgress: channel })
+    },
+}
+
 // ============ 文件 API ============
 
 export const fileAPI = {
@@ -87,10 +392,10 @@ export const fileAPI = {
         file_type: 'document' | 'image' | 'audio' | 'video'
         title: string
         content?: string
-        content_plain?: string
         file_path?: string
         file_size?: number
         mime_type?: string
+        folder_id?: string
     }): Promise<File> {
         return invoke('create_file', { data })
     },
@@ -107,12 +412,25 @@ export const fileAPI = {
         return invoke('list_files_by_type', { workspaceId, fileType })
     },
 
+    /**
+     * 分页列出文件摘要
+     */
+    async list(query: {
+        workspace_id: string
+        file_type?: string
+        sort_by?: 'title' | 'created' | 'updated' | 'size'
+        direction?: 'asc' | 'desc'
+        offset?: number
+        limit?: number
+    }): Promise<FilePage> {
+        return invoke('list_files', { query })
+    },
+
     async update(
         id: string,
         data: {
             title?: string
             content?: string
-            content_plain?: string
             file_path?: string
             file_size?: number
             mime_type?: string
@@ -126,6 +444,141 @@ export const fileAPI = {
     },
 }
 
+// ============ 历史版本 API ============
+
+export interface FileRevision {
+    id: string
+    file_id: string
+    title: string
+    content?: string
+    content_plain?: string
+    reason: 'autosave' | 'manual' | 'restore'
+    created_at: string
+}
+
+export interface FileRevisionSummary {
+    id: string
+    file_id: string
+    title: string
+    reason: 'autosave' | 'manual' | 'restore'
+    char_count: number
+    created_at: string
+}
+
+export type DiffGranularity = 'paragraph' | 'word'
+
+export interface DiffSegment {
+    op: 'equal' | 'insert' | 'delete'
+    text: string
+}
+
+export interface RevisionDiff {
+    from_id: string
+    to_id?: string
+    granularity: DiffGranularity
+    segments: DiffSegment[]
+    insertions: number
+    deletions: number
+}
+
+export const revisionAPI = {
+    async list(fileId: string): Promise<FileRevisionSummary[]> {
+        return invoke('list_file_revisions', { fileId })
+    },
+
+    async get(id: string): Promise<FileRevision | null> {
+        return invoke('get_file_revision', { id })
+    },
+
+    /**
+     * 手动保存版本；内容与最近版本相同时返回 null
+     */
+    async create(fileId: string): Promise<FileRevision | null> {
+        return invoke('create_file_revision', { fileId })
+    },
+
+    async restore(id: string): Promise<File> {
+        return invoke('restore_file_revision', { id })
+    },
+
+    /**
+     * 对比两个版本，不传 toId 时与当前内容对比
+     */
+    async diff(fromId: string, toId?: string, granularity: DiffGranularity = 'paragraph'): Promise<RevisionDiff> {
+        return invoke('diff_file_revisions', { fromId, toId, granularity })
+    },
+}
+
+// ============ 文件夹 API ============
+
+export const folderAPI = {
+    async create(workspaceId: string, name: string, parentId?: string): Promise<Folder> {
+        return invoke('create_folder', {
+            data: { workspace_id: workspaceId, parent_id: parentId, name },
+        })
+    },
+
+    async rename(id: string, name: string): Promise<Folder> {
+        return invoke('rename_folder', { id, name })
+    },
+
+    /**
+     * 移动文件夹，不传 parentId 时移到根目录
+     */
+    async move(id: string, parentId?: string): Promise<Folder> {
+        return invoke('move_folder', { id, parentId })
+    },
+
+    /**
+     * 删除文件夹：reparent（默认）把内容移到上一级，cascade 删除子文件夹并把其中的文件移入回收站
+     */
+    async delete(id: string, mode: 'cascade' | 'reparent' = 'reparent'): Promise<void> {
+        return invoke('delete_folder', { id, mode })
+    },
+
+    /**
+     * 移动文件，不传 folderId 时移到根目录
+     */
+    async moveFiles(fileIds: string[], folderId?: string): Promise<void> {
+        return invoke('move_files_to_folder', { fileIds, folderId })
+    },
+
+    async getTree(workspaceId: string): Promise<FolderTree> {
+        return invoke('get_folder_tree', { workspaceId })
+    },
+}
+
+// ============ 回收站 API ============
+
+export const trashAPI = {
+    async list(): Promise<TrashContents> {
+        return invoke('list_trash')
+    },
+
+    async restoreFile(id: string): Promise<File> {
+        return invoke('restore_file', { id })
+    },
+
+    /**
+     * 恢复工作空间及随它一起删除的文件
+     */
+    async restoreWorkspace(id: string): Promise<Workspace> {
+        return invoke('restore_workspace', { id })
+    },
+
+    async empty(): Promise<TrashPurgeResult> {
+        return invoke('empty_trash')
+    },
+
+    async getConfig(): Promise<TrashConfig> {
+        return invoke('get_trash_config')
+    },
+
+    async setConfig(config: TrashConfig): Promise<void> {
+        return invoke('set_trash_config', { config })
+    },
+}
+
 // ============ 标签 API ============
 
 export const tagAPI = {
@@ -161,12 +614,25 @@ export const tagAPI = {
 // ============ 搜索 API ============
 
 export const searchAPI = {
+    /**
+     * 全文搜索
+     *
+     * 查询语法：`"短语"`、`-排除`、`a OR b`、`a AND b`、括号分组、
+     * `title:`、`tag:`、`type:`、`folder:路径`、`before:YYYY-MM-DD`、`after:YYYY-MM-DD`。
+     * 语法错误时抛出 code 为 VALIDATION 的 AppError。
+     */
     async search(query: {
         query: string
         workspace_id?: string
         file_type?: string
         tags?: string[]
+        /** 文件夹路径（如 `projects/web`），包含子文件夹 */
+        folder?: string
         limit?: number
+        snippet_tokens?: number
+        weights?: SearchWeights
+        /** 更新时间加成，刚更新的文档分数最多乘以 1 + recency_boost */
+        recency_boost?: number
     }): Promise<SearchResult[]> {
         return invoke('search_files', { query })
     },
@@ -179,11 +645,132 @@ export const searchAPI = {
         return invoke('search_by_tags', { workspaceId, tagNames, matchAll })
     },
 
+    /**
+     * 相似文档推荐：优先按内容相似度，没有结果时按共同标签
+     */
     async findSimilar(fileId: string, limit: number = 10): Promise<SearchResult[]> {
         return invoke('find_similar_files', { fileId, limit })
     },
+
+    /**
+     * 语义搜索，返回最相近的文档片段
+     */
+    async semanticSearch(query: {
+        query: string
+        workspace_id?: string
+        file_type?: string
+        limit?: number
+    }): Promise<ChunkMatch[]> {
+        return invoke('semantic_search', { query })
+    },
+
+    /**
+     * 检索与问题相关的文档片段，用于为 AI 回答提供上下文
+     *
+     * 问题可以直接使用自然语言；tags 要求文件包含所有标签。
+     */
+    async retrieveContext(query: {
+        query: string
+        workspace_id?: string
+        file_type?: string
+        tags?: string[]
+        limit?: number
+    }): Promise<ContextChunk[]> {
+        return invoke('retrieve_context', { query })
+    },
+
+    /**
+     * 为尚未向量化或已修改的文档生成向量，返回处理的文档数
+     */
+    async indexEmbeddings(workspaceId?: string): Promise<number> {
+        return invoke('index_embeddings', { workspaceId })
+    },
+
+    async getEmbeddingConfig(): Promise<EmbeddingConfig> {
+        return invoke('get_embedding_config')
+    },
+
+    async setEmbeddingConfig(config: EmbeddingConfig): Promise<void> {
+        return invoke('set_embedding_config', { config })
+    },
 }
 
+// ============ AI 提供商类型 ============
+
+/**
+ * OpenAI 兼容的 AI 提供商，API Key 加密保存在后端，不会返回给前端
+ */
+export interface AiProvider {
+    id: string
+    name: string
+    base_url: string
+    model: string
+    enabled: boolean
+    is_default: boolean
+    has_api_key: boolean
+    created_at: string
+    updated_at: string
+}
+
+export interface CreateAiProvider {
+    name: string
+    base_url: string
+    model: string
+    enabled?: boolean
+    api_key?: string
+}
+
+export interface UpdateAiProvider {
+    name?: string
+    base_url?: string
+    model?: string
+    enabled?: boolean
+    /** 空字符串表示清除已保存的 Key */
+    api_key?: string
+}
+
+export interface ProviderConnection {
+    models: string[]
+    latency_ms: number
+}
+
+// ============ AI 提供商 API ============
+
+export const providerAPI = {
+    async list(): Promise<AiProvider[]> {
+        return invoke('list_providers')
+    },
+
+    async get(id: string): Promise<AiProvider | null> {
+        return invoke('get_provider', { id })
+    },
+
+    async create(data: CreateAiProvider): Promise<AiProvider> {
+        return invoke('create_provider', { data })
+    },
+
+    async update(id: string, data: UpdateAiProvider): Promise<AiProvider> {
+        return invoke('update_provider', { id, data })
+    },
+
+    async delete(id: string): Promise<void> {
+        return invoke('delete_provider', { id })
+    },
+
+    /**
+     * 设置默认提供商，传 null 取消默认
+     */
+    async setDefault(id: string | null): Promise<void> {
+        return invoke('set_default_provider', { id })
+    },
+
+    /**
+     * 请求提供商的 /models 接口检查地址和 Key 是否可用
+     */
+    async testConnection(id: string): Promise<ProviderConnection> {
+        return invoke('test_provider_connection', { id })
+    },
+}
 
 // ============ 媒体类型 ============
 
@@ -194,35 +781,114 @@ export interface MediaAsset {
     file_path: string
     file_size: number
     mime_type: string
-    width?: number
+    width?: number // 按 EXIF 方向摆正后的显示尺寸
     height?: number
     created_at: string
+    content_hash?: string // 内容的 SHA-256，同一工作空间中内容相同的媒体只保存一份
+}
+
+/**
+ * 媒体清理结果，dryRun 时只报告不删除
+ */
+export interface MediaGcReport {
+    dry_run: boolean
+    orphan_assets: MediaAsset[] // 没有任何文档（含回收站和历史版本）引用的媒体
+    orphan_files: string[] // 媒体目录中没有对应记录的文件
+    missing_assets: string[] // 磁盘文件已丢失的媒体 id
+    links_added: number
+    links_removed: number
+    freed_bytes: number // dryRun 时为可释放的空间
+}
+
+/**
+ * 图片元数据（按文件内容识别）
+ */
+export interface ImageInfo {
+    format: 'png' | 'jpeg' | 'gif' | 'webp' | 'bmp' | 'svg' | 'avif'
+    width: number // 文件中存储的尺寸，未按方向旋转
+    height: number
+    orientation: number // EXIF 方向 1-8
+    color_type: 'gray' | 'gray_alpha' | 'rgb' | 'rgba' | 'indexed' | 'indexed_alpha' | 'cmyk' | null
+    frame_count: number // 动画帧数，静态图片为 1
+}
+
+/**
+ * 缩略图尺寸：small 为 256px，large 为 1024px（按长边缩放）
+ */
+export type ThumbnailSize = 'small' | 'large'
+
+/**
+ * 为已有媒体补生成缩略图的结果
+ */
+export interface ThumbnailBackfillReport {
+    generated: number // 新生成的缩略图数
+    skipped: number // 缩略图已存在或原图足够小的媒体数
+    failed: { media_id: string; reason: string }[] // 无法解码或文件已丢失的媒体
+}
+
+/**
+ * 分块上传进度
+ */
+export interface MediaUploadProgress {
+    upload_id: string
+    received: number
+    total: number
 }
 
 // ============ 媒体 API ============
 
+/** 分块上传时每个分块的字节数 */
+const UPLOAD_CHUNK_SIZE = 4 * 1024 * 1024
+
+
 export const mediaAPI = {
     /**
-     * 上传媒体文件
+     * 分块上传媒体文件，文件按 UPLOAD_CHUNK_SIZE 切分后以原始字节发送
      * @param workspaceId 工作空间 ID
      * @param fileId 关联的文档 ID
      * @param file 文件对象
-     * @returns 媒体资源信息（包含 UUID）
+     * @param onProgress 每个分块写入后回调已接收的字节数
+     * @returns 媒体资源信息（包含 UUID），工作空间中已有相同内容时返回已有的媒体
      */
-    async upload(workspaceId: string, fileId: string, file: Blob, fileName: string): Promise<MediaAsset> {
-        // 转换为 base64
-        const buffer = await file.arrayBuffer()
-        const base64 = btoa(
-            new Uint8Array(buffer).reduce((data, byte) => data + String.fromCharCode(byte), '')
-        )
-
-        return invoke('upload_media', {
-            workspaceId,
-            fileId,
-            fileName,
-            mimeType: file.type || 'application/octet-stream',
-            data: base64,
+    async upload(
+        workspaceId: string,
+        fileId: string,
+        file: Blob,
+        fileName: string,
+        onProgress?: (progress: MediaUploadProgress) => void
+    ): Promise<MediaAsset> {
+        const channel = new Channel<MediaUploadProgress>()
+        channel.onmessage = (progress) => onProgress?.(progress)
+        const uploadId: string = await invoke('begin_media_upload', {
+            data: {
+                workspace_id: workspaceId,
+                file_id: fileId,
+                file_name: fileName,
+                mime_type: file.type || 'application/octet-stream',
+                size: file.size,
+            },
+            onProgress: channel,
         })
+
+        try {
+            for (let offset = 0; offset < file.size; offset += UPLOAD_CHUNK_SIZE) {
+                const chunk = await file.slice(offset, offset + UPLOAD_CHUNK_SIZE).arrayBuffer()
+                await invoke('append_media_chunk', new Uint8Array(chunk), {
+                    headers: { 'x-upload-id': uploadId, 'x-upload-offset': String(offset) },
+                })
+            }
+            return await invoke('finish_media_upload', { uploadId })
+        } catch (error) {
+            await invoke('abort_media_upload', { uploadId }).catch(() => {})
+            throw error
+        }
+    },
+
+    /**
+     * 取消进行中的上传
+     */
+    async abortUpload(uploadId: string): Promise<boolean> {
+        return invoke('abort_media_upload', { uploadId })
     },
 
     /**
@@ -239,6 +905,28 @@ export const mediaAPI = {
         return invoke('get_media_path', { id })
     },
 
+    /**
+     * 读取图片的格式、尺寸、方向、颜色类型和帧数，不是可识别的图片时返回 null
+     */
+    async getImageInfo(id: string): Promise<ImageInfo | null> {
+        return invoke('get_media_image_info', { id })
+    },
+
+    /**
+     * 获取缩略图的实际路径，缩略图不存在时生成
+     * SVG 和不超过缩略图尺寸的图片返回原图路径
+     */
+    async getThumbnailPath(id: string, size: ThumbnailSize = 'small'): Promise<string> {
+        return invoke('get_media_thumbnail', { id, size })
+    },
+
+    /**
+     * 为已有的图片媒体补生成缩略图
+     */
+    async generateThumbnails(): Promise<ThumbnailBackfillReport> {
+        return invoke('generate_media_thumbnails')
+    },
+
     /**
      * 获取文档关联的所有媒体
      */
@@ -252,6 +940,13 @@ export const mediaAPI = {
     async delete(id: string): Promise<void> {
         return invoke('delete_media', { id })
     },
+
+    /**
+     * 清理没有被任何文档引用的媒体，默认只返回报告
+     */
+    async collectGarbage(dryRun = true): Promise<MediaGcReport> {
+        return invoke('collect_media_garbage', { dryRun })
+    },
 }
 
 // ============ 媒体 URL 工具 ============
@@ -281,3 +976,14 @@ export function parseMediaUrl(url: string): string | null {
 export function isMediaUrl(url: string): boolean {
     return url.startsWith('media://')
 }
+
+/**
+ * 解析文档链接 URL（导入笔记库时 [[wiki link]] 改写为 doc://uuid）
+ * @returns 文件 ID 或 null
+ */
+export function parseDocUrl(url: string): string | null {
+    if (url.startsWith('doc://')) {
+        return url.slice(6)
+    }
+    return null
+}
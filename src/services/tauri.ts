@@ -1,5 +1,6 @@
 import { invoke } from '@tauri-apps/api/core'
-import { Document, AppSettings } from '../types'
+import { Document } from '../types'
+import type { Settings } from './database'
 
 export interface CreateDocumentRequest {
     title?: string
@@ -40,11 +41,11 @@ export class TauriService {
         return await invoke('search_documents', { request })
     }
 
-    static async getSettings(): Promise<AppSettings> {
+    static async getSettings(): Promise<Settings> {
         return await invoke('get_settings')
     }
 
-    static async updateSettings(settings: AppSettings): Promise<void> {
+    static async updateSettings(settings: Omit<Settings, 'version'>): Promise<Settings> {
         return await invoke('update_settings', { settings })
     }
 }
\ No newline at end of file
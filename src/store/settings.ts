@@ -1,11 +1,13 @@
 import { create } from 'zustand'
 import { persist } from 'zustand/middleware'
-import { emitTo } from '@tauri-apps/api/event'
-import { getAllWebviewWindows } from '@tauri-apps/api/webviewWindow'
+import { providerAPI, settingsAPI, type AiProvider, type CreateAiProvider, type UpdateAiProvider } from '../services/database'
 
 // ============ AI 提供商 ============
 
-export interface AIProvider {
+/**
+ * 旧版本保存在 localStorage 中的提供商（含明文 API Key），启动时迁移到后端
+ */
+export interface LegacyAIProvider {
     id: string
     name: string
     apiKey: string
@@ -14,6 +16,8 @@ export interface AIProvider {
     enabled: boolean
 }
 
+export const LEGACY_PROVIDERS_KEY = 'ai-editor-legacy-providers'
+
 // ============ 外观设置 ============
 
 export type ThemeMode = 'light' | 'dark' | 'system'
@@ -36,37 +40,34 @@ interface SettingsState {
     // 外观设置
     appearance: AppearanceSettings
 
-    // AI 设置
-    aiProviders: AIProvider[]
-    defaultProviderId: string | null
+    // AI 设置（保存在后端，不持久化到 localStorage）
+    aiProviders: AiProvider[]
 
     // 外观操作
     setAppearance: (settings: Partial<AppearanceSettings>, broadcast?: boolean) => void
     resetAppearance: () => void
+    syncSettings: () => Promise<void>
 
     // AI 操作
-    addProvider: (provider: Omit<AIProvider, 'id'>) => void
-    updateProvider: (id: string, updates: Partial<AIProvider>) => void
-    removeProvider: (id: string) => void
-    setDefaultProvider: (id: string | null) => void
-    getActiveProvider: () => AIProvider | null
+    loadProviders: () => Promise<void>
+    addProvider: (provider: CreateAiProvider) => Promise<void>
+    updateProvider: (id: string, updates: UpdateAiProvider) => Promise<void>
+    removeProvider: (id: string) => Promise<void>
+    setDefaultProvider: (id: string | null) => Promise<void>
 }
 
-const generateId = () => Math.random().toString(36).slice(2, 11)
-
-// 广播设置到所有窗口
-async function broadcastSettings(appearance: AppearanceSettings) {
+// 保存到后端，由后端广播到所有窗口
+async function saveSettings(appearance: AppearanceSettings) {
     try {
-        const windows = await getAllWebviewWindows()
-        console.log('Broadcasting to windows:', windows.map(w => w.label))
-        for (const win of windows) {
-            await emitTo(win.label, 'settings-changed', { appearance })
-        }
+        await settingsAPI.update({ appearance })
     } catch (e) {
-        console.error('Failed to broadcast settings:', e)
+        console.error('Failed to save settings:', e)
     }
 }
 
+// 旧版本的设置只保存在 localStorage，首次同步时写入后端
+const SETTINGS_SYNCED_KEY = 'settings-synced-to-backend'
+
 // 默认外观设置
 const defaultAppearance: AppearanceSettings = {
     theme: 'system',
@@ -83,80 +84,82 @@ export const useSettingsStore = create<SettingsState>()(
         (set, get) => ({
             appearance: defaultAppearance,
 
-            aiProviders: [
-                {
-                    id: 'openai-default',
-                    name: 'OpenAI',
-                    apiKey: '',
-                    baseUrl: 'https://api.openai.com/v1',
-                    model: 'gpt-4o-mini',
-                    enabled: false,
-                },
-                {
-                    id: 'anthropic-default',
-                    name: 'Anthropic',
-                    apiKey: '',
-                    baseUrl: 'https://api.anthropic.com/v1',
-                    model: 'claude-3-haiku-20240307',
-                    enabled: false,
-                },
-            ],
-            defaultProviderId: null,
+            aiProviders: [],
 
             setAppearance: (settings, broadcast = true) => {
                 set((state) => ({
                     appearance: { ...state.appearance, ...settings },
                 }))
-                // 广播设置变更到其他窗口
+                // 保存并广播设置变更到所有窗口
                 if (broadcast) {
-                    const newAppearance = { ...get().appearance, ...settings }
-                    broadcastSettings(newAppearance)
+                    saveSettings(get().appearance)
                 }
             },
 
             resetAppearance: () => {
                 set({ appearance: defaultAppearance })
-                broadcastSettings(defaultAppearance)
+                saveSettings(defaultAppearance)
             },
 
-            addProvider: (provider) => {
-                const newProvider: AIProvider = {
-                    ...provider,
-                    id: generateId(),
+            syncSettings: async () => {
+                if (!localStorage.getItem(SETTINGS_SYNCED_KEY)) {
+                    await settingsAPI.update({ appearance: get().appearance })
+                    localStorage.setItem(SETTINGS_SYNCED_KEY, 'true')
+                    return
                 }
-                set((state) => ({
-                    aiProviders: [...state.aiProviders, newProvider],
-                }))
+                const settings = await settingsAPI.get()
+                set({ appearance: settings.appearance })
             },
 
-            updateProvider: (id, updates) => {
-                set((state) => ({
-                    aiProviders: state.aiProviders.map((p) =>
-                        p.id === id ? { ...p, ...updates } : p
-                    ),
-                }))
+            loadProviders: async () => {
+                set({ aiProviders: await providerAPI.list() })
             },
 
-            removeProvider: (id) => {
+            addProvider: async (provider) => {
+                await providerAPI.create(provider)
+                await get().loadProviders()
+            },
+
+            updateProvider: async (id, updates) => {
+                const updated = await providerAPI.update(id, updates)
                 set((state) => ({
-                    aiProviders: state.aiProviders.filter((p) => p.id !== id),
-                    defaultProviderId: state.defaultProviderId === id ? null : state.defaultProviderId,
+                    aiProviders: state.aiProviders.map((p) => (p.id === id ? updated : p)),
                 }))
             },
 
-            setDefaultProvider: (id) => set({ defaultProviderId: id }),
+            removeProvider: async (id) => {
+                await providerAPI.delete(id)
+                await get().loadProviders()
+            },
 
-            getActiveProvider: () => {
-                const { aiProviders, defaultProviderId } = get()
-                if (defaultProviderId) {
-                    const provider = aiProviders.find((p) => p.id === defaultProviderId)
-                    if (provider?.enabled && provider.apiKey) return provider
-                }
-                return aiProviders.find((p) => p.enabled && p.apiKey) || null
+            setDefaultProvider: async (id) => {
+                await providerAPI.setDefault(id)
+                await get().loadProviders()
             },
         }),
         {
             name: 'ai-editor-settings',
+            version: 1,
+            // 以后端为准，本地只缓存外观设置，避免启动时闪烁
+            partialize: (state) => ({ appearance: state.appearance }),
+            // 版本 0 把提供商和明文 API Key 存在这里，取出后交给 migrateProvidersToBackend
+            migrate: (persisted, version) => {
+                const state = persisted as {
+                    appearance?: AppearanceSettings
+                    aiProviders?: LegacyAIProvider[]
+                    defaultProviderId?: string | null
+                }
+                if (version === 0 && state.aiProviders) {
+                    localStorage.setItem(
+                        LEGACY_PROVIDERS_KEY,
+                        JSON.stringify({
+                            providers: state.aiProviders,
+                            defaultProviderId: state.defaultProviderId ?? null,
+                        })
+                    )
+                }
+                return { appearance: state.appearance ?? defaultAppearance } as SettingsState
+            },
         }
     )
 )
@@ -1,4 +1,5 @@
-import { workspaceAPI, fileAPI } from '../services/database'
+import { workspaceAPI, fileAPI, providerAPI } from '../services/database'
+import { LEGACY_PROVIDERS_KEY, type LegacyAIProvider } from '../store/settings'
 
 interface OldDocument {
     id: string
@@ -64,3 +65,67 @@ export async function migrateFromLocalStorage(): Promise<boolean> {
         return false
     }
 }
+
+interface LegacyProvidersState {
+    providers: LegacyAIProvider[]
+    defaultProviderId: string | null
+}
+
+/**
+ * 把旧版本保存在 localStorage 中的 AI 提供商（含明文 API Key）迁移到后端
+ */
+export async function migrateProvidersToBackend(): Promise<boolean> {
+    try {
+        const legacyStr = localStorage.getItem(LEGACY_PROVIDERS_KEY)
+        if (!legacyStr) {
+            return false
+        }
+
+        const legacy: LegacyProvidersState = JSON.parse(legacyStr)
+        const existing = await providerAPI.list()
+        const remaining = [...(legacy.providers || [])]
+        const normalizeUrl = (url: string) => url.replace(/\/+$/, '')
+
+        // 每迁移一个就从 localStorage 中移除，中途失败时下次启动不会重复创建已迁移的提供商
+        while (remaining.length > 0) {
+            const provider = remaining[0]
+            const data = {
+                name: provider.name,
+                base_url: provider.baseUrl,
+                model: provider.model,
+                enabled: provider.enabled,
+                api_key: provider.apiKey || undefined,
+            }
+
+            // 内置提供商在后端已存在，更新即可；名称和地址相同的提供商视为已迁移过；
+            // 其余自定义提供商重新创建，id 会变化
+            const match =
+                existing.find((p) => p.id === provider.id) ??
+                existing.find(
+                    (p) =>
+                        p.name === provider.name &&
+                        normalizeUrl(p.base_url) === normalizeUrl(provider.baseUrl)
+                )
+            const saved = match
+                ? await providerAPI.update(match.id, data)
+                : await providerAPI.create(data)
+
+            if (provider.id === legacy.defaultProviderId) {
+                await providerAPI.setDefault(saved.id)
+            }
+
+            remaining.shift()
+            localStorage.setItem(
+                LEGACY_PROVIDERS_KEY,
+                JSON.stringify({ ...legacy, providers: remaining })
+            )
+        }
+
+        // 迁移完成后删除明文 Key
+        localStorage.removeItem(LEGACY_PROVIDERS_KEY)
+        return true
+    } catch (error) {
+        console.error('AI 提供商迁移失败：', error)
+        return false
+    }
+}